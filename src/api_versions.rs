@@ -1,6 +1,20 @@
 const API_VERSIONS: &[(i16, (i16, i16))] = &[
+    (0, (9, 11)),
     (1, (16, 16)),
+    (2, (8, 9)),
+    (3, (12, 13)),
+    (8, (8, 9)),
+    (9, (8, 9)),
+    (10, (4, 6)),
+    (11, (6, 9)),
+    (12, (4, 4)),
+    (13, (4, 5)),
+    (14, (4, 5)),
     (18, (0, 4)),
+    (19, (5, 7)),
+    (20, (6, 6)),
+    (37, (2, 3)),
+    (47, (0, 0)),
     (75, (0, 0)),
 ];
 
@@ -17,3 +31,12 @@ pub fn get_supported_api_versions(api_key: i16) -> Option<(i16, i16)> {
         }
     })
 }
+
+// request header version: 2 with tagged fields for flexible versions, 1 for the versions below that are not flexible
+pub fn request_header_version(api_key: i16, api_version: i16) -> i16 {
+    match api_key {
+        18 if api_version < 3 => 1,
+        47 => 1,
+        _ => 2,
+    }
+}
@@ -1,72 +1,241 @@
-use std::net::{TcpListener, TcpStream};
-use std::sync::{Arc, Mutex, RwLock};
+use std::collections::HashMap;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::time::Duration;
 
-use crate::broker::utils::process_request;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+use crate::broker::config::BrokerConfig;
+use crate::broker::purgatory::Purgatory;
+use crate::broker::socket_server::SocketServer;
+use crate::common::traits::Encodable;
+use crate::coordinator::group_coordinator::GroupCoordinator;
+use crate::coordinator::offset_records::{group_records_batch, partition_for, read_group_records, GroupRecord, OFFSETS_TOPIC};
+use crate::errors::ErrorCode;
+use crate::metadata::image::MetadataImage;
+use crate::metadata::loader::MetadataLoader;
+use crate::metadata::writer::MetadataWriter;
+use crate::storage::log_manager::{LogManager, TopicPartition};
+use crate::storage::log_segment::now_ms;
+use crate::storage::meta_properties::load_log_dirs;
 
 pub struct Broker {
+    // server.properties with the command line overrides
+    pub config: BrokerConfig,
+    // cluster.id from meta.properties, None when the log dirs were never formatted
+    cluster_id: Option<String>,
+
     // network management
     listening_socket: TcpListener,
-    connection_pool: RwLock<Vec<TcpStream>>,
-    max_concurrent_connections: u8,
-    current_connections: Mutex<u8>,
 
     // cluster metadata management
-    
+    // updated by the metadata writer, read by the request handlers
+    metadata_image: Arc<RwLock<MetadataImage>>,
+    // appends topic changes to the metadata log, held while a change is validated so changes do not interleave
+    pub metadata_writer: Mutex<MetadataWriter>,
+
+    // log management
+    pub log_manager: LogManager,
+
+    // request purgatories
+    pub fetch_purgatory: Arc<Purgatory>,
+
+    // consumer group membership and rebalances
+    pub group_coordinator: Arc<GroupCoordinator>,
 }
 
 impl Broker {
     // create a new broker
-    pub fn new(address: &str, max_concurrent_connections: u8) -> std::io::Result<Self> {
-        let listener = TcpListener::bind(address)?;
-        Ok(Broker {
+    pub fn new(config: BrokerConfig) -> std::io::Result<Self> {
+        // the log dirs have to belong to this cluster and node
+        let cluster_id = load_log_dirs(&config.all_log_dirs(), config.node_id)?;
+        println!("Starting node {} of cluster {}", config.node_id, cluster_id.as_deref().unwrap_or("(none)"));
+
+        let listener = TcpListener::bind(config.broker_listener().bind_address())?;
+
+        // load the cluster metadata written so far, once a partially written batch at its end is truncated
+        let metadata_image = Arc::new(RwLock::new(MetadataImage::new()));
+        let metadata_writer = MetadataWriter::open(&config.metadata_log_dir, Arc::clone(&metadata_image))?;
+        MetadataLoader::new(&config.metadata_log_dir).catch_up(&metadata_image)?;
+
+        let broker = Broker {
+            log_manager: LogManager::new(&config.log_dirs, config.log_config.clone())?,
+            group_coordinator: Arc::new(GroupCoordinator::new(config.group_config())),
+            config,
+            cluster_id,
             listening_socket: listener,
-            connection_pool: RwLock::new(Vec::new()),
-            max_concurrent_connections,
-            current_connections: Mutex::new(0),
-        })
+            metadata_image,
+            metadata_writer: Mutex::new(metadata_writer),
+            fetch_purgatory: Purgatory::start("Fetch"),
+        };
+
+        // open the partition logs, recovering them if the last shutdown was not clean
+        broker.log_manager.load_logs(&broker.topic_configs())?;
+
+        // the committed offsets are served from memory, read them back from __consumer_offsets
+        broker.load_group_offsets()?;
+
+        Ok(broker)
     }
 
     // accepting new connections
+    // runs the network layer, requests are processed by the request handler pool
     pub fn accept_new_connections(self: Arc<Self>) -> std::io::Result<()> {
-        for stream in self.listening_socket.incoming() {
-            let stream = stream?;
-            {
-                let mut pool = self.connection_pool.write().unwrap();
-                let mut current = self.current_connections.lock().unwrap();
-
-                if *current < self.max_concurrent_connections {
-                    pool.push(stream);
-                    *current += 1;
-
-                    println!("Spawing thread to handle connection...");
-                    let broker_clone = Arc::clone(&self);
-                    std::thread::spawn(move || {
-                        if let Some(connection) = broker_clone.borrow_connection() {
-                            // Use the connection
-                            println!("Processing request...");
-                            process_request(connection, broker_clone);
-                        } else {
-                            println!("No available connections.");
-                        }
-                    });
-                } else {
-                    eprintln!("Connection pool full, rejecting connection.");
-                }
+        let listener = self.listening_socket.try_clone()?;
+        let socket_server = SocketServer::new(listener, self.config.num_network_threads, self.config.max_connections, self.config.socket_request_max_bytes)?;
+
+        self.start_log_retention(self.config.log_retention_check_interval_ms);
+        self.start_log_cleaner(self.config.log_cleaner_backoff_ms);
+        self.start_offsets_expiration(self.config.offsets_retention_check_interval_ms);
+        self.start_shutdown_hook()?;
+
+        println!("Listening on {}", self.listening_socket.local_addr()?);
+        socket_server.run(self)
+    }
+
+    // delete old log segments every log.retention.check.interval.ms, like Kafka's kafka-log-retention task
+    fn start_log_retention(self: &Arc<Self>, check_interval_ms: u64) {
+        let broker = Arc::clone(self);
+
+        std::thread::spawn(move || loop {
+            std::thread::sleep(Duration::from_millis(check_interval_ms));
+
+            let deleted = broker.log_manager.cleanup_logs(&broker.topic_configs());
+            if deleted > 0 {
+                println!("Log retention deleted {} segment(s)", deleted);
+            }
+        });
+    }
+
+    // compact logs with cleanup.policy=compact, checking again every log.cleaner.backoff.ms
+    fn start_log_cleaner(self: &Arc<Self>, backoff_ms: u64) {
+        let broker = Arc::clone(self);
+
+        std::thread::spawn(move || loop {
+            std::thread::sleep(Duration::from_millis(backoff_ms));
+
+            let cleaned = broker.log_manager.clean_logs(&broker.topic_configs());
+            if cleaned > 0 {
+                println!("Log cleaner compacted {} log(s)", cleaned);
+            }
+        });
+    }
+
+    // remove expired committed offsets every offsets.retention.check.interval.ms, like Kafka's delete-expired-group-metadata task
+    fn start_offsets_expiration(self: &Arc<Self>, check_interval_ms: u64) {
+        let broker = Arc::clone(self);
+
+        std::thread::spawn(move || loop {
+            std::thread::sleep(Duration::from_millis(check_interval_ms));
+
+            let expired = broker.group_coordinator.expire_offsets(now_ms(), &|group_id, records| broker.append_group_records(group_id, records));
+            if expired > 0 {
+                println!("Offsets expiration removed {} offset(s)", expired);
             }
+        });
+    }
+
+    // shut down gracefully on SIGTERM or SIGINT, so the next start can skip log recovery
+    fn start_shutdown_hook(self: &Arc<Self>) -> std::io::Result<()> {
+        let broker = Arc::clone(self);
+        let mut signals = Signals::new([SIGTERM, SIGINT])?;
+
+        std::thread::spawn(move || {
+            if let Some(signal) = signals.forever().next() {
+                println!("Received signal {}, shutting down", signal);
+                broker.shutdown();
+                std::process::exit(0);
+            }
+        });
+
+        Ok(())
+    }
+
+    // flush and close the logs and mark the shutdown as clean
+    pub fn shutdown(&self) {
+        if let Err(e) = self.metadata_writer.lock().unwrap().close() {
+            println!("Error closing the metadata log: {}", e);
+        }
+        if let Err(e) = self.log_manager.shutdown() {
+            println!("Error shutting down the log manager: {}", e);
         }
+    }
+
+    pub fn node_id(&self) -> i32 {
+        self.config.node_id
+    }
+
+    pub fn cluster_id(&self) -> Option<&str> {
+        self.cluster_id.as_deref()
+    }
+
+    // copy of every topic's config overrides, so the metadata image is not held while logs are worked on
+    fn topic_configs(&self) -> HashMap<String, HashMap<String, String>> {
+        let metadata_image = self.metadata_image();
+        metadata_image.topics()
+            .filter_map(|topic| metadata_image.topic_configs(&topic.name).map(|configs| (topic.name.clone(), configs.clone())))
+            .collect()
+    }
+
+    // replay every partition of __consumer_offsets this broker has a log for into the group coordinator
+    fn load_group_offsets(&self) -> std::io::Result<()> {
+        let partitions: Vec<i32> = match self.metadata_image().topic_by_name(OFFSETS_TOPIC) {
+            Some(topic) => topic.partitions.keys().copied().collect(),
+            None => return Ok(()),
+        };
+
+        for partition in partitions {
+            let topic_partition = TopicPartition::new(OFFSETS_TOPIC, partition);
+            let log = match self.log_manager.get(&topic_partition) {
+                Some(log) => log,
+                None => continue,
+            };
+
+            let replayed = read_group_records(&log.lock().unwrap(), |key, value| {
+                self.group_coordinator.replay_offset_record(&key, value.as_deref());
+            })?;
+            if replayed > 0 {
+                println!("Loaded {} record(s) from {}", replayed, topic_partition);
+            }
+        }
+
         Ok(())
     }
 
-    // borrow a connection from the pool
-    pub fn borrow_connection(&self) -> Option<TcpStream> {
-        let mut pool = self.connection_pool.write().unwrap();
-        pool.pop()
+    // append the records of a group to its partition of __consumer_offsets and flush them
+    pub fn append_group_records(&self, group_id: &str, records: Vec<GroupRecord>) -> Result<(), ErrorCode> {
+        // copied out so the metadata image is not held across the append and flush
+        let (partition_id, topic_config, directories) = {
+            let metadata_image = self.metadata_image();
+
+            let topic = metadata_image.topic_by_name(OFFSETS_TOPIC).ok_or(ErrorCode::CoordinatorNotAvailable)?;
+            if topic.partitions.is_empty() {
+                return Err(ErrorCode::CoordinatorNotAvailable);
+            }
+            let partition_id = partition_for(group_id, topic.partitions.len() as i32);
+            let partition = topic.partition(partition_id).ok_or(ErrorCode::CoordinatorNotAvailable)?;
+
+            (partition_id, metadata_image.topic_configs(OFFSETS_TOPIC).cloned(), partition.directories.clone())
+        };
+        let topic_partition = TopicPartition::new(OFFSETS_TOPIC, partition_id);
+
+        // as Kafka, a partition that can not be written means this broker can not coordinate the group
+        let append = || -> std::io::Result<()> {
+            let log = self.log_manager.get_or_open(&topic_partition, topic_config.as_ref(), &directories)?;
+            let mut log = log.lock().unwrap();
+            log.append(vec![group_records_batch(records).encode()])?;
+            log.flush()
+        };
+        append().map_err(|e| {
+            println!("Error appending to {}: {}", topic_partition, e);
+            ErrorCode::NotCoordinator
+        })
     }
 
-    // return a connection to the pool
-    pub fn return_connection(&self, stream: TcpStream) {
-        let mut pool = self.connection_pool.write().unwrap();
-        pool.push(stream);
+    // read access to the cluster metadata
+    pub fn metadata_image(&self) -> RwLockReadGuard<'_, MetadataImage> {
+        self.metadata_image.read().unwrap()
     }
 
 }
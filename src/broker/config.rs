@@ -0,0 +1,437 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::broker::framing::DEFAULT_SOCKET_REQUEST_MAX_BYTES;
+use crate::broker::request_handler::DEFAULT_NUM_IO_THREADS;
+use crate::broker::socket_server::{DEFAULT_MAX_CONNECTIONS, DEFAULT_NUM_NETWORK_THREADS};
+use crate::coordinator::group_coordinator::{GroupConfig, DEFAULT_GROUP_INITIAL_REBALANCE_DELAY_MS, DEFAULT_GROUP_MAX_SESSION_TIMEOUT_MS, DEFAULT_GROUP_MAX_SIZE, DEFAULT_GROUP_MIN_SESSION_TIMEOUT_MS, DEFAULT_OFFSETS_RETENTION_CHECK_INTERVAL_MS, DEFAULT_OFFSETS_RETENTION_MINUTES, DEFAULT_OFFSET_METADATA_MAX_BYTES};
+use crate::coordinator::offset_records::{DEFAULT_OFFSETS_TOPIC_NUM_PARTITIONS, DEFAULT_OFFSETS_TOPIC_REPLICATION_FACTOR};
+use crate::errors::ConfigError;
+use crate::storage::log_cleaner::DEFAULT_LOG_CLEANER_BACKOFF_MS;
+use crate::storage::log_config::LogConfig;
+use crate::storage::log_manager::DEFAULT_RETENTION_CHECK_INTERVAL_MS;
+use crate::utils::read_properties;
+
+//
+// BrokerConfig
+//
+
+// defaults as in Kafka
+pub const DEFAULT_NODE_ID: i32 = 1;
+pub const DEFAULT_LISTENERS: &str = "PLAINTEXT://:9092";
+pub const DEFAULT_CONTROLLER_LISTENER_NAMES: &str = "CONTROLLER";
+pub const DEFAULT_NUM_PARTITIONS: i32 = 1;
+pub const DEFAULT_REPLICATION_FACTOR: i16 = 1;
+
+// where the broker keeps its data when log.dirs is not configured
+pub const DEFAULT_LOG_DIR: &str = "/tmp/kraft-combined-logs";
+
+// broker level names of the topic configs, the value of a topic without overrides
+// log.roll.hours and log.retention.minutes/hours are handled separately as they need converting
+const TOPIC_CONFIG_DEFAULTS: [(&str, &str); 12] = [
+    ("log.segment.bytes", "segment.bytes"),
+    ("log.roll.ms", "segment.ms"),
+    ("log.index.interval.bytes", "index.interval.bytes"),
+    ("log.index.size.max.bytes", "segment.index.bytes"),
+    ("log.retention.ms", "retention.ms"),
+    ("log.retention.bytes", "retention.bytes"),
+    ("log.cleanup.policy", "cleanup.policy"),
+    ("log.cleaner.delete.retention.ms", "delete.retention.ms"),
+    ("log.cleaner.min.compaction.lag.ms", "min.compaction.lag.ms"),
+    ("log.cleaner.min.cleanable.ratio", "min.cleanable.dirty.ratio"),
+    ("compression.type", "compression.type"),
+    ("message.max.bytes", "max.message.bytes"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessRole {
+    Broker,
+    Controller,
+}
+
+// one entry of listeners or advertised.listeners, NAME://host:port
+// an empty host binds every interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub listener_name: String,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Endpoint {
+    // clients can not connect to an empty host, it stands for this machine
+    pub fn advertised_host(&self) -> &str {
+        match self.host.as_str() {
+            "" | "0.0.0.0" | "::" => "localhost",
+            host => host,
+        }
+    }
+
+    pub fn bind_address(&self) -> String {
+        match self.host.as_str() {
+            "" => format!("0.0.0.0:{}", self.port),
+            host if host.contains(':') => format!("[{}]:{}", host, self.port),
+            host => format!("{}:{}", host, self.port),
+        }
+    }
+}
+
+// broker settings, read from a server.properties file with command line overrides applied
+// anything not configured keeps Kafka's default
+#[derive(Clone)]
+pub struct BrokerConfig {
+    // node.id, the id of this broker in the cluster
+    pub node_id: i32,
+    // process.roles, this server only acts as a broker, a controller role is accepted but not run
+    pub process_roles: Vec<ProcessRole>,
+    // listeners, the endpoints to accept connections on
+    pub listeners: Vec<Endpoint>,
+    // advertised.listeners, the endpoints clients are told to connect to, listeners by default
+    pub advertised_listeners: Vec<Endpoint>,
+    // controller.listener.names, listeners used by the controller rather than the broker
+    pub controller_listener_names: Vec<String>,
+    // log.dirs (or log.dir), the directories partition logs are spread over
+    pub log_dirs: Vec<PathBuf>,
+    // metadata.log.dir, where the __cluster_metadata log lives, the first log dir by default
+    pub metadata_log_dir: PathBuf,
+    // num.network.threads, processors reading and writing connections
+    pub num_network_threads: usize,
+    // num.io.threads, request handlers
+    pub num_io_threads: usize,
+    // max.connections
+    pub max_connections: usize,
+    // socket.request.max.bytes, largest request accepted
+    pub socket_request_max_bytes: usize,
+    // log.retention.check.interval.ms, how often old segments are looked for
+    pub log_retention_check_interval_ms: u64,
+    // log.cleaner.backoff.ms, how often compacted logs are looked at
+    pub log_cleaner_backoff_ms: u64,
+    // num.partitions and default.replication.factor, for topics created without them
+    pub num_partitions: i32,
+    pub default_replication_factor: i16,
+    // delete.topic.enable, DeleteTopics fails with TOPIC_DELETION_DISABLED when false
+    pub delete_topic_enable: bool,
+    // auto.create.topics.enable, Metadata and Produce create the topics they name that do not exist
+    pub auto_create_topics_enable: bool,
+    // group.min.session.timeout.ms and group.max.session.timeout.ms, the session timeouts members may ask for
+    pub group_min_session_timeout_ms: i32,
+    pub group_max_session_timeout_ms: i32,
+    // group.initial.rebalance.delay.ms, how long the first rebalance of an empty group waits for more members
+    pub group_initial_rebalance_delay_ms: i32,
+    // group.max.size, members a group may have
+    pub group_max_size: i32,
+    // offset.metadata.max.bytes, longest metadata a committed offset may carry
+    pub offset_metadata_max_bytes: i32,
+    // offsets.retention.minutes, how long the offsets of a group without members are kept
+    pub offsets_retention_minutes: i64,
+    // offsets.retention.check.interval.ms, how often expired offsets are looked for
+    pub offsets_retention_check_interval_ms: u64,
+    // offsets.topic.num.partitions and offsets.topic.replication.factor, for creating __consumer_offsets
+    pub offsets_topic_num_partitions: i32,
+    pub offsets_topic_replication_factor: i16,
+    // log.* topic defaults, the config of topics without overrides
+    pub log_config: LogConfig,
+}
+
+impl Default for BrokerConfig {
+    fn default() -> Self {
+        let listeners = parse_listeners("listeners", DEFAULT_LISTENERS).unwrap();
+        BrokerConfig {
+            node_id: DEFAULT_NODE_ID,
+            process_roles: vec![ProcessRole::Broker],
+            advertised_listeners: listeners.clone(),
+            listeners,
+            controller_listener_names: vec![DEFAULT_CONTROLLER_LISTENER_NAMES.to_string()],
+            log_dirs: vec![PathBuf::from(DEFAULT_LOG_DIR)],
+            metadata_log_dir: PathBuf::from(DEFAULT_LOG_DIR),
+            num_network_threads: DEFAULT_NUM_NETWORK_THREADS,
+            num_io_threads: DEFAULT_NUM_IO_THREADS,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            socket_request_max_bytes: DEFAULT_SOCKET_REQUEST_MAX_BYTES,
+            log_retention_check_interval_ms: DEFAULT_RETENTION_CHECK_INTERVAL_MS,
+            log_cleaner_backoff_ms: DEFAULT_LOG_CLEANER_BACKOFF_MS,
+            num_partitions: DEFAULT_NUM_PARTITIONS,
+            default_replication_factor: DEFAULT_REPLICATION_FACTOR,
+            delete_topic_enable: true,
+            auto_create_topics_enable: true,
+            group_min_session_timeout_ms: DEFAULT_GROUP_MIN_SESSION_TIMEOUT_MS,
+            group_max_session_timeout_ms: DEFAULT_GROUP_MAX_SESSION_TIMEOUT_MS,
+            group_initial_rebalance_delay_ms: DEFAULT_GROUP_INITIAL_REBALANCE_DELAY_MS,
+            group_max_size: DEFAULT_GROUP_MAX_SIZE,
+            offset_metadata_max_bytes: DEFAULT_OFFSET_METADATA_MAX_BYTES,
+            offsets_retention_minutes: DEFAULT_OFFSETS_RETENTION_MINUTES,
+            offsets_retention_check_interval_ms: DEFAULT_OFFSETS_RETENTION_CHECK_INTERVAL_MS,
+            offsets_topic_num_partitions: DEFAULT_OFFSETS_TOPIC_NUM_PARTITIONS,
+            offsets_topic_replication_factor: DEFAULT_OFFSETS_TOPIC_REPLICATION_FACTOR,
+            log_config: LogConfig::default(),
+        }
+    }
+}
+
+impl BrokerConfig {
+    // codecrafters-kafka [server.properties] [--override name=value]...
+    // overrides win over the properties file, as with kafka-server-start.sh
+    pub fn from_args(args: &[String]) -> Result<Self, ConfigError> {
+        let mut properties: HashMap<String, String> = HashMap::new();
+        let mut overrides: Vec<(String, String)> = Vec::new();
+        let mut properties_read = false;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--override" {
+                let assignment = args.next().ok_or_else(|| ConfigError::InvalidArgument(arg.clone()))?;
+                let (name, value) = assignment.split_once('=').ok_or_else(|| ConfigError::InvalidArgument(assignment.clone()))?;
+                overrides.push((name.trim().to_string(), value.trim().to_string()));
+            } else if arg.starts_with('-') || properties_read {
+                return Err(ConfigError::InvalidArgument(arg.clone()));
+            } else {
+                properties = read_properties(arg.as_ref()).map_err(|source| ConfigError::ReadError { path: arg.clone(), source })?;
+                properties_read = true;
+            }
+        }
+
+        properties.extend(overrides);
+        BrokerConfig::from_properties(&properties)
+    }
+
+    pub fn from_properties(properties: &HashMap<String, String>) -> Result<Self, ConfigError> {
+        let mut config = BrokerConfig::default();
+
+        if let Some(node_id) = parse(properties, "node.id")?.or(parse(properties, "broker.id")?) {
+            config.node_id = node_id;
+        }
+        if config.node_id < 0 {
+            return Err(invalid_value("node.id", &config.node_id.to_string(), "must be at least 0"));
+        }
+
+        if let Some(process_roles) = properties.get("process.roles") {
+            config.process_roles = parse_process_roles(process_roles)?;
+        }
+
+        if let Some(controller_listener_names) = properties.get("controller.listener.names") {
+            config.controller_listener_names = split_list(controller_listener_names).map(str::to_string).collect();
+        }
+        if let Some(listeners) = properties.get("listeners") {
+            config.listeners = parse_listeners("listeners", listeners)?;
+        }
+        config.advertised_listeners = match properties.get("advertised.listeners") {
+            Some(advertised_listeners) => parse_listeners("advertised.listeners", advertised_listeners)?,
+            None => config.broker_listeners().cloned().collect(),
+        };
+
+        // log.dirs takes precedence over log.dir
+        if let Some(log_dirs) = properties.get("log.dirs").or(properties.get("log.dir")) {
+            config.log_dirs = split_list(log_dirs).map(PathBuf::from).collect();
+        }
+        if config.log_dirs.is_empty() {
+            return Err(ConfigError::Invalid("log.dirs must name at least one directory".to_string()));
+        }
+        config.metadata_log_dir = match properties.get("metadata.log.dir") {
+            Some(metadata_log_dir) => PathBuf::from(metadata_log_dir),
+            None => config.log_dirs[0].clone(),
+        };
+
+        config.num_network_threads = parse_at_least(properties, "num.network.threads", 1)?.unwrap_or(config.num_network_threads);
+        config.num_io_threads = parse_at_least(properties, "num.io.threads", 1)?.unwrap_or(config.num_io_threads);
+        config.max_connections = parse_at_least(properties, "max.connections", 1)?.unwrap_or(config.max_connections);
+        config.socket_request_max_bytes = parse_at_least(properties, "socket.request.max.bytes", 1)?.unwrap_or(config.socket_request_max_bytes);
+        config.log_retention_check_interval_ms = parse_at_least(properties, "log.retention.check.interval.ms", 1)?.unwrap_or(config.log_retention_check_interval_ms);
+        config.log_cleaner_backoff_ms = parse_at_least(properties, "log.cleaner.backoff.ms", 1)?.unwrap_or(config.log_cleaner_backoff_ms);
+        config.num_partitions = parse_at_least(properties, "num.partitions", 1)?.unwrap_or(config.num_partitions);
+        config.default_replication_factor = parse_at_least(properties, "default.replication.factor", 1)?.unwrap_or(config.default_replication_factor);
+        config.delete_topic_enable = parse_bool(properties, "delete.topic.enable")?.unwrap_or(config.delete_topic_enable);
+        config.auto_create_topics_enable = parse_bool(properties, "auto.create.topics.enable")?.unwrap_or(config.auto_create_topics_enable);
+        config.group_min_session_timeout_ms = parse_at_least(properties, "group.min.session.timeout.ms", 1)?.unwrap_or(config.group_min_session_timeout_ms);
+        config.group_max_session_timeout_ms = parse_at_least(properties, "group.max.session.timeout.ms", config.group_min_session_timeout_ms)?.unwrap_or(config.group_max_session_timeout_ms);
+        config.group_initial_rebalance_delay_ms = parse_at_least(properties, "group.initial.rebalance.delay.ms", 0)?.unwrap_or(config.group_initial_rebalance_delay_ms);
+        config.group_max_size = parse_at_least(properties, "group.max.size", 1)?.unwrap_or(config.group_max_size);
+        config.offset_metadata_max_bytes = parse_at_least(properties, "offset.metadata.max.bytes", 0)?.unwrap_or(config.offset_metadata_max_bytes);
+        config.offsets_retention_minutes = parse_at_least(properties, "offsets.retention.minutes", 1)?.unwrap_or(config.offsets_retention_minutes);
+        config.offsets_retention_check_interval_ms = parse_at_least(properties, "offsets.retention.check.interval.ms", 1)?.unwrap_or(config.offsets_retention_check_interval_ms);
+        config.offsets_topic_num_partitions = parse_at_least(properties, "offsets.topic.num.partitions", 1)?.unwrap_or(config.offsets_topic_num_partitions);
+        config.offsets_topic_replication_factor = parse_at_least(properties, "offsets.topic.replication.factor", 1)?.unwrap_or(config.offsets_topic_replication_factor);
+
+        config.log_config = parse_log_config(properties)?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    // the log dirs and the metadata log dir, each once
+    pub fn all_log_dirs(&self) -> Vec<PathBuf> {
+        let mut log_dirs = self.log_dirs.clone();
+        if !log_dirs.contains(&self.metadata_log_dir) {
+            log_dirs.push(self.metadata_log_dir.clone());
+        }
+        log_dirs
+    }
+
+    // the listeners clients connect to, everything but the controller listeners
+    pub fn broker_listeners(&self) -> impl Iterator<Item = &Endpoint> {
+        self.listeners.iter().filter(|listener| !self.controller_listener_names.contains(&listener.listener_name))
+    }
+
+    // the listener the broker accepts connections on
+    pub fn broker_listener(&self) -> &Endpoint {
+        self.broker_listeners().next().expect("validated to have a broker listener")
+    }
+
+    // the endpoint clients are told to connect to, the advertised listener of the listener the broker accepts connections on
+    pub fn advertised_listener(&self) -> &Endpoint {
+        let listener_name = &self.broker_listener().listener_name;
+        self.advertised_listeners.iter()
+            .find(|advertised_listener| &advertised_listener.listener_name == listener_name)
+            .or(self.advertised_listeners.first())
+            .unwrap_or(self.broker_listener())
+    }
+
+    // the group.* and offset settings the group coordinator works with
+    pub fn group_config(&self) -> GroupConfig {
+        GroupConfig {
+            min_session_timeout_ms: self.group_min_session_timeout_ms,
+            max_session_timeout_ms: self.group_max_session_timeout_ms,
+            initial_rebalance_delay_ms: self.group_initial_rebalance_delay_ms,
+            max_size: self.group_max_size,
+            offset_metadata_max_bytes: self.offset_metadata_max_bytes,
+            offsets_retention_ms: self.offsets_retention_minutes * 60 * 1000,
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if !self.process_roles.contains(&ProcessRole::Broker) {
+            return Err(ConfigError::Invalid("process.roles must include broker, this server only runs the broker role".to_string()));
+        }
+
+        for (index, listener) in self.listeners.iter().enumerate() {
+            if self.listeners[..index].iter().any(|other| other.listener_name == listener.listener_name) {
+                return Err(ConfigError::Invalid(format!("listener name {} is used more than once in listeners", listener.listener_name)));
+            }
+            if self.listeners[..index].iter().any(|other| other.port == listener.port && listener.port != 0) {
+                return Err(ConfigError::Invalid(format!("port {} is used by more than one listener", listener.port)));
+            }
+        }
+
+        if self.broker_listeners().next().is_none() {
+            return Err(ConfigError::Invalid("listeners has no listener for the broker, all are controller.listener.names".to_string()));
+        }
+
+        for advertised_listener in &self.advertised_listeners {
+            if !self.broker_listeners().any(|listener| listener.listener_name == advertised_listener.listener_name) {
+                return Err(ConfigError::Invalid(format!("advertised listener {} is not one of the broker listeners", advertised_listener.listener_name)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid_value(name: &str, value: &str, reason: &str) -> ConfigError {
+    ConfigError::InvalidValue {
+        name: name.to_string(),
+        value: value.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+// comma separated values with the blanks around them removed
+fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').map(str::trim).filter(|item| !item.is_empty())
+}
+
+fn parse<T: FromStr>(properties: &HashMap<String, String>, name: &str) -> Result<Option<T>, ConfigError> {
+    match properties.get(name) {
+        Some(value) => value.parse().map(Some).map_err(|_| invalid_value(name, value, "not a number")),
+        None => Ok(None),
+    }
+}
+
+fn parse_bool(properties: &HashMap<String, String>, name: &str) -> Result<Option<bool>, ConfigError> {
+    match properties.get(name).map(|value| value.trim()) {
+        Some(value) if value.eq_ignore_ascii_case("true") => Ok(Some(true)),
+        Some(value) if value.eq_ignore_ascii_case("false") => Ok(Some(false)),
+        Some(value) => Err(invalid_value(name, value, "must be true or false")),
+        None => Ok(None),
+    }
+}
+
+fn parse_at_least<T: FromStr + PartialOrd + Display>(properties: &HashMap<String, String>, name: &str, min: T) -> Result<Option<T>, ConfigError> {
+    match parse::<T>(properties, name)? {
+        Some(value) if value < min => Err(invalid_value(name, &value.to_string(), &format!("must be at least {}", min))),
+        value => Ok(value),
+    }
+}
+
+fn parse_process_roles(value: &str) -> Result<Vec<ProcessRole>, ConfigError> {
+    let mut process_roles: Vec<ProcessRole> = Vec::new();
+    for role in split_list(value) {
+        let role = match role {
+            "broker" => ProcessRole::Broker,
+            "controller" => ProcessRole::Controller,
+            _ => return Err(invalid_value("process.roles", value, "roles are broker and controller")),
+        };
+        if !process_roles.contains(&role) {
+            process_roles.push(role);
+        }
+    }
+    Ok(process_roles)
+}
+
+// NAME://host:port,... where the host may be empty or a bracketed IPv6 address
+fn parse_listeners(name: &str, value: &str) -> Result<Vec<Endpoint>, ConfigError> {
+    let mut endpoints: Vec<Endpoint> = Vec::new();
+
+    for listener in split_list(value) {
+        let invalid = || invalid_value(name, listener, "listeners are of the form NAME://host:port");
+
+        let (listener_name, address) = listener.split_once("://").ok_or_else(invalid)?;
+        let (host, port) = address.rsplit_once(':').ok_or_else(invalid)?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if listener_name.is_empty() {
+            return Err(invalid());
+        }
+
+        endpoints.push(Endpoint {
+            listener_name: listener_name.to_uppercase(),
+            host: host.to_string(),
+            port: port.parse().map_err(|_| invalid())?,
+        });
+    }
+
+    Ok(endpoints)
+}
+
+// the log.* defaults for topic configs, in Kafka's order of precedence: ms over minutes over hours
+fn parse_log_config(properties: &HashMap<String, String>) -> Result<LogConfig, ConfigError> {
+    let mut log_config = LogConfig::default();
+
+    let to_ms = |name: &str, ms_per_unit: i64| -> Result<Option<String>, ConfigError> {
+        Ok(parse::<i64>(properties, name)?.map(|value| value.saturating_mul(ms_per_unit).to_string()))
+    };
+    let segment_ms = match properties.get("log.roll.ms") {
+        Some(_) => None,
+        None => to_ms("log.roll.hours", 60 * 60 * 1000)?,
+    };
+    let retention_ms = match properties.get("log.retention.ms") {
+        Some(_) => None,
+        None => to_ms("log.retention.minutes", 60 * 1000)?.or(to_ms("log.retention.hours", 60 * 60 * 1000)?),
+    };
+
+    let converted = [("segment.ms", segment_ms), ("retention.ms", retention_ms)];
+    for (topic_name, value) in converted {
+        if let Some(value) = value {
+            let _ = log_config.set(topic_name, &value);
+        }
+    }
+
+    for (broker_name, topic_name) in TOPIC_CONFIG_DEFAULTS {
+        if let Some(value) = properties.get(broker_name) {
+            if log_config.set(topic_name, value).is_err() {
+                return Err(invalid_value(broker_name, value, &format!("not a valid {}", topic_name)));
+            }
+        }
+    }
+
+    Ok(log_config)
+}
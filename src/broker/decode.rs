@@ -1,9 +1,10 @@
 use uuid::Uuid;
 
 use crate::common::traits::Decodable;
+use crate::broker::traits::Request;
 use crate::errors::{BrokerError, KafkaError};
-use crate::common::kafka_protocol::{ApiVersionsRequest, Cursor, DescribeTopicPartitionsRequest, FetchRequest, FetchRequestPartition, FetchRequestTopic, ForgottenTopicData, KafkaBody, KafkaHeader, KafkaMessage, RequestContext, RequestHeader, RequestTopic, TaggedFields};
-use crate::common::primitive_types::{CompactString, CompactArray};
+use crate::common::kafka_protocol::{ApiVersionsRequest, CreatableReplicaAssignment, CreatableTopic, CreatableTopicConfig, CreatePartitionsAssignment, CreatePartitionsRequest, CreatePartitionsTopic, CreateTopicsRequest, Cursor, DeleteTopicState, DeleteTopicsRequest, DescribeTopicPartitionsRequest, FetchRequest, FetchRequestPartition, FetchRequestTopic, FindCoordinatorRequest, ForgottenTopicData, HeartbeatRequest, JoinGroupRequest, JoinGroupRequestProtocol, KafkaBody, KafkaHeader, KafkaMessage, LeaveGroupRequest, ListOffsetsRequest, ListOffsetsRequestPartition, ListOffsetsRequestTopic, MemberIdentity, MetadataRequest, MetadataRequestTopic, OffsetCommitRequest, OffsetCommitRequestPartition, OffsetCommitRequestTopic, OffsetDeleteRequest, OffsetDeleteRequestPartition, OffsetDeleteRequestTopic, OffsetFetchRequest, OffsetFetchRequestGroup, OffsetFetchRequestTopics, ProduceRequest, ProduceRequestPartition, ProduceRequestTopic, RequestContext, RequestHeader, RequestTopic, SyncGroupRequest, SyncGroupRequestAssignment, TaggedFields};
+use crate::common::primitive_types::{Array, CompactArray, CompactBytes, CompactNullableString, CompactRecords, CompactString, KafkaString, UnsignedVarInt};
 
 
 
@@ -23,29 +24,9 @@ impl Decodable for KafkaMessage {
         offset += header_byte_length;
 
         // decode message body
-        let request: KafkaBody = match request_header.api_key {
-            1 => {
-                match FetchRequest::decode(&buf[offset..], request_context) {
-                    Ok((kmessage, _)) => KafkaBody::Request(Box::new(kmessage)),
-                    Err(_) => return Err(KafkaError::DecodeError)
-                }
-            },
-            18 => {
-                match ApiVersionsRequest::decode(&buf[offset..], request_context) {
-                    Ok((kmessage, _)) => KafkaBody::Request(Box::new(kmessage)),
-                    Err(_) => return Err(KafkaError::DecodeError)
-                }
-            },
-            75 => {
-                match DescribeTopicPartitionsRequest::decode(&buf[offset..], request_context) {
-                    Ok((kmessage, _)) => KafkaBody::Request(Box::new(kmessage)),
-                    Err(_) => return Err(KafkaError::DecodeError)
-                }
-            },
-            _ => { return Err(KafkaError::BrokerError(BrokerError::UnknownError)) }
-        };
+        let request = KafkaBody::Request(decode_request_body(&request_header, &buf[offset..], request_context)?);
 
-        return Ok( (KafkaMessage {
+        Ok( (KafkaMessage {
             size: message_size,
             header: KafkaHeader::Request(request_header),
             body: request,
@@ -53,6 +34,70 @@ impl Decodable for KafkaMessage {
     }
 }
 
+// decode the body of a request whose header is already decoded
+pub fn decode_request_body(request_header: &RequestHeader, buf: &[u8], request_context: &RequestContext) -> Result<Box<dyn Request>, KafkaError> {
+    let request: Box<dyn Request> = match request_header.api_key {
+        0 => Box::new(ProduceRequest::decode(buf, request_context)?.0),
+        1 => Box::new(FetchRequest::decode(buf, request_context)?.0),
+        2 => Box::new(ListOffsetsRequest::decode(buf, request_context)?.0),
+        3 => {
+            let mut request = MetadataRequest::decode(buf, request_context)?.0;
+            request.api_version = request_header.api_version;
+            Box::new(request)
+        }
+        8 => Box::new(OffsetCommitRequest::decode(buf, request_context)?.0),
+        9 => Box::new(OffsetFetchRequest::decode(buf, &with_api_version(request_context, request_header.api_version))?.0),
+        10 => Box::new(FindCoordinatorRequest::decode(buf, request_context)?.0),
+        11 => {
+            let mut request = JoinGroupRequest::decode(buf, &with_api_version(request_context, request_header.api_version))?.0;
+            request.api_version = request_header.api_version;
+            request.client_id = request_header.client_id.clone();
+            Box::new(request)
+        }
+        12 => Box::new(HeartbeatRequest::decode(buf, request_context)?.0),
+        13 => Box::new(LeaveGroupRequest::decode(buf, &with_api_version(request_context, request_header.api_version))?.0),
+        14 => {
+            let mut request = SyncGroupRequest::decode(buf, &with_api_version(request_context, request_header.api_version))?.0;
+            request.api_version = request_header.api_version;
+            Box::new(request)
+        }
+        // ApiVersions v0-2 have an empty body
+        18 if request_header.api_version < 3 => Box::new(ApiVersionsRequest {
+            client_software_name: CompactString { data: String::new() },
+            client_software_version: CompactString { data: String::new() },
+            tagged_fields: TaggedFields(None),
+        }),
+        18 => Box::new(ApiVersionsRequest::decode(buf, request_context)?.0),
+        19 => {
+            let mut request = CreateTopicsRequest::decode(buf, request_context)?.0;
+            request.api_version = request_header.api_version;
+            Box::new(request)
+        }
+        20 => Box::new(DeleteTopicsRequest::decode(buf, request_context)?.0),
+        37 => Box::new(CreatePartitionsRequest::decode(buf, request_context)?.0),
+        47 => Box::new(OffsetDeleteRequest::decode(buf, request_context)?.0),
+        75 => Box::new(DescribeTopicPartitionsRequest::decode(buf, request_context)?.0),
+        _ => return Err(KafkaError::BrokerError(BrokerError::UnsupportedVersion)),
+    };
+
+    Ok(request)
+}
+
+// the request context with the api version of the request, for bodies with fields only some versions have
+fn with_api_version(request_context: &RequestContext, api_version: i16) -> RequestContext {
+    let mut context_map = request_context.clone().unwrap_or_default();
+    context_map.insert("api_version".to_string(), api_version.to_string());
+    Some(context_map)
+}
+
+// the api version a body is decoded for, the latest when the context does not say
+fn context_api_version(request_context: &RequestContext) -> i16 {
+    request_context.as_ref()
+        .and_then(|context_map| context_map.get("api_version"))
+        .and_then(|api_version| api_version.parse().ok())
+        .unwrap_or(i16::MAX)
+}
+
 impl Decodable for ApiVersionsRequest {
     fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
         let mut offset = 0;
@@ -101,7 +146,7 @@ impl Decodable for DescribeTopicPartitionsRequest {
         let (request_topics, topics_bytes) = CompactArray::<RequestTopic>::decode(&buf[offset..], request_context)?;
         offset += topics_bytes;
 
-        println!("Request topic name: {:?}", request_topics.data[0].name.data);
+        println!("Request topic names: {:?}", request_topics.data.iter().map(|topic| &topic.name.data).collect::<Vec<_>>());
         
         // decode response partition limit
         let temp: &[u8; 4] = match buf[offset..offset+4].try_into() {
@@ -364,4 +409,1177 @@ impl Decodable for ForgottenTopicData {
             tagged_fields
         }, offset) )
     }
-}
\ No newline at end of file
+}
+
+// Produce Request
+impl Decodable for ProduceRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode ProduceRequest...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        println!("Decoding ProduceRequest...");
+
+        let (transactional_id, tid_byte_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+        offset += tid_byte_len;
+
+        let acks = i16::from_be_bytes(read_bytes!(2).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Acks: {:?}", acks);
+
+        let timeout_ms = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Timeout ms: {:?}", timeout_ms);
+
+        let (topic_data, td_byte_len) = CompactArray::<ProduceRequestTopic>::decode(&buf[offset..], request_context)?;
+        offset += td_byte_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (ProduceRequest {
+            transactional_id,
+            acks,
+            timeout_ms,
+            topic_data,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for ProduceRequestTopic {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        println!("Decoding ProduceRequestTopic...");
+
+        let (name, name_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += name_len;
+        println!("  Topic name: {:?}", name.data);
+
+        let (partition_data, pd_byte_len) = CompactArray::<ProduceRequestPartition>::decode(&buf[offset..], request_context)?;
+        offset += pd_byte_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (ProduceRequestTopic {
+            name,
+            partition_data,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for ProduceRequestPartition {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode ProduceRequestPartition...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        println!("      Decoding ProduceRequestPartition...");
+
+        let index = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("      Partition index: {:?}", index);
+
+        let (records, records_byte_len) = CompactRecords::decode(&buf[offset..], request_context)?;
+        offset += records_byte_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (ProduceRequestPartition {
+            index,
+            records,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+// Metadata Request
+impl Decodable for MetadataRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode MetadataRequest...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        println!("Decoding MetadataRequest...");
+
+        // topics is a nullable array, null (length 0) asks for every topic
+        let (topics_length, _) = UnsignedVarInt::decode(&buf[offset..], request_context)?;
+        let topics = if topics_length.data == 0 {
+            offset += 1;
+            None
+        } else {
+            let (topics, topics_len) = CompactArray::<MetadataRequestTopic>::decode(&buf[offset..], request_context)?;
+            offset += topics_len;
+            Some(topics)
+        };
+        println!("Topics: {:?}", topics.as_ref().map(|topics| topics.data.iter().map(|topic| topic.name.data.as_ref().map(|name| name.data.clone())).collect::<Vec<_>>()));
+
+        let allow_auto_topic_creation = read_bytes!(1)[0] != 0;
+        println!("Allow auto topic creation: {:?}", allow_auto_topic_creation);
+
+        let include_topic_authorized_operations = read_bytes!(1)[0] != 0;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (MetadataRequest {
+            api_version: 0,
+            topics,
+            allow_auto_topic_creation,
+            include_topic_authorized_operations,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for MetadataRequestTopic {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode MetadataRequestTopic...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        let topic_id = Uuid::from_slice(read_bytes!(16)).map_err(|_| KafkaError::DecodeError)?;
+
+        let (name, name_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+        offset += name_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (MetadataRequestTopic {
+            topic_id,
+            name,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+// ListOffsets Request
+impl Decodable for ListOffsetsRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode ListOffsetsRequest...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        println!("Decoding ListOffsetsRequest...");
+
+        let replica_id = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let isolation_level = i8::from_be_bytes(read_bytes!(1).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Isolation level: {:?}", isolation_level);
+
+        let (topics, topics_len) = CompactArray::<ListOffsetsRequestTopic>::decode(&buf[offset..], request_context)?;
+        offset += topics_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (ListOffsetsRequest {
+            replica_id,
+            isolation_level,
+            topics,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for ListOffsetsRequestTopic {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        let (name, name_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += name_len;
+        println!("  Topic name: {:?}", name.data);
+
+        let (partitions, partitions_len) = CompactArray::<ListOffsetsRequestPartition>::decode(&buf[offset..], request_context)?;
+        offset += partitions_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (ListOffsetsRequestTopic {
+            name,
+            partitions,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for ListOffsetsRequestPartition {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode ListOffsetsRequestPartition...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        let partition_index = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let current_leader_epoch = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let timestamp = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("    Partition {:?} timestamp {:?}", partition_index, timestamp);
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (ListOffsetsRequestPartition {
+            partition_index,
+            current_leader_epoch,
+            timestamp,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+// CreateTopics Request
+impl Decodable for CreateTopicsRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode CreateTopicsRequest...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        println!("Decoding CreateTopicsRequest...");
+
+        let (topics, topics_len) = CompactArray::<CreatableTopic>::decode(&buf[offset..], request_context)?;
+        offset += topics_len;
+
+        let timeout_ms = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let validate_only = read_bytes!(1)[0] != 0;
+        println!("Validate only: {:?}", validate_only);
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (CreateTopicsRequest {
+            api_version: 0,
+            topics,
+            timeout_ms,
+            validate_only,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for CreatableTopic {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode CreatableTopic...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        let (name, name_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += name_len;
+
+        let num_partitions = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let replication_factor = i16::from_be_bytes(read_bytes!(2).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("  Topic {:?} partitions {:?} replication factor {:?}", name.data, num_partitions, replication_factor);
+
+        let (assignments, assignments_len) = CompactArray::<CreatableReplicaAssignment>::decode(&buf[offset..], request_context)?;
+        offset += assignments_len;
+
+        let (configs, configs_len) = CompactArray::<CreatableTopicConfig>::decode(&buf[offset..], request_context)?;
+        offset += configs_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (CreatableTopic {
+            name,
+            num_partitions,
+            replication_factor,
+            assignments,
+            configs,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for CreatableReplicaAssignment {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode CreatableReplicaAssignment...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        let partition_index = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (broker_ids, broker_ids_len) = CompactArray::<i32>::decode(&buf[offset..], request_context)?;
+        offset += broker_ids_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (CreatableReplicaAssignment {
+            partition_index,
+            broker_ids,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for CreatableTopicConfig {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        let (name, name_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += name_len;
+
+        let (value, value_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+        offset += value_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (CreatableTopicConfig {
+            name,
+            value,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+// DeleteTopics Request
+impl Decodable for DeleteTopicsRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode DeleteTopicsRequest...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        println!("Decoding DeleteTopicsRequest...");
+
+        let (topics, topics_len) = CompactArray::<DeleteTopicState>::decode(&buf[offset..], request_context)?;
+        offset += topics_len;
+
+        let timeout_ms = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (DeleteTopicsRequest {
+            topics,
+            timeout_ms,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for DeleteTopicState {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode DeleteTopicState...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        let (name, name_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+        offset += name_len;
+
+        let topic_id = Uuid::from_slice(read_bytes!(16)).map_err(|_| KafkaError::DecodeError)?;
+        println!("  Topic {:?} id {:?}", name.data.as_ref().map(|name| name.data.clone()), topic_id);
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (DeleteTopicState {
+            name,
+            topic_id,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+// CreatePartitions Request
+impl Decodable for CreatePartitionsRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode CreatePartitionsRequest...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        println!("Decoding CreatePartitionsRequest...");
+
+        let (topics, topics_len) = CompactArray::<CreatePartitionsTopic>::decode(&buf[offset..], request_context)?;
+        offset += topics_len;
+
+        let timeout_ms = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let validate_only = read_bytes!(1)[0] != 0;
+        println!("Validate only: {:?}", validate_only);
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (CreatePartitionsRequest {
+            topics,
+            timeout_ms,
+            validate_only,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for CreatePartitionsTopic {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode CreatePartitionsTopic...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        let (name, name_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += name_len;
+
+        let count = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("  Topic {:?} count {:?}", name.data, count);
+
+        // assignments is a nullable array, null lets the broker place the new partitions
+        let (assignments_length, _) = UnsignedVarInt::decode(&buf[offset..], request_context)?;
+        let assignments = if assignments_length.data == 0 {
+            offset += 1;
+            None
+        } else {
+            let (assignments, assignments_len) = CompactArray::<CreatePartitionsAssignment>::decode(&buf[offset..], request_context)?;
+            offset += assignments_len;
+            Some(assignments)
+        };
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (CreatePartitionsTopic {
+            name,
+            count,
+            assignments,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for CreatePartitionsAssignment {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        let (broker_ids, broker_ids_len) = CompactArray::<i32>::decode(&buf[offset..], request_context)?;
+        offset += broker_ids_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (CreatePartitionsAssignment {
+            broker_ids,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+// FindCoordinator Request
+impl Decodable for FindCoordinatorRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode FindCoordinatorRequest...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        println!("Decoding FindCoordinatorRequest...");
+
+        let key_type = i8::from_be_bytes(read_bytes!(1).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (coordinator_keys, keys_len) = CompactArray::<CompactString>::decode(&buf[offset..], request_context)?;
+        offset += keys_len;
+        println!("Key type {:?}, keys: {:?}", key_type, coordinator_keys.data.iter().map(|key| key.data.clone()).collect::<Vec<_>>());
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (FindCoordinatorRequest {
+            key_type,
+            coordinator_keys,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+// JoinGroup Request
+impl Decodable for JoinGroupRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode JoinGroupRequest...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        println!("Decoding JoinGroupRequest...");
+
+        let (group_id, group_id_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += group_id_len;
+
+        let session_timeout_ms = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let rebalance_timeout_ms = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (member_id, member_id_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += member_id_len;
+        println!("Group {:?}, member {:?}", group_id.data, member_id.data);
+
+        let (group_instance_id, instance_id_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+        offset += instance_id_len;
+
+        let (protocol_type, protocol_type_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += protocol_type_len;
+
+        let (protocols, protocols_len) = CompactArray::<JoinGroupRequestProtocol>::decode(&buf[offset..], request_context)?;
+        offset += protocols_len;
+
+        let reason = if context_api_version(request_context) >= 8 {
+            let (reason, reason_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+            offset += reason_len;
+            Some(reason)
+        } else {
+            None
+        };
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (JoinGroupRequest {
+            api_version: 0,
+            client_id: String::new(),
+            group_id,
+            session_timeout_ms,
+            rebalance_timeout_ms,
+            member_id,
+            group_instance_id,
+            protocol_type,
+            protocols,
+            reason,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for JoinGroupRequestProtocol {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        let (name, name_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += name_len;
+
+        let (metadata, metadata_len) = CompactBytes::decode(&buf[offset..], request_context)?;
+        offset += metadata_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (JoinGroupRequestProtocol {
+            name,
+            metadata,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+// Heartbeat Request
+impl Decodable for HeartbeatRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode HeartbeatRequest...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        println!("Decoding HeartbeatRequest...");
+
+        let (group_id, group_id_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += group_id_len;
+
+        let generation_id = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (member_id, member_id_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += member_id_len;
+        println!("Group {:?}, generation {:?}, member {:?}", group_id.data, generation_id, member_id.data);
+
+        let (group_instance_id, instance_id_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+        offset += instance_id_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (HeartbeatRequest {
+            group_id,
+            generation_id,
+            member_id,
+            group_instance_id,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+// LeaveGroup Request
+impl Decodable for LeaveGroupRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        println!("Decoding LeaveGroupRequest...");
+
+        let (group_id, group_id_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += group_id_len;
+
+        let (members, members_len) = CompactArray::<MemberIdentity>::decode(&buf[offset..], request_context)?;
+        offset += members_len;
+        println!("Group {:?}, members: {:?}", group_id.data, members.data.iter().map(|member| member.member_id.data.clone()).collect::<Vec<_>>());
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (LeaveGroupRequest {
+            group_id,
+            members,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for MemberIdentity {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        let (member_id, member_id_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += member_id_len;
+
+        let (group_instance_id, instance_id_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+        offset += instance_id_len;
+
+        let reason = if context_api_version(request_context) >= 5 {
+            let (reason, reason_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+            offset += reason_len;
+            Some(reason)
+        } else {
+            None
+        };
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (MemberIdentity {
+            member_id,
+            group_instance_id,
+            reason,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+// SyncGroup Request
+impl Decodable for SyncGroupRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode SyncGroupRequest...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        println!("Decoding SyncGroupRequest...");
+
+        let (group_id, group_id_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += group_id_len;
+
+        let generation_id = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (member_id, member_id_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += member_id_len;
+        println!("Group {:?}, generation {:?}, member {:?}", group_id.data, generation_id, member_id.data);
+
+        let (group_instance_id, instance_id_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+        offset += instance_id_len;
+
+        let (protocol_type, protocol_name) = if context_api_version(request_context) >= 5 {
+            let (protocol_type, protocol_type_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+            offset += protocol_type_len;
+            let (protocol_name, protocol_name_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+            offset += protocol_name_len;
+            (Some(protocol_type), Some(protocol_name))
+        } else {
+            (None, None)
+        };
+
+        let (assignments, assignments_len) = CompactArray::<SyncGroupRequestAssignment>::decode(&buf[offset..], request_context)?;
+        offset += assignments_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (SyncGroupRequest {
+            api_version: 0,
+            group_id,
+            generation_id,
+            member_id,
+            group_instance_id,
+            protocol_type,
+            protocol_name,
+            assignments,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for SyncGroupRequestAssignment {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        let (member_id, member_id_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += member_id_len;
+
+        let (assignment, assignment_len) = CompactBytes::decode(&buf[offset..], request_context)?;
+        offset += assignment_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (SyncGroupRequestAssignment {
+            member_id,
+            assignment,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+// OffsetCommit Request
+impl Decodable for OffsetCommitRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode OffsetCommitRequest...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        println!("Decoding OffsetCommitRequest...");
+
+        let (group_id, group_id_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += group_id_len;
+
+        let generation_id_or_member_epoch = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (member_id, member_id_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += member_id_len;
+        println!("Group {:?}, generation {:?}, member {:?}", group_id.data, generation_id_or_member_epoch, member_id.data);
+
+        let (group_instance_id, instance_id_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+        offset += instance_id_len;
+
+        let (topics, topics_len) = CompactArray::<OffsetCommitRequestTopic>::decode(&buf[offset..], request_context)?;
+        offset += topics_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (OffsetCommitRequest {
+            group_id,
+            generation_id_or_member_epoch,
+            member_id,
+            group_instance_id,
+            topics,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for OffsetCommitRequestTopic {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        let (name, name_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += name_len;
+        println!("  Topic {:?}", name.data);
+
+        let (partitions, partitions_len) = CompactArray::<OffsetCommitRequestPartition>::decode(&buf[offset..], request_context)?;
+        offset += partitions_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (OffsetCommitRequestTopic {
+            name,
+            partitions,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for OffsetCommitRequestPartition {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode OffsetCommitRequestPartition...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        let partition_index = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let committed_offset = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let committed_leader_epoch = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("    Partition {:?} offset {:?}", partition_index, committed_offset);
+
+        let (committed_metadata, metadata_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+        offset += metadata_len;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (OffsetCommitRequestPartition {
+            partition_index,
+            committed_offset,
+            committed_leader_epoch,
+            committed_metadata,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+// OffsetFetch Request
+impl Decodable for OffsetFetchRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode OffsetFetchRequest...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        println!("Decoding OffsetFetchRequest...");
+
+        let (groups, groups_len) = CompactArray::<OffsetFetchRequestGroup>::decode(&buf[offset..], request_context)?;
+        offset += groups_len;
+
+        let require_stable = read_bytes!(1)[0] != 0;
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (OffsetFetchRequest {
+            groups,
+            require_stable,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for OffsetFetchRequestGroup {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode OffsetFetchRequestGroup...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        let (group_id, group_id_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += group_id_len;
+        println!("  Group {:?}", group_id.data);
+
+        let (member_id, member_epoch) = if context_api_version(request_context) >= 9 {
+            let (member_id, member_id_len) = CompactNullableString::decode(&buf[offset..], request_context)?;
+            offset += member_id_len;
+            let member_epoch = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+            (Some(member_id), Some(member_epoch))
+        } else {
+            (None, None)
+        };
+
+        // a null array (length 0) asks for every committed offset
+        let (topics_length, _) = UnsignedVarInt::decode(&buf[offset..], request_context)?;
+        let topics = if topics_length.data == 0 {
+            offset += 1;
+            None
+        } else {
+            let (topics, topics_len) = CompactArray::<OffsetFetchRequestTopics>::decode(&buf[offset..], request_context)?;
+            offset += topics_len;
+            Some(topics)
+        };
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (OffsetFetchRequestGroup {
+            group_id,
+            member_id,
+            member_epoch,
+            topics,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+impl Decodable for OffsetFetchRequestTopics {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        let (name, name_len) = CompactString::decode(&buf[offset..], request_context)?;
+        offset += name_len;
+
+        let (partition_indexes, partitions_len) = CompactArray::<i32>::decode(&buf[offset..], request_context)?;
+        offset += partitions_len;
+        println!("    Topic {:?} partitions {:?}", name.data, partition_indexes.data);
+
+        let (tagged_fields, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+        offset += tf_len;
+
+        Ok( (OffsetFetchRequestTopics {
+            name,
+            partition_indexes,
+            tagged_fields
+        }, offset) )
+    }
+}
+
+// OffsetDelete Request
+impl Decodable for OffsetDeleteRequest {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        println!("Decoding OffsetDeleteRequest...");
+
+        let (group_id, group_id_len) = KafkaString::decode(&buf[offset..], request_context)?;
+        offset += group_id_len;
+        println!("Group {:?}", group_id.data);
+
+        let (topics, topics_len) = Array::<OffsetDeleteRequestTopic>::decode(&buf[offset..], request_context)?;
+        offset += topics_len;
+
+        Ok( (OffsetDeleteRequest {
+            group_id,
+            topics
+        }, offset) )
+    }
+}
+
+impl Decodable for OffsetDeleteRequestTopic {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        let (name, name_len) = KafkaString::decode(&buf[offset..], request_context)?;
+        offset += name_len;
+
+        let (partitions, partitions_len) = Array::<OffsetDeleteRequestPartition>::decode(&buf[offset..], request_context)?;
+        offset += partitions_len;
+        println!("  Topic {:?} partitions {:?}", name.data, partitions.data.iter().map(|partition| partition.partition_index).collect::<Vec<_>>());
+
+        Ok( (OffsetDeleteRequestTopic {
+            name,
+            partitions
+        }, offset) )
+    }
+}
+
+impl Decodable for OffsetDeleteRequestPartition {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let (partition_index, partition_index_len) = i32::decode(buf, request_context)?;
+
+        Ok( (OffsetDeleteRequestPartition {
+            partition_index
+        }, partition_index_len) )
+    }
+}
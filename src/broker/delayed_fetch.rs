@@ -0,0 +1,71 @@
+use std::sync::{Arc, Mutex};
+
+use crate::broker::broker::Broker;
+use crate::broker::purgatory::DelayedOperation;
+use crate::broker::traits::{RequestProcess, ResponseCallback};
+use crate::common::kafka_protocol::FetchRequest;
+use crate::storage::log_manager::TopicPartition;
+
+//
+// DelayedFetch
+//
+
+pub struct FetchPartitionStatus {
+    pub topic_partition: TopicPartition,
+    pub fetch_offset: i64,
+    pub partition_max_bytes: i32,
+}
+
+// A fetch parked until min_bytes are available across its partitions or max_wait_ms elapses
+pub struct DelayedFetch {
+    broker: Arc<Broker>,
+    request: FetchRequest,
+    fetch_partitions: Vec<FetchPartitionStatus>,
+    respond: Mutex<Option<ResponseCallback>>,
+}
+
+impl DelayedFetch {
+    pub fn new(broker: Arc<Broker>, request: FetchRequest, fetch_partitions: Vec<FetchPartitionStatus>, respond: ResponseCallback) -> Self {
+        DelayedFetch {
+            broker,
+            request,
+            fetch_partitions,
+            respond: Mutex::new(Some(respond)),
+        }
+    }
+}
+
+impl DelayedOperation for DelayedFetch {
+    fn can_complete(&self) -> bool {
+        let mut accumulated_bytes: u64 = 0;
+
+        for status in &self.fetch_partitions {
+            // a partition nothing was written to yet has no bytes to wait for
+            let log = match self.broker.log_manager.get(&status.topic_partition) {
+                Some(log) => log,
+                None => continue,
+            };
+            let log = log.lock().unwrap();
+
+            // an out of range offset is reported right away
+            if status.fetch_offset < log.log_start_offset() || status.fetch_offset > log.log_end_offset() {
+                return true;
+            }
+
+            accumulated_bytes += log.bytes_after(status.fetch_offset).min(status.partition_max_bytes.max(0) as u64);
+        }
+
+        accumulated_bytes >= self.request.min_bytes as u64
+    }
+
+    fn on_complete(&self) {
+        let respond = self.respond.lock().unwrap().take();
+        if let Some(respond) = respond {
+            respond(self.request.process(&self.broker));
+        }
+    }
+
+    fn on_expiration(&self) {
+        println!("Delayed fetch expired after {} ms", self.request.max_wait_ms);
+    }
+}
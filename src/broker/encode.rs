@@ -1,4 +1,4 @@
-use crate::common::kafka_protocol::{ApiVersionsResponse, Cursor, DescribeTopicPartitionsResponse, FetchResponse, FetchResponseAbortedTransactions, FetchResponsePartition, FetchResponseTopic, PartitionMetadata, ResponseTopic};
+use crate::common::kafka_protocol::{ApiVersionsResponse, Coordinator, CreatableTopicConfigs, CreatableTopicResult, CreatePartitionsResponse, CreatePartitionsTopicResult, CreateTopicsResponse, Cursor, DeletableTopicResult, DeleteTopicsResponse, DescribeTopicPartitionsResponse, FetchResponse, FetchResponseAbortedTransactions, FetchResponsePartition, FetchResponseTopic, FindCoordinatorResponse, HeartbeatResponse, JoinGroupResponse, JoinGroupResponseMember, LeaveGroupResponse, ListOffsetsResponse, ListOffsetsResponsePartition, ListOffsetsResponseTopic, MemberResponse, MetadataResponse, MetadataResponseBroker, MetadataResponsePartition, MetadataResponseTopic, OffsetCommitResponse, OffsetCommitResponsePartition, OffsetCommitResponseTopic, OffsetDeleteResponse, OffsetDeleteResponsePartition, OffsetDeleteResponseTopic, OffsetFetchResponse, OffsetFetchResponseGroup, OffsetFetchResponsePartitions, OffsetFetchResponseTopics, PartitionMetadata, ProduceResponse, ProduceResponsePartition, ProduceResponseRecordError, ProduceResponseTopic, ResponseTopic, SyncGroupResponse};
 use crate::common::traits::Encodable;
 use crate::common::primitive_types::SVarInt;
 
@@ -176,4 +176,504 @@ impl Encodable for FetchResponseAbortedTransactions {
 
         buf
     }
-}
\ No newline at end of file
+}
+
+impl Encodable for ProduceResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.responses.encode());
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for ProduceResponseTopic {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.name.encode());
+        buf.extend(self.partition_responses.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for ProduceResponsePartition {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.index.to_be_bytes());
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.base_offset.to_be_bytes());
+        buf.extend(self.log_append_time_ms.to_be_bytes());
+        buf.extend(self.log_start_offset.to_be_bytes());
+        buf.extend(self.record_errors.encode());
+        buf.extend(self.error_message.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for ProduceResponseRecordError {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.batch_index.to_be_bytes());
+        buf.extend(self.batch_index_error_message.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for MetadataResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.brokers.encode());
+        buf.extend(self.cluster_id.encode());
+        buf.extend(self.controller_id.to_be_bytes());
+        buf.extend(self.topics.encode());
+        if let Some(error_code) = self.error_code {
+            buf.extend(error_code.to_be_bytes());
+        }
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for MetadataResponseBroker {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.node_id.to_be_bytes());
+        buf.extend(self.host.encode());
+        buf.extend(self.port.to_be_bytes());
+        buf.extend(self.rack.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for MetadataResponseTopic {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.name.encode());
+        buf.extend(self.topic_id.encode());
+        buf.push(self.is_internal as u8);
+        buf.extend(self.partitions.encode());
+        buf.extend(self.topic_authorized_operations.to_be_bytes());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for MetadataResponsePartition {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.partition_index.to_be_bytes());
+        buf.extend(self.leader_id.to_be_bytes());
+        buf.extend(self.leader_epoch.to_be_bytes());
+        buf.extend(self.replica_nodes.encode());
+        buf.extend(self.isr_nodes.encode());
+        buf.extend(self.offline_replicas.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for ListOffsetsResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.topics.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for ListOffsetsResponseTopic {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.name.encode());
+        buf.extend(self.partitions.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for ListOffsetsResponsePartition {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.partition_index.to_be_bytes());
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.timestamp.to_be_bytes());
+        buf.extend(self.offset.to_be_bytes());
+        buf.extend(self.leader_epoch.to_be_bytes());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for CreateTopicsResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.topics.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for CreatableTopicResult {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.name.encode());
+        if let Some(topic_id) = self.topic_id {
+            buf.extend(topic_id.encode());
+        }
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.error_message.encode());
+        buf.extend(self.num_partitions.to_be_bytes());
+        buf.extend(self.replication_factor.to_be_bytes());
+        match &self.configs {
+            Some(configs) => buf.extend(configs.encode()),
+            None => buf.push(0), // null array
+        }
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for CreatableTopicConfigs {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.name.encode());
+        buf.extend(self.value.encode());
+        buf.push(self.read_only as u8);
+        buf.extend(self.config_source.to_be_bytes());
+        buf.push(self.is_sensitive as u8);
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for DeleteTopicsResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.responses.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for DeletableTopicResult {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.name.encode());
+        buf.extend(self.topic_id.encode());
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.error_message.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for CreatePartitionsResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.results.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for CreatePartitionsTopicResult {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.name.encode());
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.error_message.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for FindCoordinatorResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.coordinators.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for Coordinator {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.key.encode());
+        buf.extend(self.node_id.to_be_bytes());
+        buf.extend(self.host.encode());
+        buf.extend(self.port.to_be_bytes());
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.error_message.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for JoinGroupResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.generation_id.to_be_bytes());
+        if let Some(protocol_type) = &self.protocol_type {
+            buf.extend(protocol_type.encode());
+        }
+        buf.extend(self.protocol_name.encode());
+        buf.extend(self.leader.encode());
+        if let Some(skip_assignment) = self.skip_assignment {
+            buf.push(skip_assignment as u8);
+        }
+        buf.extend(self.member_id.encode());
+        buf.extend(self.members.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for JoinGroupResponseMember {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.member_id.encode());
+        buf.extend(self.group_instance_id.encode());
+        buf.extend(self.metadata.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for HeartbeatResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for LeaveGroupResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.members.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for MemberResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.member_id.encode());
+        buf.extend(self.group_instance_id.encode());
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for SyncGroupResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.error_code.to_be_bytes());
+        if let Some(protocol_type) = &self.protocol_type {
+            buf.extend(protocol_type.encode());
+        }
+        if let Some(protocol_name) = &self.protocol_name {
+            buf.extend(protocol_name.encode());
+        }
+        buf.extend(self.assignment.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetCommitResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.topics.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetCommitResponseTopic {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.name.encode());
+        buf.extend(self.partitions.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetCommitResponsePartition {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.partition_index.to_be_bytes());
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetFetchResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.groups.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetFetchResponseGroup {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.group_id.encode());
+        buf.extend(self.topics.encode());
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetFetchResponseTopics {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.name.encode());
+        buf.extend(self.partitions.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetFetchResponsePartitions {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.partition_index.to_be_bytes());
+        buf.extend(self.committed_offset.to_be_bytes());
+        buf.extend(self.committed_leader_epoch.to_be_bytes());
+        buf.extend(self.metadata.encode());
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetDeleteResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.error_code.to_be_bytes());
+        buf.extend(self.throttle_time_ms.to_be_bytes());
+        buf.extend(self.topics.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetDeleteResponseTopic {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.name.encode());
+        buf.extend(self.partitions.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetDeleteResponsePartition {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(self.partition_index.to_be_bytes());
+        buf.extend(self.error_code.to_be_bytes());
+
+        buf
+    }
+}
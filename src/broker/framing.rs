@@ -0,0 +1,75 @@
+use crate::errors::KafkaError;
+
+// size prefix of every request and response
+pub const SIZE_PREFIX_BYTES: usize = 4;
+
+// default for socket.request.max.bytes, as in Kafka
+pub const DEFAULT_SOCKET_REQUEST_MAX_BYTES: usize = 100 * 1024 * 1024;
+
+//
+// FrameDecoder
+//
+
+// Splits the bytes received on a connection into size-prefixed request frames.
+// Bytes can arrive in any chunks: a frame may be split across reads, and one read
+// may hold several pipelined frames.
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+    max_frame_bytes: usize,
+}
+
+impl FrameDecoder {
+    pub fn new(max_frame_bytes: usize) -> Self {
+        FrameDecoder {
+            buf: Vec::new(),
+            max_frame_bytes,
+        }
+    }
+
+    // add bytes read from the connection
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    // take the next complete frame, size prefix included, or None until more bytes arrive
+    // a negative size or one above socket.request.max.bytes is an error, the connection can not be resynchronized
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, KafkaError> {
+        if self.buf.len() < SIZE_PREFIX_BYTES {
+            return Ok(None);
+        }
+
+        let size = i32::from_be_bytes(self.buf[..SIZE_PREFIX_BYTES].try_into().map_err(|_| KafkaError::DecodeError)?);
+        if size < 0 {
+            println!("Invalid request size: {}", size);
+            return Err(KafkaError::DecodeError);
+        }
+        if size as usize > self.max_frame_bytes {
+            println!("Request size {} larger than socket.request.max.bytes ({})", size, self.max_frame_bytes);
+            return Err(KafkaError::DecodeError);
+        }
+
+        let frame_bytes = SIZE_PREFIX_BYTES + size as usize;
+        if self.buf.len() < frame_bytes {
+            return Ok(None);
+        }
+
+        let rest = self.buf.split_off(frame_bytes);
+        Ok(Some(std::mem::replace(&mut self.buf, rest)))
+    }
+
+    // whether the next frame is complete, or its size already makes `next_frame` fail
+    pub fn has_frame(&self) -> bool {
+        match self.buf.get(..SIZE_PREFIX_BYTES) {
+            Some(prefix) => {
+                let size = i32::from_be_bytes(prefix.try_into().unwrap());
+                size < 0 || size as usize > self.max_frame_bytes || self.buf.len() >= SIZE_PREFIX_BYTES + size as usize
+            }
+            None => false,
+        }
+    }
+
+    // bytes received that are not part of a complete frame yet
+    pub fn buffered_bytes(&self) -> usize {
+        self.buf.len()
+    }
+}
@@ -1,6 +1,13 @@
 pub mod utils;
+pub mod framing;
+pub mod socket_server;
+pub mod request_channel;
+pub mod request_handler;
 pub mod traits;
 pub mod encode;
 pub mod decode;
 pub mod process;
-pub mod broker;
\ No newline at end of file
+pub mod purgatory;
+pub mod delayed_fetch;
+pub mod broker;
+pub mod config;
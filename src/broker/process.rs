@@ -1,102 +1,70 @@
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::prelude::*;
-use std::path::Path;
-
-use crate::common::traits::Decodable;
-use crate::common::primitive_types::{CompactArray, CompactNullableString, CompactString};
-use crate::common::kafka_record::{PartitionRecord, RecordBatch, RecordValue};
-use crate::common::kafka_protocol::{ApiKey, ApiVersionsRequest, ApiVersionsResponse, DescribeTopicPartitionsRequest, DescribeTopicPartitionsResponse, FetchRequest, FetchResponse, FetchResponsePartition, FetchResponseTopic, KafkaBody, PartitionMetadata, RequestContext, ResponseTopic, TaggedFields};
-
-use crate::broker::traits::RequestProcess;
-use crate::errors::BrokerError;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::common::traits::{Decodable, Encodable};
+use crate::common::primitive_types::{Array, CompactArray, CompactBytes, CompactNullableString, CompactRecords, CompactString};
+use crate::common::kafka_record::RecordBatch;
+use crate::common::compression::{CompressionType, COMPRESSION_CODEC_MASK};
+use crate::common::kafka_protocol::{ApiKey, ApiVersionsRequest, ApiVersionsResponse, Coordinator, CreatableReplicaAssignment, CreatableTopic, CreatableTopicConfigs, CreatableTopicResult, CreatePartitionsRequest, CreatePartitionsResponse, CreatePartitionsTopic, CreatePartitionsTopicResult, CreateTopicsRequest, CreateTopicsResponse, DeletableTopicResult, DeleteTopicsRequest, DeleteTopicsResponse, DescribeTopicPartitionsRequest, DescribeTopicPartitionsResponse, FetchRequest, FetchRequestPartition, FetchResponse, FetchResponsePartition, FetchResponseTopic, FindCoordinatorRequest, FindCoordinatorResponse, HeartbeatRequest, HeartbeatResponse, JoinGroupRequest, JoinGroupResponse, JoinGroupResponseMember, KafkaBody, LeaveGroupRequest, LeaveGroupResponse, ListOffsetsRequest, ListOffsetsRequestPartition, ListOffsetsResponse, ListOffsetsResponsePartition, ListOffsetsResponseTopic, MetadataRequest, MetadataResponse, MetadataResponseBroker, MetadataResponsePartition, MetadataResponseTopic, MemberResponse, OffsetCommitRequest, OffsetCommitResponse, OffsetCommitResponsePartition, OffsetCommitResponseTopic, OffsetDeleteRequest, OffsetDeleteResponse, OffsetDeleteResponsePartition, OffsetDeleteResponseTopic, OffsetFetchRequest, OffsetFetchResponse, OffsetFetchResponseGroup, OffsetFetchResponsePartitions, OffsetFetchResponseTopics, PartitionMetadata, ProduceRequest, ProduceResponse, ProduceResponsePartition, ProduceResponseTopic, RequestContext, ResponseTopic, SyncGroupRequest, SyncGroupResponse, TaggedFields};
+
+use crate::broker::broker::Broker;
+use crate::broker::delayed_fetch::{DelayedFetch, FetchPartitionStatus};
+use crate::broker::traits::{RequestProcess, ResponseCallback};
+use crate::coordinator::group::OffsetAndMetadata;
+use crate::coordinator::group_coordinator::{JoinGroupParams, JoinGroupResult, OffsetCommitParams, SyncGroupParams};
+use crate::coordinator::offset_records::{OFFSETS_TOPIC, OFFSETS_TOPIC_SEGMENT_BYTES};
+use crate::errors::{BrokerError, ErrorCode, LogConfigError};
 use crate::api_versions::get_all_apis;
+use crate::storage::log::{batch_attributes, batch_crc, batch_last_offset_delta, compute_batch_crc, RawBatchIter, CONTROL_FLAG, MAGIC_POS};
+use crate::metadata::image::{MetadataImage, PartitionImage, TopicImage};
+use crate::metadata::loader::METADATA_TOPIC;
+use crate::metadata::writer::{partition_record, remove_topic_record, topic_config_record, topic_record, MetadataWriter, UNASSIGNED_DIRECTORY};
+use crate::utils::{is_internal_topic, valid_topic_name, MAX_TOPIC_NAME_LENGTH};
+use crate::storage::log_manager::TopicPartition;
+use crate::storage::log_segment::now_ms;
 
 use uuid::Uuid;
 
 impl RequestProcess for KafkaBody {
-    fn process(&self) -> Result<KafkaBody, BrokerError> {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
         match self {
             KafkaBody::Request(request) => {
-                request.process()
+                request.process(broker)
             },
             KafkaBody::Response(_) => {
                 Err(BrokerError::UnknownError)
             }
         }
     }
-}
-
-impl RequestProcess for DescribeTopicPartitionsRequest {
-    fn process(&self) -> Result<KafkaBody, BrokerError> {
-        println!("Processing DescribeTopicPartitionsRequest...");
-
-        // open cluster metadata file
-        let metadata_file_path = Path::new("/tmp/kraft-combined-logs/__cluster_metadata-0/00000000000000000000.log");
-        let mut metadata_file = File::open(&metadata_file_path).map_err(|_| BrokerError::UnknownError)?;
-
-        // decode cluster metadata
-        let mut buf: Vec<u8> = Vec::new();
-        metadata_file.read_to_end(&mut buf).map_err(|_| BrokerError::UnknownError)?;
 
-        println!("Initial Buffer length: {:?}", buf.len());
-        println!("Decoding record batch...");
-
-        let mut record_batches: Vec<RecordBatch> = Vec::new();
-        let mut offset = 0;
-
-        let mut context_map: HashMap<String, String> = HashMap::new();
-        context_map.insert("is_metadata_request".to_string(), "true".to_string());
-        let request_context = &RequestContext::Some(context_map);
-
-        while offset < buf.len() {
-            let (record_batch, batch_byte_len) = match RecordBatch::decode(&buf[offset..], request_context) {
-                Ok( (record_batch, batch_byte_len) ) => {
-                    (record_batch, batch_byte_len)
-                }
-                Err(_) => {
-                    return Err(BrokerError::UnknownError);
-                }
-            };
-
-            record_batches.push(record_batch);
-            offset += batch_byte_len;
+    fn handle(&self, broker: &Arc<Broker>, respond: ResponseCallback) {
+        match self {
+            KafkaBody::Request(request) => request.handle(broker, respond),
+            KafkaBody::Response(_) => respond(Err(BrokerError::UnknownError)),
         }
+    }
 
-        // iterate over record values from record batches
-        let topic_name_to_uuid: HashMap<String, Uuid> = record_batches
-            .iter()
-            .flat_map(|record_batch| {
-                record_batch.records.iter().filter_map(|metadata_record| {
-                    match &metadata_record.value {
-                        RecordValue::TopicRecord(topic_record) => {
-                            Some((topic_record.topic_name.data.clone(), topic_record.topic_id))
-                        }
-                        _ => None,
-                    }
-                })
-            })
-            .collect();
-
-        for (topic_name, uuid) in &topic_name_to_uuid {
-            println!("Topic Name: {}, UUID: {}", topic_name, uuid);
+    fn expects_response(&self) -> bool {
+        match self {
+            KafkaBody::Request(request) => request.expects_response(),
+            KafkaBody::Response(_) => false,
         }
+    }
 
-        let mut topic_uuid_to_partitions: HashMap<Uuid, Vec<&PartitionRecord>> = HashMap::new();
-        for record_batch in &record_batches {
-            for metadata_record in &record_batch.records {
-                if let RecordValue::PartitionRecord(partition_record) = &metadata_record.value {
-                    topic_uuid_to_partitions
-                        .entry(partition_record.topic_id)
-                        .or_insert_with(Vec::new)
-                        .push(partition_record);
-                }
-            }
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        match self {
+            KafkaBody::Request(request) => request.error_response(error),
+            KafkaBody::Response(_) => unreachable!("responses are never processed"),
         }
+    }
+}
 
-        for (uuid, temp_partitions) in &topic_uuid_to_partitions {
-            println!("Topic UUID: {}, #Partitions: {}", uuid, temp_partitions.len());
-        }
+impl RequestProcess for DescribeTopicPartitionsRequest {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        println!("Processing DescribeTopicPartitionsRequest...");
+
+        let metadata_image = broker.metadata_image();
 
         // create response
         let mut response_topics: Vec<ResponseTopic> = Vec::new();
@@ -107,7 +75,7 @@ impl RequestProcess for DescribeTopicPartitionsRequest {
             // create response placeholders
             let request_topic_uuid: Uuid = "00000000-0000-0000-0000-000000000000".parse::<Uuid>().unwrap();
             let mut response_topic = ResponseTopic {
-                error_code: 3,
+                error_code: ErrorCode::UnknownTopicOrPartition.code(),
                 name: CompactNullableString {
                     data: Some( CompactString { data: request_topic_name.clone() } )
                 },
@@ -118,38 +86,33 @@ impl RequestProcess for DescribeTopicPartitionsRequest {
                 tagged_fields: TaggedFields(None)
             };
             
-            match topic_name_to_uuid.get(&request_topic_name) {
-                Some(topic_uuid) => {
-                    response_topic.error_code = 0;
-                    response_topic.topic_id = topic_uuid.clone();
-                    
-                    match topic_uuid_to_partitions.get(topic_uuid) {
-                        Some(partitions) => {
-                            let mut response_partitions: Vec<PartitionMetadata> = Vec::new();
-                            for partition in partitions {
-                                let response_partition = PartitionMetadata {
-                                    error_code: 0,
-                                    partition_index: partition.partition_id,
-                                    leader_id: partition.leader,
-                                    leader_epoch: partition.leader_epoch,
-                                    replica_nodes: partition.replica_array.data.clone(),
-                                    isr_nodes: partition.isr_array.data.clone(),
-                                    eligible_leader_replicas: vec![],
-                                    last_known_elr: vec![],
-                                    offline_replicas: vec![],
-                                    tagged_fields: TaggedFields(None),
-                                };
+            match metadata_image.topic_by_name(&request_topic_name) {
+                Some(topic) => {
+                    response_topic.error_code = ErrorCode::None.code();
+                    response_topic.topic_id = topic.topic_id;
+
+                    let mut response_partitions: Vec<PartitionMetadata> = Vec::new();
+                    for partition in topic.partitions.values() {
+                        let response_partition = PartitionMetadata {
+                            error_code: ErrorCode::None.code(),
+                            partition_index: partition.partition_id,
+                            leader_id: partition.leader,
+                            leader_epoch: partition.leader_epoch,
+                            replica_nodes: partition.replicas.clone(),
+                            isr_nodes: partition.isr.clone(),
+                            eligible_leader_replicas: vec![],
+                            last_known_elr: vec![],
+                            offline_replicas: vec![],
+                            tagged_fields: TaggedFields(None),
+                        };
 
-                                response_partitions.push(response_partition);
-                            }
+                        response_partitions.push(response_partition);
+                    }
 
-                            response_topic.partitions = CompactArray { data: response_partitions };
-                        }
-                        None => {}
-                    };
+                    response_topic.partitions = CompactArray { data: response_partitions };
                 },
                 None => {
-                    response_topic.error_code = 3;
+                    response_topic.error_code = ErrorCode::UnknownTopicOrPartition.code();
                 },
             };
 
@@ -168,16 +131,189 @@ impl RequestProcess for DescribeTopicPartitionsRequest {
 
         Ok( response_body )
     }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        let response_topics: Vec<ResponseTopic> = self.topics.data.iter()
+            .map(|request_topic| ResponseTopic {
+                error_code: error.code(),
+                name: CompactNullableString {
+                    data: Some( CompactString { data: request_topic.name.data.clone() } )
+                },
+                topic_id: Uuid::nil(),
+                is_internal: false,
+                partitions: CompactArray { data: vec![] },
+                topic_authorized_operations: 0,
+                tagged_fields: TaggedFields(None)
+            }).collect();
+
+        KafkaBody::Response(Box::new(
+            DescribeTopicPartitionsResponse {
+                throttle_time_ms: 0,
+                topics: CompactArray { data: response_topics },
+                next_cursor: None,
+                tagged_fields: TaggedFields(None),
+            }
+        ))
+    }
+}
+
+// operations allowed on a topic (READ, WRITE, CREATE, DELETE, ALTER, DESCRIBE, DESCRIBE_CONFIGS, ALTER_CONFIGS) as a bit field,
+// there is no authorizer so every client may do all of them
+const TOPIC_AUTHORIZED_OPERATIONS: i32 = (1 << 3) | (1 << 4) | (1 << 5) | (1 << 6) | (1 << 7) | (1 << 8) | (1 << 10) | (1 << 11);
+
+// topic_authorized_operations when the client did not ask for them
+const AUTHORIZED_OPERATIONS_OMITTED: i32 = i32::MIN;
+
+impl RequestProcess for MetadataRequest {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        println!("Processing MetadataRequest...");
+
+        let auto_create_errors = match (&self.topics, self.allow_auto_topic_creation) {
+            (Some(request_topics), true) => {
+                let names: Vec<&str> = request_topics.data.iter()
+                    .filter_map(|request_topic| request_topic.name.data.as_ref().map(|name| name.data.as_str()))
+                    .collect();
+                auto_create_topics(broker, &names)
+            }
+            _ => HashMap::new(),
+        };
+
+        let metadata_image = broker.metadata_image();
+
+        let mut response = MetadataResponse::empty(self.api_version);
+        response.brokers = CompactArray { data: metadata_brokers(broker, &metadata_image) };
+        response.cluster_id = CompactNullableString { data: broker.cluster_id().map(|cluster_id| CompactString::new(cluster_id.to_string())) };
+        // clients only use the controller id to send admin requests, which this broker handles itself
+        response.controller_id = broker.node_id();
+
+        let authorized_operations = match self.include_topic_authorized_operations {
+            true => TOPIC_AUTHORIZED_OPERATIONS,
+            false => AUTHORIZED_OPERATIONS_OMITTED,
+        };
+
+        let topics: Vec<MetadataResponseTopic> = match &self.topics {
+            // every topic
+            None => {
+                let mut topics: Vec<&TopicImage> = metadata_image.topics().collect();
+                topics.sort_by(|a, b| a.name.cmp(&b.name));
+                topics.into_iter().map(|topic| metadata_topic(topic, authorized_operations)).collect()
+            }
+            Some(request_topics) => request_topics.data.iter()
+                .map(|request_topic| {
+                    let name = request_topic.name.data.as_ref().map(|name| name.data.clone());
+
+                    let topic = match &name {
+                        Some(name) if !valid_topic_name(name) => {
+                            return MetadataResponseTopic::error(Some(name.clone()), Uuid::nil(), ErrorCode::InvalidTopicException);
+                        }
+                        Some(name) => metadata_image.topic_by_name(name),
+                        None => metadata_image.topic_by_id(&request_topic.topic_id),
+                    };
+
+                    match topic {
+                        Some(topic) => metadata_topic(topic, authorized_operations),
+                        // not auto-created, or its creation failed
+                        None if name.is_some() => {
+                            let error = name.as_ref().and_then(|name| auto_create_errors.get(name)).copied().unwrap_or(ErrorCode::UnknownTopicOrPartition);
+                            MetadataResponseTopic::error(name, Uuid::nil(), error)
+                        }
+                        None => MetadataResponseTopic::error(None, request_topic.topic_id, ErrorCode::UnknownTopicId),
+                    }
+                })
+                .collect(),
+        };
+        response.topics = CompactArray { data: topics };
+
+        Ok(KafkaBody::Response(Box::new(response)))
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        let mut response = MetadataResponse::empty(self.api_version);
+        if let Some(request_topics) = &self.topics {
+            response.topics.data = request_topics.data.iter()
+                .map(|request_topic| MetadataResponseTopic::error(request_topic.name.data.as_ref().map(|name| name.data.clone()), request_topic.topic_id, error))
+                .collect();
+        }
+        if let Some(error_code) = response.error_code.as_mut() {
+            *error_code = error.code();
+        }
+
+        KafkaBody::Response(Box::new(response))
+    }
+}
+
+// the brokers clients may connect to: the registered, unfenced ones on this broker's listener, and this broker
+// as advertised in its config, which it may not have registered
+fn metadata_brokers(broker: &Broker, metadata_image: &MetadataImage) -> Vec<MetadataResponseBroker> {
+    let advertised_listener = broker.config.advertised_listener();
+
+    let mut brokers: Vec<MetadataResponseBroker> = vec![MetadataResponseBroker {
+        node_id: broker.node_id(),
+        host: CompactString::new(advertised_listener.advertised_host().to_string()),
+        port: advertised_listener.port as i32,
+        rack: CompactNullableString { data: None },
+        tagged_fields: TaggedFields(None),
+    }];
+
+    for registration in metadata_image.brokers() {
+        if registration.fenced || registration.broker_id == broker.node_id() {
+            continue;
+        }
+
+        let endpoint = match registration.endpoints.iter().find(|endpoint| endpoint.name == advertised_listener.listener_name) {
+            Some(endpoint) => endpoint,
+            None => continue,
+        };
+
+        brokers.push(MetadataResponseBroker {
+            node_id: registration.broker_id,
+            host: CompactString::new(endpoint.host.clone()),
+            port: endpoint.port as i32,
+            rack: CompactNullableString { data: registration.rack.clone().map(CompactString::new) },
+            tagged_fields: TaggedFields(None),
+        });
+    }
+
+    brokers
+}
+
+// partitions without a leader report LEADER_NOT_AVAILABLE, clients retry them
+fn metadata_topic(topic: &TopicImage, authorized_operations: i32) -> MetadataResponseTopic {
+    let partitions = topic.partitions.values()
+        .map(|partition| MetadataResponsePartition {
+            error_code: match partition.leader {
+                -1 => ErrorCode::LeaderNotAvailable.code(),
+                _ => ErrorCode::None.code(),
+            },
+            partition_index: partition.partition_id,
+            leader_id: partition.leader,
+            leader_epoch: partition.leader_epoch,
+            replica_nodes: CompactArray::new(partition.replicas.clone()),
+            isr_nodes: CompactArray::new(partition.isr.clone()),
+            offline_replicas: CompactArray::new(vec![]),
+            tagged_fields: TaggedFields(None),
+        })
+        .collect();
+
+    MetadataResponseTopic {
+        error_code: ErrorCode::None.code(),
+        name: CompactNullableString { data: Some(CompactString::new(topic.name.clone())) },
+        topic_id: topic.topic_id,
+        is_internal: is_internal_topic(&topic.name),
+        partitions: CompactArray { data: partitions },
+        topic_authorized_operations: authorized_operations,
+        tagged_fields: TaggedFields(None),
+    }
 }
 
 impl RequestProcess for ApiVersionsRequest {
-    fn process(&self) -> Result<KafkaBody, BrokerError> {
+    fn process(&self, _broker: &Broker) -> Result<KafkaBody, BrokerError> {
         println!("Processing ApiVersionsRequest...");
 
         // create response
         let response_body = KafkaBody::Response(Box::new(
             ApiVersionsResponse {
-                error_code: 0,
+                error_code: ErrorCode::None.code(),
                 api_versions: get_all_apis().iter()
                                 .map(|&(api_key, (min_version, max_version))| ApiKey {
                                     api_key,
@@ -191,199 +327,1479 @@ impl RequestProcess for ApiVersionsRequest {
 
         Ok(response_body)
     }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        KafkaBody::Response(Box::new(api_versions_error_response(error)))
+    }
+}
+
+// ApiVersions response carrying `error`
+// the supported versions are only listed for UNSUPPORTED_VERSION, so the client can pick a version it knows
+pub fn api_versions_error_response(error: ErrorCode) -> ApiVersionsResponse {
+    let api_versions = if error == ErrorCode::UnsupportedVersion {
+        get_all_apis().iter()
+            .map(|&(api_key, (min_version, max_version))| ApiKey {
+                api_key,
+                min_version,
+                max_version,
+                tagged_fields: TaggedFields(None),
+            }).collect()
+    } else {
+        vec![]
+    };
+
+    ApiVersionsResponse {
+        error_code: error.code(),
+        api_versions,
+        throttle_time_ms: 0,
+        tagged_fields: TaggedFields(None),
+    }
 }
 
 impl RequestProcess for FetchRequest {
-    fn process(&self) -> Result<KafkaBody, BrokerError> {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
         println!("Processing FetchRequest...");
 
-        if self.topics.data.len() == 0 {
+        if self.topics.data.is_empty() {
             return Ok( KafkaBody::Response(Box::new(FetchResponse::empty())) )
         }
 
-        // FIXME: Implement OOP for record batch reading
-        // read record batches from log files
-        // open cluster metadata file
-        let metadata_file_path = Path::new("/tmp/kraft-combined-logs/__cluster_metadata-0/00000000000000000000.log");
-        let mut metadata_file = File::open(&metadata_file_path).map_err(|_| BrokerError::UnknownError)?;
-
-        // decode cluster metadata
-        let mut buf: Vec<u8> = Vec::new();
-        metadata_file.read_to_end(&mut buf).map_err(|_| BrokerError::UnknownError)?;
+        let metadata_image = broker.metadata_image();
 
-        println!("Initial Buffer length: {:?}", buf.len());
-        println!("Decoding record batch...");
+        let mut response = FetchResponse::empty();
 
-        let mut metadata_record_batches: Vec<RecordBatch> = Vec::new();
-        let mut offset = 0;
+        // max_bytes is shared by all partitions of the response
+        let mut response_bytes_left = self.max_bytes.max(0) as usize;
+        let mut response_has_records = false;
 
-        let mut context_map: HashMap<String, String> = HashMap::new();
-        context_map.insert("is_metadata_request".to_string(), "true".to_string());
-        let request_context = &RequestContext::Some(context_map);
+        // check topic existence
+        for topic in &self.topics.data {
+            let topic_id = topic.topic_id;
+            let mut response_topic = FetchResponseTopic {
+                topic_id,
+                partitions: CompactArray { data: vec![] },
+                tagged_fields: TaggedFields(None),
+            };
 
-        while offset < buf.len() {
-            let (record_batch, batch_byte_len) = match RecordBatch::decode(&buf[offset..], request_context) {
-                Ok( (record_batch, batch_byte_len) ) => {
-                    (record_batch, batch_byte_len)
+            match metadata_image.topic_by_id(&topic_id) {
+                None => {
+                    for fetch_partition in &topic.partitions.data {
+                        response_topic.partitions.data.push(FetchResponsePartition::error(fetch_partition.partition, ErrorCode::UnknownTopicId));
+                    }
                 }
-                Err(_) => {
-                    return Err(BrokerError::UnknownError);
+                Some(topic_image) => {
+                    // read each requested partition from its log, starting at the fetch offset
+                    for fetch_partition in &topic.partitions.data {
+                        if !topic_image.has_partition(fetch_partition.partition) {
+                            response_topic.partitions.data.push(FetchResponsePartition::error(fetch_partition.partition, ErrorCode::UnknownTopicOrPartition));
+                            continue;
+                        }
+
+                        let topic_partition = TopicPartition::new(&topic_image.name, fetch_partition.partition);
+                        let partition = read_partition(broker, &metadata_image, &topic_partition, fetch_partition, self.isolation_level, response_bytes_left, !response_has_records);
+
+                        let records_len = partition.records.data.as_ref().map_or(0, |records| records.len());
+                        response_bytes_left = response_bytes_left.saturating_sub(records_len);
+                        response_has_records |= records_len > 0;
+
+                        response_topic.partitions.data.push(partition);
+                    }
                 }
-            };
+            }
 
-            metadata_record_batches.push(record_batch);
-            offset += batch_byte_len;
+            response.responses.data.push(response_topic);
         }
 
-        // iterate over record values from record batches
-        let topic_name_to_uuid: HashMap<String, Uuid> = metadata_record_batches
-            .iter()
-            .flat_map(|record_batch| {
-                record_batch.records.iter().filter_map(|metadata_record| {
-                    match &metadata_record.value {
-                        RecordValue::TopicRecord(topic_record) => {
-                            Some((topic_record.topic_name.data.clone(), topic_record.topic_id))
-                        }
-                        _ => None,
-                    }
-                })
-            })
-            .collect();
+        Ok( KafkaBody::Response(Box::new(response)) )
 
-        for (topic_name, uuid) in &topic_name_to_uuid {
-            println!("Topic Name: {}, UUID: {}", topic_name, uuid);
+        // Err(BrokerError::UnknownError)
+    }
+
+    fn handle(&self, broker: &Arc<Broker>, respond: ResponseCallback) {
+        // nothing to wait for
+        if self.max_wait_ms <= 0 || self.min_bytes <= 0 || self.topics.data.is_empty() {
+            return respond(self.process(broker));
         }
 
-        let mut topic_uuid_to_partitions: HashMap<Uuid, Vec<&PartitionRecord>> = HashMap::new();
-        for record_batch in &metadata_record_batches {
-            for metadata_record in &record_batch.records {
-                if let RecordValue::PartitionRecord(partition_record) = &metadata_record.value {
-                    topic_uuid_to_partitions
-                        .entry(partition_record.topic_id)
-                        .or_insert_with(Vec::new)
-                        .push(partition_record);
-                }
+        let fetch_partitions = match resolve_fetch_partitions(broker, self) {
+            Some(fetch_partitions) => fetch_partitions,
+            // unknown topics and partitions are reported right away
+            None => return respond(self.process(broker)),
+        };
+
+        // park the fetch until enough data is appended to the partitions it reads, or max_wait_ms elapses
+        let keys: Vec<TopicPartition> = fetch_partitions.iter().map(|status| status.topic_partition.clone()).collect();
+        let delayed_fetch = DelayedFetch::new(Arc::clone(broker), self.clone(), fetch_partitions, respond);
+        broker.fetch_purgatory.try_complete_else_watch(Box::new(delayed_fetch), Duration::from_millis(self.max_wait_ms as u64), keys);
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        let mut response = FetchResponse::empty();
+        response.error_code = error.code();
+
+        for topic in &self.topics.data {
+            response.responses.data.push(FetchResponseTopic {
+                topic_id: topic.topic_id,
+                partitions: CompactArray {
+                    data: topic.partitions.data.iter()
+                        .map(|fetch_partition| FetchResponsePartition::error(fetch_partition.partition, error))
+                        .collect()
+                },
+                tagged_fields: TaggedFields(None),
+            });
+        }
+
+        KafkaBody::Response(Box::new(response))
+    }
+}
+
+// map the requested topic ids to partitions of known topics
+// returns None if any requested topic or partition does not exist
+fn resolve_fetch_partitions(broker: &Broker, request: &FetchRequest) -> Option<Vec<FetchPartitionStatus>> {
+    let metadata_image = broker.metadata_image();
+
+    let mut fetch_partitions: Vec<FetchPartitionStatus> = Vec::new();
+    for topic in &request.topics.data {
+        let topic_image = metadata_image.topic_by_id(&topic.topic_id)?;
+
+        for partition in &topic.partitions.data {
+            if !topic_image.has_partition(partition.partition) {
+                return None;
             }
+
+            fetch_partitions.push(FetchPartitionStatus {
+                topic_partition: TopicPartition::new(&topic_image.name, partition.partition),
+                fetch_offset: partition.fetch_offset,
+                partition_max_bytes: partition.partition_max_bytes,
+            });
         }
+    }
+
+    Some(fetch_partitions)
+}
 
-        for (uuid, temp_partitions) in &topic_uuid_to_partitions {
-            println!("Topic UUID: {}, #Partitions: {}", uuid, temp_partitions.len());
+// read one partition starting with the batch that contains the fetch offset, without exceeding
+// partition_max_bytes or the bytes left in the response
+// consumers only see records up to the high watermark, or up to the last stable offset with read_committed (isolation level 1)
+// with `min_one_batch`, the first batch is returned even when it is larger than the limits so consumers can make progress
+fn read_partition(broker: &Broker, metadata_image: &MetadataImage, topic_partition: &TopicPartition, fetch_partition: &FetchRequestPartition, isolation_level: i8, response_bytes_left: usize, min_one_batch: bool) -> FetchResponsePartition {
+    let topic_config = metadata_image.topic_configs(&topic_partition.topic);
+    let directories = metadata_image.topic_by_name(&topic_partition.topic)
+        .and_then(|topic_image| topic_image.partition(topic_partition.partition))
+        .map_or(&[][..], |partition| &partition.directories);
+
+    let log = match broker.log_manager.get_or_open(topic_partition, topic_config, directories) {
+        Ok(log) => log,
+        Err(e) => {
+            println!("Error opening log for {}: {}", topic_partition.dir_name(), e);
+            return FetchResponsePartition::error(topic_partition.partition, ErrorCode::from(e));
         }
+    };
+    let log = log.lock().unwrap();
 
-        let mut response = FetchResponse::empty();
+    if fetch_partition.fetch_offset < log.log_start_offset() || fetch_partition.fetch_offset > log.log_end_offset() {
+        println!("Fetch offset {} out of range [{}, {}] for {}", fetch_partition.fetch_offset, log.log_start_offset(), log.log_end_offset(), topic_partition.dir_name());
+        return FetchResponsePartition::error(topic_partition.partition, ErrorCode::OffsetOutOfRange);
+    }
 
-        // check topic existence
+    let max_offset = if isolation_level == 1 { log.last_stable_offset() } else { log.high_watermark() };
+    let max_bytes = (fetch_partition.partition_max_bytes.max(0) as usize).min(response_bytes_left);
+    let records = match log.read(fetch_partition.fetch_offset, max_offset, max_bytes, min_one_batch) {
+        Ok(records) => records,
+        Err(e) => {
+            println!("Error reading log {}: {}", topic_partition.dir_name(), e);
+            return FetchResponsePartition::error(topic_partition.partition, ErrorCode::from(e));
+        }
+    };
+
+    println!("Read {} bytes from {} at offset {}", records.len(), topic_partition.dir_name(), fetch_partition.fetch_offset);
+
+    FetchResponsePartition {
+        partition_index: topic_partition.partition,
+        error_code: ErrorCode::None.code(),
+        high_watermark: log.high_watermark(),
+        last_stable_offset: log.last_stable_offset(),
+        log_start_offset: log.log_start_offset(),
+        aborted_transactions: CompactArray { data: vec![] },
+        preferred_read_replica: -1,
+        records: CompactRecords::new(Some(records)),
+        tagged_fields: TaggedFields(None),
+    }
+}
+
+// ListOffsets timestamps asking for an offset rather than looking one up by time
+const LATEST_TIMESTAMP: i64 = -1;
+const EARLIEST_TIMESTAMP: i64 = -2;
+const MAX_TIMESTAMP: i64 = -3;
+// there is no tiered storage, the whole log is local and nothing is tiered
+const EARLIEST_LOCAL_TIMESTAMP: i64 = -4;
+const LATEST_TIERED_TIMESTAMP: i64 = -5;
+
+impl RequestProcess for ListOffsetsRequest {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        println!("Processing ListOffsetsRequest...");
+
+        let metadata_image = broker.metadata_image();
+
+        let mut response = ListOffsetsResponse::empty();
+
+        // a partition asked for more than once is answered with INVALID_REQUEST every time
+        let mut requested: HashMap<(&str, i32), usize> = HashMap::new();
         for topic in &self.topics.data {
-            let topic_id = topic.topic_id;
-            let mut response_topic = FetchResponseTopic {
-                topic_id: topic_id,
+            for partition in &topic.partitions.data {
+                *requested.entry((topic.name.data.as_str(), partition.partition_index)).or_insert(0) += 1;
+            }
+        }
+
+        for topic in &self.topics.data {
+            let mut response_topic = ListOffsetsResponseTopic {
+                name: topic.name.clone(),
                 partitions: CompactArray { data: vec![] },
                 tagged_fields: TaggedFields(None),
             };
 
-            if !topic_uuid_to_partitions.contains_key(&topic_id) {
-                let partition = FetchResponsePartition {
-                    partition_index: 0,
-                    error_code: 100, 
-                    high_watermark: 0,
-                    last_stable_offset: 0,
-                    log_start_offset: 0,
-                    aborted_transactions: CompactArray { data: vec![] },
-                    preferred_read_replica: -1,
-                    records: CompactArray { data: vec![] },
-                    tagged_fields: TaggedFields(None),
+            let topic_image = metadata_image.topic_by_name(&topic.name.data);
+
+            for partition in &topic.partitions.data {
+                let response_partition = match topic_image.and_then(|topic_image| topic_image.partition(partition.partition_index)) {
+                    _ if requested[&(topic.name.data.as_str(), partition.partition_index)] > 1 => {
+                        println!("Partition {}-{} listed more than once", topic.name.data, partition.partition_index);
+                        ListOffsetsResponsePartition::error(partition.partition_index, ErrorCode::InvalidRequest)
+                    }
+                    Some(partition_image) => {
+                        let topic_partition = TopicPartition::new(&topic.name.data, partition.partition_index);
+                        list_partition_offset(broker, &metadata_image, &topic_partition, partition_image, partition, self.isolation_level)
+                    }
+                    None => {
+                        println!("Unknown topic or partition: {}-{}", topic.name.data, partition.partition_index);
+                        ListOffsetsResponsePartition::error(partition.partition_index, ErrorCode::UnknownTopicOrPartition)
+                    }
                 };
 
-                response_topic.partitions.data.push(partition);
-            } else {
-                // topic exists
-                // go to the topic partition folder
-                // find the log file and read the records
-                // construct the response
-
-                // find topic name from UUID
-                let topic_name = topic_name_to_uuid.iter()
-                    .find(|(_, &uuid)| uuid == topic_id)
-                    .map(|(name, _)| name)
-                    .unwrap();
-
-                // find number of available partitions
-                let num_partitions = topic_uuid_to_partitions.get(&topic_id).unwrap().len();
-
-                let mut partitions_to_fetch: Vec<i32> = Vec::new();
-                for p in &topic.partitions.data {
-                    partitions_to_fetch.push(p.partition);
-                }
+                response_topic.partitions.data.push(response_partition);
+            }
 
-                for i in 0..num_partitions {
+            response.topics.data.push(response_topic);
+        }
 
-                    if !partitions_to_fetch.contains(&(i as i32)) {
-                        continue;
-                    }
+        Ok( KafkaBody::Response(Box::new(response)) )
+    }
 
-                    let log_file_path_str = format!("/tmp/kraft-combined-logs/{}/00000000000000000000.log", topic_name.to_owned()+"-"+&i.to_string());
-                    let log_file_path = Path::new(&log_file_path_str);
-                    let mut log_file = File::open(log_file_path).map_err(|_| BrokerError::UnknownError)?;
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        let mut response = ListOffsetsResponse::empty();
 
-                    // decode log file
-                    let mut log_buf: Vec<u8> = Vec::new();
-                    log_file.read_to_end(&mut log_buf).map_err(|_| BrokerError::UnknownError)?;
+        for topic in &self.topics.data {
+            response.topics.data.push(ListOffsetsResponseTopic {
+                name: topic.name.clone(),
+                partitions: CompactArray {
+                    data: topic.partitions.data.iter()
+                        .map(|partition| ListOffsetsResponsePartition::error(partition.partition_index, error))
+                        .collect()
+                },
+                tagged_fields: TaggedFields(None),
+            });
+        }
 
-                    println!("Initial Log Buffer length: {:?}", log_buf.len());
-                    println!("Decoding record batch...");
+        KafkaBody::Response(Box::new(response))
+    }
+}
 
-                    let mut topic_record_batches: Vec<RecordBatch> = Vec::new();
-                    let mut offset = 0;
+// resolve the offset a ListOffsets partition asks for
+// latest is the high watermark, or the last stable offset with read_committed (isolation level 1), and offsets
+// found by timestamp past it are not returned; a timestamp no record reaches gives offset and timestamp -1
+// the leader epoch is the partition's current one, there is no epoch cache to tell in which epoch an offset was written
+fn list_partition_offset(broker: &Broker, metadata_image: &MetadataImage, topic_partition: &TopicPartition, partition_image: &PartitionImage, request_partition: &ListOffsetsRequestPartition, isolation_level: i8) -> ListOffsetsResponsePartition {
+    let partition_index = topic_partition.partition;
+    let leader_epoch = partition_image.leader_epoch;
+
+    // -1 means the client does not know the epoch
+    let current_leader_epoch = request_partition.current_leader_epoch;
+    if current_leader_epoch >= 0 && current_leader_epoch != leader_epoch {
+        println!("Leader epoch {} of {} does not match the current epoch {}", current_leader_epoch, topic_partition.dir_name(), leader_epoch);
+        let error = if current_leader_epoch < leader_epoch { ErrorCode::FencedLeaderEpoch } else { ErrorCode::UnknownLeaderEpoch };
+        return ListOffsetsResponsePartition::error(partition_index, error);
+    }
 
-                    let mut context_map: HashMap<String, String> = HashMap::new();
-                    context_map.insert("is_metadata_request".to_string(), "false".to_string());
-                    let request_context = &RequestContext::Some(context_map);
+    let log = match broker.log_manager.get_or_open(topic_partition, metadata_image.topic_configs(&topic_partition.topic), &partition_image.directories) {
+        Ok(log) => log,
+        Err(e) => {
+            println!("Error opening log for {}: {}", topic_partition.dir_name(), e);
+            return ListOffsetsResponsePartition::error(partition_index, ErrorCode::from(e));
+        }
+    };
+    let log = log.lock().unwrap();
+
+    let last_fetchable_offset = if isolation_level == 1 { log.last_stable_offset() } else { log.high_watermark() };
+
+    let found = match request_partition.timestamp {
+        LATEST_TIMESTAMP => Ok(Some((-1, last_fetchable_offset))),
+        EARLIEST_TIMESTAMP | EARLIEST_LOCAL_TIMESTAMP => Ok(Some((-1, log.log_start_offset()))),
+        LATEST_TIERED_TIMESTAMP => Ok(None),
+        timestamp => {
+            let found = if timestamp == MAX_TIMESTAMP { log.fetch_offset_of_max_timestamp() } else { log.fetch_offset_by_timestamp(timestamp) };
+            found.map(|found| found.filter(|&(_, offset)| offset < last_fetchable_offset))
+        }
+    };
+
+    match found {
+        Ok(Some((timestamp, offset))) => {
+            println!("Listed offset {} for {} at timestamp {}", offset, topic_partition.dir_name(), request_partition.timestamp);
+
+            ListOffsetsResponsePartition {
+                partition_index,
+                error_code: ErrorCode::None.code(),
+                timestamp,
+                offset,
+                leader_epoch,
+                tagged_fields: TaggedFields(None),
+            }
+        }
+        Ok(None) => ListOffsetsResponsePartition::error(partition_index, ErrorCode::None),
+        Err(e) => {
+            println!("Error looking up timestamp {} in {}: {}", request_partition.timestamp, topic_partition.dir_name(), e);
+            ListOffsetsResponsePartition::error(partition_index, ErrorCode::from(e))
+        }
+    }
+}
 
-                    while offset < log_buf.len() {
-                        let (record_batch, batch_byte_len) = match RecordBatch::decode(&log_buf[offset..], request_context) {
-                            Ok( (record_batch, batch_byte_len) ) => {
-                                (record_batch, batch_byte_len)
-                            }
-                            Err(_) => {
-                                return Err(BrokerError::UnknownError);
-                            }
-                        };
+impl RequestProcess for ProduceRequest {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        println!("Processing ProduceRequest...");
 
-                        topic_record_batches.push(record_batch);
-                        offset += batch_byte_len;
-                    }
+        // only -1 (all), 0 (none) and 1 (leader) are valid, nothing is appended otherwise
+        if !(-1..=1).contains(&self.acks) {
+            println!("Invalid required acks {}", self.acks);
+            return Ok( self.error_response(ErrorCode::InvalidRequiredAcks) );
+        }
 
-                    let mut partition = FetchResponsePartition {
-                        partition_index: i as i32,
-                        error_code: 0, 
-                        high_watermark: 0,
-                        last_stable_offset: 0,
-                        log_start_offset: 0,
-                        aborted_transactions: CompactArray { data: vec![] },
-                        preferred_read_replica: -1,
-                        records: CompactArray { data: vec![] },
-                        tagged_fields: TaggedFields(None),
-                    };
+        let names: Vec<&str> = self.topic_data.data.iter().map(|topic| topic.name.data.as_str()).collect();
+        auto_create_topics(broker, &names);
+
+        // the topic configs and partition directories the appends need, copied out so the metadata image is
+        // released first: waking delayed fetches reads it again, which would queue behind a waiting metadata writer
+        let topic_images = {
+            let metadata_image = broker.metadata_image();
+            self.topic_data.data.iter()
+                .map(|topic| metadata_image.topic_by_name(&topic.name.data).map(|topic_image| (
+                    metadata_image.topic_configs(&topic.name.data).cloned(),
+                    topic_image.partitions.iter().map(|(&partition_id, partition)| (partition_id, partition.directories.clone())).collect::<HashMap<_, _>>(),
+                )))
+                .collect::<Vec<_>>()
+        };
+
+        let mut response = ProduceResponse::empty();
+
+        for (topic, topic_image) in self.topic_data.data.iter().zip(&topic_images) {
+            let mut response_topic = ProduceResponseTopic {
+                name: topic.name.clone(),
+                partition_responses: CompactArray { data: vec![] },
+                tagged_fields: TaggedFields(None),
+            };
 
-                    println!("\n\n\n\n\n\n\n Number of record batches: {:?} \n\n\n\n\n\n", topic_record_batches.len());
-                    if topic_record_batches.len() > 0 {
-                        for record_batch in topic_record_batches {
-                            partition.records.data.push(record_batch);
-                        }
+            for partition in &topic.partition_data.data {
+                let directories = topic_image.as_ref().and_then(|(_, partitions)| partitions.get(&partition.index));
+                let response_partition = match (topic_image, directories) {
+                    (Some((topic_config, _)), Some(directories)) => {
+                        let topic_partition = TopicPartition::new(&topic.name.data, partition.index);
+                        append_to_partition(broker, &topic_partition, topic_config.as_ref(), directories, &partition.records)
                     }
+                    _ => {
+                        println!("Unknown topic or partition: {}-{}", topic.name.data, partition.index);
+                        ProduceResponsePartition::error(partition.index, ErrorCode::UnknownTopicOrPartition)
+                    }
+                };
 
-                    response_topic.partitions.data.push(partition);
-
-                }
-
+                response_topic.partition_responses.data.push(response_partition);
             }
 
             response.responses.data.push(response_topic);
-            
         }
 
         Ok( KafkaBody::Response(Box::new(response)) )
+    }
 
-        // Err(BrokerError::UnknownError)
+    fn expects_response(&self) -> bool {
+        self.acks != 0
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        let mut response = ProduceResponse::empty();
+
+        for topic in &self.topic_data.data {
+            response.responses.data.push(ProduceResponseTopic {
+                name: topic.name.clone(),
+                partition_responses: CompactArray {
+                    data: topic.partition_data.data.iter()
+                        .map(|partition| ProduceResponsePartition::error(partition.index, error))
+                        .collect()
+                },
+                tagged_fields: TaggedFields(None),
+            });
+        }
+
+        KafkaBody::Response(Box::new(response))
+    }
+}
+
+// validate the produced record batches and append them to the partition log
+fn append_to_partition(broker: &Broker, topic_partition: &TopicPartition, topic_config: Option<&HashMap<String, String>>, directories: &[Uuid], records: &CompactRecords) -> ProduceResponsePartition {
+    let log_config = broker.log_manager.log_config(topic_config);
+    let batches = match validate_record_batches(records, log_config.target_compression(), log_config.max_message_bytes) {
+        Ok(batches) => batches,
+        Err(error) => {
+            println!("Rejecting records for {}: {}", topic_partition.dir_name(), error.name());
+            return ProduceResponsePartition::error(topic_partition.partition, error);
+        }
+    };
+
+    let log = match broker.log_manager.get_or_open(topic_partition, topic_config, directories) {
+        Ok(log) => log,
+        Err(e) => {
+            println!("Error opening log for {}: {}", topic_partition.dir_name(), e);
+            return ProduceResponsePartition::error(topic_partition.partition, ErrorCode::from(e));
+        }
+    };
+    let mut log = log.lock().unwrap();
+
+    let response_partition = match log.append(batches) {
+        Ok(base_offset) => {
+            println!("Appended records to {} at base offset {}", topic_partition.dir_name(), base_offset);
+
+            let mut response_partition = ProduceResponsePartition::error(topic_partition.partition, ErrorCode::None);
+            response_partition.base_offset = base_offset;
+            response_partition.log_start_offset = log.log_start_offset();
+            response_partition
+        }
+        Err(e) => {
+            println!("Error appending to log {}: {}", topic_partition.dir_name(), e);
+            return ProduceResponsePartition::error(topic_partition.partition, ErrorCode::from(e));
+        }
+    };
+
+    // delayed fetches read the log, so wake them once the lock is released
+    drop(log);
+    broker.fetch_purgatory.check_and_complete(topic_partition);
+
+    response_partition
+}
+
+// split the produced records into record batches and check each one is well formed,
+// recompressing them when the topic's compression.type is set and differs from the producer's codec
+// batches larger than max.message.bytes, or whose records decompress to more, are rejected
+// returns the batches to append, or the error code to send back for the partition
+fn validate_record_batches(records: &CompactRecords, target_compression: Option<CompressionType>, max_message_bytes: i32) -> Result<Vec<Vec<u8>>, ErrorCode> {
+    let data = match &records.data {
+        Some(data) if !data.is_empty() => data,
+        _ => return Err(ErrorCode::InvalidRecord),
+    };
+
+    // the CRC is checked below on the raw batch, before decoding
+    let mut context_map: HashMap<String, String> = HashMap::new();
+    context_map.insert("is_metadata_request".to_string(), "false".to_string());
+    context_map.insert("skip_crc_validation".to_string(), "true".to_string());
+    context_map.insert("max_decompressed_bytes".to_string(), max_message_bytes.to_string());
+    let request_context = &RequestContext::Some(context_map);
+
+    let mut batches: Vec<Vec<u8>> = Vec::new();
+    let mut batch_iter = RawBatchIter::new(data);
+
+    for batch in batch_iter.by_ref() {
+        if batch[MAGIC_POS] != 2 {
+            return Err(ErrorCode::UnsupportedForMessageFormat);
+        }
+
+        if batch.len() > max_message_bytes as usize {
+            println!("Record batch of {} bytes is larger than max.message.bytes {}", batch.len(), max_message_bytes);
+            return Err(ErrorCode::MessageTooLarge);
+        }
+
+        // checked on the raw bytes so compressed batches are covered too
+        if batch_crc(batch) != compute_batch_crc(batch) {
+            return Err(ErrorCode::CorruptMessage);
+        }
+
+        let attributes = batch_attributes(batch);
+        let compression_type = CompressionType::from_attributes(attributes).ok_or(ErrorCode::UnsupportedCompressionType)?;
+        let (mut record_batch, _) = RecordBatch::decode(batch, request_context).map_err(|_| ErrorCode::CorruptMessage)?;
+
+        // offsets inside a batch must be consecutive, starting at 0
+        if record_batch.records.len() as i32 != batch_last_offset_delta(batch) + 1 {
+            return Err(ErrorCode::InvalidRecord);
+        }
+        for (index, record) in record_batch.records.iter().enumerate() {
+            if record.offset_delta.data != index as i32 {
+                return Err(ErrorCode::InvalidRecord);
+            }
+        }
+
+        match target_compression {
+            Some(target_compression) if target_compression != compression_type && attributes & CONTROL_FLAG == 0 => {
+                println!("Recompressing batch from {} to {}", compression_type.name(), target_compression.name());
+                record_batch.attributes = (attributes & !COMPRESSION_CODEC_MASK) | target_compression.id();
+                batches.push(record_batch.encode());
+            }
+            _ => batches.push(batch.to_vec()),
+        }
+    }
+
+    // trailing bytes that do not form a complete batch
+    if batches.is_empty() || batch_iter.position() != data.len() {
+        return Err(ErrorCode::CorruptMessage);
     }
-}
\ No newline at end of file
+
+    Ok(batches)
+}
+
+// ConfigSource.DYNAMIC_TOPIC_CONFIG, a config set on the topic
+const DYNAMIC_TOPIC_CONFIG: i8 = 1;
+
+impl RequestProcess for CreateTopicsRequest {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        println!("Processing CreateTopicsRequest...");
+
+        // held until every topic is created, so a name is not taken between its validation and its records
+        let mut metadata_writer = broker.metadata_writer.lock().unwrap();
+
+        let mut response = CreateTopicsResponse::empty();
+
+        // a topic asked for more than once is answered with INVALID_REQUEST every time
+        let mut requested: HashMap<&str, usize> = HashMap::new();
+        for topic in &self.topics.data {
+            *requested.entry(topic.name.data.as_str()).or_insert(0) += 1;
+        }
+
+        for topic in &self.topics.data {
+            let name = &topic.name.data;
+
+            let result = if requested[name.as_str()] > 1 {
+                Err((ErrorCode::InvalidRequest, format!("Topic {} is listed more than once.", name)))
+            } else {
+                create_topic(broker, &mut metadata_writer, topic, self.api_version, self.validate_only)
+            };
+
+            let result = match result {
+                Ok(result) => result,
+                Err((error, error_message)) => {
+                    println!("Not creating topic {}: {}", name, error_message);
+                    CreatableTopicResult::error(name, self.api_version, error, Some(error_message))
+                }
+            };
+            response.topics.data.push(result);
+        }
+
+        Ok( KafkaBody::Response(Box::new(response)) )
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        let mut response = CreateTopicsResponse::empty();
+        response.topics.data = self.topics.data.iter()
+            .map(|topic| CreatableTopicResult::error(&topic.name.data, self.api_version, error, None))
+            .collect();
+
+        KafkaBody::Response(Box::new(response))
+    }
+}
+
+// validate a topic and, unless `validate_only`, write its TopicRecord, PartitionRecords and ConfigRecords
+// in one batch and create the logs of the partitions this broker is a replica of
+// num_partitions and replication_factor of -1 take num.partitions and default.replication.factor
+fn create_topic(broker: &Broker, metadata_writer: &mut MetadataWriter, topic: &CreatableTopic, api_version: i16, validate_only: bool) -> Result<CreatableTopicResult, (ErrorCode, String)> {
+    let name = &topic.name.data;
+
+    // the topic's config overrides, as ConfigRecords will set them
+    let mut configs: Vec<(&str, &str)> = Vec::new();
+    let mut log_config = broker.log_manager.log_config(None);
+    for config in &topic.configs.data {
+        let value = match &config.value.data {
+            Some(value) => &value.data,
+            None => return Err((ErrorCode::InvalidConfig, format!("Null value not supported for topic config {}.", config.name.data))),
+        };
+        match log_config.set(&config.name.data, value) {
+            Ok(()) => {}
+            Err(LogConfigError::UnknownConfig) => return Err((ErrorCode::InvalidConfig, format!("Unknown topic config name: {}", config.name.data))),
+            Err(LogConfigError::InvalidValue) => return Err((ErrorCode::InvalidConfig, format!("Invalid value {} for configuration {}.", value, config.name.data))),
+        }
+        configs.push((&config.name.data, value));
+    }
+
+    let assignments: Vec<Vec<i32>> = {
+        let metadata_image = broker.metadata_image();
+
+        validate_new_topic_name(&metadata_image, name)?;
+
+        let brokers = replica_brokers(broker, &metadata_image);
+
+        if topic.assignments.data.is_empty() {
+            let num_partitions = match topic.num_partitions {
+                -1 => broker.config.num_partitions,
+                num_partitions if num_partitions <= 0 => return Err((ErrorCode::InvalidPartitions, "Number of partitions was set to an invalid non-positive value.".to_string())),
+                num_partitions => num_partitions,
+            };
+            let replication_factor = match topic.replication_factor {
+                -1 => broker.config.default_replication_factor,
+                replication_factor if replication_factor <= 0 => return Err((ErrorCode::InvalidReplicationFactor, "Replication factor must be larger than 0, or -1 to use the default value.".to_string())),
+                replication_factor => replication_factor,
+            };
+
+            assign_replicas(&brokers, 0, num_partitions, replication_factor)?
+        } else {
+            if topic.num_partitions != -1 || topic.replication_factor != -1 {
+                return Err((ErrorCode::InvalidRequest, "Both numPartitions or replicationFactor and replicasAssignments were set. Both cannot be used at the same time.".to_string()));
+            }
+
+            let mut assignments: Vec<&CreatableReplicaAssignment> = topic.assignments.data.iter().collect();
+            assignments.sort_by_key(|assignment| assignment.partition_index);
+            for (partition_id, assignment) in assignments.iter().enumerate() {
+                if assignment.partition_index != partition_id as i32 {
+                    return Err((ErrorCode::InvalidReplicaAssignment, "Partitions should be a consecutive 0-based integer sequence.".to_string()));
+                }
+            }
+
+            let assignments: Vec<Vec<i32>> = assignments.into_iter().map(|assignment| assignment.broker_ids.data.clone()).collect();
+            validate_assignments(&brokers, &assignments, None)?;
+            assignments
+        }
+    };
+
+    let mut result = CreatableTopicResult {
+        name: topic.name.clone(),
+        topic_id: (api_version >= 7).then_some(Uuid::nil()),
+        error_code: ErrorCode::None.code(),
+        error_message: CompactNullableString { data: None },
+        num_partitions: assignments.len() as i32,
+        replication_factor: assignments[0].len() as i16,
+        // only the overrides, the broker defaults are left out
+        configs: Some(CompactArray {
+            data: configs.iter()
+                .map(|&(name, value)| CreatableTopicConfigs {
+                    name: CompactString::new(name.to_string()),
+                    value: CompactNullableString { data: Some(CompactString::new(value.to_string())) },
+                    read_only: false,
+                    config_source: DYNAMIC_TOPIC_CONFIG,
+                    is_sensitive: false,
+                    tagged_fields: TaggedFields(None),
+                })
+                .collect()
+        }),
+        tagged_fields: TaggedFields(None),
+    };
+
+    if validate_only {
+        println!("Validated topic {} with {} partitions", name, assignments.len());
+        return Ok(result);
+    }
+
+    let topic_id = write_topic(broker, metadata_writer, name, &assignments, &configs)?;
+
+    if let Some(result_topic_id) = result.topic_id.as_mut() {
+        *result_topic_id = topic_id;
+    }
+    Ok(result)
+}
+
+// the name of a topic about to be created must be legal, not taken, and not collide with an existing one
+fn validate_new_topic_name(metadata_image: &MetadataImage, name: &str) -> Result<(), (ErrorCode, String)> {
+    if !valid_topic_name(name) {
+        return Err((ErrorCode::InvalidTopicException, format!("Topic name {:?} is illegal, it must be 1 to {} characters of ASCII alphanumerics, '.', '_' and '-', other than \".\" and \"..\".", name, MAX_TOPIC_NAME_LENGTH)));
+    }
+    if name == METADATA_TOPIC {
+        return Err((ErrorCode::InvalidRequest, format!("Creation of internal topic {} is prohibited.", name)));
+    }
+    if metadata_image.topic_by_name(name).is_some() {
+        return Err((ErrorCode::TopicAlreadyExists, format!("Topic '{}' already exists.", name)));
+    }
+    // '.' and '_' are the same character in metric names
+    if let Some(existing) = metadata_image.topics().find(|existing| unify_collision_chars(&existing.name) == unify_collision_chars(name)) {
+        return Err((ErrorCode::InvalidTopicException, format!("Topic '{}' collides with existing topic: {}", name, existing.name)));
+    }
+
+    Ok(())
+}
+
+// write a new topic's TopicRecord, PartitionRecords and ConfigRecords in one batch, then create the logs
+// of the partitions this broker is a replica of; returns the new topic id
+fn write_topic(broker: &Broker, metadata_writer: &mut MetadataWriter, name: &str, assignments: &[Vec<i32>], configs: &[(&str, &str)]) -> Result<Uuid, (ErrorCode, String)> {
+    let topic_id = Uuid::new_v4();
+    let directories = replica_directories(broker, assignments)?;
+
+    let mut records = vec![topic_record(name, topic_id)];
+    for (partition_id, (replicas, directories)) in assignments.iter().zip(&directories).enumerate() {
+        records.push(partition_record(topic_id, partition_id as i32, replicas.clone(), directories.clone()));
+    }
+    for &(config_name, value) in configs {
+        records.push(topic_config_record(name, config_name, Some(value)));
+    }
+    metadata_writer.append(records)
+        .map_err(|e| (ErrorCode::from(&e), format!("Error writing the metadata log: {}", e)))?;
+    println!("Created topic {} ({}) with {} partitions", name, topic_id, assignments.len());
+
+    let topic_config: HashMap<String, String> = configs.iter()
+        .map(|&(config_name, value)| (config_name.to_string(), value.to_string()))
+        .collect();
+    create_partition_logs(broker, name, 0, assignments, &directories, Some(&topic_config));
+
+    Ok(topic_id)
+}
+
+// auto.create.topics.enable: create the topics in `names` that do not exist yet with num.partitions and
+// default.replication.factor, before the request reads the metadata image
+// returns the error of each topic that could not be created
+fn auto_create_topics(broker: &Broker, names: &[&str]) -> HashMap<String, ErrorCode> {
+    let mut errors: HashMap<String, ErrorCode> = HashMap::new();
+
+    // the metadata image must be released before the writer is taken
+    let missing: Vec<&str> = {
+        let metadata_image = broker.metadata_image();
+        names.iter()
+            .copied()
+            .filter(|name| metadata_image.topic_by_name(name).is_none())
+            .collect()
+    };
+    if missing.is_empty() || !broker.config.auto_create_topics_enable {
+        return errors;
+    }
+
+    let mut metadata_writer = broker.metadata_writer.lock().unwrap();
+
+    for name in missing {
+        // internal topics are created by the coordinators that own them
+        if is_internal_topic(name) || !valid_topic_name(name) {
+            continue;
+        }
+
+        let assignments = {
+            let metadata_image = broker.metadata_image();
+
+            match validate_new_topic_name(&metadata_image, name) {
+                // created since it was found missing
+                Err((ErrorCode::TopicAlreadyExists, _)) => continue,
+                Err(error) => Err(error),
+                Ok(()) => assign_replicas(&replica_brokers(broker, &metadata_image), 0, broker.config.num_partitions, broker.config.default_replication_factor),
+            }
+        };
+
+        let created = assignments.and_then(|assignments| write_topic(broker, &mut metadata_writer, name, &assignments, &[]));
+        if let Err((error, error_message)) = created {
+            println!("Could not auto-create topic {}: {}", name, error_message);
+            errors.insert(name.to_string(), error);
+        }
+    }
+
+    errors
+}
+
+// Kafka's Topic.unifyCollisionChars, topics that only differ in '.' and '_' collide
+fn unify_collision_chars(name: &str) -> String {
+    name.replace('.', "_")
+}
+
+// the brokers replicas may be placed on, by id: this broker and the registered, unfenced ones
+fn replica_brokers(broker: &Broker, metadata_image: &MetadataImage) -> Vec<i32> {
+    let mut brokers: Vec<i32> = metadata_image.brokers()
+        .filter(|registration| !registration.fenced)
+        .map(|registration| registration.broker_id)
+        .collect();
+    if !brokers.contains(&broker.node_id()) {
+        brokers.push(broker.node_id());
+    }
+    brokers.sort();
+
+    brokers
+}
+
+// round robin replica placement from `first_partition`: replica i of partition p goes to brokers[(p + i) % brokers],
+// so leaders, the first replicas, are spread over the brokers
+fn assign_replicas(brokers: &[i32], first_partition: i32, num_partitions: i32, replication_factor: i16) -> Result<Vec<Vec<i32>>, (ErrorCode, String)> {
+    if replication_factor as usize > brokers.len() {
+        return Err((ErrorCode::InvalidReplicationFactor, format!("Unable to replicate the partition {} time(s): The target replication factor of {} cannot be reached because only {} broker(s) are registered.", replication_factor, replication_factor, brokers.len())));
+    }
+
+    Ok((first_partition..first_partition + num_partitions)
+        .map(|partition_id| (0..replication_factor as usize)
+            .map(|replica| brokers[(partition_id as usize + replica) % brokers.len()])
+            .collect())
+        .collect())
+}
+
+// manual assignments must place every partition on `replication_factor` (or the first partition's number of)
+// distinct, known brokers
+fn validate_assignments(brokers: &[i32], assignments: &[Vec<i32>], replication_factor: Option<usize>) -> Result<(), (ErrorCode, String)> {
+    let replication_factor = match replication_factor.or(assignments.first().map(|replicas| replicas.len())) {
+        Some(replication_factor) => replication_factor,
+        None => return Ok(()),
+    };
+
+    for (index, replicas) in assignments.iter().enumerate() {
+        if replicas.is_empty() {
+            return Err((ErrorCode::InvalidReplicaAssignment, format!("The manual partition assignment includes an empty replica list for partition {}.", index)));
+        }
+        if replicas.len() != replication_factor {
+            return Err((ErrorCode::InvalidReplicaAssignment, format!("The manual partition assignment includes a partition with {} replica(s), but this is not consistent with the other partitions, which have {} replica(s).", replicas.len(), replication_factor)));
+        }
+        if let Some(broker_id) = replicas.iter().find(|broker_id| !brokers.contains(broker_id)) {
+            return Err((ErrorCode::InvalidReplicaAssignment, format!("The manual partition assignment includes broker {}, but no such broker is registered.", broker_id)));
+        }
+        if replicas.iter().enumerate().any(|(i, broker_id)| replicas[..i].contains(broker_id)) {
+            return Err((ErrorCode::InvalidReplicaAssignment, format!("The manual partition assignment includes the broker more than once for partition {}: {:?}.", index, replicas)));
+        }
+    }
+
+    Ok(())
+}
+
+// the log dir of every replica: this broker's go to its least used log dirs, other brokers pick their own
+fn replica_directories(broker: &Broker, assignments: &[Vec<i32>]) -> Result<Vec<Vec<Uuid>>, (ErrorCode, String)> {
+    let local_replicas = assignments.iter()
+        .filter(|replicas| replicas.contains(&broker.node_id()))
+        .count();
+    let mut local_directories = broker.log_manager.assign_log_dirs(local_replicas)
+        .map_err(|e| (ErrorCode::from(&e), format!("Error assigning log dirs: {}", e)))?
+        .into_iter();
+
+    Ok(assignments.iter()
+        .map(|replicas| replicas.iter()
+            .map(|&broker_id| match broker_id == broker.node_id() {
+                true => local_directories.next().unwrap_or(UNASSIGNED_DIRECTORY),
+                false => UNASSIGNED_DIRECTORY,
+            })
+            .collect())
+        .collect())
+}
+
+// create the logs of the new partitions from `first_partition` this broker is a replica of, so their directories
+// exist before the first produce; a log that fails to open is created again on its first append
+fn create_partition_logs(broker: &Broker, topic: &str, first_partition: i32, assignments: &[Vec<i32>], directories: &[Vec<Uuid>], topic_config: Option<&HashMap<String, String>>) {
+    for (index, (replicas, directories)) in assignments.iter().zip(directories).enumerate() {
+        if !replicas.contains(&broker.node_id()) {
+            continue;
+        }
+
+        let topic_partition = TopicPartition::new(topic, first_partition + index as i32);
+        if let Err(e) = broker.log_manager.get_or_open(&topic_partition, topic_config, directories) {
+            println!("Error creating log for {}: {}", topic_partition.dir_name(), e);
+        }
+    }
+}
+
+impl RequestProcess for DeleteTopicsRequest {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        println!("Processing DeleteTopicsRequest...");
+
+        let mut metadata_writer = broker.metadata_writer.lock().unwrap();
+
+        let mut response = DeleteTopicsResponse::empty();
+
+        // a topic named more than once, by name or id, is answered with INVALID_REQUEST every time
+        let mut requested: HashMap<(Option<&str>, Uuid), usize> = HashMap::new();
+        for topic in &self.topics.data {
+            *requested.entry((topic.name.data.as_ref().map(|name| name.data.as_str()), topic.topic_id)).or_insert(0) += 1;
+        }
+
+        for topic in &self.topics.data {
+            let name = topic.name.data.as_ref().map(|name| name.data.clone());
+
+            let result = if !broker.config.delete_topic_enable {
+                Err((ErrorCode::TopicDeletionDisabled, "Topic deletion is disabled.".to_string()))
+            } else if requested[&(name.as_deref(), topic.topic_id)] > 1 {
+                Err((ErrorCode::InvalidRequest, "Duplicate topic name or id.".to_string()))
+            } else {
+                delete_topic(broker, &mut metadata_writer, name.as_deref(), topic.topic_id)
+            };
+
+            let result = match result {
+                Ok(result) => result,
+                Err((error, error_message)) => {
+                    println!("Not deleting topic {:?} ({}): {}", name, topic.topic_id, error_message);
+                    DeletableTopicResult::error(name, topic.topic_id, error, Some(error_message))
+                }
+            };
+            response.responses.data.push(result);
+        }
+
+        Ok( KafkaBody::Response(Box::new(response)) )
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        let mut response = DeleteTopicsResponse::empty();
+        response.responses.data = self.topics.data.iter()
+            .map(|topic| DeletableTopicResult::error(topic.name.data.as_ref().map(|name| name.data.clone()), topic.topic_id, error, None))
+            .collect();
+
+        KafkaBody::Response(Box::new(response))
+    }
+}
+
+// write a RemoveTopicRecord for the topic, named by either `name` or `topic_id`, and delete its logs
+fn delete_topic(broker: &Broker, metadata_writer: &mut MetadataWriter, name: Option<&str>, topic_id: Uuid) -> Result<DeletableTopicResult, (ErrorCode, String)> {
+    let (name, topic_id, partitions): (String, Uuid, Vec<i32>) = {
+        let metadata_image = broker.metadata_image();
+
+        let topic = match name {
+            Some(_) if !topic_id.is_nil() => return Err((ErrorCode::InvalidRequest, "Topic name and topic id must not both be set.".to_string())),
+            Some(name) => metadata_image.topic_by_name(name).ok_or((ErrorCode::UnknownTopicOrPartition, format!("Topic {} does not exist.", name)))?,
+            None => metadata_image.topic_by_id(&topic_id).ok_or((ErrorCode::UnknownTopicId, format!("Topic id {} does not exist.", topic_id)))?,
+        };
+        if is_internal_topic(&topic.name) {
+            return Err((ErrorCode::InvalidRequest, format!("Internal topic {} can not be deleted.", topic.name)));
+        }
+
+        (topic.name.clone(), topic.topic_id, topic.partitions.keys().copied().collect())
+    };
+
+    metadata_writer.append(vec![remove_topic_record(topic_id)])
+        .map_err(|e| (ErrorCode::from(&e), format!("Error writing the metadata log: {}", e)))?;
+    println!("Deleted topic {} ({})", name, topic_id);
+
+    // the topic is gone whether or not its files are, a failure here only leaves them on disk
+    for partition_id in partitions {
+        let topic_partition = TopicPartition::new(&name, partition_id);
+        if let Err(e) = broker.log_manager.delete_log(&topic_partition) {
+            println!("Error deleting log for {}: {}", topic_partition.dir_name(), e);
+        }
+        broker.fetch_purgatory.check_and_complete(&topic_partition);
+    }
+
+    // committed offsets of the topic would otherwise outlive it, and be picked up by a topic recreated with its name
+    broker.group_coordinator.on_topics_deleted(std::slice::from_ref(&name), &|group_id, records| broker.append_group_records(group_id, records));
+
+    Ok(DeletableTopicResult::error(Some(name), topic_id, ErrorCode::None, None))
+}
+
+impl RequestProcess for CreatePartitionsRequest {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        println!("Processing CreatePartitionsRequest...");
+
+        let mut metadata_writer = broker.metadata_writer.lock().unwrap();
+
+        let mut response = CreatePartitionsResponse::empty();
+
+        let mut requested: HashMap<&str, usize> = HashMap::new();
+        for topic in &self.topics.data {
+            *requested.entry(topic.name.data.as_str()).or_insert(0) += 1;
+        }
+
+        for topic in &self.topics.data {
+            let name = &topic.name.data;
+
+            let result = if requested[name.as_str()] > 1 {
+                Err((ErrorCode::InvalidRequest, format!("Topic {} is listed more than once.", name)))
+            } else {
+                create_partitions(broker, &mut metadata_writer, topic, self.validate_only)
+            };
+
+            let result = match result {
+                Ok(()) => CreatePartitionsTopicResult::error(name, ErrorCode::None, None),
+                Err((error, error_message)) => {
+                    println!("Not creating partitions for topic {}: {}", name, error_message);
+                    CreatePartitionsTopicResult::error(name, error, Some(error_message))
+                }
+            };
+            response.results.data.push(result);
+        }
+
+        Ok( KafkaBody::Response(Box::new(response)) )
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        let mut response = CreatePartitionsResponse::empty();
+        response.results.data = self.topics.data.iter()
+            .map(|topic| CreatePartitionsTopicResult::error(&topic.name.data, error, None))
+            .collect();
+
+        KafkaBody::Response(Box::new(response))
+    }
+}
+
+// grow the topic to `count` partitions with the replication factor of its existing ones
+fn create_partitions(broker: &Broker, metadata_writer: &mut MetadataWriter, topic: &CreatePartitionsTopic, validate_only: bool) -> Result<(), (ErrorCode, String)> {
+    let name = &topic.name.data;
+
+    let (topic_id, first_partition, assignments, topic_config) = {
+        let metadata_image = broker.metadata_image();
+
+        let topic_image = metadata_image.topic_by_name(name)
+            .ok_or((ErrorCode::UnknownTopicOrPartition, format!("Topic {} does not exist.", name)))?;
+
+        let current = topic_image.partitions.len() as i32;
+        if topic.count < current {
+            return Err((ErrorCode::InvalidPartitions, format!("Topic currently has {} partitions, which is higher than the requested {}.", current, topic.count)));
+        }
+        if topic.count == current {
+            return Err((ErrorCode::InvalidPartitions, format!("Topic already has {} partitions.", current)));
+        }
+        let replication_factor = topic_image.partitions.values().next().map_or(1, |partition| partition.replicas.len());
+
+        let brokers = replica_brokers(broker, &metadata_image);
+        let new_partitions = topic.count - current;
+
+        let assignments = match &topic.assignments {
+            Some(assignments) => {
+                if assignments.data.len() != new_partitions as usize {
+                    return Err((ErrorCode::InvalidReplicaAssignment, format!("Attempted to add {} additional partition(s), but only {} assignment(s) were specified.", new_partitions, assignments.data.len())));
+                }
+
+                let assignments: Vec<Vec<i32>> = assignments.data.iter().map(|assignment| assignment.broker_ids.data.clone()).collect();
+                validate_assignments(&brokers, &assignments, Some(replication_factor))?;
+                assignments
+            }
+            None => assign_replicas(&brokers, current, new_partitions, replication_factor as i16)?,
+        };
+
+        (topic_image.topic_id, current, assignments, metadata_image.topic_configs(name).cloned())
+    };
+
+    if validate_only {
+        println!("Validated {} new partitions for topic {}", assignments.len(), name);
+        return Ok(());
+    }
+
+    let directories = replica_directories(broker, &assignments)?;
+
+    let records = assignments.iter().zip(&directories).enumerate()
+        .map(|(index, (replicas, directories))| partition_record(topic_id, first_partition + index as i32, replicas.clone(), directories.clone()))
+        .collect();
+    metadata_writer.append(records)
+        .map_err(|e| (ErrorCode::from(&e), format!("Error writing the metadata log: {}", e)))?;
+    println!("Created {} new partitions for topic {}", assignments.len(), name);
+
+    create_partition_logs(broker, name, first_partition, &assignments, &directories, topic_config.as_ref());
+
+    Ok(())
+}
+
+// FindCoordinator key types
+const COORDINATOR_KEY_TYPE_GROUP: i8 = 0;
+const COORDINATOR_KEY_TYPE_TRANSACTION: i8 = 1;
+
+impl RequestProcess for FindCoordinatorRequest {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        println!("Processing FindCoordinatorRequest...");
+
+        let advertised_listener = broker.config.advertised_listener();
+
+        // the group coordinator is only available once the offsets topic exists
+        let group_coordinator_error = match self.key_type == COORDINATOR_KEY_TYPE_GROUP && !self.coordinator_keys.data.is_empty() {
+            true => ensure_offsets_topic(broker).err(),
+            false => None,
+        };
+
+        let mut response = FindCoordinatorResponse::empty();
+
+        // this broker coordinates every group
+        for key in &self.coordinator_keys.data {
+            let coordinator = match self.key_type {
+                COORDINATOR_KEY_TYPE_GROUP => match group_coordinator_error {
+                    Some(error) => Coordinator::error(&key.data, error, None),
+                    None => Coordinator {
+                        key: key.clone(),
+                        node_id: broker.node_id(),
+                        host: CompactString::new(advertised_listener.advertised_host().to_string()),
+                        port: advertised_listener.port as i32,
+                        error_code: ErrorCode::None.code(),
+                        error_message: CompactNullableString { data: None },
+                        tagged_fields: TaggedFields(None),
+                    },
+                },
+                COORDINATOR_KEY_TYPE_TRANSACTION => Coordinator::error(&key.data, ErrorCode::CoordinatorNotAvailable, Some("Transactions are not supported.".to_string())),
+                _ => Coordinator::error(&key.data, ErrorCode::InvalidRequest, Some(format!("Unknown coordinator key type {}.", self.key_type))),
+            };
+            response.coordinators.data.push(coordinator);
+        }
+
+        Ok( KafkaBody::Response(Box::new(response)) )
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        let mut response = FindCoordinatorResponse::empty();
+        response.coordinators.data = self.coordinator_keys.data.iter()
+            .map(|key| Coordinator::error(&key.data, error, None))
+            .collect();
+
+        KafkaBody::Response(Box::new(response))
+    }
+}
+
+impl RequestProcess for JoinGroupRequest {
+    // the join is answered once the group rebalanced, only from `handle` so no request handler blocks on it
+    // `handle` is overridden and never calls this
+    fn process(&self, _broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        unreachable!("JoinGroup is answered through handle")
+    }
+
+    fn handle(&self, broker: &Arc<Broker>, respond: ResponseCallback) {
+        self.join_group(broker, Box::new(move |response| respond(Ok(KafkaBody::Response(Box::new(response))))));
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        KafkaBody::Response(Box::new(JoinGroupResponse::error(self.api_version, error, &self.member_id.data)))
+    }
+}
+
+impl JoinGroupRequest {
+    fn join_group(&self, broker: &Broker, respond: Box<dyn FnOnce(JoinGroupResponse) + Send>) {
+        println!("Processing JoinGroupRequest...");
+
+        let join = JoinGroupParams {
+            group_id: self.group_id.data.clone(),
+            member_id: self.member_id.data.clone(),
+            group_instance_id: self.group_instance_id.data.as_ref().map(|group_instance_id| group_instance_id.data.clone()),
+            client_id: self.client_id.clone(),
+            session_timeout_ms: self.session_timeout_ms,
+            rebalance_timeout_ms: self.rebalance_timeout_ms,
+            protocol_type: self.protocol_type.data.clone(),
+            protocols: self.protocols.data.iter().map(|protocol| (protocol.name.data.clone(), protocol.metadata.data.clone())).collect(),
+        };
+
+        let api_version = self.api_version;
+        broker.group_coordinator.join_group(join, Box::new(move |result| respond(join_group_response(api_version, result))));
+    }
+}
+
+fn join_group_response(api_version: i16, result: JoinGroupResult) -> JoinGroupResponse {
+    JoinGroupResponse {
+        throttle_time_ms: 0,
+        error_code: result.error.code(),
+        generation_id: result.generation_id,
+        protocol_type: (api_version >= 7).then(|| CompactNullableString { data: result.protocol_type.map(CompactString::new) }),
+        // not nullable before v7
+        protocol_name: CompactNullableString { data: match api_version {
+            7.. => result.protocol_name.map(CompactString::new),
+            _ => Some(CompactString::new(result.protocol_name.unwrap_or_default())),
+        } },
+        leader: CompactString::new(result.leader_id),
+        // the leader always computes the assignment
+        skip_assignment: (api_version >= 9).then_some(false),
+        member_id: CompactString::new(result.member_id),
+        members: CompactArray::new(result.members.into_iter().map(|member| JoinGroupResponseMember {
+            member_id: CompactString::new(member.member_id),
+            group_instance_id: CompactNullableString { data: member.group_instance_id.map(CompactString::new) },
+            metadata: CompactBytes::new(member.metadata),
+            tagged_fields: TaggedFields(None),
+        }).collect()),
+        tagged_fields: TaggedFields(None),
+    }
+}
+
+impl RequestProcess for HeartbeatRequest {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        println!("Processing HeartbeatRequest...");
+
+        let group_instance_id = self.group_instance_id.data.as_ref().map(|group_instance_id| group_instance_id.data.as_str());
+        let error = broker.group_coordinator.heartbeat(&self.group_id.data, &self.member_id.data, group_instance_id, self.generation_id);
+
+        Ok( KafkaBody::Response(Box::new(HeartbeatResponse::error(error))) )
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        KafkaBody::Response(Box::new(HeartbeatResponse::error(error)))
+    }
+}
+
+impl RequestProcess for LeaveGroupRequest {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        println!("Processing LeaveGroupRequest...");
+
+        let members: Vec<(String, Option<String>)> = self.members.data.iter()
+            .map(|member| (member.member_id.data.clone(), member.group_instance_id.data.as_ref().map(|group_instance_id| group_instance_id.data.clone())))
+            .collect();
+
+        let errors = match broker.group_coordinator.leave_group(&self.group_id.data, &members) {
+            Ok(errors) => errors,
+            Err(error) => return Ok( self.error_response(error) ),
+        };
+
+        let mut response = LeaveGroupResponse::error(ErrorCode::None);
+        response.members.data = self.members.data.iter().zip(errors)
+            .map(|(member, error)| MemberResponse {
+                member_id: member.member_id.clone(),
+                group_instance_id: CompactNullableString { data: member.group_instance_id.data.clone() },
+                error_code: error.code(),
+                tagged_fields: TaggedFields(None),
+            })
+            .collect();
+
+        Ok( KafkaBody::Response(Box::new(response)) )
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        KafkaBody::Response(Box::new(LeaveGroupResponse::error(error)))
+    }
+}
+
+impl RequestProcess for SyncGroupRequest {
+    // the followers are answered once the leader sent the assignment, only from `handle` so no request handler blocks on it
+    // `handle` is overridden and never calls this
+    fn process(&self, _broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        unreachable!("SyncGroup is answered through handle")
+    }
+
+    fn handle(&self, broker: &Arc<Broker>, respond: ResponseCallback) {
+        self.sync_group(broker, Box::new(move |response| respond(Ok(KafkaBody::Response(Box::new(response))))));
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        KafkaBody::Response(Box::new(SyncGroupResponse::error(self.api_version, error)))
+    }
+}
+
+impl SyncGroupRequest {
+    fn sync_group(&self, broker: &Broker, respond: Box<dyn FnOnce(SyncGroupResponse) + Send>) {
+        println!("Processing SyncGroupRequest...");
+
+        let nullable_string = |value: &Option<CompactNullableString>| value.as_ref().and_then(|value| value.data.as_ref()).map(|value| value.data.clone());
+        let sync = SyncGroupParams {
+            group_id: self.group_id.data.clone(),
+            generation_id: self.generation_id,
+            member_id: self.member_id.data.clone(),
+            group_instance_id: self.group_instance_id.data.as_ref().map(|group_instance_id| group_instance_id.data.clone()),
+            protocol_type: nullable_string(&self.protocol_type),
+            protocol_name: nullable_string(&self.protocol_name),
+            assignments: self.assignments.data.iter().map(|assignment| (assignment.member_id.data.clone(), assignment.assignment.data.clone())).collect(),
+        };
+
+        let api_version = self.api_version;
+        broker.group_coordinator.sync_group(sync, Box::new(move |result| {
+            respond(SyncGroupResponse {
+                throttle_time_ms: 0,
+                error_code: result.error.code(),
+                protocol_type: (api_version >= 5).then(|| CompactNullableString { data: result.protocol_type.map(CompactString::new) }),
+                protocol_name: (api_version >= 5).then(|| CompactNullableString { data: result.protocol_name.map(CompactString::new) }),
+                assignment: CompactBytes::new(result.assignment),
+                tagged_fields: TaggedFields(None),
+            })
+        }));
+    }
+}
+
+// the group coordinator keeps its state in __consumer_offsets, created with offsets.topic.num.partitions and
+// offsets.topic.replication.factor the first time a group coordinator is looked for
+fn ensure_offsets_topic(broker: &Broker) -> Result<(), ErrorCode> {
+    // the metadata image must be released before the writer is taken
+    if broker.metadata_image().topic_by_name(OFFSETS_TOPIC).is_some() {
+        return Ok(());
+    }
+
+    let mut metadata_writer = broker.metadata_writer.lock().unwrap();
+
+    let assignments = {
+        let metadata_image = broker.metadata_image();
+
+        match validate_new_topic_name(&metadata_image, OFFSETS_TOPIC) {
+            // created since it was found missing
+            Err((ErrorCode::TopicAlreadyExists, _)) => return Ok(()),
+            Err(error) => Err(error),
+            Ok(()) => assign_replicas(&replica_brokers(broker, &metadata_image), 0, broker.config.offsets_topic_num_partitions, broker.config.offsets_topic_replication_factor),
+        }
+    };
+
+    let segment_bytes = OFFSETS_TOPIC_SEGMENT_BYTES.to_string();
+    let configs = [("cleanup.policy", "compact"), ("segment.bytes", segment_bytes.as_str()), ("compression.type", "producer")];
+    let created = assignments.and_then(|assignments| write_topic(broker, &mut metadata_writer, OFFSETS_TOPIC, &assignments, &configs));
+    created.map(|_| ()).map_err(|(_, error_message)| {
+        println!("Could not create topic {}: {}", OFFSETS_TOPIC, error_message);
+        ErrorCode::CoordinatorNotAvailable
+    })
+}
+
+impl RequestProcess for OffsetCommitRequest {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        println!("Processing OffsetCommitRequest...");
+
+        // the error of every partition in request order, partitions of unknown topics are not committed
+        let mut errors: Vec<ErrorCode> = Vec::new();
+        let mut offsets: Vec<(TopicPartition, OffsetAndMetadata)> = Vec::new();
+        let mut committed: Vec<usize> = Vec::new();
+        {
+            let metadata_image = broker.metadata_image();
+            let commit_timestamp = now_ms();
+
+            for topic in &self.topics.data {
+                let topic_image = metadata_image.topic_by_name(&topic.name.data);
+                for partition in &topic.partitions.data {
+                    if !topic_image.is_some_and(|topic_image| topic_image.has_partition(partition.partition_index)) {
+                        errors.push(ErrorCode::UnknownTopicOrPartition);
+                        continue;
+                    }
+
+                    committed.push(errors.len());
+                    errors.push(ErrorCode::None);
+                    offsets.push((TopicPartition::new(&topic.name.data, partition.partition_index), OffsetAndMetadata {
+                        offset: partition.committed_offset,
+                        leader_epoch: partition.committed_leader_epoch,
+                        metadata: partition.committed_metadata.data.as_ref().map(|metadata| metadata.data.clone()).unwrap_or_default(),
+                        commit_timestamp,
+                        expire_timestamp: None,
+                    }));
+                }
+            }
+        }
+
+        if !offsets.is_empty() {
+            let commit = OffsetCommitParams {
+                group_id: self.group_id.data.clone(),
+                generation_id: self.generation_id_or_member_epoch,
+                member_id: self.member_id.data.clone(),
+                group_instance_id: self.group_instance_id.data.as_ref().map(|group_instance_id| group_instance_id.data.clone()),
+                offsets,
+            };
+
+            let commit_errors = match broker.group_coordinator.commit_offsets(commit, &|group_id, records| broker.append_group_records(group_id, records)) {
+                Ok(commit_errors) => commit_errors,
+                Err(error) => vec![error; committed.len()],
+            };
+            for (index, error) in committed.into_iter().zip(commit_errors) {
+                errors[index] = error;
+            }
+        }
+
+        let mut errors = errors.into_iter();
+        let mut response = OffsetCommitResponse::empty();
+        response.topics.data = self.topics.data.iter()
+            .map(|topic| OffsetCommitResponseTopic {
+                name: topic.name.clone(),
+                partitions: CompactArray { data: topic.partitions.data.iter()
+                    .map(|partition| OffsetCommitResponsePartition::error(partition.partition_index, errors.next().unwrap_or(ErrorCode::None)))
+                    .collect() },
+                tagged_fields: TaggedFields(None),
+            })
+            .collect();
+
+        Ok( KafkaBody::Response(Box::new(response)) )
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        let mut response = OffsetCommitResponse::empty();
+        response.topics.data = self.topics.data.iter()
+            .map(|topic| OffsetCommitResponseTopic {
+                name: topic.name.clone(),
+                partitions: CompactArray { data: topic.partitions.data.iter()
+                    .map(|partition| OffsetCommitResponsePartition::error(partition.partition_index, error))
+                    .collect() },
+                tagged_fields: TaggedFields(None),
+            })
+            .collect();
+
+        KafkaBody::Response(Box::new(response))
+    }
+}
+
+impl RequestProcess for OffsetFetchRequest {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        println!("Processing OffsetFetchRequest...");
+
+        // without pending transactional offsets every committed offset is stable, require_stable needs no waiting
+        let mut response = OffsetFetchResponse::empty();
+        for group in &self.groups.data {
+            let partitions = group.topics.as_ref().map(|topics| topics.data.iter()
+                .flat_map(|topic| topic.partition_indexes.data.iter().map(|&partition_index| TopicPartition::new(&topic.name.data, partition_index)))
+                .collect());
+
+            let mut offsets = match broker.group_coordinator.fetch_offsets(&group.group_id.data, partitions) {
+                Ok(offsets) => offsets,
+                Err(error) => {
+                    response.groups.data.push(OffsetFetchResponseGroup::error(&group.group_id.data, error));
+                    continue;
+                }
+            };
+
+            // every offset of the group is returned sorted by topic and partition, requested ones in request order
+            let mut offsets_by_topic: Vec<(String, Vec<OffsetFetchResponsePartitions>)> = Vec::new();
+            if group.topics.is_none() {
+                offsets.sort_by(|(a, _), (b, _)| (&a.topic, a.partition).cmp(&(&b.topic, b.partition)));
+            }
+            for (topic_partition, offset_and_metadata) in offsets {
+                let partition = match offset_and_metadata {
+                    Some(offset_and_metadata) => OffsetFetchResponsePartitions {
+                        partition_index: topic_partition.partition,
+                        committed_offset: offset_and_metadata.offset,
+                        committed_leader_epoch: offset_and_metadata.leader_epoch,
+                        metadata: CompactNullableString { data: Some(CompactString::new(offset_and_metadata.metadata)) },
+                        error_code: ErrorCode::None.code(),
+                        tagged_fields: TaggedFields(None),
+                    },
+                    None => OffsetFetchResponsePartitions::no_offset(topic_partition.partition),
+                };
+
+                match offsets_by_topic.last_mut() {
+                    Some((topic, partitions)) if *topic == topic_partition.topic => partitions.push(partition),
+                    _ => offsets_by_topic.push((topic_partition.topic, vec![partition])),
+                }
+            }
+
+            let mut response_group = OffsetFetchResponseGroup::error(&group.group_id.data, ErrorCode::None);
+            response_group.topics.data = offsets_by_topic.into_iter()
+                .map(|(name, partitions)| OffsetFetchResponseTopics {
+                    name: CompactString::new(name),
+                    partitions: CompactArray { data: partitions },
+                    tagged_fields: TaggedFields(None),
+                })
+                .collect();
+            response.groups.data.push(response_group);
+        }
+
+        Ok( KafkaBody::Response(Box::new(response)) )
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        let mut response = OffsetFetchResponse::empty();
+        response.groups.data = self.groups.data.iter()
+            .map(|group| OffsetFetchResponseGroup::error(&group.group_id.data, error))
+            .collect();
+
+        KafkaBody::Response(Box::new(response))
+    }
+}
+
+impl RequestProcess for OffsetDeleteRequest {
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError> {
+        println!("Processing OffsetDeleteRequest...");
+
+        // the error of every partition in request order, partitions of unknown topics are not deleted
+        let mut errors: Vec<ErrorCode> = Vec::new();
+        let mut partitions: Vec<TopicPartition> = Vec::new();
+        let mut deleted: Vec<usize> = Vec::new();
+        {
+            let metadata_image = broker.metadata_image();
+
+            for topic in &self.topics.data {
+                let topic_image = metadata_image.topic_by_name(&topic.name.data);
+                for partition in &topic.partitions.data {
+                    if topic_image.is_some_and(|topic_image| topic_image.has_partition(partition.partition_index)) {
+                        deleted.push(errors.len());
+                        partitions.push(TopicPartition::new(&topic.name.data, partition.partition_index));
+                    }
+                    errors.push(ErrorCode::UnknownTopicOrPartition);
+                }
+            }
+        }
+
+        match broker.group_coordinator.delete_offsets(&self.group_id.data, &partitions, &|group_id, records| broker.append_group_records(group_id, records)) {
+            Ok(delete_errors) => {
+                for (index, error) in deleted.into_iter().zip(delete_errors) {
+                    errors[index] = error;
+                }
+            }
+            Err(error) => return Ok( self.error_response(error) ),
+        }
+
+        let mut errors = errors.into_iter();
+        let mut response = OffsetDeleteResponse::error(ErrorCode::None);
+        response.topics.data = self.topics.data.iter()
+            .map(|topic| OffsetDeleteResponseTopic {
+                name: topic.name.clone(),
+                partitions: Array::new(topic.partitions.data.iter()
+                    .map(|partition| OffsetDeleteResponsePartition {
+                        partition_index: partition.partition_index,
+                        error_code: errors.next().unwrap_or(ErrorCode::None).code(),
+                    })
+                    .collect()),
+            })
+            .collect();
+
+        Ok( KafkaBody::Response(Box::new(response)) )
+    }
+
+    fn error_response(&self, error: ErrorCode) -> KafkaBody {
+        KafkaBody::Response(Box::new(OffsetDeleteResponse::error(error)))
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::storage::log_manager::TopicPartition;
+
+//
+// Delayed operation purgatory
+//
+
+// An operation that is parked until some condition holds or its timeout expires,
+// like Kafka's DelayedOperation
+pub trait DelayedOperation: Send + Sync {
+    // check whether the operation can be completed now, must not complete it
+    fn can_complete(&self) -> bool;
+
+    // complete the operation, called exactly once, either when `can_complete` holds or on expiration
+    fn on_complete(&self);
+
+    fn on_expiration(&self) {}
+}
+
+struct WatchedOperation {
+    operation: Box<dyn DelayedOperation>,
+    deadline: Instant,
+    completed: AtomicBool,
+}
+
+impl WatchedOperation {
+    fn is_completed(&self) -> bool {
+        self.completed.load(Ordering::Acquire)
+    }
+
+    // complete the operation if nobody else did, returns true if this call completed it
+    fn force_complete(&self) -> bool {
+        if self.completed.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+            self.operation.on_complete();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn try_complete(&self) -> bool {
+        !self.is_completed() && self.operation.can_complete() && self.force_complete()
+    }
+}
+
+// Holds delayed operations, watched by the keys that can unblock them (the partitions a fetch reads,
+// the group a join waits on), and expires them from a reaper thread once their deadline passes
+pub struct Purgatory<K = TopicPartition> {
+    name: String,
+    watchers: Mutex<HashMap<K, Vec<Arc<WatchedOperation>>>>,
+    timer: Mutex<Vec<Arc<WatchedOperation>>>,
+    timer_wakeup: Condvar,
+}
+
+impl<K: Eq + Hash + Clone + Display + Send + 'static> Purgatory<K> {
+    // create a purgatory and start its expiration reaper
+    pub fn start(name: &str) -> Arc<Self> {
+        let purgatory = Arc::new(Purgatory {
+            name: name.to_string(),
+            watchers: Mutex::new(HashMap::new()),
+            timer: Mutex::new(Vec::new()),
+            timer_wakeup: Condvar::new(),
+        });
+
+        let reaper = Arc::clone(&purgatory);
+        std::thread::spawn(move || reaper.run_reaper());
+
+        purgatory
+    }
+
+    // complete the operation right away if possible, otherwise watch it on `keys` until it can be
+    // completed or `timeout` elapses
+    pub fn try_complete_else_watch(&self, operation: Box<dyn DelayedOperation>, timeout: Duration, keys: Vec<K>) {
+        let watched = Arc::new(WatchedOperation {
+            operation,
+            deadline: Instant::now() + timeout,
+            completed: AtomicBool::new(false),
+        });
+
+        if watched.try_complete() {
+            return;
+        }
+
+        {
+            let mut watchers = self.watchers.lock().unwrap();
+            for key in keys {
+                watchers.entry(key).or_default().push(Arc::clone(&watched));
+            }
+        }
+
+        // the condition may have become true while the operation was being registered
+        if watched.try_complete() {
+            return;
+        }
+
+        println!("[{}] Parking delayed operation", self.name);
+        self.timer.lock().unwrap().push(watched);
+        self.timer_wakeup.notify_one();
+    }
+
+    // re-check the operations watching `key`, called after something changed for that key (e.g. an append)
+    // returns the number of operations completed
+    pub fn check_and_complete(&self, key: &K) -> usize {
+        let operations: Vec<Arc<WatchedOperation>> = match self.watchers.lock().unwrap().get_mut(key) {
+            Some(operations) => {
+                operations.retain(|operation| !operation.is_completed());
+                operations.clone()
+            }
+            None => return 0,
+        };
+
+        // operations are completed without holding the watchers lock
+        let completed = operations.iter().filter(|operation| operation.try_complete()).count();
+        if completed > 0 {
+            println!("[{}] Completed {} delayed operations for {}", self.name, completed, key);
+        }
+
+        completed
+    }
+
+    fn run_reaper(&self) {
+        let mut timer = self.timer.lock().unwrap();
+
+        loop {
+            let now = Instant::now();
+
+            let mut expired: Vec<Arc<WatchedOperation>> = Vec::new();
+            timer.retain(|operation| {
+                if operation.is_completed() {
+                    false
+                } else if operation.deadline <= now {
+                    expired.push(Arc::clone(operation));
+                    false
+                } else {
+                    true
+                }
+            });
+
+            if !expired.is_empty() {
+                drop(timer);
+
+                for operation in expired {
+                    if operation.force_complete() {
+                        operation.operation.on_expiration();
+                    }
+                }
+                self.purge_completed();
+
+                timer = self.timer.lock().unwrap();
+                continue;
+            }
+
+            // sleep until the next deadline, or until a new operation is parked
+            timer = match timer.iter().map(|operation| operation.deadline).min() {
+                Some(deadline) => self.timer_wakeup.wait_timeout(timer, deadline.saturating_duration_since(now)).unwrap().0,
+                None => self.timer_wakeup.wait(timer).unwrap(),
+            };
+        }
+    }
+
+    // drop completed operations from the watcher lists of keys that never changed
+    fn purge_completed(&self) {
+        let mut watchers = self.watchers.lock().unwrap();
+        watchers.retain(|_, operations| {
+            operations.retain(|operation| !operation.is_completed());
+            !operations.is_empty()
+        });
+    }
+}
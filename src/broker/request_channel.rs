@@ -0,0 +1,85 @@
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+
+use mio::{Token, Waker};
+
+// default for queued.max.requests, as in Kafka
+pub const DEFAULT_QUEUED_MAX_REQUESTS: usize = 500;
+
+//
+// RequestChannel
+//
+
+// A request frame read by a network processor, waiting for a request handler thread
+pub struct QueuedRequest {
+    pub frame: Vec<u8>,
+    pub connection: Token,
+    pub responses: Arc<ResponseQueue>,
+}
+
+pub enum Response {
+    Send(Vec<u8>),
+    // the request completed without a response, e.g. a Produce with acks=0
+    NoResponse,
+    CloseConnection,
+}
+
+// Hands requests from the network processors to the request handlers.
+// The queue is bounded so processors stop reading when the handlers fall behind.
+pub struct RequestChannel {
+    sender: SyncSender<QueuedRequest>,
+    receiver: Mutex<Receiver<QueuedRequest>>,
+}
+
+impl RequestChannel {
+    pub fn new(queued_max_requests: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(queued_max_requests);
+        RequestChannel {
+            sender,
+            receiver: Mutex::new(receiver),
+        }
+    }
+
+    // blocks while the queue is full
+    pub fn send_request(&self, request: QueuedRequest) {
+        // the receiver lives as long as the channel
+        let _ = self.sender.send(request);
+    }
+
+    // blocks until a request is available
+    pub fn receive_request(&self) -> Option<QueuedRequest> {
+        self.receiver.lock().unwrap().recv().ok()
+    }
+}
+
+// Responses for the connections of one network processor, which is woken up to write them
+pub struct ResponseQueue {
+    responses: Mutex<Vec<(Token, Response)>>,
+    waker: Waker,
+}
+
+impl ResponseQueue {
+    pub fn new(waker: Waker) -> Self {
+        ResponseQueue {
+            responses: Mutex::new(Vec::new()),
+            waker,
+        }
+    }
+
+    pub fn send_response(&self, connection: Token, response: Response) {
+        self.responses.lock().unwrap().push((connection, response));
+        self.wake();
+    }
+
+    // take the responses queued so far, in the order they were sent
+    pub fn drain(&self) -> Vec<(Token, Response)> {
+        std::mem::take(&mut *self.responses.lock().unwrap())
+    }
+
+    // wake the processor's event loop
+    pub fn wake(&self) {
+        if let Err(e) = self.waker.wake() {
+            println!("Error waking network processor: {}", e);
+        }
+    }
+}
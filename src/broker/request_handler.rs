@@ -0,0 +1,62 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::broker::broker::Broker;
+use crate::broker::request_channel::{RequestChannel, Response};
+use crate::broker::utils::process_request;
+
+// default for num.io.threads, as in Kafka
+pub const DEFAULT_NUM_IO_THREADS: usize = 8;
+
+//
+// RequestHandlerPool
+//
+
+// Threads that decode and process the requests queued by the network processors,
+// like Kafka's KafkaRequestHandlerPool
+pub struct RequestHandlerPool {
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl RequestHandlerPool {
+    pub fn start(num_threads: usize, broker: Arc<Broker>, request_channel: Arc<RequestChannel>) -> Self {
+        let threads = (0..num_threads)
+            .map(|id| {
+                let broker = Arc::clone(&broker);
+                let request_channel = Arc::clone(&request_channel);
+                std::thread::spawn(move || run_handler(id, broker, request_channel))
+            })
+            .collect();
+
+        RequestHandlerPool { threads }
+    }
+}
+
+fn run_handler(id: usize, broker: Arc<Broker>, request_channel: Arc<RequestChannel>) {
+    println!("Request handler {} started", id);
+
+    while let Some(request) = request_channel.receive_request() {
+        let connection = request.connection;
+        let responses = Arc::clone(&request.responses);
+        let responded = Arc::new(AtomicBool::new(false));
+
+        // the response is queued now, or later by a purgatory for delayed requests
+        let result = {
+            let responded = Arc::clone(&responded);
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                process_request(&request.frame, &broker, Box::new(move |response| {
+                    responded.store(true, Ordering::Release);
+                    responses.send_response(connection, response);
+                }));
+            }))
+        };
+
+        // a request that crashed its handler can not be answered, drop the connection instead of leaving it waiting
+        if result.is_err() && !responded.load(Ordering::Acquire) {
+            println!("Request handler {} failed processing a request, closing the connection", id);
+            request.responses.send_response(connection, Response::CloseConnection);
+        }
+    }
+}
@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use mio::net::{TcpListener, TcpStream};
+use mio::{Events, Interest, Poll, Token, Waker};
+
+use crate::broker::broker::Broker;
+use crate::broker::framing::FrameDecoder;
+use crate::broker::request_channel::{QueuedRequest, RequestChannel, Response, ResponseQueue, DEFAULT_QUEUED_MAX_REQUESTS};
+use crate::broker::request_handler::RequestHandlerPool;
+
+// default for num.network.threads, as in Kafka
+pub const DEFAULT_NUM_NETWORK_THREADS: usize = 3;
+
+// default for max.connections, as in Kafka
+pub const DEFAULT_MAX_CONNECTIONS: usize = i32::MAX as usize;
+
+const LISTENER_TOKEN: Token = Token(0);
+const WAKER_TOKEN: Token = Token(0);
+
+//
+// SocketServer
+//
+
+// Non-blocking network layer, like Kafka's SocketServer:
+// - an acceptor accepts connections and hands them to the processors round robin
+// - each processor runs an event loop over its connections, reads request frames
+//   into the request channel and writes the responses back
+// - the request handler pool processes the requests
+// A connection has at most one request in flight, so requests are processed and
+// answered in the order they were sent
+pub struct SocketServer {
+    acceptor: Acceptor,
+    processors: Vec<Processor>,
+    request_channel: Arc<RequestChannel>,
+}
+
+impl SocketServer {
+    pub fn new(listener: std::net::TcpListener, num_network_threads: usize, max_connections: usize, socket_request_max_bytes: usize) -> std::io::Result<Self> {
+        listener.set_nonblocking(true)?;
+
+        let request_channel = Arc::new(RequestChannel::new(DEFAULT_QUEUED_MAX_REQUESTS));
+        let connection_count = Arc::new(AtomicUsize::new(0));
+
+        let mut processors: Vec<Processor> = Vec::new();
+        for id in 0..num_network_threads.max(1) {
+            processors.push(Processor::new(id, Arc::clone(&request_channel), Arc::clone(&connection_count), socket_request_max_bytes)?);
+        }
+
+        let acceptor = Acceptor {
+            listener: TcpListener::from_std(listener),
+            poll: Poll::new()?,
+            processors: processors.iter().map(|processor| Arc::clone(&processor.handle)).collect(),
+            next_processor: 0,
+            connection_count,
+            max_connections,
+        };
+
+        Ok(SocketServer {
+            acceptor,
+            processors,
+            request_channel,
+        })
+    }
+
+    // start the processors and the request handlers, then accept connections on the calling thread
+    pub fn run(self, broker: Arc<Broker>) -> std::io::Result<()> {
+        let _request_handlers = RequestHandlerPool::start(broker.config.num_io_threads, broker, Arc::clone(&self.request_channel));
+
+        for processor in self.processors {
+            std::thread::spawn(move || processor.run());
+        }
+
+        self.acceptor.run()
+    }
+}
+
+// what the acceptor shares with a processor
+struct ProcessorHandle {
+    new_connections: Mutex<Vec<(TcpStream, SocketAddr)>>,
+    responses: Arc<ResponseQueue>,
+}
+
+//
+// Acceptor
+//
+
+struct Acceptor {
+    listener: TcpListener,
+    poll: Poll,
+    processors: Vec<Arc<ProcessorHandle>>,
+    next_processor: usize,
+    connection_count: Arc<AtomicUsize>,
+    max_connections: usize,
+}
+
+impl Acceptor {
+    fn run(mut self) -> std::io::Result<()> {
+        self.poll.registry().register(&mut self.listener, LISTENER_TOKEN, Interest::READABLE)?;
+        let mut events = Events::with_capacity(128);
+
+        loop {
+            if let Err(e) = self.poll.poll(&mut events, None) {
+                if e.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(e);
+            }
+
+            // the listener is edge triggered, accept until there is nothing left
+            loop {
+                match self.listener.accept() {
+                    Ok((stream, address)) => self.assign(stream, address),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(e) => {
+                        println!("Error accepting connection: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn assign(&mut self, stream: TcpStream, address: SocketAddr) {
+        if self.connection_count.load(Ordering::Acquire) >= self.max_connections {
+            println!("Too many connections ({}), rejecting connection from {}", self.max_connections, address);
+            return; // dropping the stream closes it
+        }
+        self.connection_count.fetch_add(1, Ordering::AcqRel);
+
+        let processor = &self.processors[self.next_processor];
+        self.next_processor = (self.next_processor + 1) % self.processors.len();
+
+        processor.new_connections.lock().unwrap().push((stream, address));
+        processor.responses.wake();
+    }
+}
+
+//
+// Processor
+//
+
+struct Connection {
+    stream: TcpStream,
+    address: SocketAddr,
+    frame_decoder: FrameDecoder,
+    // response bytes not written yet
+    write_buf: Vec<u8>,
+    write_interest: bool,
+    // a request is being processed, the connection is not read until its response is sent
+    // so the next requests wait in the socket buffer rather than piling up in the frame decoder
+    in_flight: bool,
+}
+
+impl Connection {
+    // read until a complete frame is buffered or nothing more is available, returns false once the client closed the connection
+    // a client pipelining requests without reading responses leaves them in the socket buffer, so at most
+    // one frame and a read's worth of bytes are buffered; the rest is read once its response is sent
+    fn read(&mut self) -> std::io::Result<bool> {
+        let mut buf = [0; 4096];
+        while !self.frame_decoder.has_frame() {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Ok(false),
+                Ok(bytes_read) => self.frame_decoder.extend(&buf[..bytes_read]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(true),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    // write as much of the pending responses as the socket takes
+    fn flush(&mut self) -> std::io::Result<()> {
+        while !self.write_buf.is_empty() {
+            match self.stream.write(&self.write_buf) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(bytes_written) => {
+                    self.write_buf.drain(..bytes_written);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Processor {
+    id: usize,
+    poll: Poll,
+    handle: Arc<ProcessorHandle>,
+    connections: HashMap<Token, Connection>,
+    // tokens are never reused, so a late response can not reach another connection
+    next_token: usize,
+    request_channel: Arc<RequestChannel>,
+    connection_count: Arc<AtomicUsize>,
+    socket_request_max_bytes: usize,
+}
+
+impl Processor {
+    fn new(id: usize, request_channel: Arc<RequestChannel>, connection_count: Arc<AtomicUsize>, socket_request_max_bytes: usize) -> std::io::Result<Self> {
+        let poll = Poll::new()?;
+        let waker = Waker::new(poll.registry(), WAKER_TOKEN)?;
+
+        Ok(Processor {
+            id,
+            poll,
+            handle: Arc::new(ProcessorHandle {
+                new_connections: Mutex::new(Vec::new()),
+                responses: Arc::new(ResponseQueue::new(waker)),
+            }),
+            connections: HashMap::new(),
+            next_token: WAKER_TOKEN.0 + 1,
+            request_channel,
+            connection_count,
+            socket_request_max_bytes,
+        })
+    }
+
+    fn run(mut self) {
+        println!("Network processor {} started", self.id);
+        let mut events = Events::with_capacity(1024);
+
+        loop {
+            if let Err(e) = self.poll.poll(&mut events, None) {
+                if e.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                println!("Network processor {} failed: {}", self.id, e);
+                return;
+            }
+
+            for event in events.iter() {
+                match event.token() {
+                    WAKER_TOKEN => {
+                        self.register_new_connections();
+                        self.process_responses();
+                    }
+                    token => self.process_io(token, event.is_readable(), event.is_writable()),
+                }
+            }
+        }
+    }
+
+    fn register_new_connections(&mut self) {
+        let new_connections = std::mem::take(&mut *self.handle.new_connections.lock().unwrap());
+
+        for (mut stream, address) in new_connections {
+            let token = Token(self.next_token);
+            self.next_token += 1;
+
+            if let Err(e) = self.poll.registry().register(&mut stream, token, Interest::READABLE) {
+                println!("Error registering connection from {}: {}", address, e);
+                self.connection_count.fetch_sub(1, Ordering::AcqRel);
+                continue;
+            }
+
+            println!("Client connected: {} (processor {})", address, self.id);
+            self.connections.insert(token, Connection {
+                stream,
+                address,
+                frame_decoder: FrameDecoder::new(self.socket_request_max_bytes),
+                write_buf: Vec::new(),
+                write_interest: false,
+                in_flight: false,
+            });
+        }
+    }
+
+    fn process_io(&mut self, token: Token, readable: bool, writable: bool) {
+        let connection = match self.connections.get_mut(&token) {
+            Some(connection) => connection,
+            None => return,
+        };
+
+        // muted while a request is in flight, the response path reads what arrived meanwhile
+        if readable && !connection.in_flight && !self.read(token) {
+            return;
+        }
+
+        let connection = match self.connections.get_mut(&token) {
+            Some(connection) => connection,
+            None => return,
+        };
+
+        if writable {
+            if let Err(e) = connection.flush() {
+                println!("Error writing to {}: {}", connection.address, e);
+                return self.close(token);
+            }
+        }
+
+        self.dispatch_next_request(token);
+    }
+
+    fn process_responses(&mut self) {
+        for (token, response) in self.handle.responses.drain() {
+            let connection = match self.connections.get_mut(&token) {
+                Some(connection) => connection,
+                None => continue, // the client went away while the request was processed
+            };
+
+            match response {
+                Response::Send(encoded_response) => {
+                    connection.write_buf.extend(encoded_response);
+                    if let Err(e) = connection.flush() {
+                        println!("Error writing to {}: {}", connection.address, e);
+                        self.close(token);
+                        continue;
+                    }
+                }
+                Response::NoResponse => {}
+                Response::CloseConnection => {
+                    println!("Closing connection to {}", connection.address);
+                    self.close(token);
+                    continue;
+                }
+            }
+
+            // unmute the connection, readable events while in flight were not acted on
+            connection.in_flight = false;
+            if !self.read(token) {
+                continue;
+            }
+            self.dispatch_next_request(token);
+        }
+    }
+
+    // read the connection, closing it when the client went away, returns false once closed
+    fn read(&mut self, token: Token) -> bool {
+        let connection = match self.connections.get_mut(&token) {
+            Some(connection) => connection,
+            None => return false,
+        };
+
+        match connection.read() {
+            Ok(true) => true,
+            Ok(false) => {
+                println!("Client disconnected: {}", connection.address);
+                self.close(token);
+                false
+            }
+            Err(e) => {
+                println!("Error reading from {}: {}", connection.address, e);
+                self.close(token);
+                false
+            }
+        }
+    }
+
+    // queue the next complete request of the connection unless one is in flight or a response is pending,
+    // and watch for writability while responses are pending
+    fn dispatch_next_request(&mut self, token: Token) {
+        let connection = match self.connections.get_mut(&token) {
+            Some(connection) => connection,
+            None => return,
+        };
+
+        // a response still being written holds the next request back too, a client not reading them would grow write_buf
+        if !connection.in_flight && connection.write_buf.is_empty() {
+            match connection.frame_decoder.next_frame() {
+                Ok(Some(frame)) => {
+                    connection.in_flight = true;
+                    self.request_channel.send_request(QueuedRequest {
+                        frame,
+                        connection: token,
+                        responses: Arc::clone(&self.handle.responses),
+                    });
+                }
+                Ok(None) => {}
+                Err(_) => return self.close(token),
+            }
+        }
+
+        let write_interest = !connection.write_buf.is_empty();
+        if write_interest != connection.write_interest {
+            let interest = if write_interest { Interest::READABLE | Interest::WRITABLE } else { Interest::READABLE };
+            if let Err(e) = self.poll.registry().reregister(&mut connection.stream, token, interest) {
+                println!("Error updating interest for {}: {}", connection.address, e);
+                return self.close(token);
+            }
+            connection.write_interest = write_interest;
+        }
+    }
+
+    fn close(&mut self, token: Token) {
+        if let Some(mut connection) = self.connections.remove(&token) {
+            let _ = self.poll.registry().deregister(&mut connection.stream);
+            self.connection_count.fetch_sub(1, Ordering::AcqRel);
+            println!("Connection closed: {}", connection.address);
+        }
+    }
+}
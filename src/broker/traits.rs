@@ -2,15 +2,37 @@
 // Broker specific traits
 //
 
+use std::sync::Arc;
+
+use crate::broker::broker::Broker;
 use crate::common::kafka_protocol::KafkaBody;
 use crate::common::traits::Codec;
-use crate::errors::BrokerError;
+use crate::errors::{BrokerError, ErrorCode};
+
+// receives the outcome of a request once it is available
+pub type ResponseCallback = Box<dyn FnOnce(Result<KafkaBody, BrokerError>) + Send>;
 
 pub trait RequestProcess {
-    fn process(&self) -> Result<KafkaBody, BrokerError>;
+    fn process(&self, broker: &Broker) -> Result<KafkaBody, BrokerError>;
+
+    // hand the outcome of the request to `respond`
+    // requests that may have to wait (e.g. Fetch with max_wait_ms) override this to respond later from a purgatory
+    fn handle(&self, broker: &Arc<Broker>, respond: ResponseCallback) {
+        respond(self.process(broker));
+    }
+
+    // requests like Produce with acks=0 are fire-and-forget, the client does not read a response
+    fn expects_response(&self) -> bool {
+        true
+    }
+
+    // response reporting `error` for everything the request asked for,
+    // sent when the request could not be processed
+    fn error_response(&self, error: ErrorCode) -> KafkaBody;
 }
 
-pub trait Request: Codec + RequestProcess {}
+// requests are shared with the response callback, which runs on whichever thread completes them
+pub trait Request: Codec + RequestProcess + Send + Sync {}
 
 // blanket implementation for all types that implement Codec and RequestProcess
-impl<T> Request for T where T: Codec + RequestProcess {}
\ No newline at end of file
+impl<T> Request for T where T: Codec + RequestProcess + Send + Sync {}
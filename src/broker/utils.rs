@@ -1,126 +1,130 @@
 
-use std::{io::{Read, Write}, net::TcpStream, sync::Arc};
+use std::sync::Arc;
 
-use crate::common::kafka_protocol::{ApiKey, ApiVersionsResponse, KafkaBody, KafkaHeader, KafkaMessage, RequestContext, ResponseHeader, TaggedFields};
-use crate::common::traits::Decodable;
+use crate::common::kafka_protocol::{CreatePartitionsResponse, CreateTopicsResponse, DeleteTopicsResponse, DescribeTopicPartitionsResponse, FetchResponse, FindCoordinatorResponse, HeartbeatResponse, JoinGroupResponse, KafkaBody, KafkaHeader, KafkaMessage, LeaveGroupResponse, ListOffsetsResponse, MetadataResponse, OffsetCommitResponse, OffsetDeleteResponse, OffsetFetchResponse, ProduceResponse, RequestContext, RequestHeader, ResponseHeader, SyncGroupResponse};
 use crate::broker::broker::Broker;
-use crate::broker::traits::RequestProcess;
-use crate::api_versions::{get_all_apis, get_supported_api_versions};
-
-pub fn process_request(mut stream: TcpStream, broker: Arc<Broker>) {
-    // read request
-    let mut buf = [0; 1024];
-    println!("Client connected: {:?}", stream.peer_addr());
-
-    while let Ok(bytes_read) = stream.read(&mut buf) {
-        if bytes_read == 0 {
-            println!("Client disconnected");
-            break; // exit the loop only when the client closes the connection
+use crate::broker::decode::decode_request_body;
+use crate::broker::framing::SIZE_PREFIX_BYTES;
+use crate::broker::process::api_versions_error_response;
+use crate::broker::request_channel::Response;
+use crate::broker::traits::Request;
+use crate::api_versions::get_supported_api_versions;
+use crate::errors::ErrorCode;
+
+// decode one request frame and hand it to its handler
+// `respond` is called exactly once, right away or when a delayed request completes
+// failures are reported with an error response to the request, only frames without a readable header close the connection
+pub fn process_request(frame: &[u8], broker: &Arc<Broker>, respond: Box<dyn FnOnce(Response) + Send>) {
+    // decode request header, skipping the size prefix
+    let (request_header, body_offset) = match RequestHeader::decode(&frame[SIZE_PREFIX_BYTES..]) {
+        Ok((request_header, header_byte_length)) => (request_header, SIZE_PREFIX_BYTES + header_byte_length),
+        Err(_) => {
+            println!("Error decoding request header");
+            return respond(Response::CloseConnection);
         }
+    };
 
-        // decode request sent by the client
-        let request = match KafkaMessage::decode(&buf[..bytes_read], &RequestContext::None) {
-            Ok((kmessage, _)) => kmessage,
-            Err(_) => {
-                println!("Error decoding request");
-                break;
-            }
-        };
+    let correlation_id = request_header.correlation_id;
+    let api_key = request_header.api_key;
+    let header_version = find_header_version(api_key);
 
-        // extract correlation ID and error code from the request header
-        let correlation_id = match &request.header {
-            KafkaHeader::Request(req_header) => req_header.correlation_id,
-            _ => {
-                println!("Invalid request header");
-                break;
-            }
-        };
+    let error = validate_api_version(&request_header);
 
-        let error_code = validate_api_version(&request.header);
-
-        // create client response
-        let kmessage: KafkaMessage;
-
-        if error_code != 0 {
-            println!("Unsupported API version");
-
-            // create error response
-            // requests with unsupported API version are treated as ApiVersionsRequest v0
-            // from the Kafka codebase -> https://github.com/apache/kafka/blob/trunk/clients/src/main/java/org/apache/kafka/common/requests/RequestContext.java#L111
-            kmessage = KafkaMessage {
-                size: 0,
-                header: KafkaHeader::Response(ResponseHeader::new(correlation_id, 0)),
-                body: KafkaBody::Response(Box::new(ApiVersionsResponse {
-                    error_code,
-                    api_versions: get_all_apis().iter()
-                                        .map(|&(api_key, (min_version, max_version))| ApiKey {
-                                            api_key,
-                                            min_version,
-                                            max_version,
-                                            tagged_fields: TaggedFields(None),
-                                        }).collect(),
-                    throttle_time_ms: 0,
-                    tagged_fields: TaggedFields(None),
-                })),
-            };
-
-        } else {
-
-            // create valid response
-            kmessage = match request.body.process() {
-                Ok(response) => KafkaMessage {
-                    size: 0,
-                    header: KafkaHeader::Response(ResponseHeader::new(correlation_id, find_header_version(request.header.get_api_key())),
-                    ),
-                    body: response,
-                },
-                Err(_) => {
-                    println!("Error processing request");
-                    break;
-                }
-            };
-        }
+    if error != ErrorCode::None {
+        println!("Unsupported API version");
 
-        // encode the response
-        let encoded_response = kmessage.encode();
+        // requests with unsupported API version are treated as ApiVersionsRequest v0
+        // from the Kafka codebase -> https://github.com/apache/kafka/blob/trunk/clients/src/main/java/org/apache/kafka/common/requests/RequestContext.java#L111
+        let response = KafkaBody::Response(Box::new(api_versions_error_response(error)));
+        return respond(Response::Send(encode_response(correlation_id, 0, response)));
+    }
 
-        // write encoded response to the socket
-        if let Err(e) = stream.write_all(&encoded_response) {
-            println!("Error writing to stream: {}", e);
-            break;
+    // decode request body
+    let request: Arc<dyn Request> = match decode_request_body(&request_header, &frame[body_offset..], &RequestContext::None) {
+        Ok(request) => Arc::from(request),
+        Err(_) => {
+            println!("Error decoding request body (api key {}, version {})", api_key, request_header.api_version);
+            return respond(Response::Send(encode_response(correlation_id, header_version, invalid_request_response(&request_header))));
         }
+    };
 
-        println!("Response sent, waiting for the next request...");
-    }
+    let expects_response = request.expects_response();
+    let failed_request = Arc::clone(&request);
+
+    // create valid response, now or once the request completes
+    request.handle(broker, Box::new(move |result| {
+        let response = match result {
+            Ok(_) if !expects_response => return respond(Response::NoResponse),
+            Ok(response) => response,
+            Err(e) => {
+                let error = ErrorCode::from(e);
+                println!("Error processing request (api key {}): {}", api_key, error.name());
+                failed_request.error_response(error)
+            }
+        };
 
-    // return the borrowed connection to the pool
-    broker.return_connection(stream);
-    println!("Connection closed...")
+        respond(Response::Send(encode_response(correlation_id, header_version, response)));
+    }));
 }
 
-fn validate_api_version(req_header: &KafkaHeader) -> i16 {
-    let error_code = match req_header {
-        KafkaHeader::Request(req_header) => {
-            match get_supported_api_versions(req_header.api_key) {
-                Some(supported_versions) => {
-                    if req_header.api_version > supported_versions.1 || req_header.api_version < supported_versions.0 {
-                        35 as i16
-                    } else {
-                        0 as i16
-                    }
-                },
-                None => 35 as i16,
-            }
-        }
-        _ => 35 as i16,
+fn encode_response(correlation_id: i32, header_version: i8, body: KafkaBody) -> Vec<u8> {
+    let kmessage = KafkaMessage {
+        size: 0,
+        header: KafkaHeader::Response(ResponseHeader::new(correlation_id, header_version)),
+        body,
     };
 
-    error_code
+    kmessage.encode()
+}
+
+// response to a request whose body could not be decoded, with INVALID_REQUEST where the API has a top-level error code
+fn invalid_request_response(request_header: &RequestHeader) -> KafkaBody {
+    match request_header.api_key {
+        0 => KafkaBody::Response(Box::new(ProduceResponse::empty())),
+        1 => {
+            let mut response = FetchResponse::empty();
+            response.error_code = ErrorCode::InvalidRequest.code();
+            KafkaBody::Response(Box::new(response))
+        }
+        2 => KafkaBody::Response(Box::new(ListOffsetsResponse::empty())),
+        3 => {
+            let mut response = MetadataResponse::empty(request_header.api_version);
+            response.error_code = response.error_code.map(|_| ErrorCode::InvalidRequest.code());
+            KafkaBody::Response(Box::new(response))
+        }
+        8 => KafkaBody::Response(Box::new(OffsetCommitResponse::empty())),
+        9 => KafkaBody::Response(Box::new(OffsetFetchResponse::empty())),
+        10 => KafkaBody::Response(Box::new(FindCoordinatorResponse::empty())),
+        11 => KafkaBody::Response(Box::new(JoinGroupResponse::error(request_header.api_version, ErrorCode::InvalidRequest, ""))),
+        12 => KafkaBody::Response(Box::new(HeartbeatResponse::error(ErrorCode::InvalidRequest))),
+        13 => KafkaBody::Response(Box::new(LeaveGroupResponse::error(ErrorCode::InvalidRequest))),
+        14 => KafkaBody::Response(Box::new(SyncGroupResponse::error(request_header.api_version, ErrorCode::InvalidRequest))),
+        18 => KafkaBody::Response(Box::new(api_versions_error_response(ErrorCode::InvalidRequest))),
+        19 => KafkaBody::Response(Box::new(CreateTopicsResponse::empty())),
+        20 => KafkaBody::Response(Box::new(DeleteTopicsResponse::empty())),
+        37 => KafkaBody::Response(Box::new(CreatePartitionsResponse::empty())),
+        47 => KafkaBody::Response(Box::new(OffsetDeleteResponse::error(ErrorCode::InvalidRequest))),
+        _ => KafkaBody::Response(Box::new(DescribeTopicPartitionsResponse::empty())),
+    }
+}
+
+fn validate_api_version(req_header: &RequestHeader) -> ErrorCode {
+    match get_supported_api_versions(req_header.api_key) {
+        Some(supported_versions) => {
+            if req_header.api_version > supported_versions.1 || req_header.api_version < supported_versions.0 {
+                ErrorCode::UnsupportedVersion
+            } else {
+                ErrorCode::None
+            }
+        },
+        None => ErrorCode::UnsupportedVersion,
+    }
 }
 
 fn find_header_version(api_key: i16) -> i8 {
     match api_key {
         18 => 0,
+        47 => 0,
         75 => 1,
         _ => 1,
     }
@@ -1,5 +1,5 @@
-use crate::errors::KafkaError;
-use crate::common::kafka_protocol::{ApiVersionsResponse, DescribeTopicPartitionsResponse, FetchResponse, RequestContext, TaggedFields};
+use crate::errors::{ErrorCode, KafkaError};
+use crate::common::kafka_protocol::{ApiVersionsResponse, CreatePartitionsResponse, CreateTopicsResponse, DeleteTopicsResponse, DescribeTopicPartitionsResponse, FetchResponse, FindCoordinatorResponse, HeartbeatResponse, JoinGroupResponse, LeaveGroupResponse, ListOffsetsResponse, MetadataResponse, OffsetCommitResponse, OffsetDeleteResponse, OffsetFetchResponse, ProduceResponse, RequestContext, SyncGroupResponse, TaggedFields};
 use crate::common::primitive_types::CompactArray;
 use crate::common::traits::Decodable;
 
@@ -36,4 +36,88 @@ impl Decodable for FetchResponse {
             tagged_fields: TaggedFields(None)
         }, 0) )
     }
-}
\ No newline at end of file
+}
+
+impl Decodable for ProduceResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (ProduceResponse::empty(), 0) )
+    }
+}
+
+impl Decodable for MetadataResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (MetadataResponse::empty(12), 0) )
+    }
+}
+
+impl Decodable for ListOffsetsResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (ListOffsetsResponse::empty(), 0) )
+    }
+}
+
+impl Decodable for CreateTopicsResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (CreateTopicsResponse::empty(), 0) )
+    }
+}
+
+impl Decodable for DeleteTopicsResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (DeleteTopicsResponse::empty(), 0) )
+    }
+}
+
+impl Decodable for CreatePartitionsResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (CreatePartitionsResponse::empty(), 0) )
+    }
+}
+
+impl Decodable for FindCoordinatorResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (FindCoordinatorResponse::empty(), 0) )
+    }
+}
+
+impl Decodable for JoinGroupResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (JoinGroupResponse::error(9, ErrorCode::None, ""), 0) )
+    }
+}
+
+impl Decodable for HeartbeatResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (HeartbeatResponse::error(ErrorCode::None), 0) )
+    }
+}
+
+impl Decodable for LeaveGroupResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (LeaveGroupResponse::error(ErrorCode::None), 0) )
+    }
+}
+
+impl Decodable for SyncGroupResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (SyncGroupResponse::error(5, ErrorCode::None), 0) )
+    }
+}
+
+impl Decodable for OffsetCommitResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (OffsetCommitResponse::empty(), 0) )
+    }
+}
+
+impl Decodable for OffsetFetchResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (OffsetFetchResponse::empty(), 0) )
+    }
+}
+
+impl Decodable for OffsetDeleteResponse {
+    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        Ok( (OffsetDeleteResponse::error(ErrorCode::None), 0) )
+    }
+}
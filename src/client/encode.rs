@@ -1,4 +1,4 @@
-use crate::common::kafka_protocol::{ApiVersionsRequest, DescribeTopicPartitionsRequest, FetchRequest};
+use crate::common::kafka_protocol::{ApiVersionsRequest, CreatableReplicaAssignment, CreatableTopic, CreatableTopicConfig, CreatePartitionsAssignment, CreatePartitionsRequest, CreatePartitionsTopic, CreateTopicsRequest, DeleteTopicState, DeleteTopicsRequest, DescribeTopicPartitionsRequest, FetchRequest, FindCoordinatorRequest, HeartbeatRequest, JoinGroupRequest, JoinGroupRequestProtocol, LeaveGroupRequest, ListOffsetsRequest, ListOffsetsRequestPartition, ListOffsetsRequestTopic, MemberIdentity, MetadataRequest, MetadataRequestTopic, OffsetCommitRequest, OffsetCommitRequestPartition, OffsetCommitRequestTopic, OffsetDeleteRequest, OffsetDeleteRequestPartition, OffsetDeleteRequestTopic, OffsetFetchRequest, OffsetFetchRequestGroup, OffsetFetchRequestTopics, ProduceRequest, ProduceRequestPartition, ProduceRequestTopic, SyncGroupRequest, SyncGroupRequestAssignment};
 use crate::common::traits::Encodable;
 
 impl Encodable for ApiVersionsRequest {
@@ -35,4 +35,423 @@ impl Encodable for FetchRequest {
         // FIXME:
         buf
     }
-}
\ No newline at end of file
+}
+
+impl Encodable for ProduceRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.transactional_id.encode());
+        buf.extend(self.acks.to_be_bytes());
+        buf.extend(self.timeout_ms.to_be_bytes());
+        buf.extend(self.topic_data.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for ProduceRequestTopic {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.name.encode());
+        buf.extend(self.partition_data.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for ProduceRequestPartition {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.index.to_be_bytes());
+        buf.extend(self.records.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for MetadataRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match &self.topics {
+            Some(topics) => buf.extend(topics.encode()),
+            None => buf.push(0), // every topic
+        }
+        buf.push(self.allow_auto_topic_creation as u8);
+        buf.push(self.include_topic_authorized_operations as u8);
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for MetadataRequestTopic {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.topic_id.encode());
+        buf.extend(self.name.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for ListOffsetsRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.replica_id.to_be_bytes());
+        buf.extend(self.isolation_level.to_be_bytes());
+        buf.extend(self.topics.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for ListOffsetsRequestTopic {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.name.encode());
+        buf.extend(self.partitions.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for ListOffsetsRequestPartition {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.partition_index.to_be_bytes());
+        buf.extend(self.current_leader_epoch.to_be_bytes());
+        buf.extend(self.timestamp.to_be_bytes());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for CreateTopicsRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.topics.encode());
+        buf.extend(self.timeout_ms.to_be_bytes());
+        buf.push(self.validate_only as u8);
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for CreatableTopic {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.name.encode());
+        buf.extend(self.num_partitions.to_be_bytes());
+        buf.extend(self.replication_factor.to_be_bytes());
+        buf.extend(self.assignments.encode());
+        buf.extend(self.configs.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for CreatableReplicaAssignment {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.partition_index.to_be_bytes());
+        buf.extend(self.broker_ids.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for CreatableTopicConfig {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.name.encode());
+        buf.extend(self.value.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for DeleteTopicsRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.topics.encode());
+        buf.extend(self.timeout_ms.to_be_bytes());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for DeleteTopicState {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.name.encode());
+        buf.extend(self.topic_id.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for CreatePartitionsRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.topics.encode());
+        buf.extend(self.timeout_ms.to_be_bytes());
+        buf.push(self.validate_only as u8);
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for CreatePartitionsTopic {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.name.encode());
+        buf.extend(self.count.to_be_bytes());
+        match &self.assignments {
+            Some(assignments) => buf.extend(assignments.encode()),
+            None => buf.push(0), // let the broker assign
+        }
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for CreatePartitionsAssignment {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.broker_ids.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for FindCoordinatorRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.key_type.to_be_bytes());
+        buf.extend(self.coordinator_keys.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for JoinGroupRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.group_id.encode());
+        buf.extend(self.session_timeout_ms.to_be_bytes());
+        buf.extend(self.rebalance_timeout_ms.to_be_bytes());
+        buf.extend(self.member_id.encode());
+        buf.extend(self.group_instance_id.encode());
+        buf.extend(self.protocol_type.encode());
+        buf.extend(self.protocols.encode());
+        if let Some(reason) = &self.reason {
+            buf.extend(reason.encode());
+        }
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for JoinGroupRequestProtocol {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.name.encode());
+        buf.extend(self.metadata.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for HeartbeatRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.group_id.encode());
+        buf.extend(self.generation_id.to_be_bytes());
+        buf.extend(self.member_id.encode());
+        buf.extend(self.group_instance_id.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for LeaveGroupRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.group_id.encode());
+        buf.extend(self.members.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for MemberIdentity {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.member_id.encode());
+        buf.extend(self.group_instance_id.encode());
+        if let Some(reason) = &self.reason {
+            buf.extend(reason.encode());
+        }
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for SyncGroupRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.group_id.encode());
+        buf.extend(self.generation_id.to_be_bytes());
+        buf.extend(self.member_id.encode());
+        buf.extend(self.group_instance_id.encode());
+        if let Some(protocol_type) = &self.protocol_type {
+            buf.extend(protocol_type.encode());
+        }
+        if let Some(protocol_name) = &self.protocol_name {
+            buf.extend(protocol_name.encode());
+        }
+        buf.extend(self.assignments.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for SyncGroupRequestAssignment {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.member_id.encode());
+        buf.extend(self.assignment.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetCommitRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.group_id.encode());
+        buf.extend(self.generation_id_or_member_epoch.to_be_bytes());
+        buf.extend(self.member_id.encode());
+        buf.extend(self.group_instance_id.encode());
+        buf.extend(self.topics.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetCommitRequestTopic {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.name.encode());
+        buf.extend(self.partitions.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetCommitRequestPartition {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.partition_index.to_be_bytes());
+        buf.extend(self.committed_offset.to_be_bytes());
+        buf.extend(self.committed_leader_epoch.to_be_bytes());
+        buf.extend(self.committed_metadata.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetFetchRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.groups.encode());
+        buf.push(self.require_stable as u8);
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetFetchRequestGroup {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.group_id.encode());
+        if let Some(member_id) = &self.member_id {
+            buf.extend(member_id.encode());
+        }
+        if let Some(member_epoch) = self.member_epoch {
+            buf.extend(member_epoch.to_be_bytes());
+        }
+        match &self.topics {
+            Some(topics) => buf.extend(topics.encode()),
+            None => buf.push(0), // null topics
+        }
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetFetchRequestTopics {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.name.encode());
+        buf.extend(self.partition_indexes.encode());
+        buf.extend(self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetDeleteRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.group_id.encode());
+        buf.extend(self.topics.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetDeleteRequestTopic {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(self.name.encode());
+        buf.extend(self.partitions.encode());
+
+        buf
+    }
+}
+
+impl Encodable for OffsetDeleteRequestPartition {
+    fn encode(&self) -> Vec<u8> {
+        self.partition_index.to_be_bytes().to_vec()
+    }
+}
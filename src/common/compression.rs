@@ -0,0 +1,181 @@
+use std::io::{Read, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use lz4_flex::frame::{FrameDecoder, FrameEncoder};
+
+//
+// Compression
+//
+
+// codecs of the compression bits (0~2) of RecordBatch attributes
+// the records of a compressed batch, everything after the records count, are compressed as one block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionType {
+    None,
+    Gzip,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+pub const COMPRESSION_CODEC_MASK: i16 = 0x07;
+
+// snappy-java's stream format, which the Java client writes:
+// magic, version and compatible version, then blocks each prefixed with their INT32 length
+const XERIAL_SNAPPY_MAGIC: [u8; 8] = [0x82, b'S', b'N', b'A', b'P', b'P', b'Y', 0];
+const XERIAL_SNAPPY_VERSION: i32 = 1;
+const XERIAL_SNAPPY_HEADER_SIZE: usize = 16;
+const XERIAL_SNAPPY_BLOCK_SIZE: usize = 32 * 1024;
+
+impl CompressionType {
+    // None for the ids Kafka does not define (5~7)
+    pub fn from_id(id: i16) -> Option<Self> {
+        match id {
+            0 => Some(CompressionType::None),
+            1 => Some(CompressionType::Gzip),
+            2 => Some(CompressionType::Snappy),
+            3 => Some(CompressionType::Lz4),
+            4 => Some(CompressionType::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn from_attributes(attributes: i16) -> Option<Self> {
+        CompressionType::from_id(attributes & COMPRESSION_CODEC_MASK)
+    }
+
+    // the names compression.type takes, "uncompressed" as in the topic config or "none" as in the client config
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "uncompressed" | "none" => Some(CompressionType::None),
+            "gzip" => Some(CompressionType::Gzip),
+            "snappy" => Some(CompressionType::Snappy),
+            "lz4" => Some(CompressionType::Lz4),
+            "zstd" => Some(CompressionType::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> i16 {
+        match self {
+            CompressionType::None => 0,
+            CompressionType::Gzip => 1,
+            CompressionType::Snappy => 2,
+            CompressionType::Lz4 => 3,
+            CompressionType::Zstd => 4,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompressionType::None => "none",
+            CompressionType::Gzip => "gzip",
+            CompressionType::Snappy => "snappy",
+            CompressionType::Lz4 => "lz4",
+            CompressionType::Zstd => "zstd",
+        }
+    }
+
+    pub fn compress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            CompressionType::None => Ok(data.to_vec()),
+            CompressionType::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            CompressionType::Snappy => xerial_snappy_compress(data),
+            CompressionType::Lz4 => {
+                // independent 64KB blocks without checksums, as Kafka's KafkaLZ4BlockOutputStream writes them
+                let mut encoder = FrameEncoder::new(Vec::new());
+                encoder.write_all(data)?;
+                encoder.finish().map_err(std::io::Error::other)
+            }
+            CompressionType::Zstd => zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL),
+        }
+    }
+
+    // fails once the records would decompress to more than max_bytes
+    pub fn decompress(&self, data: &[u8], max_bytes: usize) -> std::io::Result<Vec<u8>> {
+        let mut decompressed: Vec<u8> = Vec::new();
+        // one byte past the cap is read, to tell data ending at the cap from data going over it
+        let limit = (max_bytes as u64).saturating_add(1);
+
+        match self {
+            CompressionType::None => decompressed.extend(data),
+            CompressionType::Gzip => {
+                GzDecoder::new(data).take(limit).read_to_end(&mut decompressed)?;
+            }
+            CompressionType::Snappy => decompressed = snappy_decompress(data, max_bytes)?,
+            CompressionType::Lz4 => {
+                FrameDecoder::new(data).take(limit).read_to_end(&mut decompressed)?;
+            }
+            CompressionType::Zstd => {
+                zstd::stream::read::Decoder::new(data)?.take(limit).read_to_end(&mut decompressed)?;
+            }
+        }
+
+        if decompressed.len() > max_bytes {
+            return Err(too_large(max_bytes));
+        }
+
+        Ok(decompressed)
+    }
+}
+
+fn too_large(max_bytes: usize) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, format!("decompressed records larger than {} bytes", max_bytes))
+}
+
+fn xerial_snappy_compress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = snap::raw::Encoder::new();
+
+    let mut buf: Vec<u8> = Vec::new();
+    buf.extend(XERIAL_SNAPPY_MAGIC);
+    buf.extend(XERIAL_SNAPPY_VERSION.to_be_bytes());
+    buf.extend(XERIAL_SNAPPY_VERSION.to_be_bytes());
+
+    for block in data.chunks(XERIAL_SNAPPY_BLOCK_SIZE) {
+        let compressed = encoder.compress_vec(block).map_err(std::io::Error::other)?;
+        buf.extend((compressed.len() as i32).to_be_bytes());
+        buf.extend(compressed);
+    }
+
+    Ok(buf)
+}
+
+// the snappy-java stream format, or a single raw snappy block as librdkafka writes it
+// block lengths are read from their headers, so nothing past max_bytes is decompressed
+fn snappy_decompress(data: &[u8], max_bytes: usize) -> std::io::Result<Vec<u8>> {
+    let mut decoder = snap::raw::Decoder::new();
+
+    if !data.starts_with(&XERIAL_SNAPPY_MAGIC) {
+        if snap::raw::decompress_len(data).map_err(std::io::Error::other)? > max_bytes {
+            return Err(too_large(max_bytes));
+        }
+        return decoder.decompress_vec(data).map_err(std::io::Error::other);
+    }
+    if data.len() < XERIAL_SNAPPY_HEADER_SIZE {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "truncated snappy stream header"));
+    }
+
+    let mut decompressed: Vec<u8> = Vec::new();
+    let mut offset = XERIAL_SNAPPY_HEADER_SIZE;
+    while offset < data.len() {
+        let block_length = data.get(offset..offset + 4)
+            .map(|length| i32::from_be_bytes(length.try_into().unwrap()))
+            .filter(|&length| length >= 0 && offset + 4 + length as usize <= data.len())
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "truncated snappy stream block"))?;
+        offset += 4;
+
+        let block = &data[offset..offset + block_length as usize];
+        if decompressed.len().saturating_add(snap::raw::decompress_len(block).map_err(std::io::Error::other)?) > max_bytes {
+            return Err(too_large(max_bytes));
+        }
+        decompressed.extend(decoder.decompress_vec(block).map_err(std::io::Error::other)?);
+        offset += block_length as usize;
+    }
+
+    Ok(decompressed)
+}
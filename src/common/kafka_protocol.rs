@@ -3,10 +3,10 @@ use std::str;
 
 use uuid::Uuid;
 
+use crate::api_versions::request_header_version;
 use crate::broker::traits::Request;
-use crate::errors::KafkaError;
-use super::kafka_record::RecordBatch;
-use super::primitive_types::{CompactArray, CompactNullableString, CompactString};
+use crate::errors::{ErrorCode, KafkaError};
+use super::primitive_types::{Array, CompactArray, CompactBytes, CompactNullableString, CompactRecords, CompactString, KafkaString, UnsignedVarInt};
 use super::traits::{Decodable, Encodable, Codec};
 
 
@@ -149,35 +149,41 @@ impl RequestHeader {
 
         let mut offset = 0;
 
-        let temp: &[u8; 2] = &bytes[0..2].try_into().expect("Could not get request API key from buffer...\n");
+        // api_key, api_version, correlation_id and the client id length
+        if bytes.len() < 10 {
+            return Err(KafkaError::DecodeError);
+        }
+
+        let temp: &[u8; 2] = &bytes[0..2].try_into().map_err(|_| KafkaError::DecodeError)?;
         let api_key = i16::from_be_bytes(*temp);
-        // println!("{}", api_key);
 
-        let temp: &[u8; 2] = &bytes[2..4].try_into().expect("Could not get request API key from buffer...\n");
+        let temp: &[u8; 2] = &bytes[2..4].try_into().map_err(|_| KafkaError::DecodeError)?;
         let api_version = i16::from_be_bytes(*temp);
-        // println!("{}", api_version);
 
-        let temp: &[u8; 4] = &bytes[4..8].try_into().expect("Could not get correlation id from buffer...\n");
+        let temp: &[u8; 4] = &bytes[4..8].try_into().map_err(|_| KafkaError::DecodeError)?;
         let correlation_id = i32::from_be_bytes(*temp);
         offset += 8;
 
-        let temp: &[u8; 2] = &bytes[offset..offset+2].try_into().expect("Could not get client id length from buffer...\n");
+        let temp: &[u8; 2] = &bytes[offset..offset+2].try_into().map_err(|_| KafkaError::DecodeError)?;
         let client_id_len = i16::from_be_bytes(*temp);
         offset += 2;
 
-        if offset + client_id_len as usize > bytes.len() {
+        // client_id is a NULLABLE_STRING, -1 is null
+        let client_id_len = client_id_len.max(0) as usize;
+        if offset + client_id_len > bytes.len() {
             return Err(KafkaError::DecodeError);
         }
         
-        let client_id = match str::from_utf8(&bytes[offset..offset+client_id_len as usize]) {
+        let client_id = match str::from_utf8(&bytes[offset..offset+client_id_len]) {
             Ok(value) => value.to_string(),
             Err(_) => return Err(KafkaError::DecodeError)
         };
-        offset += client_id_len as usize;
+        offset += client_id_len;
 
-        let (tagged_fields, tf_len) = match TaggedFields::decode(&bytes, &RequestContext::None) {
-            Ok((tagged_fields, tf_len)) => (tagged_fields, tf_len),
-            Err(_) => return Err(KafkaError::DecodeError)
+        // header version 1 ends with the client id
+        let (tagged_fields, tf_len) = match request_header_version(api_key, api_version) {
+            2 => TaggedFields::decode(&bytes[offset..], &RequestContext::None).map_err(|_| KafkaError::DecodeError)?,
+            _ => (TaggedFields(None), 0),
         };
         offset += tf_len;
 
@@ -197,7 +203,7 @@ impl ResponseHeader {
         Self {
             correlation_id,
             tagged_fields: TaggedFields(None),
-            header_version: header_version
+            header_version
         }
     }
 
@@ -254,28 +260,75 @@ pub type RequestContext = Option<HashMap<String, String>>;
 // API SPECIFIC SCHEMA ARE DEFINED BELOW
 // =======================================
 
+// tag: UNSIGNED_VARINT
+// size: UNSIGNED_VARINT
+// data: size bytes, the field value encoded as its declared type
+#[derive(Clone)]
 pub struct TaggedField {
-    // TODO:
+    pub tag: u32,
+    pub data: Vec<u8>,
 }
 
 impl Encodable for TaggedField {
     fn encode(&self) -> Vec<u8> {
-        Vec::new()
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(UnsignedVarInt::new(self.tag).encode());
+        buf.extend(UnsignedVarInt::new(self.data.len() as u32).encode());
+        buf.extend(&self.data);
+
+        buf
     }
 }
 
 impl Decodable for TaggedField {
-    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
-        Ok((TaggedField{}, 1))
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        let (tag, tag_byte_len) = UnsignedVarInt::decode(&buf[offset..], request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += tag_byte_len;
+
+        let (size, size_byte_len) = UnsignedVarInt::decode(&buf[offset..], request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += size_byte_len;
+
+        if buf.len() < offset + size.data as usize {
+            println!("Insufficient data to decode TaggedField");
+            return Err(KafkaError::DecodeError);
+        }
+        let data = buf[offset..offset + size.data as usize].to_vec();
+        offset += size.data as usize;
+
+        Ok((TaggedField { tag: tag.data, data }, offset))
     }
 }
 
+// the tagged fields of a flexible version structure, unlike COMPACT_ARRAY the count is not incremented by one
+#[derive(Clone)]
 pub struct TaggedFields(pub Option<CompactArray<TaggedField>>);
 
 impl TaggedFields {
     pub fn new(fields: Option<CompactArray<TaggedField>>) -> Self {
         TaggedFields(fields)
     }
+
+    // raw value of the field with `tag`
+    pub fn get(&self, tag: u32) -> Option<&[u8]> {
+        self.0.as_ref()?.data.iter().find(|field| field.tag == tag).map(|field| field.data.as_slice())
+    }
+
+    // remove the field with `tag`, returning its raw value
+    pub fn take(&mut self, tag: u32) -> Option<Vec<u8>> {
+        let fields = &mut self.0.as_mut()?.data;
+        let index = fields.iter().position(|field| field.tag == tag)?;
+        Some(fields.remove(index).data)
+    }
+
+    // add or replace a field, fields are kept sorted by tag as the protocol requires
+    pub fn insert(&mut self, tag: u32, data: Vec<u8>) {
+        let fields = &mut self.0.get_or_insert_with(|| CompactArray::new(vec![])).data;
+        fields.retain(|field| field.tag != tag);
+        let index = fields.partition_point(|field| field.tag < tag);
+        fields.insert(index, TaggedField { tag, data });
+    }
 }
 
 impl Encodable for TaggedFields {
@@ -283,6 +336,7 @@ impl Encodable for TaggedFields {
         let mut buf = Vec::new();
         match &self.0 {
             Some(fields) => {
+                buf.extend(UnsignedVarInt::new(fields.data.len() as u32).encode());
                 for field in fields.data.iter() {
                     buf.extend(field.encode());
                 }
@@ -296,8 +350,29 @@ impl Encodable for TaggedFields {
 }
 
 impl Decodable for TaggedFields {
-    fn decode(_buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
-        Ok((TaggedFields(None), 1))
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        // structures that end without a tag buffer
+        if buf.is_empty() {
+            return Ok((TaggedFields(None), 0));
+        }
+
+        let mut offset = 0;
+
+        let (count, count_byte_len) = UnsignedVarInt::decode(buf, request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += count_byte_len;
+
+        if count.data == 0 {
+            return Ok((TaggedFields(None), offset));
+        }
+
+        let mut fields: Vec<TaggedField> = Vec::new();
+        for _ in 0..count.data {
+            let (field, field_byte_len) = TaggedField::decode(&buf[offset..], request_context)?;
+            offset += field_byte_len;
+            fields.push(field);
+        }
+
+        Ok((TaggedFields(Some(CompactArray::new(fields))), offset))
     }
 }
 
@@ -398,7 +473,7 @@ pub struct DescribeTopicPartitionsResponse {
 }
 
 impl DescribeTopicPartitionsResponse {
-    pub fn empty(&self) -> DescribeTopicPartitionsResponse {
+    pub fn empty() -> DescribeTopicPartitionsResponse {
         DescribeTopicPartitionsResponse {
             throttle_time_ms: 0,
             topics: CompactArray { data: vec![] },
@@ -412,7 +487,7 @@ impl DescribeTopicPartitionsResponse {
             throttle_time_ms: 0,
             topics: CompactArray { data: vec![
                 ResponseTopic {
-                    error_code: 3,
+                    error_code: ErrorCode::UnknownTopicOrPartition.code(),
                     name: CompactNullableString {
                         data: Some( CompactString { data: "test".to_string() } )
                     },
@@ -477,6 +552,7 @@ pub struct PartitionMetadata {
 //     topic_id => UUID
 //     partitions => INT32
 //   rack_id => COMPACT_STRING
+#[derive(Clone)]
 pub struct FetchRequest {
     pub max_wait_ms: i32,
     pub min_bytes: i32,
@@ -490,12 +566,14 @@ pub struct FetchRequest {
     pub tagged_fields: TaggedFields
 }
 
+#[derive(Clone)]
 pub struct FetchRequestTopic {
     pub topic_id: Uuid,
     pub partitions: CompactArray<FetchRequestPartition>,
     pub tagged_fields: TaggedFields
 }
 
+#[derive(Clone)]
 pub struct FetchRequestPartition {
     pub partition: i32,
     pub current_leader_epoch: i32,
@@ -506,6 +584,7 @@ pub struct FetchRequestPartition {
     pub tagged_fields: TaggedFields
 }
 
+#[derive(Clone)]
 pub struct ForgottenTopicData {
     pub topic_id: Uuid,
     pub partitions: CompactArray<i32>,
@@ -568,10 +647,26 @@ pub struct FetchResponsePartition {
     pub log_start_offset: i64,
     pub aborted_transactions: CompactArray<FetchResponseAbortedTransactions>,
     pub preferred_read_replica: i32,
-    pub records: CompactArray<RecordBatch>,
+    pub records: CompactRecords,
     pub tagged_fields: TaggedFields
 }
 
+impl FetchResponsePartition {
+    pub fn error(partition_index: i32, error: ErrorCode) -> FetchResponsePartition {
+        FetchResponsePartition {
+            partition_index,
+            error_code: error.code(),
+            high_watermark: -1,
+            last_stable_offset: -1,
+            log_start_offset: -1,
+            aborted_transactions: CompactArray { data: vec![] },
+            preferred_read_replica: -1,
+            records: CompactRecords { data: None },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
 pub struct FetchResponseAbortedTransactions {
     pub producer_id: i64,
     pub first_offset: i64,
@@ -582,7 +677,7 @@ impl FetchResponse {
     pub fn empty() -> FetchResponse {
         FetchResponse {
             throttle_time_ms: 0,
-            error_code: 0,
+            error_code: ErrorCode::None.code(),
             session_id: 0,
             responses: CompactArray { data: vec![] },
             tagged_fields: TaggedFields(None)
@@ -591,9 +686,1129 @@ impl FetchResponse {
 }
 
 //
-// Produce
+// Produce API
+//
+
+// Produce Request (Version: 9) => transactional_id acks timeout_ms [topic_data] TAG_BUFFER 
+//   transactional_id => COMPACT_NULLABLE_STRING
+//   acks => INT16
+//   timeout_ms => INT32
+//   topic_data => name [partition_data] TAG_BUFFER 
+//     name => COMPACT_STRING
+//     partition_data => index records TAG_BUFFER 
+//       index => INT32
+//       records => COMPACT_RECORDS
+pub struct ProduceRequest {
+    pub transactional_id: CompactNullableString,
+    pub acks: i16,
+    pub timeout_ms: i32,
+    pub topic_data: CompactArray<ProduceRequestTopic>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct ProduceRequestTopic {
+    pub name: CompactString,
+    pub partition_data: CompactArray<ProduceRequestPartition>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct ProduceRequestPartition {
+    pub index: i32,
+    pub records: CompactRecords,
+    pub tagged_fields: TaggedFields
+}
+
+// Produce Response (Version: 9) => [responses] throttle_time_ms TAG_BUFFER 
+//   responses => name [partition_responses] TAG_BUFFER 
+//     name => COMPACT_STRING
+//     partition_responses => index error_code base_offset log_append_time_ms log_start_offset [record_errors] error_message TAG_BUFFER 
+//       index => INT32
+//       error_code => INT16
+//       base_offset => INT64
+//       log_append_time_ms => INT64
+//       log_start_offset => INT64
+//       record_errors => batch_index batch_index_error_message TAG_BUFFER 
+//         batch_index => INT32
+//         batch_index_error_message => COMPACT_NULLABLE_STRING
+//       error_message => COMPACT_NULLABLE_STRING
+//   throttle_time_ms => INT32
+pub struct ProduceResponse {
+    pub responses: CompactArray<ProduceResponseTopic>,
+    pub throttle_time_ms: i32,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct ProduceResponseTopic {
+    pub name: CompactString,
+    pub partition_responses: CompactArray<ProduceResponsePartition>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct ProduceResponsePartition {
+    pub index: i32,
+    pub error_code: i16,
+    pub base_offset: i64,
+    pub log_append_time_ms: i64,
+    pub log_start_offset: i64,
+    pub record_errors: CompactArray<ProduceResponseRecordError>,
+    pub error_message: CompactNullableString,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct ProduceResponseRecordError {
+    pub batch_index: i32,
+    pub batch_index_error_message: CompactNullableString,
+    pub tagged_fields: TaggedFields
+}
+
+impl ProduceResponse {
+    pub fn empty() -> ProduceResponse {
+        ProduceResponse {
+            responses: CompactArray { data: vec![] },
+            throttle_time_ms: 0,
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+impl ProduceResponsePartition {
+    pub fn error(index: i32, error: ErrorCode) -> ProduceResponsePartition {
+        ProduceResponsePartition {
+            index,
+            error_code: error.code(),
+            base_offset: -1,
+            log_append_time_ms: -1,
+            log_start_offset: -1,
+            record_errors: CompactArray { data: vec![] },
+            error_message: CompactNullableString { data: None },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+//
+// Metadata API
+//
+
+// Metadata Request (Version: 12) => [topics] allow_auto_topic_creation include_topic_authorized_operations TAG_BUFFER 
+//   topics => topic_id name TAG_BUFFER 
+//     topic_id => UUID
+//     name => COMPACT_NULLABLE_STRING
+//   allow_auto_topic_creation => BOOLEAN
+//   include_topic_authorized_operations => BOOLEAN
+// a null topics array asks for every topic, an empty one for none
+pub struct MetadataRequest {
+    // not part of the body, the response layout depends on it
+    pub api_version: i16,
+    pub topics: Option<CompactArray<MetadataRequestTopic>>,
+    pub allow_auto_topic_creation: bool,
+    pub include_topic_authorized_operations: bool,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct MetadataRequestTopic {
+    pub topic_id: Uuid,
+    pub name: CompactNullableString,
+    pub tagged_fields: TaggedFields
+}
+
+// Metadata Response (Version: 13) => throttle_time_ms [brokers] cluster_id controller_id [topics] error_code TAG_BUFFER 
+//   throttle_time_ms => INT32
+//   brokers => node_id host port rack TAG_BUFFER 
+//     node_id => INT32
+//     host => COMPACT_STRING
+//     port => INT32
+//     rack => COMPACT_NULLABLE_STRING
+//   cluster_id => COMPACT_NULLABLE_STRING
+//   controller_id => INT32
+//   topics => error_code name topic_id is_internal [partitions] topic_authorized_operations TAG_BUFFER 
+//     error_code => INT16
+//     name => COMPACT_NULLABLE_STRING
+//     topic_id => UUID
+//     is_internal => BOOLEAN
+//     partitions => error_code partition_index leader_id leader_epoch [replica_nodes] [isr_nodes] [offline_replicas] TAG_BUFFER 
+//       error_code => INT16
+//       partition_index => INT32
+//       leader_id => INT32
+//       leader_epoch => INT32
+//       replica_nodes => INT32
+//       isr_nodes => INT32
+//       offline_replicas => INT32
+//     topic_authorized_operations => INT32
+//   error_code => INT16 (version 13+)
+pub struct MetadataResponse {
+    pub throttle_time_ms: i32,
+    pub brokers: CompactArray<MetadataResponseBroker>,
+    pub cluster_id: CompactNullableString,
+    pub controller_id: i32,
+    pub topics: CompactArray<MetadataResponseTopic>,
+    // only encoded from version 13
+    pub error_code: Option<i16>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct MetadataResponseBroker {
+    pub node_id: i32,
+    pub host: CompactString,
+    pub port: i32,
+    pub rack: CompactNullableString,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct MetadataResponseTopic {
+    pub error_code: i16,
+    pub name: CompactNullableString,
+    pub topic_id: Uuid,
+    pub is_internal: bool,
+    pub partitions: CompactArray<MetadataResponsePartition>,
+    pub topic_authorized_operations: i32,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct MetadataResponsePartition {
+    pub error_code: i16,
+    pub partition_index: i32,
+    pub leader_id: i32,
+    pub leader_epoch: i32,
+    pub replica_nodes: CompactArray<i32>,
+    pub isr_nodes: CompactArray<i32>,
+    pub offline_replicas: CompactArray<i32>,
+    pub tagged_fields: TaggedFields
+}
+
+impl MetadataResponse {
+    pub fn empty(api_version: i16) -> MetadataResponse {
+        MetadataResponse {
+            throttle_time_ms: 0,
+            brokers: CompactArray { data: vec![] },
+            cluster_id: CompactNullableString { data: None },
+            controller_id: -1,
+            topics: CompactArray { data: vec![] },
+            error_code: (api_version >= 13).then_some(ErrorCode::None.code()),
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+impl MetadataResponseTopic {
+    pub fn error(name: Option<String>, topic_id: Uuid, error: ErrorCode) -> MetadataResponseTopic {
+        MetadataResponseTopic {
+            error_code: error.code(),
+            name: CompactNullableString { data: name.map(CompactString::new) },
+            topic_id,
+            is_internal: false,
+            partitions: CompactArray { data: vec![] },
+            topic_authorized_operations: i32::MIN,
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+//
+// ListOffsets API
+//
+
+// ListOffsets Request (Version: 8) => replica_id isolation_level [topics] TAG_BUFFER 
+//   replica_id => INT32
+//   isolation_level => INT8
+//   topics => name [partitions] TAG_BUFFER 
+//     name => COMPACT_STRING
+//     partitions => partition_index current_leader_epoch timestamp TAG_BUFFER 
+//       partition_index => INT32
+//       current_leader_epoch => INT32
+//       timestamp => INT64
+// timestamp is either a time in ms or one of the special values -1 (latest), -2 (earliest), -3 (max timestamp),
+// -4 (earliest local, version 8+) and -5 (latest tiered, version 9+)
+pub struct ListOffsetsRequest {
+    pub replica_id: i32,
+    pub isolation_level: i8,
+    pub topics: CompactArray<ListOffsetsRequestTopic>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct ListOffsetsRequestTopic {
+    pub name: CompactString,
+    pub partitions: CompactArray<ListOffsetsRequestPartition>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct ListOffsetsRequestPartition {
+    pub partition_index: i32,
+    pub current_leader_epoch: i32,
+    pub timestamp: i64,
+    pub tagged_fields: TaggedFields
+}
+
+// ListOffsets Response (Version: 8) => throttle_time_ms [topics] TAG_BUFFER 
+//   throttle_time_ms => INT32
+//   topics => name [partitions] TAG_BUFFER 
+//     name => COMPACT_STRING
+//     partitions => partition_index error_code timestamp offset leader_epoch TAG_BUFFER 
+//       partition_index => INT32
+//       error_code => INT16
+//       timestamp => INT64
+//       offset => INT64
+//       leader_epoch => INT32
+pub struct ListOffsetsResponse {
+    pub throttle_time_ms: i32,
+    pub topics: CompactArray<ListOffsetsResponseTopic>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct ListOffsetsResponseTopic {
+    pub name: CompactString,
+    pub partitions: CompactArray<ListOffsetsResponsePartition>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct ListOffsetsResponsePartition {
+    pub partition_index: i32,
+    pub error_code: i16,
+    pub timestamp: i64,
+    pub offset: i64,
+    pub leader_epoch: i32,
+    pub tagged_fields: TaggedFields
+}
+
+impl ListOffsetsResponse {
+    pub fn empty() -> ListOffsetsResponse {
+        ListOffsetsResponse {
+            throttle_time_ms: 0,
+            topics: CompactArray { data: vec![] },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+impl ListOffsetsResponsePartition {
+    pub fn error(partition_index: i32, error: ErrorCode) -> ListOffsetsResponsePartition {
+        ListOffsetsResponsePartition {
+            partition_index,
+            error_code: error.code(),
+            timestamp: -1,
+            offset: -1,
+            leader_epoch: -1,
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+//
+// CreateTopics API
+//
+
+// CreateTopics Request (Version: 7) => [topics] timeout_ms validate_only TAG_BUFFER 
+//   topics => name num_partitions replication_factor [assignments] [configs] TAG_BUFFER 
+//     name => COMPACT_STRING
+//     num_partitions => INT32
+//     replication_factor => INT16
+//     assignments => partition_index [broker_ids] TAG_BUFFER 
+//       partition_index => INT32
+//       broker_ids => INT32
+//     configs => name value TAG_BUFFER 
+//       name => COMPACT_STRING
+//       value => COMPACT_NULLABLE_STRING
+//   timeout_ms => INT32
+//   validate_only => BOOLEAN
+// num_partitions and replication_factor are -1 to use the broker defaults, or when assignments are given
+pub struct CreateTopicsRequest {
+    // not part of the body, the response layout depends on it
+    pub api_version: i16,
+    pub topics: CompactArray<CreatableTopic>,
+    pub timeout_ms: i32,
+    pub validate_only: bool,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct CreatableTopic {
+    pub name: CompactString,
+    pub num_partitions: i32,
+    pub replication_factor: i16,
+    pub assignments: CompactArray<CreatableReplicaAssignment>,
+    pub configs: CompactArray<CreatableTopicConfig>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct CreatableReplicaAssignment {
+    pub partition_index: i32,
+    pub broker_ids: CompactArray<i32>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct CreatableTopicConfig {
+    pub name: CompactString,
+    pub value: CompactNullableString,
+    pub tagged_fields: TaggedFields
+}
+
+// CreateTopics Response (Version: 7) => throttle_time_ms [topics] TAG_BUFFER 
+//   throttle_time_ms => INT32
+//   topics => name topic_id error_code error_message num_partitions replication_factor [configs] TAG_BUFFER 
+//     name => COMPACT_STRING
+//     topic_id => UUID (version 7+)
+//     error_code => INT16
+//     error_message => COMPACT_NULLABLE_STRING
+//     num_partitions => INT32
+//     replication_factor => INT16
+//     configs => name value read_only config_source is_sensitive TAG_BUFFER 
+//       name => COMPACT_STRING
+//       value => COMPACT_NULLABLE_STRING
+//       read_only => BOOLEAN
+//       config_source => INT8
+//       is_sensitive => BOOLEAN
+// configs is a nullable array, null when the topic was not created
+pub struct CreateTopicsResponse {
+    pub throttle_time_ms: i32,
+    pub topics: CompactArray<CreatableTopicResult>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct CreatableTopicResult {
+    pub name: CompactString,
+    // only encoded from version 7
+    pub topic_id: Option<Uuid>,
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub num_partitions: i32,
+    pub replication_factor: i16,
+    pub configs: Option<CompactArray<CreatableTopicConfigs>>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct CreatableTopicConfigs {
+    pub name: CompactString,
+    pub value: CompactNullableString,
+    pub read_only: bool,
+    pub config_source: i8,
+    pub is_sensitive: bool,
+    pub tagged_fields: TaggedFields
+}
+
+impl CreateTopicsResponse {
+    pub fn empty() -> CreateTopicsResponse {
+        CreateTopicsResponse {
+            throttle_time_ms: 0,
+            topics: CompactArray { data: vec![] },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+impl CreatableTopicResult {
+    pub fn error(name: &str, api_version: i16, error: ErrorCode, error_message: Option<String>) -> CreatableTopicResult {
+        CreatableTopicResult {
+            name: CompactString::new(name.to_string()),
+            topic_id: (api_version >= 7).then_some(Uuid::nil()),
+            error_code: error.code(),
+            error_message: CompactNullableString { data: error_message.map(CompactString::new) },
+            num_partitions: -1,
+            replication_factor: -1,
+            configs: None,
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+//
+// DeleteTopics API
 //
 
-pub struct ProduceRequest {}
+// DeleteTopics Request (Version: 6) => [topics] timeout_ms TAG_BUFFER 
+//   topics => name topic_id TAG_BUFFER 
+//     name => COMPACT_NULLABLE_STRING
+//     topic_id => UUID
+//   timeout_ms => INT32
+// a topic is named either by name, with a zero topic_id, or by topic_id, with a null name
+pub struct DeleteTopicsRequest {
+    pub topics: CompactArray<DeleteTopicState>,
+    pub timeout_ms: i32,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct DeleteTopicState {
+    pub name: CompactNullableString,
+    pub topic_id: Uuid,
+    pub tagged_fields: TaggedFields
+}
 
-pub struct ProduceResponse {}
+// DeleteTopics Response (Version: 6) => throttle_time_ms [responses] TAG_BUFFER 
+//   throttle_time_ms => INT32
+//   responses => name topic_id error_code error_message TAG_BUFFER 
+//     name => COMPACT_NULLABLE_STRING
+//     topic_id => UUID
+//     error_code => INT16
+//     error_message => COMPACT_NULLABLE_STRING
+pub struct DeleteTopicsResponse {
+    pub throttle_time_ms: i32,
+    pub responses: CompactArray<DeletableTopicResult>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct DeletableTopicResult {
+    pub name: CompactNullableString,
+    pub topic_id: Uuid,
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub tagged_fields: TaggedFields
+}
+
+impl DeleteTopicsResponse {
+    pub fn empty() -> DeleteTopicsResponse {
+        DeleteTopicsResponse {
+            throttle_time_ms: 0,
+            responses: CompactArray { data: vec![] },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+impl DeletableTopicResult {
+    pub fn error(name: Option<String>, topic_id: Uuid, error: ErrorCode, error_message: Option<String>) -> DeletableTopicResult {
+        DeletableTopicResult {
+            name: CompactNullableString { data: name.map(CompactString::new) },
+            topic_id,
+            error_code: error.code(),
+            error_message: CompactNullableString { data: error_message.map(CompactString::new) },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+//
+// CreatePartitions API
+//
+
+// CreatePartitions Request (Version: 3) => [topics] timeout_ms validate_only TAG_BUFFER 
+//   topics => name count [assignments] TAG_BUFFER 
+//     name => COMPACT_STRING
+//     count => INT32
+//     assignments => [broker_ids] TAG_BUFFER 
+//       broker_ids => INT32
+//   timeout_ms => INT32
+//   validate_only => BOOLEAN
+// count is the new total number of partitions, assignments is a nullable array with the replicas of each new partition
+pub struct CreatePartitionsRequest {
+    pub topics: CompactArray<CreatePartitionsTopic>,
+    pub timeout_ms: i32,
+    pub validate_only: bool,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct CreatePartitionsTopic {
+    pub name: CompactString,
+    pub count: i32,
+    pub assignments: Option<CompactArray<CreatePartitionsAssignment>>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct CreatePartitionsAssignment {
+    pub broker_ids: CompactArray<i32>,
+    pub tagged_fields: TaggedFields
+}
+
+// CreatePartitions Response (Version: 3) => throttle_time_ms [results] TAG_BUFFER 
+//   throttle_time_ms => INT32
+//   results => name error_code error_message TAG_BUFFER 
+//     name => COMPACT_STRING
+//     error_code => INT16
+//     error_message => COMPACT_NULLABLE_STRING
+pub struct CreatePartitionsResponse {
+    pub throttle_time_ms: i32,
+    pub results: CompactArray<CreatePartitionsTopicResult>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct CreatePartitionsTopicResult {
+    pub name: CompactString,
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub tagged_fields: TaggedFields
+}
+
+impl CreatePartitionsResponse {
+    pub fn empty() -> CreatePartitionsResponse {
+        CreatePartitionsResponse {
+            throttle_time_ms: 0,
+            results: CompactArray { data: vec![] },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+impl CreatePartitionsTopicResult {
+    pub fn error(name: &str, error: ErrorCode, error_message: Option<String>) -> CreatePartitionsTopicResult {
+        CreatePartitionsTopicResult {
+            name: CompactString::new(name.to_string()),
+            error_code: error.code(),
+            error_message: CompactNullableString { data: error_message.map(CompactString::new) },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+//
+// FindCoordinator API
+//
+
+// FindCoordinator Request (Version: 6) => key_type [coordinator_keys] TAG_BUFFER 
+//   key_type => INT8
+//   coordinator_keys => COMPACT_STRING
+// key_type is 0 for a group, 1 for a transactional id and 2 for a share group
+pub struct FindCoordinatorRequest {
+    pub key_type: i8,
+    pub coordinator_keys: CompactArray<CompactString>,
+    pub tagged_fields: TaggedFields
+}
+
+// FindCoordinator Response (Version: 6) => throttle_time_ms [coordinators] TAG_BUFFER 
+//   throttle_time_ms => INT32
+//   coordinators => key node_id host port error_code error_message TAG_BUFFER 
+//     key => COMPACT_STRING
+//     node_id => INT32
+//     host => COMPACT_STRING
+//     port => INT32
+//     error_code => INT16
+//     error_message => COMPACT_NULLABLE_STRING
+pub struct FindCoordinatorResponse {
+    pub throttle_time_ms: i32,
+    pub coordinators: CompactArray<Coordinator>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct Coordinator {
+    pub key: CompactString,
+    pub node_id: i32,
+    pub host: CompactString,
+    pub port: i32,
+    pub error_code: i16,
+    pub error_message: CompactNullableString,
+    pub tagged_fields: TaggedFields
+}
+
+impl FindCoordinatorResponse {
+    pub fn empty() -> FindCoordinatorResponse {
+        FindCoordinatorResponse {
+            throttle_time_ms: 0,
+            coordinators: CompactArray { data: vec![] },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+impl Coordinator {
+    pub fn error(key: &str, error: ErrorCode, error_message: Option<String>) -> Coordinator {
+        Coordinator {
+            key: CompactString::new(key.to_string()),
+            node_id: -1,
+            host: CompactString::new(String::new()),
+            port: -1,
+            error_code: error.code(),
+            error_message: CompactNullableString { data: error_message.map(CompactString::new) },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+//
+// JoinGroup API
+//
+
+// JoinGroup Request (Version: 9) => group_id session_timeout_ms rebalance_timeout_ms member_id group_instance_id protocol_type [protocols] reason TAG_BUFFER 
+//   group_id => COMPACT_STRING
+//   session_timeout_ms => INT32
+//   rebalance_timeout_ms => INT32
+//   member_id => COMPACT_STRING
+//   group_instance_id => COMPACT_NULLABLE_STRING
+//   protocol_type => COMPACT_STRING
+//   protocols => name metadata TAG_BUFFER 
+//     name => COMPACT_STRING
+//     metadata => COMPACT_BYTES
+//   reason => COMPACT_NULLABLE_STRING (version 8+)
+// member_id is empty on the first join, the coordinator answers MEMBER_ID_REQUIRED with the id to join with
+pub struct JoinGroupRequest {
+    // not part of the body, the response layout depends on it
+    pub api_version: i16,
+    // client_id of the request header, new member ids start with it
+    pub client_id: String,
+    pub group_id: CompactString,
+    pub session_timeout_ms: i32,
+    pub rebalance_timeout_ms: i32,
+    pub member_id: CompactString,
+    pub group_instance_id: CompactNullableString,
+    pub protocol_type: CompactString,
+    pub protocols: CompactArray<JoinGroupRequestProtocol>,
+    // only decoded from version 8
+    pub reason: Option<CompactNullableString>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct JoinGroupRequestProtocol {
+    pub name: CompactString,
+    pub metadata: CompactBytes,
+    pub tagged_fields: TaggedFields
+}
+
+// JoinGroup Response (Version: 9) => throttle_time_ms error_code generation_id protocol_type protocol_name leader skip_assignment member_id [members] TAG_BUFFER 
+//   throttle_time_ms => INT32
+//   error_code => INT16
+//   generation_id => INT32
+//   protocol_type => COMPACT_NULLABLE_STRING (version 7+)
+//   protocol_name => COMPACT_NULLABLE_STRING (COMPACT_STRING before version 7)
+//   leader => COMPACT_STRING
+//   skip_assignment => BOOLEAN (version 9+)
+//   member_id => COMPACT_STRING
+//   members => member_id group_instance_id metadata TAG_BUFFER 
+//     member_id => COMPACT_STRING
+//     group_instance_id => COMPACT_NULLABLE_STRING
+//     metadata => COMPACT_BYTES
+// only the leader gets the members, with their metadata for the selected protocol
+pub struct JoinGroupResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub generation_id: i32,
+    // only encoded from version 7
+    pub protocol_type: Option<CompactNullableString>,
+    pub protocol_name: CompactNullableString,
+    pub leader: CompactString,
+    // only encoded from version 9
+    pub skip_assignment: Option<bool>,
+    pub member_id: CompactString,
+    pub members: CompactArray<JoinGroupResponseMember>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct JoinGroupResponseMember {
+    pub member_id: CompactString,
+    pub group_instance_id: CompactNullableString,
+    pub metadata: CompactBytes,
+    pub tagged_fields: TaggedFields
+}
+
+impl JoinGroupResponse {
+    pub fn error(api_version: i16, error: ErrorCode, member_id: &str) -> JoinGroupResponse {
+        JoinGroupResponse {
+            throttle_time_ms: 0,
+            error_code: error.code(),
+            generation_id: -1,
+            protocol_type: (api_version >= 7).then_some(CompactNullableString { data: None }),
+            // not nullable before version 7
+            protocol_name: CompactNullableString { data: (api_version < 7).then(|| CompactString::new(String::new())) },
+            leader: CompactString::new(String::new()),
+            skip_assignment: (api_version >= 9).then_some(false),
+            member_id: CompactString::new(member_id.to_string()),
+            members: CompactArray { data: vec![] },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+//
+// Heartbeat API
+//
+
+// Heartbeat Request (Version: 4) => group_id generation_id member_id group_instance_id TAG_BUFFER 
+//   group_id => COMPACT_STRING
+//   generation_id => INT32
+//   member_id => COMPACT_STRING
+//   group_instance_id => COMPACT_NULLABLE_STRING
+pub struct HeartbeatRequest {
+    pub group_id: CompactString,
+    pub generation_id: i32,
+    pub member_id: CompactString,
+    pub group_instance_id: CompactNullableString,
+    pub tagged_fields: TaggedFields
+}
+
+// Heartbeat Response (Version: 4) => throttle_time_ms error_code TAG_BUFFER 
+//   throttle_time_ms => INT32
+//   error_code => INT16
+pub struct HeartbeatResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub tagged_fields: TaggedFields
+}
+
+impl HeartbeatResponse {
+    pub fn error(error: ErrorCode) -> HeartbeatResponse {
+        HeartbeatResponse {
+            throttle_time_ms: 0,
+            error_code: error.code(),
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+//
+// LeaveGroup API
+//
+
+// LeaveGroup Request (Version: 5) => group_id [members] TAG_BUFFER 
+//   group_id => COMPACT_STRING
+//   members => member_id group_instance_id reason TAG_BUFFER 
+//     member_id => COMPACT_STRING
+//     group_instance_id => COMPACT_NULLABLE_STRING
+//     reason => COMPACT_NULLABLE_STRING (version 5+)
+pub struct LeaveGroupRequest {
+    pub group_id: CompactString,
+    pub members: CompactArray<MemberIdentity>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct MemberIdentity {
+    pub member_id: CompactString,
+    pub group_instance_id: CompactNullableString,
+    // only decoded from version 5
+    pub reason: Option<CompactNullableString>,
+    pub tagged_fields: TaggedFields
+}
+
+// LeaveGroup Response (Version: 5) => throttle_time_ms error_code [members] TAG_BUFFER 
+//   throttle_time_ms => INT32
+//   error_code => INT16
+//   members => member_id group_instance_id error_code TAG_BUFFER 
+//     member_id => COMPACT_STRING
+//     group_instance_id => COMPACT_NULLABLE_STRING
+//     error_code => INT16
+pub struct LeaveGroupResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub members: CompactArray<MemberResponse>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct MemberResponse {
+    pub member_id: CompactString,
+    pub group_instance_id: CompactNullableString,
+    pub error_code: i16,
+    pub tagged_fields: TaggedFields
+}
+
+impl LeaveGroupResponse {
+    pub fn error(error: ErrorCode) -> LeaveGroupResponse {
+        LeaveGroupResponse {
+            throttle_time_ms: 0,
+            error_code: error.code(),
+            members: CompactArray { data: vec![] },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+//
+// SyncGroup API
+//
+
+// SyncGroup Request (Version: 5) => group_id generation_id member_id group_instance_id protocol_type protocol_name [assignments] TAG_BUFFER 
+//   group_id => COMPACT_STRING
+//   generation_id => INT32
+//   member_id => COMPACT_STRING
+//   group_instance_id => COMPACT_NULLABLE_STRING
+//   protocol_type => COMPACT_NULLABLE_STRING (version 5+)
+//   protocol_name => COMPACT_NULLABLE_STRING (version 5+)
+//   assignments => member_id assignment TAG_BUFFER 
+//     member_id => COMPACT_STRING
+//     assignment => COMPACT_BYTES
+// only the leader sends assignments, the other members send an empty array
+pub struct SyncGroupRequest {
+    // not part of the body, the response layout depends on it
+    pub api_version: i16,
+    pub group_id: CompactString,
+    pub generation_id: i32,
+    pub member_id: CompactString,
+    pub group_instance_id: CompactNullableString,
+    // only decoded from version 5
+    pub protocol_type: Option<CompactNullableString>,
+    pub protocol_name: Option<CompactNullableString>,
+    pub assignments: CompactArray<SyncGroupRequestAssignment>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct SyncGroupRequestAssignment {
+    pub member_id: CompactString,
+    pub assignment: CompactBytes,
+    pub tagged_fields: TaggedFields
+}
+
+// SyncGroup Response (Version: 5) => throttle_time_ms error_code protocol_type protocol_name assignment TAG_BUFFER 
+//   throttle_time_ms => INT32
+//   error_code => INT16
+//   protocol_type => COMPACT_NULLABLE_STRING (version 5+)
+//   protocol_name => COMPACT_NULLABLE_STRING (version 5+)
+//   assignment => COMPACT_BYTES
+pub struct SyncGroupResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    // only encoded from version 5
+    pub protocol_type: Option<CompactNullableString>,
+    pub protocol_name: Option<CompactNullableString>,
+    pub assignment: CompactBytes,
+    pub tagged_fields: TaggedFields
+}
+
+impl SyncGroupResponse {
+    pub fn error(api_version: i16, error: ErrorCode) -> SyncGroupResponse {
+        SyncGroupResponse {
+            throttle_time_ms: 0,
+            error_code: error.code(),
+            protocol_type: (api_version >= 5).then_some(CompactNullableString { data: None }),
+            protocol_name: (api_version >= 5).then_some(CompactNullableString { data: None }),
+            assignment: CompactBytes::new(vec![]),
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+//
+// OffsetCommit API
+//
+
+// OffsetCommit Request (Version: 9) => group_id generation_id_or_member_epoch member_id group_instance_id [topics] TAG_BUFFER 
+//   group_id => COMPACT_STRING
+//   generation_id_or_member_epoch => INT32
+//   member_id => COMPACT_STRING
+//   group_instance_id => COMPACT_NULLABLE_STRING
+//   topics => name [partitions] TAG_BUFFER 
+//     name => COMPACT_STRING
+//     partitions => partition_index committed_offset committed_leader_epoch committed_metadata TAG_BUFFER 
+//       partition_index => INT32
+//       committed_offset => INT64
+//       committed_leader_epoch => INT32
+//       committed_metadata => COMPACT_NULLABLE_STRING
+// a consumer outside of any group commits with generation -1 and an empty member id
+pub struct OffsetCommitRequest {
+    pub group_id: CompactString,
+    pub generation_id_or_member_epoch: i32,
+    pub member_id: CompactString,
+    pub group_instance_id: CompactNullableString,
+    pub topics: CompactArray<OffsetCommitRequestTopic>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct OffsetCommitRequestTopic {
+    pub name: CompactString,
+    pub partitions: CompactArray<OffsetCommitRequestPartition>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct OffsetCommitRequestPartition {
+    pub partition_index: i32,
+    pub committed_offset: i64,
+    pub committed_leader_epoch: i32,
+    pub committed_metadata: CompactNullableString,
+    pub tagged_fields: TaggedFields
+}
+
+// OffsetCommit Response (Version: 9) => throttle_time_ms [topics] TAG_BUFFER 
+//   throttle_time_ms => INT32
+//   topics => name [partitions] TAG_BUFFER 
+//     name => COMPACT_STRING
+//     partitions => partition_index error_code TAG_BUFFER 
+//       partition_index => INT32
+//       error_code => INT16
+pub struct OffsetCommitResponse {
+    pub throttle_time_ms: i32,
+    pub topics: CompactArray<OffsetCommitResponseTopic>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct OffsetCommitResponseTopic {
+    pub name: CompactString,
+    pub partitions: CompactArray<OffsetCommitResponsePartition>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct OffsetCommitResponsePartition {
+    pub partition_index: i32,
+    pub error_code: i16,
+    pub tagged_fields: TaggedFields
+}
+
+impl OffsetCommitResponse {
+    pub fn empty() -> OffsetCommitResponse {
+        OffsetCommitResponse {
+            throttle_time_ms: 0,
+            topics: CompactArray { data: vec![] },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+impl OffsetCommitResponsePartition {
+    pub fn error(partition_index: i32, error: ErrorCode) -> OffsetCommitResponsePartition {
+        OffsetCommitResponsePartition {
+            partition_index,
+            error_code: error.code(),
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+//
+// OffsetFetch API
+//
+
+// OffsetFetch Request (Version: 9) => [groups] require_stable TAG_BUFFER 
+//   groups => group_id member_id member_epoch [topics] TAG_BUFFER 
+//     group_id => COMPACT_STRING
+//     member_id => COMPACT_NULLABLE_STRING (version 9+)
+//     member_epoch => INT32 (version 9+)
+//     topics => name [partition_indexes] TAG_BUFFER 
+//       name => COMPACT_STRING
+//       partition_indexes => INT32
+//   require_stable => BOOLEAN
+// null topics fetch the offsets of every partition the group committed
+pub struct OffsetFetchRequest {
+    pub groups: CompactArray<OffsetFetchRequestGroup>,
+    pub require_stable: bool,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct OffsetFetchRequestGroup {
+    pub group_id: CompactString,
+    // only decoded from version 9, used by the consumer group protocol rather than classic groups
+    pub member_id: Option<CompactNullableString>,
+    pub member_epoch: Option<i32>,
+    pub topics: Option<CompactArray<OffsetFetchRequestTopics>>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct OffsetFetchRequestTopics {
+    pub name: CompactString,
+    pub partition_indexes: CompactArray<i32>,
+    pub tagged_fields: TaggedFields
+}
+
+// OffsetFetch Response (Version: 9) => throttle_time_ms [groups] TAG_BUFFER 
+//   throttle_time_ms => INT32
+//   groups => group_id [topics] error_code TAG_BUFFER 
+//     group_id => COMPACT_STRING
+//     topics => name [partitions] TAG_BUFFER 
+//       name => COMPACT_STRING
+//       partitions => partition_index committed_offset committed_leader_epoch metadata error_code TAG_BUFFER 
+//         partition_index => INT32
+//         committed_offset => INT64
+//         committed_leader_epoch => INT32
+//         metadata => COMPACT_NULLABLE_STRING
+//         error_code => INT16
+//     error_code => INT16
+pub struct OffsetFetchResponse {
+    pub throttle_time_ms: i32,
+    pub groups: CompactArray<OffsetFetchResponseGroup>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct OffsetFetchResponseGroup {
+    pub group_id: CompactString,
+    pub topics: CompactArray<OffsetFetchResponseTopics>,
+    pub error_code: i16,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct OffsetFetchResponseTopics {
+    pub name: CompactString,
+    pub partitions: CompactArray<OffsetFetchResponsePartitions>,
+    pub tagged_fields: TaggedFields
+}
+
+pub struct OffsetFetchResponsePartitions {
+    pub partition_index: i32,
+    pub committed_offset: i64,
+    pub committed_leader_epoch: i32,
+    pub metadata: CompactNullableString,
+    pub error_code: i16,
+    pub tagged_fields: TaggedFields
+}
+
+impl OffsetFetchResponse {
+    pub fn empty() -> OffsetFetchResponse {
+        OffsetFetchResponse {
+            throttle_time_ms: 0,
+            groups: CompactArray { data: vec![] },
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+impl OffsetFetchResponseGroup {
+    pub fn error(group_id: &str, error: ErrorCode) -> OffsetFetchResponseGroup {
+        OffsetFetchResponseGroup {
+            group_id: CompactString::new(group_id.to_string()),
+            topics: CompactArray { data: vec![] },
+            error_code: error.code(),
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+impl OffsetFetchResponsePartitions {
+    // a partition without a committed offset
+    pub fn no_offset(partition_index: i32) -> OffsetFetchResponsePartitions {
+        OffsetFetchResponsePartitions {
+            partition_index,
+            committed_offset: -1,
+            committed_leader_epoch: -1,
+            metadata: CompactNullableString { data: Some(CompactString::new(String::new())) },
+            error_code: ErrorCode::None.code(),
+            tagged_fields: TaggedFields(None)
+        }
+    }
+}
+
+//
+// OffsetDelete API
+//
+
+// OffsetDelete Request (Version: 0) => group_id [topics] 
+//   group_id => STRING
+//   topics => name [partitions] 
+//     name => STRING
+//     partitions => partition_index 
+//       partition_index => INT32
+// not a flexible version, no tagged fields
+pub struct OffsetDeleteRequest {
+    pub group_id: KafkaString,
+    pub topics: Array<OffsetDeleteRequestTopic>
+}
+
+pub struct OffsetDeleteRequestTopic {
+    pub name: KafkaString,
+    pub partitions: Array<OffsetDeleteRequestPartition>
+}
+
+pub struct OffsetDeleteRequestPartition {
+    pub partition_index: i32
+}
+
+// OffsetDelete Response (Version: 0) => error_code throttle_time_ms [topics] 
+//   error_code => INT16
+//   throttle_time_ms => INT32
+//   topics => name [partitions] 
+//     name => STRING
+//     partitions => partition_index error_code 
+//       partition_index => INT32
+//       error_code => INT16
+pub struct OffsetDeleteResponse {
+    pub error_code: i16,
+    pub throttle_time_ms: i32,
+    pub topics: Array<OffsetDeleteResponseTopic>
+}
+
+pub struct OffsetDeleteResponseTopic {
+    pub name: KafkaString,
+    pub partitions: Array<OffsetDeleteResponsePartition>
+}
+
+pub struct OffsetDeleteResponsePartition {
+    pub partition_index: i32,
+    pub error_code: i16
+}
+
+impl OffsetDeleteResponse {
+    pub fn error(error: ErrorCode) -> OffsetDeleteResponse {
+        OffsetDeleteResponse {
+            error_code: error.code(),
+            throttle_time_ms: 0,
+            topics: Array { data: vec![] }
+        }
+    }
+}
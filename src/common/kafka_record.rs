@@ -1,23 +1,52 @@
 use std::result::Result::Ok;
 use crc32c::crc32c;
 
+use crate::common::compression::{CompressionType, COMPRESSION_CODEC_MASK};
 use crate::errors::KafkaError;
-use super::{kafka_protocol::{RequestContext, TaggedFields}, primitive_types::{CompactArray, CompactString, SVarInt, UnsignedVarInt}, traits::{Decodable, Encodable}};
+use crate::storage::log_config::DEFAULT_MAX_MESSAGE_BYTES;
+use super::{kafka_protocol::{RequestContext, TaggedFields}, primitive_types::{CompactArray, CompactNullableString, CompactString, SVarInt}, traits::{Decodable, Encodable}};
 
 pub enum RecordValue {
+    RegisterBrokerRecord(RegisterBrokerRecord),
+    UnregisterBrokerRecord(UnregisterBrokerRecord),
     TopicRecord(TopicRecord),
     PartitionRecord(PartitionRecord),
+    ConfigRecord(ConfigRecord),
+    PartitionChangeRecord(PartitionChangeRecord),
+    FenceBrokerRecord(BrokerFencingRecord),
+    UnfenceBrokerRecord(BrokerFencingRecord),
+    RemoveTopicRecord(RemoveTopicRecord),
     FeatureLevelRecord(FeatureLevelRecord),
+    ProducerIdsRecord(ProducerIdsRecord),
+    BrokerRegistrationChangeRecord(BrokerRegistrationChangeRecord),
+    NoOpRecord(EmptyMetadataRecord),
+    BeginTransactionRecord(MetadataTransactionRecord),
+    EndTransactionRecord(EmptyMetadataRecord),
+    AbortTransactionRecord(MetadataTransactionRecord),
+    // user records, and metadata records of types this broker does not know, kept as-is
     RawBytesRecord(RawBytesRecord),
+    // null value, a tombstone in compacted topics
+    Null,
 }
 
 impl Encodable for RecordValue {
     fn encode(&self) -> Vec<u8> {
         match self {
+            RecordValue::RegisterBrokerRecord(register_broker_record) => register_broker_record.encode(),
+            RecordValue::UnregisterBrokerRecord(unregister_broker_record) => unregister_broker_record.encode(),
             RecordValue::TopicRecord(topic_record) => topic_record.encode(),
             RecordValue::PartitionRecord(partition_record) => partition_record.encode(),
+            RecordValue::ConfigRecord(config_record) => config_record.encode(),
+            RecordValue::PartitionChangeRecord(partition_change_record) => partition_change_record.encode(),
+            RecordValue::FenceBrokerRecord(fencing_record) | RecordValue::UnfenceBrokerRecord(fencing_record) => fencing_record.encode(),
+            RecordValue::RemoveTopicRecord(remove_topic_record) => remove_topic_record.encode(),
             RecordValue::FeatureLevelRecord(feature_level_record) => feature_level_record.encode(),
+            RecordValue::ProducerIdsRecord(producer_ids_record) => producer_ids_record.encode(),
+            RecordValue::BrokerRegistrationChangeRecord(registration_change_record) => registration_change_record.encode(),
+            RecordValue::NoOpRecord(empty_record) | RecordValue::EndTransactionRecord(empty_record) => empty_record.encode(),
+            RecordValue::BeginTransactionRecord(transaction_record) | RecordValue::AbortTransactionRecord(transaction_record) => transaction_record.encode(),
             RecordValue::RawBytesRecord(raw_bytes) => raw_bytes.data.to_vec(),
+            RecordValue::Null => Vec::new(),
         }
     }
 }
@@ -28,25 +57,36 @@ impl Decodable for RecordValue {
 
         // Helper function to decode metadata records
         let decode_metadata_record = |record_type: i8, buf: &[u8], request_context: &RequestContext| -> Result<(Self, usize), KafkaError> {
+            macro_rules! decode_as {
+                ($record:ident, $variant:ident) => {{
+                    let (record, record_size) = $record::decode(buf, request_context).map_err(|_| KafkaError::DecodeError)?;
+                    Ok( (RecordValue::$variant(record), record_size) )
+                }};
+            }
+
             match record_type {
-                2 => {
-                    let (topic_record, topic_record_size) =
-                        TopicRecord::decode(buf, request_context).map_err(|_| KafkaError::DecodeError)?;
-                    Ok( (RecordValue::TopicRecord(topic_record), topic_record_size) )
-                }
-                3 => {
-                    let (partition_record, partition_record_size) =
-                        PartitionRecord::decode(buf, request_context).map_err(|_| KafkaError::DecodeError)?;
-                    Ok( (RecordValue::PartitionRecord(partition_record), partition_record_size) )
-                }
-                12 => {
-                    let (feature_level_record, feature_level_record_size) =
-                        FeatureLevelRecord::decode(buf, request_context).map_err(|_| KafkaError::DecodeError)?;
-                    Ok( (RecordValue::FeatureLevelRecord(feature_level_record), feature_level_record_size) )
-                }
+                0 => decode_as!(RegisterBrokerRecord, RegisterBrokerRecord),
+                1 => decode_as!(UnregisterBrokerRecord, UnregisterBrokerRecord),
+                2 => decode_as!(TopicRecord, TopicRecord),
+                3 => decode_as!(PartitionRecord, PartitionRecord),
+                4 => decode_as!(ConfigRecord, ConfigRecord),
+                5 => decode_as!(PartitionChangeRecord, PartitionChangeRecord),
+                7 => decode_as!(BrokerFencingRecord, FenceBrokerRecord),
+                8 => decode_as!(BrokerFencingRecord, UnfenceBrokerRecord),
+                9 => decode_as!(RemoveTopicRecord, RemoveTopicRecord),
+                12 => decode_as!(FeatureLevelRecord, FeatureLevelRecord),
+                15 => decode_as!(ProducerIdsRecord, ProducerIdsRecord),
+                17 => decode_as!(BrokerRegistrationChangeRecord, BrokerRegistrationChangeRecord),
+                20 => decode_as!(EmptyMetadataRecord, NoOpRecord),
+                23 => decode_as!(MetadataTransactionRecord, BeginTransactionRecord),
+                24 => decode_as!(EmptyMetadataRecord, EndTransactionRecord),
+                25 => decode_as!(MetadataTransactionRecord, AbortTransactionRecord),
                 _ => {
-                    println!("Unrecognized metadata record type: {}", record_type);
-                    Err(KafkaError::DecodeError)
+                    // keep records this broker does not understand (ACLs, SCRAM credentials, quotas, ...)
+                    // so the rest of the log stays readable
+                    println!("Unrecognized metadata record type: {}, keeping raw bytes", record_type);
+                    let (raw_bytes, raw_bytes_size) = RawBytesRecord::decode(buf, request_context)?;
+                    Ok( (RecordValue::RawBytesRecord(raw_bytes), raw_bytes_size) )
                 }
             }
         };
@@ -70,16 +110,30 @@ impl Decodable for RecordValue {
 }
 
 
+// headerKeyLength: varint
+// headerKey: String
+// headerValueLength: varint
+// Value: byte[]
 pub struct RecordHeader {
     pub header_key: CompactString,
-    pub value: Vec<u8>,
+    pub value: Option<Vec<u8>>,
 }
 
 impl Encodable for RecordHeader {
     fn encode(&self) -> Vec<u8> {
         let mut buf: Vec<u8> = Vec::new();
-        buf.extend(&self.header_key.encode());
-        buf.extend(&self.value);
+        buf.extend(SVarInt::new(self.header_key.data.len() as i32).encode());
+        buf.extend(self.header_key.data.as_bytes());
+
+        match &self.value {
+            Some(value) => {
+                buf.extend(SVarInt::new(value.len() as i32).encode());
+                buf.extend(value);
+            },
+            None => {
+                buf.extend(SVarInt::new(-1_i32).encode());
+            }
+        }
 
         buf
     }
@@ -90,15 +144,34 @@ impl Decodable for RecordHeader {
         println!("  Decoding record header...");
         let mut offset = 0;
 
-        let (header_key, header_key_size) = CompactString::decode(&buf[offset..], &RequestContext::None).map_err(|_| KafkaError::DecodeError)?;
-        offset += header_key_size;
+        let empty_request_context = &RequestContext::None;
+
+        let (key_size, key_size_byte_len) = SVarInt::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += key_size_byte_len;
+        if key_size.data < 0 || buf.len() < offset + key_size.data as usize {
+            println!("Insufficient data to decode RecordHeader key");
+            return Err(KafkaError::DecodeError);
+        }
+        let header_key = String::from_utf8(buf[offset..offset + key_size.data as usize].to_vec()).map_err(|_| KafkaError::DecodeError)?;
+        offset += key_size.data as usize;
 
-        let value = &buf[offset..];
-        offset += value.len();
+        let (value_size, value_size_byte_len) = SVarInt::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += value_size_byte_len;
+        let value = if value_size.data < 0 { // -1 means null
+            None
+        } else {
+            if buf.len() < offset + value_size.data as usize {
+                println!("Insufficient data to decode RecordHeader value");
+                return Err(KafkaError::DecodeError);
+            }
+            let value = &buf[offset..offset + value_size.data as usize];
+            offset += value_size.data as usize;
+            Some(value.to_vec())
+        };
 
         Ok((RecordHeader {
-            header_key,
-            value: value.to_vec(),
+            header_key: CompactString::new(header_key),
+            value,
         }, offset))
     }
 }
@@ -127,18 +200,25 @@ impl Encodable for Record {
                 temp_buf.extend(key);
             },
             None => {
-                temp_buf.extend(SVarInt::new(-1 as i32).encode());
+                temp_buf.extend(SVarInt::new(-1_i32).encode());
             }
         }
 
         // encode value
         // we need to encode the record value first to get the length because, record is not a Vec<u8> but a type like TopicRecord, PartitionRecord, etc.
-        let value_encoded = &self.value.encode();
-        temp_buf.extend(SVarInt::new(value_encoded.len() as i32).encode());
-        temp_buf.extend(value_encoded);
+        match &self.value {
+            RecordValue::Null => {
+                temp_buf.extend(SVarInt::new(-1_i32).encode());
+            },
+            value => {
+                let value_encoded = &value.encode();
+                temp_buf.extend(SVarInt::new(value_encoded.len() as i32).encode());
+                temp_buf.extend(value_encoded);
+            }
+        }
 
         // encode headers
-        temp_buf.extend(UnsignedVarInt::new(self.headers.len() as u32).encode());
+        temp_buf.extend(SVarInt::new(self.headers.len() as i32).encode());
         for header in &self.headers {
             temp_buf.extend(header.encode());
         }
@@ -188,22 +268,25 @@ impl Decodable for Record {
         let (key_size, key_size_byte_len) = SVarInt::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
         offset += key_size_byte_len;
         println!("  Key size: {}, {}", key_size.data, key_size_byte_len);
-        let key = if key_size.data == -1 { // -1 means null
+        let key = if key_size.data < 0 { // -1 means null
             None
         } else {
-            let key = &buf[offset..offset + key_size.data as usize];
-            offset += key_size.data as usize;
-            Some(key.to_vec())
+            Some(read_bytes!(key_size.data as usize).to_vec())
         };
 
         let (value_size, value_size_byte_len) = SVarInt::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
         offset += value_size_byte_len;
         println!("  Value size: {}, #bytes: {}", value_size.data, value_size_byte_len);
 
-        let (value, _) = RecordValue::decode(&buf[offset..offset+value_size.data as usize], request_context).map_err(|_| KafkaError::DecodeError)?;
-        offset += value_size.data as usize;
+        let value = if value_size.data < 0 { // -1 means null
+            RecordValue::Null
+        } else {
+            let (value, _) = RecordValue::decode(read_bytes!(value_size.data as usize), request_context).map_err(|_| KafkaError::DecodeError)?;
+            value
+        };
 
-        let (headers_size, hs_byte_len) = UnsignedVarInt::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        // headers count is a signed varint like the other record fields
+        let (headers_size, hs_byte_len) = SVarInt::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
         offset += hs_byte_len;
         println!("  Headers size: {}, {}", headers_size.data, hs_byte_len);
 
@@ -234,12 +317,7 @@ pub struct RecordValueMetadata {
 
 impl Encodable for RecordValueMetadata {
     fn encode(&self) -> Vec<u8> {
-        let mut buf: Vec<u8> = Vec::new();
-        buf.push(self.frame_version as u8);
-        buf.push(self.record_type as u8);
-        buf.push(self.version as u8);
-
-        buf
+        vec![self.frame_version as u8, self.record_type as u8, self.version as u8]
     }
 }
 
@@ -383,6 +461,9 @@ pub struct PartitionRecord {
     pub leader_epoch: i32,
     pub partition_epoch: i32,
     pub directories: CompactArray<uuid::Uuid>,
+    pub leader_recovery_state: i8,
+    pub eligible_leader_replicas: Option<CompactArray<i32>>,
+    pub last_known_elr: Option<CompactArray<i32>>,
     pub tagged_fields: TaggedFields,
 }
 
@@ -399,8 +480,21 @@ impl Encodable for PartitionRecord {
         buf.extend(&self.leader.to_be_bytes());
         buf.extend(&self.leader_epoch.to_be_bytes());
         buf.extend(&self.partition_epoch.to_be_bytes());
-        buf.extend(&self.directories.encode());
-        buf.extend(&self.tagged_fields.encode());
+        if self.value_metadata.version >= 1 {
+            buf.extend(&self.directories.encode());
+        }
+
+        let mut tagged_fields = self.tagged_fields.clone();
+        if self.leader_recovery_state != 0 {
+            tagged_fields.insert(0, self.leader_recovery_state.encode());
+        }
+        if let Some(eligible_leader_replicas) = &self.eligible_leader_replicas {
+            tagged_fields.insert(1, eligible_leader_replicas.encode());
+        }
+        if let Some(last_known_elr) = &self.last_known_elr {
+            tagged_fields.insert(2, last_known_elr.encode());
+        }
+        buf.extend(&tagged_fields.encode());
 
         buf
     }
@@ -449,12 +543,21 @@ impl Decodable for PartitionRecord {
         let leader_epoch = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
         let partition_epoch = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
 
-        let (directories, dir_byte_len) = CompactArray::<uuid::Uuid>::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
-        offset += dir_byte_len;
+        let directories = if value_metadata.version >= 1 {
+            let (directories, dir_byte_len) = CompactArray::<uuid::Uuid>::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+            offset += dir_byte_len;
+            directories
+        } else {
+            CompactArray::new(vec![])
+        };
 
-        let (tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        let (mut tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
         offset += tf_byte_len;
 
+        let leader_recovery_state = take_tagged::<i8>(&mut tagged_fields, 0)?.unwrap_or(0);
+        let eligible_leader_replicas = take_tagged::<CompactArray<i32>>(&mut tagged_fields, 1)?;
+        let last_known_elr = take_tagged::<CompactArray<i32>>(&mut tagged_fields, 2)?;
+
         Ok((PartitionRecord {
             value_metadata,
             partition_id,
@@ -467,6 +570,9 @@ impl Decodable for PartitionRecord {
             leader_epoch,
             partition_epoch,
             directories,
+            leader_recovery_state,
+            eligible_leader_replicas,
+            last_known_elr,
             tagged_fields,
         }, offset))
 
@@ -552,111 +658,202 @@ impl Decodable for FeatureLevelRecord {
 }
 
 
-// RawBytesRecord
-pub struct RawBytesRecord {
-    pub data: Vec<u8>,
+// {
+//     "apiKey": 0,
+//     "type": "metadata",
+//     "name": "RegisterBrokerRecord",
+//     // Version 1 adds InControlledShutdown
+//     // Version 2 adds IsMigratingZkBroker
+//     // Version 3 adds LogDirs
+//     "validVersions": "0-3",
+//     "flexibleVersions": "0+",
+//     "fields": [
+//       { "name": "BrokerId", "type": "int32", "versions": "0+", "entityType": "brokerId",
+//         "about": "The broker id." },
+//       { "name": "IsMigratingZkBroker", "type": "bool", "versions": "2+", "default": "false",
+//         "about": "True if the registering broker is a ZK broker." },
+//       { "name": "IncarnationId", "type": "uuid", "versions": "0+",
+//         "about": "The incarnation ID of the broker process" },
+//       { "name": "BrokerEpoch", "type": "int64", "versions": "0+",
+//         "about": "The broker epoch assigned by the controller." },
+//       { "name": "EndPoints", "type": "[]BrokerEndpoint", "versions": "0+",
+//         "about": "The endpoints that can be used to communicate with this broker.", "fields": [
+//         { "name": "Name", "type": "string", "versions": "0+", "mapKey": true,
+//           "about": "The name of the endpoint." },
+//         { "name": "Host", "type": "string", "versions": "0+",
+//           "about": "The hostname." },
+//         { "name": "Port", "type": "uint16", "versions": "0+",
+//           "about": "The port." },
+//         { "name": "SecurityProtocol", "type": "int16", "versions": "0+",
+//           "about": "The security protocol." }
+//       ]},
+//       { "name": "Features", "type": "[]BrokerFeature",
+//         "about": "The features on this broker", "versions": "0+", "fields": [
+//         { "name": "Name", "type": "string", "versions": "0+", "mapKey": true,
+//           "about": "The feature name." },
+//         { "name": "MinSupportedVersion", "type": "int16", "versions": "0+",
+//           "about": "The minimum supported feature level." },
+//         { "name": "MaxSupportedVersion", "type": "int16", "versions": "0+",
+//           "about": "The maximum supported feature level." }
+//       ]},
+//       { "name": "Rack", "type": "string", "versions": "0+", "nullableVersions": "0+",
+//         "about": "The broker rack." },
+//       { "name": "Fenced", "type": "bool", "versions": "0+", "default": "true",
+//         "about": "True if the broker is fenced." },
+//       { "name": "InControlledShutdown", "type": "bool", "versions": "1+", "default": "false",
+//         "about": "True if the broker is in controlled shutdown." },
+//       { "name": "LogDirs", "type":  "[]uuid", "versions":  "3+",
+//         "about": "Log directories configured in this broker which are available." }
+//     ]
+// }
+pub struct RegisterBrokerRecord {
+    pub value_metadata: RecordValueMetadata,
+    pub broker_id: i32,
+    pub is_migrating_zk_broker: bool,
+    pub incarnation_id: uuid::Uuid,
+    pub broker_epoch: i64,
+    pub end_points: CompactArray<BrokerEndpoint>,
+    pub features: CompactArray<BrokerFeature>,
+    pub rack: CompactNullableString,
+    pub fenced: bool,
+    pub in_controlled_shutdown: bool,
+    pub log_dirs: CompactArray<uuid::Uuid>,
+    pub tagged_fields: TaggedFields,
 }
 
-impl Encodable for RawBytesRecord {
+impl Encodable for RegisterBrokerRecord {
     fn encode(&self) -> Vec<u8> {
-        self.data.to_vec()
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(&self.value_metadata.encode());
+        buf.extend(&self.broker_id.to_be_bytes());
+        if self.value_metadata.version >= 2 {
+            buf.push(self.is_migrating_zk_broker as u8);
+        }
+        buf.extend(self.incarnation_id.as_bytes());
+        buf.extend(&self.broker_epoch.to_be_bytes());
+        buf.extend(&self.end_points.encode());
+        buf.extend(&self.features.encode());
+        buf.extend(&self.rack.encode());
+        buf.push(self.fenced as u8);
+        if self.value_metadata.version >= 1 {
+            buf.push(self.in_controlled_shutdown as u8);
+        }
+        if self.value_metadata.version >= 3 {
+            buf.extend(&self.log_dirs.encode());
+        }
+        buf.extend(&self.tagged_fields.encode());
+
+        buf
     }
 }
 
-impl Decodable for RawBytesRecord {
-    fn decode(buf: &[u8], _: &RequestContext) -> Result<(RawBytesRecord, usize), KafkaError> {
+impl Decodable for RegisterBrokerRecord {
+    fn decode(buf: &[u8], _: &RequestContext) -> Result<(RegisterBrokerRecord, usize), KafkaError> {
+        println!("      Decoding register broker record...");
+        let mut offset = 0;
 
-        Ok((RawBytesRecord {
-            data: buf.to_vec(),
-        }, buf.len()))
+        macro_rules! read_bytes {
+            ($size:expr) => {{
+            if buf.len() < offset + $size {
+                println!("Insufficient data to decode RegisterBrokerRecord");
+                return Err(KafkaError::DecodeError);
+            }
+            let bytes = &buf[offset..offset + $size];
+            offset += $size;
+            bytes
+            }};
+        }
+
+        let empty_request_context = &RequestContext::None;
+
+        let (value_metadata, vm_byte_len) = RecordValueMetadata::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += vm_byte_len;
+
+        let broker_id = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let is_migrating_zk_broker = if value_metadata.version >= 2 {
+            read_bytes!(1)[0] != 0
+        } else {
+            false
+        };
+
+        let incarnation_id = uuid::Uuid::from_slice(read_bytes!(16)).map_err(|_| KafkaError::DecodeError)?;
+        let broker_epoch = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (end_points, ep_byte_len) = CompactArray::<BrokerEndpoint>::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += ep_byte_len;
+
+        let (features, f_byte_len) = CompactArray::<BrokerFeature>::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += f_byte_len;
+
+        let (rack, rack_byte_len) = CompactNullableString::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += rack_byte_len;
+
+        let fenced = read_bytes!(1)[0] != 0;
+
+        let in_controlled_shutdown = if value_metadata.version >= 1 {
+            read_bytes!(1)[0] != 0
+        } else {
+            false
+        };
+
+        let log_dirs = if value_metadata.version >= 3 {
+            let (log_dirs, ld_byte_len) = CompactArray::<uuid::Uuid>::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+            offset += ld_byte_len;
+            log_dirs
+        } else {
+            CompactArray::new(vec![])
+        };
+
+        let (tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += tf_byte_len;
+
+        Ok((RegisterBrokerRecord {
+            value_metadata,
+            broker_id,
+            is_migrating_zk_broker,
+            incarnation_id,
+            broker_epoch,
+            end_points,
+            features,
+            rack,
+            fenced,
+            in_controlled_shutdown,
+            log_dirs,
+            tagged_fields,
+        }, offset))
     }
 }
 
-// baseOffset: int64
-// batchLength: int32
-// partitionLeaderEpoch: int32
-// magic: int8 (current magic value is 2)
-// crc: uint32
-// attributes: int16
-//     bit 0~2:
-//         0: no compression
-//         1: gzip
-//         2: snappy
-//         3: lz4
-//         4: zstd
-//     bit 3: timestampType
-//     bit 4: isTransactional (0 means not transactional)
-//     bit 5: isControlBatch (0 means not a control batch)
-//     bit 6: hasDeleteHorizonMs (0 means baseTimestamp is not set as the delete horizon for compaction)
-//     bit 7~15: unused
-// lastOffsetDelta: int32
-// baseTimestamp: int64
-// maxTimestamp: int64
-// producerId: int64
-// producerEpoch: int16
-// baseSequence: int32
-// records: [Record]
-pub struct RecordBatch {
-    pub base_offset: i64,
-    pub partition_leader_epoch: i32,
-    pub magic: i8,
-    pub crc: u32,
-    pub attributes: i16,
-    pub last_offset_delta: i32,
-    pub base_timestamp: i64,
-    pub max_timestamp: i64,
-    pub producer_id: i64,
-    pub producer_epoch: i16,
-    pub base_sequence: i32,
-    pub records: Vec<Record>,
+pub struct BrokerEndpoint {
+    pub name: CompactString,
+    pub host: CompactString,
+    pub port: u16,
+    pub security_protocol: i16,
+    pub tagged_fields: TaggedFields,
 }
 
-impl Encodable for RecordBatch {
+impl Encodable for BrokerEndpoint {
     fn encode(&self) -> Vec<u8> {
-        // collect all the fields into a temporary buffer
-        let mut after_crc_buf: Vec<u8> = Vec::new();
-        after_crc_buf.extend(&self.attributes.to_be_bytes());
-        after_crc_buf.extend(&self.last_offset_delta.to_be_bytes());
-        after_crc_buf.extend(&self.base_timestamp.to_be_bytes());
-        after_crc_buf.extend(&self.max_timestamp.to_be_bytes());
-        after_crc_buf.extend(&self.producer_id.to_be_bytes());
-        after_crc_buf.extend(&self.producer_epoch.to_be_bytes());
-        after_crc_buf.extend(&self.base_sequence.to_be_bytes());
-
-        // encode records
-        after_crc_buf.extend(&(self.records.len() as i32).to_be_bytes());
-        for record in &self.records {
-            after_crc_buf.extend(record.encode());
-        }
-
-        // compute CRC
-        let crc = crc32c(&after_crc_buf);
-
-        // collect all the fields into a temporary buffer
-        let mut temp_buf: Vec<u8> = Vec::new();
-        temp_buf.extend(&self.partition_leader_epoch.to_be_bytes());
-        temp_buf.extend(&self.magic.to_be_bytes());
-        temp_buf.extend(&crc.to_be_bytes());
-        temp_buf.extend(&after_crc_buf);
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(&self.name.encode());
+        buf.extend(&self.host.encode());
+        buf.extend(&self.port.to_be_bytes());
+        buf.extend(&self.security_protocol.to_be_bytes());
+        buf.extend(&self.tagged_fields.encode());
 
-        // finally, encode the batch length and append the temporary buffer
-        let mut buf = Vec::new();
-        buf.extend(&self.base_offset.to_be_bytes());
-        buf.extend(&(temp_buf.len() as i32).to_be_bytes());
-        buf.extend(&temp_buf);
-        
         buf
     }
 }
 
-impl Decodable for RecordBatch {
-    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(RecordBatch, usize), KafkaError> {
+impl Decodable for BrokerEndpoint {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(BrokerEndpoint, usize), KafkaError> {
         let mut offset = 0;
 
         macro_rules! read_bytes {
             ($size:expr) => {{
             if buf.len() < offset + $size {
-                println!("Insufficient data to decode RecordBatch");
+                println!("Insufficient data to decode BrokerEndpoint");
                 return Err(KafkaError::DecodeError);
             }
             let bytes = &buf[offset..offset + $size];
@@ -665,56 +862,1040 @@ impl Decodable for RecordBatch {
             }};
         }
 
-        let base_offset = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
-        println!("\nBase offset: {}", base_offset);
+        let (name, name_byte_len) = CompactString::decode(&buf[offset..], request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += name_byte_len;
 
-        let batch_length = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
-        println!("Batch length: {}", batch_length);
+        let (host, host_byte_len) = CompactString::decode(&buf[offset..], request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += host_byte_len;
 
-        let partition_leader_epoch = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
-        println!("Partition leader epoch: {}", partition_leader_epoch);
-        
-        let magic = i8::from_be_bytes(read_bytes!(1).try_into().map_err(|_| KafkaError::DecodeError)?);
-        println!("Magic: {}", magic);
+        let port = u16::from_be_bytes(read_bytes!(2).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let security_protocol = i16::from_be_bytes(read_bytes!(2).try_into().map_err(|_| KafkaError::DecodeError)?);
 
-        let crc = u32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
-        println!("CRC: {}", crc);
+        let (tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += tf_byte_len;
 
-        let attributes = i16::from_be_bytes(read_bytes!(2).try_into().map_err(|_| KafkaError::DecodeError)?);
-        println!("Attributes: {}", attributes);
+        Ok((BrokerEndpoint {
+            name,
+            host,
+            port,
+            security_protocol,
+            tagged_fields,
+        }, offset))
+    }
+}
 
-        let last_offset_delta = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
-        println!("Last offset delta: {}", last_offset_delta);
+pub struct BrokerFeature {
+    pub name: CompactString,
+    pub min_supported_version: i16,
+    pub max_supported_version: i16,
+    pub tagged_fields: TaggedFields,
+}
 
-        let base_timestamp = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
-        println!("Base timestamp: {}", base_timestamp);
+impl Encodable for BrokerFeature {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(&self.name.encode());
+        buf.extend(&self.min_supported_version.to_be_bytes());
+        buf.extend(&self.max_supported_version.to_be_bytes());
+        buf.extend(&self.tagged_fields.encode());
 
-        let max_timestamp = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
-        println!("Max timestamp: {}", max_timestamp);
+        buf
+    }
+}
 
-        let producer_id = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
-        println!("Producer ID: {}", producer_id);
+impl Decodable for BrokerFeature {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(BrokerFeature, usize), KafkaError> {
+        let mut offset = 0;
 
-        let producer_epoch = i16::from_be_bytes(read_bytes!(2).try_into().map_err(|_| KafkaError::DecodeError)?);
-        println!("Producer epoch: {}", producer_epoch);
+        macro_rules! read_bytes {
+            ($size:expr) => {{
+            if buf.len() < offset + $size {
+                println!("Insufficient data to decode BrokerFeature");
+                return Err(KafkaError::DecodeError);
+            }
+            let bytes = &buf[offset..offset + $size];
+            offset += $size;
+            bytes
+            }};
+        }
 
-        let base_sequence = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
-        println!("Base sequence: {}", base_sequence);
+        let (name, name_byte_len) = CompactString::decode(&buf[offset..], request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += name_byte_len;
 
-        let num_records = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
-        println!("Number of records: {}", num_records);
+        let min_supported_version = i16::from_be_bytes(read_bytes!(2).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let max_supported_version = i16::from_be_bytes(read_bytes!(2).try_into().map_err(|_| KafkaError::DecodeError)?);
 
-        // TODO: Use this to read multiple record batches simultaneously from the file
-        // batch_length -= 49; // adjust batch length by subtracting the length of all the fields above
-        println!("Decoding {} records...", num_records);
-        let mut records: Vec<Record> = Vec::new();
-        for _ in 0..num_records {
-            let (record, record_size) = Record::decode(&buf[offset..], request_context).map_err(|_| KafkaError::DecodeError)?;
-            offset += record_size;
-            records.push(record);
-        }
+        let (tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += tf_byte_len;
 
-        println!("Decoded {} records", records.len());
+        Ok((BrokerFeature {
+            name,
+            min_supported_version,
+            max_supported_version,
+            tagged_fields,
+        }, offset))
+    }
+}
+
+// {
+//     "apiKey": 1,
+//     "type": "metadata",
+//     "name": "UnregisterBrokerRecord",
+//     "validVersions": "0",
+//     "flexibleVersions": "0+",
+//     "fields": [
+//       { "name": "BrokerId", "type": "int32", "versions": "0+", "entityType": "brokerId",
+//         "about": "The broker id." },
+//       { "name": "BrokerEpoch", "type": "int64", "versions": "0+",
+//         "about": "The broker epoch." }
+//     ]
+// }
+pub struct UnregisterBrokerRecord {
+    pub value_metadata: RecordValueMetadata,
+    pub broker_id: i32,
+    pub broker_epoch: i64,
+    pub tagged_fields: TaggedFields,
+}
+
+impl Encodable for UnregisterBrokerRecord {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(&self.value_metadata.encode());
+        buf.extend(&self.broker_id.to_be_bytes());
+        buf.extend(&self.broker_epoch.to_be_bytes());
+        buf.extend(&self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Decodable for UnregisterBrokerRecord {
+    fn decode(buf: &[u8], _: &RequestContext) -> Result<(UnregisterBrokerRecord, usize), KafkaError> {
+        println!("      Decoding unregister broker record...");
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($size:expr) => {{
+            if buf.len() < offset + $size {
+                println!("Insufficient data to decode UnregisterBrokerRecord");
+                return Err(KafkaError::DecodeError);
+            }
+            let bytes = &buf[offset..offset + $size];
+            offset += $size;
+            bytes
+            }};
+        }
+
+        let empty_request_context = &RequestContext::None;
+
+        let (value_metadata, vm_byte_len) = RecordValueMetadata::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += vm_byte_len;
+
+        let broker_id = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let broker_epoch = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += tf_byte_len;
+
+        Ok((UnregisterBrokerRecord {
+            value_metadata,
+            broker_id,
+            broker_epoch,
+            tagged_fields,
+        }, offset))
+    }
+}
+
+// {
+//     "apiKey": 4,
+//     "type": "metadata",
+//     "name": "ConfigRecord",
+//     "validVersions": "0",
+//     "flexibleVersions": "0+",
+//     "fields": [
+//       { "name": "ResourceType", "type": "int8", "versions": "0+",
+//         "about": "The type of resource this configuration applies to." },
+//       { "name": "ResourceName", "type": "string", "versions": "0+",
+//         "about": "The name of the resource this configuration applies to." },
+//       { "name": "Name", "type": "string", "versions": "0+",
+//         "about": "The name of the configuration key." },
+//       { "name": "Value", "type": "string", "versions": "0+", "nullableVersions": "0+",
+//         "about": "The value of the configuration, or null if the it should be deleted." }
+//     ]
+// }
+pub struct ConfigRecord {
+    pub value_metadata: RecordValueMetadata,
+    pub resource_type: i8,
+    pub resource_name: CompactString,
+    pub name: CompactString,
+    pub value: CompactNullableString,
+    pub tagged_fields: TaggedFields,
+}
+
+impl Encodable for ConfigRecord {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(&self.value_metadata.encode());
+        buf.extend(&self.resource_type.to_be_bytes());
+        buf.extend(&self.resource_name.encode());
+        buf.extend(&self.name.encode());
+        buf.extend(&self.value.encode());
+        buf.extend(&self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Decodable for ConfigRecord {
+    fn decode(buf: &[u8], _: &RequestContext) -> Result<(ConfigRecord, usize), KafkaError> {
+        println!("      Decoding config record...");
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($size:expr) => {{
+            if buf.len() < offset + $size {
+                println!("Insufficient data to decode ConfigRecord");
+                return Err(KafkaError::DecodeError);
+            }
+            let bytes = &buf[offset..offset + $size];
+            offset += $size;
+            bytes
+            }};
+        }
+
+        let empty_request_context = &RequestContext::None;
+
+        let (value_metadata, vm_byte_len) = RecordValueMetadata::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += vm_byte_len;
+
+        let resource_type = i8::from_be_bytes(read_bytes!(1).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (resource_name, rn_byte_len) = CompactString::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += rn_byte_len;
+
+        let (name, name_byte_len) = CompactString::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += name_byte_len;
+
+        let (value, value_byte_len) = CompactNullableString::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += value_byte_len;
+
+        let (tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += tf_byte_len;
+
+        Ok((ConfigRecord {
+            value_metadata,
+            resource_type,
+            resource_name,
+            name,
+            value,
+            tagged_fields,
+        }, offset))
+    }
+}
+
+// {
+//     "apiKey": 5,
+//     "type": "metadata",
+//     "name": "PartitionChangeRecord",
+//     // Version 1 adds Directories for KIP-858.
+//     // Version 2 implements Eligible Leader Replicas and LastKnownElr as described in KIP-966.
+//     "validVersions": "0-2",
+//     "flexibleVersions": "0+",
+//     "fields": [
+//       { "name": "PartitionId", "type": "int32", "versions": "0+", "default": "-1",
+//         "about": "The partition id." },
+//       { "name": "TopicId", "type": "uuid", "versions": "0+",
+//         "about": "The unique ID of this topic." },
+//       { "name": "Isr", "type":  "[]int32", "default": "null", "entityType": "brokerId",
+//         "versions": "0+", "nullableVersions": "0+", "taggedVersions": "0+", "tag": 0,
+//         "about": "null if the ISR didn't change; the new in-sync replicas otherwise." },
+//       { "name": "Leader", "type": "int32", "default": "-2", "entityType": "brokerId",
+//         "versions": "0+", "taggedVersions": "0+", "tag": 1,
+//         "about": "-1 if there is now no leader; -2 if the leader didn't change; the new leader otherwise." },
+//       { "name": "Replicas", "type": "[]int32", "default": "null", "entityType": "brokerId",
+//         "versions": "0+", "nullableVersions": "0+", "taggedVersions": "0+", "tag": 2,
+//         "about": "null if the replicas didn't change; the new replicas otherwise." },
+//       { "name": "RemovingReplicas", "type": "[]int32", "default": "null", "entityType": "brokerId",
+//         "versions": "0+", "nullableVersions": "0+", "taggedVersions": "0+", "tag": 3,
+//         "about": "null if the removing replicas didn't change; the new removing replicas otherwise." },
+//       { "name": "AddingReplicas", "type": "[]int32", "default": "null", "entityType": "brokerId",
+//         "versions": "0+", "nullableVersions": "0+", "taggedVersions": "0+", "tag": 4,
+//         "about": "null if the adding replicas didn't change; the new adding replicas otherwise." },
+//       { "name": "LeaderRecoveryState", "type": "int8", "default": "-1", "versions": "0+", "taggedVersions": "0+", "tag": 5,
+//         "about": "-1 if it didn't change; 0 if the leader was elected from the ISR or recovered from an unclean election; 1 if the leader that was elected using unclean leader election and it is still recovering." },
+//       { "name": "EligibleLeaderReplicas", "type": "[]int32", "default": "null", "entityType": "brokerId",
+//         "versions": "2+", "nullableVersions": "2+", "taggedVersions": "2+", "tag": 6,
+//         "about": "null if the ELR didn't change; the new eligible leader replicas otherwise." },
+//       { "name": "LastKnownElr", "type": "[]int32", "default": "null", "entityType": "brokerId",
+//         "versions": "2+", "nullableVersions": "2+", "taggedVersions": "2+", "tag": 7,
+//         "about": "null if the LastKnownElr didn't change; the last known eligible leader replicas otherwise." },
+//       { "name": "Directories", "type": "[]uuid", "default": "null",
+//         "versions": "1+", "nullableVersions": "1+", "taggedVersions": "1+", "tag": 8,
+//         "about": "null if the log dirs didn't change; the new log directory for each replica otherwise."}
+//     ]
+// }
+pub struct PartitionChangeRecord {
+    pub value_metadata: RecordValueMetadata,
+    pub partition_id: i32,
+    pub topic_id: uuid::Uuid,
+    pub isr: Option<CompactArray<i32>>,
+    pub leader: i32,
+    pub replicas: Option<CompactArray<i32>>,
+    pub removing_replicas: Option<CompactArray<i32>>,
+    pub adding_replicas: Option<CompactArray<i32>>,
+    pub leader_recovery_state: i8,
+    pub eligible_leader_replicas: Option<CompactArray<i32>>,
+    pub last_known_elr: Option<CompactArray<i32>>,
+    pub directories: Option<CompactArray<uuid::Uuid>>,
+    pub tagged_fields: TaggedFields,
+}
+
+// Leader value of a PartitionChangeRecord that keeps the current leader
+pub const NO_LEADER_CHANGE: i32 = -2;
+
+impl Encodable for PartitionChangeRecord {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(&self.value_metadata.encode());
+        buf.extend(&self.partition_id.to_be_bytes());
+        buf.extend(self.topic_id.as_bytes());
+
+        // everything but the partition and topic ids is only written when it changed
+        let mut tagged_fields = self.tagged_fields.clone();
+        if let Some(isr) = &self.isr {
+            tagged_fields.insert(0, isr.encode());
+        }
+        if self.leader != NO_LEADER_CHANGE {
+            tagged_fields.insert(1, self.leader.encode());
+        }
+        if let Some(replicas) = &self.replicas {
+            tagged_fields.insert(2, replicas.encode());
+        }
+        if let Some(removing_replicas) = &self.removing_replicas {
+            tagged_fields.insert(3, removing_replicas.encode());
+        }
+        if let Some(adding_replicas) = &self.adding_replicas {
+            tagged_fields.insert(4, adding_replicas.encode());
+        }
+        if self.leader_recovery_state != -1 {
+            tagged_fields.insert(5, self.leader_recovery_state.encode());
+        }
+        if let Some(eligible_leader_replicas) = &self.eligible_leader_replicas {
+            tagged_fields.insert(6, eligible_leader_replicas.encode());
+        }
+        if let Some(last_known_elr) = &self.last_known_elr {
+            tagged_fields.insert(7, last_known_elr.encode());
+        }
+        if let Some(directories) = &self.directories {
+            tagged_fields.insert(8, directories.encode());
+        }
+        buf.extend(&tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Decodable for PartitionChangeRecord {
+    fn decode(buf: &[u8], _: &RequestContext) -> Result<(PartitionChangeRecord, usize), KafkaError> {
+        println!("      Decoding partition change record...");
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($size:expr) => {{
+            if buf.len() < offset + $size {
+                println!("Insufficient data to decode PartitionChangeRecord");
+                return Err(KafkaError::DecodeError);
+            }
+            let bytes = &buf[offset..offset + $size];
+            offset += $size;
+            bytes
+            }};
+        }
+
+        let empty_request_context = &RequestContext::None;
+
+        let (value_metadata, vm_byte_len) = RecordValueMetadata::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += vm_byte_len;
+
+        let partition_id = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let topic_id = uuid::Uuid::from_slice(read_bytes!(16)).map_err(|_| KafkaError::DecodeError)?;
+
+        let (mut tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += tf_byte_len;
+
+        Ok((PartitionChangeRecord {
+            value_metadata,
+            partition_id,
+            topic_id,
+            isr: take_tagged(&mut tagged_fields, 0)?,
+            leader: take_tagged(&mut tagged_fields, 1)?.unwrap_or(NO_LEADER_CHANGE),
+            replicas: take_tagged(&mut tagged_fields, 2)?,
+            removing_replicas: take_tagged(&mut tagged_fields, 3)?,
+            adding_replicas: take_tagged(&mut tagged_fields, 4)?,
+            leader_recovery_state: take_tagged(&mut tagged_fields, 5)?.unwrap_or(-1),
+            eligible_leader_replicas: take_tagged(&mut tagged_fields, 6)?,
+            last_known_elr: take_tagged(&mut tagged_fields, 7)?,
+            directories: take_tagged(&mut tagged_fields, 8)?,
+            tagged_fields,
+        }, offset))
+    }
+}
+
+// {
+//     "apiKey": 7,
+//     "type": "metadata",
+//     "name": "FenceBrokerRecord",
+//     "validVersions": "0",
+//     "flexibleVersions": "0+",
+//     "fields": [
+//       { "name": "Id", "type": "int32", "versions": "0+", "entityType": "brokerId",
+//         "about": "The broker ID to fence. It will be removed from all ISRs." },
+//       { "name": "Epoch", "type": "int64", "versions": "0+",
+//         "about": "The epoch of the broker to fence." }
+//     ]
+// }
+//
+// {
+//     "apiKey": 8,
+//     "type": "metadata",
+//     "name": "UnfenceBrokerRecord",
+//     "validVersions": "0",
+//     "flexibleVersions": "0+",
+//     "fields": [
+//       { "name": "Id", "type": "int32", "versions": "0+", "entityType": "brokerId",
+//         "about": "The broker ID to unfence." },
+//       { "name": "Epoch", "type": "int64", "versions": "0+",
+//         "about": "The epoch of the broker to unfence." }
+//     ]
+// }
+// both records share the same layout, the record type in the value metadata tells them apart
+pub struct BrokerFencingRecord {
+    pub value_metadata: RecordValueMetadata,
+    pub id: i32,
+    pub epoch: i64,
+    pub tagged_fields: TaggedFields,
+}
+
+impl Encodable for BrokerFencingRecord {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(&self.value_metadata.encode());
+        buf.extend(&self.id.to_be_bytes());
+        buf.extend(&self.epoch.to_be_bytes());
+        buf.extend(&self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Decodable for BrokerFencingRecord {
+    fn decode(buf: &[u8], _: &RequestContext) -> Result<(BrokerFencingRecord, usize), KafkaError> {
+        println!("      Decoding broker fencing record...");
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($size:expr) => {{
+            if buf.len() < offset + $size {
+                println!("Insufficient data to decode BrokerFencingRecord");
+                return Err(KafkaError::DecodeError);
+            }
+            let bytes = &buf[offset..offset + $size];
+            offset += $size;
+            bytes
+            }};
+        }
+
+        let empty_request_context = &RequestContext::None;
+
+        let (value_metadata, vm_byte_len) = RecordValueMetadata::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += vm_byte_len;
+
+        let id = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let epoch = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += tf_byte_len;
+
+        Ok((BrokerFencingRecord {
+            value_metadata,
+            id,
+            epoch,
+            tagged_fields,
+        }, offset))
+    }
+}
+
+// {
+//     "apiKey": 9,
+//     "type": "metadata",
+//     "name": "RemoveTopicRecord",
+//     "validVersions": "0",
+//     "flexibleVersions": "0+",
+//     "fields": [
+//       { "name": "TopicId", "type": "uuid", "versions": "0+",
+//         "about": "The topic to remove. All associated partitions will be removed as well." }
+//     ]
+// }
+pub struct RemoveTopicRecord {
+    pub value_metadata: RecordValueMetadata,
+    pub topic_id: uuid::Uuid,
+    pub tagged_fields: TaggedFields,
+}
+
+impl Encodable for RemoveTopicRecord {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(&self.value_metadata.encode());
+        buf.extend(self.topic_id.as_bytes());
+        buf.extend(&self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Decodable for RemoveTopicRecord {
+    fn decode(buf: &[u8], _: &RequestContext) -> Result<(RemoveTopicRecord, usize), KafkaError> {
+        println!("      Decoding remove topic record...");
+        let mut offset = 0;
+
+        let empty_request_context = &RequestContext::None;
+
+        let (value_metadata, vm_byte_len) = RecordValueMetadata::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += vm_byte_len;
+
+        let (topic_id, topic_id_byte_len) = uuid::Uuid::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += topic_id_byte_len;
+
+        let (tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += tf_byte_len;
+
+        Ok((RemoveTopicRecord {
+            value_metadata,
+            topic_id,
+            tagged_fields,
+        }, offset))
+    }
+}
+
+// {
+//     "apiKey": 15,
+//     "type": "metadata",
+//     "name": "ProducerIdsRecord",
+//     "validVersions": "0",
+//     "flexibleVersions": "0+",
+//     "fields": [
+//       { "name": "BrokerId", "type": "int32", "versions": "0+", "entityType": "brokerId",
+//         "about": "The ID of the requesting broker" },
+//       { "name": "BrokerEpoch", "type": "int64", "versions": "0+", "default": "-1",
+//         "about": "The epoch of the requesting broker" },
+//       { "name": "NextProducerId", "type": "int64", "versions": "0+",
+//         "about": "The next producerId that will be assigned (i.e. the first producerId in the next assigned block)"}
+//     ]
+// }
+pub struct ProducerIdsRecord {
+    pub value_metadata: RecordValueMetadata,
+    pub broker_id: i32,
+    pub broker_epoch: i64,
+    pub next_producer_id: i64,
+    pub tagged_fields: TaggedFields,
+}
+
+impl Encodable for ProducerIdsRecord {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(&self.value_metadata.encode());
+        buf.extend(&self.broker_id.to_be_bytes());
+        buf.extend(&self.broker_epoch.to_be_bytes());
+        buf.extend(&self.next_producer_id.to_be_bytes());
+        buf.extend(&self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Decodable for ProducerIdsRecord {
+    fn decode(buf: &[u8], _: &RequestContext) -> Result<(ProducerIdsRecord, usize), KafkaError> {
+        println!("      Decoding producer ids record...");
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($size:expr) => {{
+            if buf.len() < offset + $size {
+                println!("Insufficient data to decode ProducerIdsRecord");
+                return Err(KafkaError::DecodeError);
+            }
+            let bytes = &buf[offset..offset + $size];
+            offset += $size;
+            bytes
+            }};
+        }
+
+        let empty_request_context = &RequestContext::None;
+
+        let (value_metadata, vm_byte_len) = RecordValueMetadata::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += vm_byte_len;
+
+        let broker_id = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let broker_epoch = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let next_producer_id = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += tf_byte_len;
+
+        Ok((ProducerIdsRecord {
+            value_metadata,
+            broker_id,
+            broker_epoch,
+            next_producer_id,
+            tagged_fields,
+        }, offset))
+    }
+}
+
+// {
+//     "apiKey": 17,
+//     "type": "metadata",
+//     "name": "BrokerRegistrationChangeRecord",
+//     // Version 1 adds InControlledShutdown
+//     // Version 2 adds LogDirs
+//     "validVersions": "0-2",
+//     "flexibleVersions": "0+",
+//     "fields": [
+//       { "name": "BrokerId", "type": "int32", "versions": "0+", "entityType": "brokerId",
+//         "about": "The broker id." },
+//       { "name": "BrokerEpoch", "type": "int64", "versions": "0+",
+//         "about": "The broker epoch assigned by the controller." },
+//       { "name": "Fenced", "type": "int8", "versions": "0+", "taggedVersions": "0+", "tag": 0,
+//         "about": "-1 if the broker has been unfenced, 0 if no change, 1 if the broker has been fenced." },
+//       { "name": "InControlledShutdown", "type": "int8", "versions": "1+", "taggedVersions": "1+", "tag": 1,
+//         "about": "0 if no change, 1 if the broker is in controlled shutdown." },
+//       { "name": "LogDirs", "type":  "[]uuid", "versions":  "2+", "taggedVersions": "2+", "tag": 2,
+//         "about": "Log directories configured in this broker which are available." }
+//     ]
+// }
+pub struct BrokerRegistrationChangeRecord {
+    pub value_metadata: RecordValueMetadata,
+    pub broker_id: i32,
+    pub broker_epoch: i64,
+    pub fenced: i8,
+    pub in_controlled_shutdown: i8,
+    pub log_dirs: Option<CompactArray<uuid::Uuid>>,
+    pub tagged_fields: TaggedFields,
+}
+
+impl Encodable for BrokerRegistrationChangeRecord {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(&self.value_metadata.encode());
+        buf.extend(&self.broker_id.to_be_bytes());
+        buf.extend(&self.broker_epoch.to_be_bytes());
+
+        let mut tagged_fields = self.tagged_fields.clone();
+        if self.fenced != 0 {
+            tagged_fields.insert(0, self.fenced.encode());
+        }
+        if self.in_controlled_shutdown != 0 {
+            tagged_fields.insert(1, self.in_controlled_shutdown.encode());
+        }
+        if let Some(log_dirs) = &self.log_dirs {
+            tagged_fields.insert(2, log_dirs.encode());
+        }
+        buf.extend(&tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Decodable for BrokerRegistrationChangeRecord {
+    fn decode(buf: &[u8], _: &RequestContext) -> Result<(BrokerRegistrationChangeRecord, usize), KafkaError> {
+        println!("      Decoding broker registration change record...");
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($size:expr) => {{
+            if buf.len() < offset + $size {
+                println!("Insufficient data to decode BrokerRegistrationChangeRecord");
+                return Err(KafkaError::DecodeError);
+            }
+            let bytes = &buf[offset..offset + $size];
+            offset += $size;
+            bytes
+            }};
+        }
+
+        let empty_request_context = &RequestContext::None;
+
+        let (value_metadata, vm_byte_len) = RecordValueMetadata::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += vm_byte_len;
+
+        let broker_id = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        let broker_epoch = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (mut tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += tf_byte_len;
+
+        Ok((BrokerRegistrationChangeRecord {
+            value_metadata,
+            broker_id,
+            broker_epoch,
+            fenced: take_tagged(&mut tagged_fields, 0)?.unwrap_or(0),
+            in_controlled_shutdown: take_tagged(&mut tagged_fields, 1)?.unwrap_or(0),
+            log_dirs: take_tagged(&mut tagged_fields, 2)?,
+            tagged_fields,
+        }, offset))
+    }
+}
+
+// {
+//     "apiKey": 20,
+//     "type": "metadata",
+//     "name": "NoOpRecord",
+//     "validVersions": "0",
+//     "flexibleVersions": "0+",
+//     "fields": []
+// }
+//
+// {
+//     "apiKey": 24,
+//     "type": "metadata",
+//     "name": "EndTransactionRecord",
+//     "validVersions": "0",
+//     "flexibleVersions": "0+",
+//     "fields": []
+// }
+// records without fields, the record type in the value metadata tells them apart
+pub struct EmptyMetadataRecord {
+    pub value_metadata: RecordValueMetadata,
+    pub tagged_fields: TaggedFields,
+}
+
+impl Encodable for EmptyMetadataRecord {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(&self.value_metadata.encode());
+        buf.extend(&self.tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Decodable for EmptyMetadataRecord {
+    fn decode(buf: &[u8], _: &RequestContext) -> Result<(EmptyMetadataRecord, usize), KafkaError> {
+        let mut offset = 0;
+
+        let empty_request_context = &RequestContext::None;
+
+        let (value_metadata, vm_byte_len) = RecordValueMetadata::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += vm_byte_len;
+
+        let (tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += tf_byte_len;
+
+        Ok((EmptyMetadataRecord {
+            value_metadata,
+            tagged_fields,
+        }, offset))
+    }
+}
+
+// {
+//     "apiKey": 23,
+//     "type": "metadata",
+//     "name": "BeginTransactionRecord",
+//     "validVersions": "0",
+//     "flexibleVersions": "0+",
+//     "fields": [
+//       { "name": "Name", "type": "string", "versions": "0+", "nullableVersions": "0+",
+//         "taggedVersions": "0+", "tag": 0, "default": "null",
+//         "about": "An optional textual description of this transaction." }
+//     ]
+// }
+//
+// {
+//     "apiKey": 25,
+//     "type": "metadata",
+//     "name": "AbortTransactionRecord",
+//     "validVersions": "0",
+//     "flexibleVersions": "0+",
+//     "fields": [
+//       { "name": "Reason", "type": "string", "versions": "0+", "nullableVersions": "0+",
+//         "taggedVersions": "0+", "tag": 0, "default": "null",
+//         "about": "An optional textual description of why the transaction was aborted." }
+//     ]
+// }
+// metadata transaction markers, the description is the only field of both records
+pub struct MetadataTransactionRecord {
+    pub value_metadata: RecordValueMetadata,
+    pub description: Option<CompactNullableString>,
+    pub tagged_fields: TaggedFields,
+}
+
+impl Encodable for MetadataTransactionRecord {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(&self.value_metadata.encode());
+
+        let mut tagged_fields = self.tagged_fields.clone();
+        if let Some(description) = &self.description {
+            tagged_fields.insert(0, description.encode());
+        }
+        buf.extend(&tagged_fields.encode());
+
+        buf
+    }
+}
+
+impl Decodable for MetadataTransactionRecord {
+    fn decode(buf: &[u8], _: &RequestContext) -> Result<(MetadataTransactionRecord, usize), KafkaError> {
+        println!("      Decoding metadata transaction record...");
+        let mut offset = 0;
+
+        let empty_request_context = &RequestContext::None;
+
+        let (value_metadata, vm_byte_len) = RecordValueMetadata::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += vm_byte_len;
+
+        let (mut tagged_fields, tf_byte_len) = TaggedFields::decode(&buf[offset..], empty_request_context).map_err(|_| KafkaError::DecodeError)?;
+        offset += tf_byte_len;
+
+        Ok((MetadataTransactionRecord {
+            value_metadata,
+            description: take_tagged(&mut tagged_fields, 0)?,
+            tagged_fields,
+        }, offset))
+    }
+}
+
+// decode and remove a tagged field, None when the record does not carry it
+fn take_tagged<T: Decodable>(tagged_fields: &mut TaggedFields, tag: u32) -> Result<Option<T>, KafkaError> {
+    match tagged_fields.take(tag) {
+        Some(data) => {
+            let (value, _) = T::decode(&data, &RequestContext::None).map_err(|_| KafkaError::DecodeError)?;
+            Ok(Some(value))
+        }
+        None => Ok(None),
+    }
+}
+
+// RawBytesRecord
+pub struct RawBytesRecord {
+    pub data: Vec<u8>,
+}
+
+impl Encodable for RawBytesRecord {
+    fn encode(&self) -> Vec<u8> {
+        self.data.to_vec()
+    }
+}
+
+impl Decodable for RawBytesRecord {
+    fn decode(buf: &[u8], _: &RequestContext) -> Result<(RawBytesRecord, usize), KafkaError> {
+
+        Ok((RawBytesRecord {
+            data: buf.to_vec(),
+        }, buf.len()))
+    }
+}
+
+// baseOffset: int64
+// batchLength: int32
+// partitionLeaderEpoch: int32
+// magic: int8 (current magic value is 2)
+// crc: uint32
+// attributes: int16
+//     bit 0~2:
+//         0: no compression
+//         1: gzip
+//         2: snappy
+//         3: lz4
+//         4: zstd
+//     bit 3: timestampType
+//     bit 4: isTransactional (0 means not transactional)
+//     bit 5: isControlBatch (0 means not a control batch)
+//     bit 6: hasDeleteHorizonMs (0 means baseTimestamp is not set as the delete horizon for compaction)
+//     bit 7~15: unused
+// lastOffsetDelta: int32
+// baseTimestamp: int64
+// maxTimestamp: int64
+// producerId: int64
+// producerEpoch: int16
+// baseSequence: int32
+// records: [Record]
+pub struct RecordBatch {
+    pub base_offset: i64,
+    pub partition_leader_epoch: i32,
+    pub magic: i8,
+    pub crc: u32,
+    pub attributes: i16,
+    pub last_offset_delta: i32,
+    pub base_timestamp: i64,
+    pub max_timestamp: i64,
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    pub base_sequence: i32,
+    pub records: Vec<Record>,
+}
+
+impl Encodable for RecordBatch {
+    fn encode(&self) -> Vec<u8> {
+        // collect all the fields into a temporary buffer
+        let mut after_crc_buf: Vec<u8> = Vec::new();
+        after_crc_buf.extend(&self.attributes.to_be_bytes());
+        after_crc_buf.extend(&self.last_offset_delta.to_be_bytes());
+        after_crc_buf.extend(&self.base_timestamp.to_be_bytes());
+        after_crc_buf.extend(&self.max_timestamp.to_be_bytes());
+        after_crc_buf.extend(&self.producer_id.to_be_bytes());
+        after_crc_buf.extend(&self.producer_epoch.to_be_bytes());
+        after_crc_buf.extend(&self.base_sequence.to_be_bytes());
+
+        // encode records, compressed together with the codec in the attributes
+        after_crc_buf.extend(&(self.records.len() as i32).to_be_bytes());
+        let mut records_buf: Vec<u8> = Vec::new();
+        for record in &self.records {
+            records_buf.extend(record.encode());
+        }
+
+        let compression_type = CompressionType::from_attributes(self.attributes).unwrap_or(CompressionType::None);
+        match compression_type.compress(&records_buf) {
+            Ok(compressed) => after_crc_buf.extend(compressed),
+            Err(e) => {
+                // keep the batch readable, uncompressed
+                println!("Error compressing records with {}: {}, writing them uncompressed", compression_type.name(), e);
+                after_crc_buf[0..2].copy_from_slice(&(self.attributes & !COMPRESSION_CODEC_MASK).to_be_bytes());
+                after_crc_buf.extend(records_buf);
+            }
+        }
+
+        // compute CRC
+        let crc = crc32c(&after_crc_buf);
+
+        // collect all the fields into a temporary buffer
+        let mut temp_buf: Vec<u8> = Vec::new();
+        temp_buf.extend(&self.partition_leader_epoch.to_be_bytes());
+        temp_buf.extend(&self.magic.to_be_bytes());
+        temp_buf.extend(&crc.to_be_bytes());
+        temp_buf.extend(&after_crc_buf);
+
+        // finally, encode the batch length and append the temporary buffer
+        let mut buf = Vec::new();
+        buf.extend(&self.base_offset.to_be_bytes());
+        buf.extend(&(temp_buf.len() as i32).to_be_bytes());
+        buf.extend(&temp_buf);
+        
+        buf
+    }
+}
+
+impl Decodable for RecordBatch {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(RecordBatch, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($size:expr) => {{
+            if buf.len() < offset + $size {
+                println!("Insufficient data to decode RecordBatch");
+                return Err(KafkaError::DecodeError);
+            }
+            let bytes = &buf[offset..offset + $size];
+            offset += $size;
+            bytes
+            }};
+        }
+
+        let base_offset = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("\nBase offset: {}", base_offset);
+
+        let batch_length = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Batch length: {}", batch_length);
+
+        // the batch ends batch_length bytes after the length field, never read past it
+        let batch_end = offset + batch_length.max(0) as usize;
+        if buf.len() < batch_end {
+            println!("Insufficient data to decode RecordBatch");
+            return Err(KafkaError::DecodeError);
+        }
+        let buf = &buf[..batch_end];
+
+        let partition_leader_epoch = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Partition leader epoch: {}", partition_leader_epoch);
+        
+        let magic = i8::from_be_bytes(read_bytes!(1).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Magic: {}", magic);
+
+        let crc = u32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("CRC: {}", crc);
+
+        // the CRC-32C covers everything from the attributes to the end of the batch
+        // batches read back from the local log were checked when appended, callers may skip it for them
+        let skip_crc_validation = request_context.as_ref()
+            .is_some_and(|context_map| context_map.get("skip_crc_validation") == Some(&"true".to_string()));
+        if !skip_crc_validation {
+            let computed_crc = crc32c(&buf[offset..]);
+            if computed_crc != crc {
+                println!("RecordBatch CRC mismatch: stored {}, computed {}", crc, computed_crc);
+                return Err(KafkaError::DecodeError);
+            }
+        }
+
+        let attributes = i16::from_be_bytes(read_bytes!(2).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Attributes: {}", attributes);
+
+        let last_offset_delta = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Last offset delta: {}", last_offset_delta);
+
+        let base_timestamp = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Base timestamp: {}", base_timestamp);
+
+        let max_timestamp = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Max timestamp: {}", max_timestamp);
+
+        let producer_id = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Producer ID: {}", producer_id);
+
+        let producer_epoch = i16::from_be_bytes(read_bytes!(2).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Producer epoch: {}", producer_epoch);
+
+        let base_sequence = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Base sequence: {}", base_sequence);
+
+        let num_records = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+        println!("Number of records: {}", num_records);
+
+        // the records of a compressed batch are one compressed block up to the end of the batch
+        let compression_type = match CompressionType::from_attributes(attributes) {
+            Some(compression_type) => compression_type,
+            None => {
+                println!("Unknown compression codec: {}", attributes & COMPRESSION_CODEC_MASK);
+                return Err(KafkaError::DecodeError);
+            }
+        };
+        // a cap on the decompressed records, so a small batch cannot inflate without bound
+        // callers pass the topic's max.message.bytes, the broker default applies otherwise
+        let max_decompressed_bytes = request_context.as_ref()
+            .and_then(|context_map| context_map.get("max_decompressed_bytes"))
+            .and_then(|max_bytes| max_bytes.parse().ok())
+            .unwrap_or(DEFAULT_MAX_MESSAGE_BYTES as usize);
+        let records_buf = compression_type.decompress(&buf[offset..], max_decompressed_bytes).map_err(|e| {
+            println!("Error decompressing {} records: {}", compression_type.name(), e);
+            KafkaError::DecodeError
+        })?;
+        offset = batch_end;
+
+        println!("Decoding {} records...", num_records);
+        let mut records_offset = 0;
+        let mut records: Vec<Record> = Vec::new();
+        for _ in 0..num_records {
+            let (record, record_size) = Record::decode(&records_buf[records_offset..], request_context).map_err(|_| KafkaError::DecodeError)?;
+            records_offset += record_size;
+            records.push(record);
+        }
+
+        println!("Decoded {} records", records.len());
+
+        if records_offset != records_buf.len() {
+            println!("Record batch length does not match its records");
+            return Err(KafkaError::DecodeError);
+        }
 
         Ok((RecordBatch {
             base_offset,
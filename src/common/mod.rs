@@ -1,4 +1,4 @@
 pub mod primitive_types;
 pub mod traits;
 pub mod kafka_protocol;
-pub mod kafka_record;
\ No newline at end of file
+pub mod kafka_record;pub mod compression;
@@ -20,7 +20,7 @@ impl SVarInt {
 
 impl Encodable for SVarInt {
     fn encode(&self) -> Vec<u8> {
-        let mut integer = self.data as i32;
+        let mut integer = self.data;
 
         // map negative numbers to even numbers
         integer = (integer << 1) ^ (integer >> 31);
@@ -43,7 +43,7 @@ impl Decodable for SVarInt {
             },
             Err(_) => {
                 println!("Could not decode VarInt");
-                return Err(KafkaError::DecodeError);
+                Err(KafkaError::DecodeError)
             }
         }
     }
@@ -96,14 +96,14 @@ pub struct UnsignedVarInt {
 impl UnsignedVarInt {
     pub fn new(data: u32) -> Self {
         UnsignedVarInt {
-            data: data
+            data
         }
     }
 }
 
 impl Encodable for UnsignedVarInt {
     fn encode(&self) -> Vec<u8> {
-        encode_unsigned_var_int(self.data as u32)
+        encode_unsigned_var_int(self.data)
     }
 }
 
@@ -152,7 +152,7 @@ pub struct CompactString {
 impl CompactString {
     pub fn new(data: String) -> Self {
         CompactString {
-            data: data
+            data
         }
     }
 }
@@ -179,7 +179,7 @@ impl Decodable for CompactString {
         match UnsignedVarInt::decode(buf, request_context) {
             Ok( (varint, varint_byte_length) ) => {
                 byte_offset += varint_byte_length;
-                let data_length = varint.data - 1;
+                let data_length = varint.data.saturating_sub(1);
 
                 if data_length == 0 {
                     return Ok( (CompactString {
@@ -196,19 +196,19 @@ impl Decodable for CompactString {
                 match String::from_utf8(buf[byte_offset..byte_offset + data_length as usize].to_vec()) {
                     Ok(data) => {
                         byte_offset += data_length as usize;
-                        return Ok((CompactString {
+                        Ok((CompactString {
                             data
-                        }, byte_offset));
+                        }, byte_offset))
                     },
                     Err(_) => {
                         println!("Could not decode UTF-8 string");
-                        return Err(KafkaError::DecodeError);
+                        Err(KafkaError::DecodeError)
                     }
                 }
             },
             Err(_) => {
                 println!("Could not decode VarInt");
-                return Err(KafkaError::DecodeError);
+                Err(KafkaError::DecodeError)
             }
         }
     }
@@ -226,7 +226,7 @@ pub struct NullableString {
 impl NullableString {
     pub fn new(data: Option<String>) -> Self {
         NullableString {
-            data: data
+            data
         }
     }
 }
@@ -241,7 +241,7 @@ impl Encodable for NullableString {
                 buf.extend(value.bytes());
             }
             None => {
-                buf.extend((-1 as i16).to_be_bytes()); // -1 indicates a null string
+                buf.extend((-1_i16).to_be_bytes()); // -1 indicates a null string
             }
         }
         buf
@@ -277,6 +277,59 @@ impl Decodable for NullableString {
 }
 
 
+//
+// STRING
+//
+
+// a non-nullable string with an INT16 length prefix, as in non-flexible messages
+#[derive(Clone)]
+pub struct KafkaString {
+    pub data: String
+}
+
+impl KafkaString {
+    pub fn new(data: String) -> Self {
+        KafkaString {
+            data
+        }
+    }
+}
+
+impl Encodable for KafkaString {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+
+        buf.extend((self.data.len() as i16).to_be_bytes()); // length prefix
+        buf.extend(self.data.bytes());
+
+        buf
+    }
+}
+
+impl Decodable for KafkaString {
+    fn decode(buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        if buf.len() < 2 {
+            println!("Buffer too short to decode String");
+            return Err(KafkaError::DecodeError);
+        }
+
+        let length = i16::from_be_bytes([buf[0], buf[1]]);
+        if length < 0 || buf.len() < 2 + length as usize {
+            println!("Buffer does not contain enough data for String");
+            return Err(KafkaError::DecodeError);
+        }
+
+        match String::from_utf8(buf[2..2 + length as usize].to_vec()) {
+            Ok(data) => Ok( (KafkaString { data }, 2 + length as usize) ),
+            Err(_) => {
+                println!("Could not decode UTF-8 string");
+                Err(KafkaError::DecodeError)
+            }
+        }
+    }
+}
+
+
 //
 // COMPACT_NULLABLE_STRING
 //
@@ -288,7 +341,7 @@ pub struct CompactNullableString {
 impl CompactNullableString {
     pub fn new(data: Option<CompactString>) -> Self {
         CompactNullableString {
-            data: data
+            data
         }
     }
 }
@@ -302,7 +355,7 @@ impl Encodable for CompactNullableString {
                 buf.extend(value.encode());
             }
             None => {
-                buf.extend((0 as u8).to_be_bytes()); // length of 0 indicates a null string
+                buf.extend(0_u8.to_be_bytes()); // length of 0 indicates a null string
             }
         }
         buf
@@ -312,34 +365,25 @@ impl Encodable for CompactNullableString {
 impl Decodable for CompactNullableString {
     fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
 
-        let mut byte_offset = 0;
-
         match UnsignedVarInt::decode(buf, request_context) {
-            Ok( (varint,varint_byte_length) ) => {
-                byte_offset += varint_byte_length;
-                let data_length = varint.data - 1;
-
-                if data_length == 0 {
+            Ok( (varint, varint_byte_length) ) => {
+                // length of 0 indicates a null string
+                if varint.data == 0 {
                     return Ok((CompactNullableString {
                         data: None
-                    }, byte_offset));
-                }
-
-                if buf.len() < byte_offset + data_length as usize {
-                    println!("Buffer does not contain enough data for the string");
-                    return Err(KafkaError::DecodeError);
+                    }, varint_byte_length));
                 }
 
-                let data = CompactString::decode(&buf[byte_offset..byte_offset + data_length as usize], request_context)?;
-                byte_offset += data.1;
+                // the length prefix is shared with CompactString
+                let (data, data_byte_length) = CompactString::decode(buf, request_context)?;
 
-                return Ok((CompactNullableString {
-                    data: Some(data.0)
-                }, byte_offset));
+                Ok((CompactNullableString {
+                    data: Some(data)
+                }, data_byte_length))
             },
             Err(_) => {
                 println!("Could not decode VarInt");
-                return Err(KafkaError::DecodeError);
+                Err(KafkaError::DecodeError)
             }
         }
     }
@@ -350,6 +394,7 @@ impl Decodable for CompactNullableString {
 // COMPACT_ARRAY
 //
 
+#[derive(Clone)]
 pub struct CompactArray<T> {
     pub data: Vec<T>
 }
@@ -357,7 +402,7 @@ pub struct CompactArray<T> {
 impl<T> CompactArray<T> {
     pub fn new(data: Vec<T>) -> Self {
         CompactArray {
-            data: data
+            data
         }
     }
 }
@@ -387,7 +432,7 @@ impl<T: Decodable> Decodable for CompactArray<T> {
         match UnsignedVarInt::decode(buf, request_context) {
             Ok( (varint,varint_byte_length) ) => {
                 byte_offset += varint_byte_length;
-                let array_length = varint.data - 1;
+                let array_length = varint.data.saturating_sub(1); // a null array (0) decodes as empty
 
                 let mut array: Vec<T> = Vec::new();
                 for _ in 0..array_length {
@@ -396,18 +441,88 @@ impl<T: Decodable> Decodable for CompactArray<T> {
                     array.push(item.0);
                 }
 
-                return Ok((CompactArray {
+                Ok((CompactArray {
                     data: array
-                }, byte_offset));
+                }, byte_offset))
             },
             Err(_) => {
                 println!("Could not decode VarInt");
-                return Err(KafkaError::DecodeError);
+                Err(KafkaError::DecodeError)
             }
         }
     }
 }
 
+//
+// ARRAY
+//
+
+// an array with an INT32 length prefix, as in non-flexible messages
+#[derive(Clone)]
+pub struct Array<T> {
+    pub data: Vec<T>
+}
+
+impl<T> Array<T> {
+    pub fn new(data: Vec<T>) -> Self {
+        Array {
+            data
+        }
+    }
+}
+
+impl<T: Encodable> Encodable for Array<T> {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+
+        buf.extend((self.data.len() as i32).to_be_bytes());
+        for item in &self.data {
+            buf.extend(item.encode());
+        }
+
+        buf
+    }
+}
+
+impl<T: Decodable> Decodable for Array<T> {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let (array_length, mut byte_offset) = i32::decode(buf, request_context)?;
+
+        // a null array (-1) decodes as empty
+        let mut array: Vec<T> = Vec::new();
+        for _ in 0..array_length.max(0) {
+            let item = T::decode(&buf[byte_offset..], request_context)?;
+            byte_offset += item.1;
+            array.push(item.0);
+        }
+
+        Ok((Array {
+            data: array
+        }, byte_offset))
+    }
+}
+
+//
+// INT8
+//
+
+impl Encodable for i8 {
+    fn encode(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+    }
+}
+
+impl Decodable for i8 {
+    fn decode(buf: &[u8], _: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        if buf.is_empty() {
+            println!("Buffer too short to decode i8");
+            return Err(KafkaError::DecodeError);
+        }
+
+        Ok( (buf[0] as i8, 1) )
+    }
+}
+
 //
 // INT32
 //
@@ -451,4 +566,103 @@ impl Decodable for uuid::Uuid {
 
         Ok( (uuid::Uuid::from_bytes(uuid_bytes), 16) )
     }
-}
\ No newline at end of file
+}
+
+//
+// COMPACT_RECORDS
+//
+
+// Represents a sequence of Kafka records as a COMPACT_NULLABLE_BYTES.
+// The bytes are kept as-is so record batches can be validated and appended without re-encoding.
+pub struct CompactRecords {
+    pub data: Option<Vec<u8>>
+}
+
+impl CompactRecords {
+    pub fn new(data: Option<Vec<u8>>) -> Self {
+        CompactRecords {
+            data
+        }
+    }
+}
+
+impl Encodable for CompactRecords {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+
+        match &self.data {
+            Some(value) => {
+                buf.extend(UnsignedVarInt::new(value.len() as u32 + 1).encode());
+                buf.extend(value);
+            }
+            None => {
+                buf.push(0); // length of 0 indicates null records
+            }
+        }
+        buf
+    }
+}
+
+impl Decodable for CompactRecords {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let (varint, varint_byte_length) = UnsignedVarInt::decode(buf, request_context)?;
+
+        if varint.data == 0 {
+            return Ok( (CompactRecords { data: None }, varint_byte_length) );
+        }
+
+        let data_length = (varint.data - 1) as usize;
+        if buf.len() < varint_byte_length + data_length {
+            println!("Buffer does not contain enough data for CompactRecords");
+            return Err(KafkaError::DecodeError);
+        }
+
+        Ok( (CompactRecords {
+            data: Some(buf[varint_byte_length..varint_byte_length + data_length].to_vec())
+        }, varint_byte_length + data_length) )
+    }
+}
+
+//
+// COMPACT_BYTES
+//
+
+// opaque bytes, as the group protocol metadata and assignments consumers exchange through the coordinator
+#[derive(Clone)]
+pub struct CompactBytes {
+    pub data: Vec<u8>
+}
+
+impl CompactBytes {
+    pub fn new(data: Vec<u8>) -> Self {
+        CompactBytes {
+            data
+        }
+    }
+}
+
+impl Encodable for CompactBytes {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.extend(UnsignedVarInt::new(self.data.len() as u32 + 1).encode());
+        buf.extend(&self.data);
+        buf
+    }
+}
+
+impl Decodable for CompactBytes {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let (varint, varint_byte_length) = UnsignedVarInt::decode(buf, request_context)?;
+
+        // COMPACT_BYTES is never null, a length of 0 is treated as empty
+        let data_length = varint.data.saturating_sub(1) as usize;
+        if buf.len() < varint_byte_length + data_length {
+            println!("Buffer does not contain enough data for CompactBytes");
+            return Err(KafkaError::DecodeError);
+        }
+
+        Ok( (CompactBytes {
+            data: buf[varint_byte_length..varint_byte_length + data_length].to_vec()
+        }, varint_byte_length + data_length) )
+    }
+}
@@ -0,0 +1,46 @@
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::broker::purgatory::DelayedOperation;
+use crate::coordinator::group::GroupMetadata;
+use crate::coordinator::group_coordinator::GroupCoordinator;
+
+//
+// DelayedHeartbeat
+//
+
+// The session expiration of a member, completed by its next heartbeat; when it expires the member is
+// removed from the group. For a member id handed out with MEMBER_ID_REQUIRED (`pending`), it is completed
+// by the member joining with it.
+pub struct DelayedHeartbeat {
+    coordinator: Arc<GroupCoordinator>,
+    group: Arc<Mutex<GroupMetadata>>,
+    member_id: String,
+    deadline: Instant,
+    pending: bool,
+}
+
+impl DelayedHeartbeat {
+    pub fn new(coordinator: Arc<GroupCoordinator>, group: Arc<Mutex<GroupMetadata>>, member_id: &str, deadline: Instant, pending: bool) -> Self {
+        DelayedHeartbeat {
+            coordinator,
+            group,
+            member_id: member_id.to_string(),
+            deadline,
+            pending,
+        }
+    }
+}
+
+impl DelayedOperation for DelayedHeartbeat {
+    fn can_complete(&self) -> bool {
+        self.coordinator.heartbeat_satisfied(&self.group, &self.member_id, self.deadline, self.pending)
+    }
+
+    // nothing to answer, a heartbeat is responded to right away
+    fn on_complete(&self) {}
+
+    fn on_expiration(&self) {
+        self.coordinator.on_expire_heartbeat(&self.group, &self.member_id, self.deadline, self.pending);
+    }
+}
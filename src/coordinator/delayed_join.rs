@@ -0,0 +1,35 @@
+use std::sync::{Arc, Mutex};
+
+use crate::broker::purgatory::DelayedOperation;
+use crate::coordinator::group::GroupMetadata;
+use crate::coordinator::group_coordinator::GroupCoordinator;
+
+//
+// DelayedJoin
+//
+
+// The join of a rebalance, parked until every member rejoined or the longest rebalance timeout of the members
+// expires; members that did not rejoin by then are left out of the next generation
+pub struct DelayedJoin {
+    coordinator: Arc<GroupCoordinator>,
+    group: Arc<Mutex<GroupMetadata>>,
+}
+
+impl DelayedJoin {
+    pub fn new(coordinator: Arc<GroupCoordinator>, group: Arc<Mutex<GroupMetadata>>) -> Self {
+        DelayedJoin {
+            coordinator,
+            group,
+        }
+    }
+}
+
+impl DelayedOperation for DelayedJoin {
+    fn can_complete(&self) -> bool {
+        self.coordinator.can_complete_join(&self.group)
+    }
+
+    fn on_complete(&self) {
+        self.coordinator.on_complete_join(&self.group);
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Instant;
+
+use crate::coordinator::group_coordinator::{JoinCallback, JoinGroupResult, JoinGroupResultMember, SyncCallback, SyncGroupResult};
+use crate::errors::ErrorCode;
+use crate::storage::log_manager::TopicPartition;
+use crate::storage::log_segment::now_ms;
+
+// the protocol type of consumers, whose member metadata is a ConsumerProtocolSubscription
+pub const CONSUMER_PROTOCOL_TYPE: &str = "consumer";
+
+//
+// GroupState
+//
+
+// the classic rebalance protocol states, as Kafka's GroupState
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupState {
+    // no members, the group only exists for its committed offsets
+    Empty,
+    // members are rejoining, the join completes once every member rejoined or the rebalance timeout expires
+    PreparingRebalance,
+    // the new generation is formed, members wait for the leader's assignment in SyncGroup
+    CompletingRebalance,
+    // every member has its assignment and keeps its session alive with heartbeats
+    Stable,
+    // the group is being removed, its members have to find the coordinator again
+    Dead,
+}
+
+impl GroupState {
+    // the states a group may be in before moving to this one
+    fn valid_previous_states(&self) -> &'static [GroupState] {
+        match self {
+            GroupState::Empty => &[GroupState::PreparingRebalance],
+            GroupState::PreparingRebalance => &[GroupState::Empty, GroupState::Stable, GroupState::CompletingRebalance],
+            GroupState::CompletingRebalance => &[GroupState::PreparingRebalance],
+            GroupState::Stable => &[GroupState::CompletingRebalance],
+            GroupState::Dead => &[GroupState::Empty, GroupState::PreparingRebalance, GroupState::CompletingRebalance, GroupState::Stable, GroupState::Dead],
+        }
+    }
+}
+
+//
+// MemberMetadata
+//
+
+pub struct MemberMetadata {
+    pub member_id: String,
+    // group.instance.id of a static member, which keeps its place in the group across restarts
+    pub group_instance_id: Option<String>,
+    pub client_id: String,
+    pub rebalance_timeout_ms: i32,
+    pub session_timeout_ms: i32,
+    pub protocol_type: String,
+    // the protocols the member supports with their metadata, most preferred first
+    pub supported_protocols: Vec<(String, Vec<u8>)>,
+    // the member's part of the leader's assignment for the current generation
+    pub assignment: Vec<u8>,
+    // JoinGroup and SyncGroup responses the member waits for
+    pub awaiting_join: Option<JoinCallback>,
+    pub awaiting_sync: Option<SyncCallback>,
+    // the session expires session_timeout_ms after the last heartbeat, join or sync
+    pub last_heartbeat: Instant,
+}
+
+impl MemberMetadata {
+    // the member's metadata for `protocol`, empty if it does not support it
+    pub fn metadata(&self, protocol: &str) -> Vec<u8> {
+        self.supported_protocols.iter()
+            .find(|(name, _)| name == protocol)
+            .map(|(_, metadata)| metadata.clone())
+            .unwrap_or_default()
+    }
+
+    // whether the member rejoins with the protocols it joined with
+    pub fn matches(&self, protocols: &[(String, Vec<u8>)]) -> bool {
+        self.supported_protocols == protocols
+    }
+}
+
+//
+// OffsetAndMetadata
+//
+
+// an offset committed for a partition, as kept in the __consumer_offsets topic
+#[derive(Clone, Debug)]
+pub struct OffsetAndMetadata {
+    pub offset: i64,
+    pub leader_epoch: i32,
+    pub metadata: String,
+    pub commit_timestamp: i64,
+    // only set by commits of old clients asking for their own retention time
+    pub expire_timestamp: Option<i64>,
+}
+
+//
+// GroupMetadata
+//
+
+pub struct GroupMetadata {
+    pub group_id: String,
+    pub state: GroupState,
+    pub generation_id: i32,
+    // the protocol type ("consumer" for consumers) every member has to join with
+    pub protocol_type: Option<String>,
+    // the protocol (assignor) selected for the current generation
+    pub protocol_name: Option<String>,
+    pub leader_id: Option<String>,
+    // ordered so the leader learns the members in the same order every generation
+    pub members: BTreeMap<String, MemberMetadata>,
+    // member ids handed out with MEMBER_ID_REQUIRED that have not joined with them yet
+    pub pending_members: HashSet<String>,
+    // member id of each static member by group.instance.id
+    pub static_members: HashMap<String, String>,
+    // until when the first rebalance of an empty group waits for more members to join
+    pub initial_rebalance_deadline: Option<Instant>,
+    // the offsets committed by the group
+    pub offsets: HashMap<TopicPartition, OffsetAndMetadata>,
+    // when the group moved to its current state, not known for groups loaded from the offsets topic
+    pub current_state_timestamp: Option<i64>,
+}
+
+impl GroupMetadata {
+    pub fn new(group_id: &str) -> Self {
+        GroupMetadata {
+            group_id: group_id.to_string(),
+            state: GroupState::Empty,
+            generation_id: 0,
+            protocol_type: None,
+            protocol_name: None,
+            leader_id: None,
+            members: BTreeMap::new(),
+            pending_members: HashSet::new(),
+            static_members: HashMap::new(),
+            initial_rebalance_deadline: None,
+            offsets: HashMap::new(),
+            current_state_timestamp: Some(now_ms()),
+        }
+    }
+
+    pub fn transition_to(&mut self, state: GroupState) {
+        if !state.valid_previous_states().contains(&self.state) {
+            println!("Group {} should not move from {:?} to {:?}", self.group_id, self.state, state);
+        }
+        println!("Group {} moves from {:?} to {:?} in generation {}", self.group_id, self.state, state, self.generation_id);
+        self.state = state;
+        self.current_state_timestamp = Some(now_ms());
+    }
+
+    pub fn is_leader(&self, member_id: &str) -> bool {
+        self.leader_id.as_deref() == Some(member_id)
+    }
+
+    // members and member ids handed out, checked against group.max.size
+    pub fn size(&self) -> usize {
+        self.members.len() + self.pending_members.len()
+    }
+
+    pub fn add_member(&mut self, member: MemberMetadata) {
+        if self.members.is_empty() {
+            self.protocol_type = Some(member.protocol_type.clone());
+        }
+        if self.leader_id.is_none() {
+            self.leader_id = Some(member.member_id.clone());
+        }
+        if let Some(group_instance_id) = &member.group_instance_id {
+            self.static_members.insert(group_instance_id.clone(), member.member_id.clone());
+        }
+        self.members.insert(member.member_id.clone(), member);
+    }
+
+    // remove a member, answering the JoinGroup or SyncGroup it waits on with `error`
+    // the leader moves to another member
+    pub fn remove_member(&mut self, member_id: &str, error: ErrorCode) -> Option<MemberMetadata> {
+        let mut member = self.members.remove(member_id)?;
+
+        if let Some(respond) = member.awaiting_join.take() {
+            respond(JoinGroupResult::error(member_id, error));
+        }
+        if let Some(respond) = member.awaiting_sync.take() {
+            respond(SyncGroupResult::error(error));
+        }
+        if let Some(group_instance_id) = &member.group_instance_id {
+            if self.static_members.get(group_instance_id).map(String::as_str) == Some(member_id) {
+                self.static_members.remove(group_instance_id);
+            }
+        }
+        if self.is_leader(member_id) {
+            self.leader_id = self.members.keys().next().cloned();
+        }
+
+        Some(member)
+    }
+
+    // another member took over `group_instance_id` since this one joined with it
+    pub fn is_fenced(&self, member_id: &str, group_instance_id: Option<&str>) -> bool {
+        group_instance_id
+            .and_then(|group_instance_id| self.static_members.get(group_instance_id))
+            .is_some_and(|static_member_id| static_member_id != member_id)
+    }
+
+    // whether a member may join with `protocol_type` and `protocols`: an empty group takes any non empty
+    // protocol list, otherwise the type has to match and one protocol has to be supported by every member
+    pub fn supports_protocols(&self, protocol_type: &str, protocols: &[(String, Vec<u8>)]) -> bool {
+        if self.members.is_empty() {
+            return !protocol_type.is_empty() && !protocols.is_empty();
+        }
+
+        self.protocol_type.as_deref() == Some(protocol_type)
+            && protocols.iter().any(|(name, _)| self.members.values().all(|member| member.supported_protocols.iter().any(|(supported, _)| supported == name)))
+    }
+
+    // every member rejoined and the member ids handed out were used, so the join can complete
+    pub fn has_all_members_joined(&self) -> bool {
+        self.members.values().all(|member| member.awaiting_join.is_some()) && self.pending_members.is_empty()
+    }
+
+    // the longest rebalance timeout of the members, how long the join waits for them to rejoin
+    pub fn max_rebalance_timeout_ms(&self) -> i32 {
+        self.members.values().map(|member| member.rebalance_timeout_ms).max().unwrap_or(0)
+    }
+
+    // each member votes for its most preferred protocol among those every member supports,
+    // the one with the most votes is used
+    pub fn select_protocol(&self) -> Option<String> {
+        let candidates: Vec<&String> = self.members.values().next()?
+            .supported_protocols.iter()
+            .map(|(name, _)| name)
+            .filter(|name| self.members.values().all(|member| member.supported_protocols.iter().any(|(supported, _)| supported == *name)))
+            .collect();
+
+        let mut votes: Vec<(&String, usize)> = candidates.iter().map(|&name| (name, 0)).collect();
+        for member in self.members.values() {
+            let vote = member.supported_protocols.iter().find(|(name, _)| candidates.contains(&name));
+            if let Some((name, _)) = vote {
+                if let Some(entry) = votes.iter_mut().find(|(candidate, _)| *candidate == name) {
+                    entry.1 += 1;
+                }
+            }
+        }
+
+        let mut selected: Option<(&String, usize)> = None;
+        for (name, count) in votes {
+            if selected.map_or(true, |(_, selected_count)| count > selected_count) {
+                selected = Some((name, count));
+            }
+        }
+        selected.map(|(name, _)| name.clone())
+    }
+
+    // the JoinGroup response of a member for the current generation, only the leader gets the members
+    pub fn join_result(&self, member_id: &str) -> JoinGroupResult {
+        let protocol = self.protocol_name.clone().unwrap_or_default();
+        let members = match self.is_leader(member_id) {
+            true => self.members.values()
+                .map(|member| JoinGroupResultMember {
+                    member_id: member.member_id.clone(),
+                    group_instance_id: member.group_instance_id.clone(),
+                    metadata: member.metadata(&protocol),
+                })
+                .collect(),
+            false => vec![],
+        };
+
+        JoinGroupResult {
+            members,
+            member_id: member_id.to_string(),
+            generation_id: self.generation_id,
+            protocol_type: self.protocol_type.clone(),
+            protocol_name: self.protocol_name.clone(),
+            leader_id: self.leader_id.clone().unwrap_or_default(),
+            error: ErrorCode::None,
+        }
+    }
+
+    // the SyncGroup response of a member for the current generation
+    pub fn sync_result(&self, member_id: &str) -> SyncGroupResult {
+        SyncGroupResult {
+            protocol_type: self.protocol_type.clone(),
+            protocol_name: self.protocol_name.clone(),
+            assignment: self.members.get(member_id).map(|member| member.assignment.clone()).unwrap_or_default(),
+            error: ErrorCode::None,
+        }
+    }
+
+    // the topics the members of a consumer group subscribe to with the selected protocol, None when they
+    // are not known: the group is not a consumer group, has no generation yet or a subscription can not be read
+    pub fn subscribed_topics(&self) -> Option<HashSet<String>> {
+        if self.protocol_type.as_deref() != Some(CONSUMER_PROTOCOL_TYPE) {
+            return None;
+        }
+        let protocol = self.protocol_name.as_deref()?;
+
+        let mut topics = HashSet::new();
+        for member in self.members.values() {
+            topics.extend(subscription_topics(&member.metadata(protocol))?);
+        }
+        Some(topics)
+    }
+}
+
+// ConsumerProtocolSubscription => version [topics] user_data ...
+//   version => INT16
+//   topics => STRING
+// only the topics are read, they come first in every version
+fn subscription_topics(metadata: &[u8]) -> Option<Vec<String>> {
+    let mut offset = 2;
+
+    let count = i32::from_be_bytes(metadata.get(offset..offset + 4)?.try_into().ok()?);
+    offset += 4;
+
+    let mut topics = Vec::new();
+    for _ in 0..count.max(0) {
+        let length = i16::from_be_bytes(metadata.get(offset..offset + 2)?.try_into().ok()?);
+        offset += 2;
+        let topic = std::str::from_utf8(metadata.get(offset..offset + length.max(0) as usize)?).ok()?;
+        offset += length.max(0) as usize;
+        topics.push(topic.to_string());
+    }
+    Some(topics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a ConsumerProtocolSubscription of `topics`, user data and later fields left out unless given
+    fn subscription(version: i16, topics: &[&str], trailing: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend(version.to_be_bytes());
+        buf.extend((topics.len() as i32).to_be_bytes());
+        for topic in topics {
+            buf.extend((topic.len() as i16).to_be_bytes());
+            buf.extend(topic.as_bytes());
+        }
+        buf.extend(trailing);
+        buf
+    }
+
+    fn member(member_id: &str, protocols: &[(&str, Vec<u8>)]) -> MemberMetadata {
+        MemberMetadata {
+            member_id: member_id.to_string(),
+            group_instance_id: None,
+            client_id: "client".to_string(),
+            rebalance_timeout_ms: 1000,
+            session_timeout_ms: 1000,
+            protocol_type: CONSUMER_PROTOCOL_TYPE.to_string(),
+            supported_protocols: protocols.iter().map(|(name, metadata)| (name.to_string(), metadata.clone())).collect(),
+            assignment: vec![],
+            awaiting_join: None,
+            awaiting_sync: None,
+            last_heartbeat: Instant::now(),
+        }
+    }
+
+    fn group(members: Vec<MemberMetadata>) -> GroupMetadata {
+        let mut group = GroupMetadata::new("group");
+        for member in members {
+            group.add_member(member);
+        }
+        group
+    }
+
+    #[test]
+    fn subscription_topics_reads_the_topics() {
+        assert_eq!(subscription_topics(&subscription(0, &["foo", "bar"], &[])), Some(vec!["foo".to_string(), "bar".to_string()]));
+        // user data (null here) and the owned partitions of later versions follow the topics
+        assert_eq!(subscription_topics(&subscription(3, &["foo"], &[0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0])), Some(vec!["foo".to_string()]));
+        assert_eq!(subscription_topics(&subscription(1, &[], &[])), Some(vec![]));
+    }
+
+    #[test]
+    fn subscription_topics_rejects_truncated_metadata() {
+        assert_eq!(subscription_topics(&[]), None);
+        assert_eq!(subscription_topics(&[0, 0, 0, 0]), None);
+
+        let metadata = subscription(0, &["foo", "bar"], &[]);
+        assert_eq!(subscription_topics(&metadata[..metadata.len() - 1]), None);
+    }
+
+    #[test]
+    fn subscribed_topics_are_the_union_for_the_selected_protocol() {
+        let mut group = group(vec![
+            member("a", &[("range", subscription(0, &["foo"], &[]))]),
+            member("b", &[("range", subscription(0, &["foo", "bar"], &[]))]),
+        ]);
+        assert_eq!(group.subscribed_topics(), None);
+
+        group.protocol_name = Some("range".to_string());
+        assert_eq!(group.subscribed_topics(), Some(HashSet::from(["foo".to_string(), "bar".to_string()])));
+    }
+
+    #[test]
+    fn select_protocol_without_members() {
+        assert_eq!(group(vec![]).select_protocol(), None);
+    }
+
+    #[test]
+    fn select_protocol_takes_the_most_voted_common_protocol() {
+        let group = group(vec![
+            member("a", &[("range", vec![]), ("roundrobin", vec![])]),
+            member("b", &[("roundrobin", vec![]), ("range", vec![])]),
+            member("c", &[("roundrobin", vec![]), ("range", vec![])]),
+        ]);
+        assert_eq!(group.select_protocol(), Some("roundrobin".to_string()));
+    }
+
+    #[test]
+    fn select_protocol_ignores_protocols_not_every_member_supports() {
+        // "sticky" is preferred by two members, but "c" does not support it
+        let group = group(vec![
+            member("a", &[("sticky", vec![]), ("range", vec![])]),
+            member("b", &[("sticky", vec![]), ("range", vec![])]),
+            member("c", &[("range", vec![])]),
+        ]);
+        assert_eq!(group.select_protocol(), Some("range".to_string()));
+    }
+
+    #[test]
+    fn select_protocol_breaks_ties_in_the_first_member_order() {
+        let group = group(vec![
+            member("a", &[("range", vec![]), ("roundrobin", vec![])]),
+            member("b", &[("roundrobin", vec![]), ("range", vec![])]),
+        ]);
+        assert_eq!(group.select_protocol(), Some("range".to_string()));
+    }
+}
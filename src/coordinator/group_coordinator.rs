@@ -0,0 +1,997 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::broker::purgatory::Purgatory;
+use crate::coordinator::delayed_heartbeat::DelayedHeartbeat;
+use crate::coordinator::delayed_join::DelayedJoin;
+use crate::coordinator::group::{GroupMetadata, GroupState, MemberMetadata, OffsetAndMetadata, CONSUMER_PROTOCOL_TYPE};
+use crate::coordinator::offset_records::{offset_commit_record, GroupRecord, OffsetsRecordKey};
+use crate::common::kafka_protocol::RequestContext;
+use crate::common::traits::Decodable;
+use crate::errors::ErrorCode;
+use crate::storage::log_manager::TopicPartition;
+
+// group.min.session.timeout.ms, group.max.session.timeout.ms, group.initial.rebalance.delay.ms and group.max.size defaults
+pub const DEFAULT_GROUP_MIN_SESSION_TIMEOUT_MS: i32 = 6000;
+pub const DEFAULT_GROUP_MAX_SESSION_TIMEOUT_MS: i32 = 1800000;
+pub const DEFAULT_GROUP_INITIAL_REBALANCE_DELAY_MS: i32 = 3000;
+pub const DEFAULT_GROUP_MAX_SIZE: i32 = i32::MAX;
+// offset.metadata.max.bytes, offsets.retention.minutes and offsets.retention.check.interval.ms defaults
+pub const DEFAULT_OFFSET_METADATA_MAX_BYTES: i32 = 4096;
+pub const DEFAULT_OFFSETS_RETENTION_MINUTES: i64 = 7 * 24 * 60;
+pub const DEFAULT_OFFSETS_RETENTION_CHECK_INTERVAL_MS: u64 = 600000;
+
+#[derive(Clone, Debug)]
+pub struct GroupConfig {
+    // the session timeouts members may ask for
+    pub min_session_timeout_ms: i32,
+    pub max_session_timeout_ms: i32,
+    // how long the first rebalance of an empty group waits for more members to join
+    pub initial_rebalance_delay_ms: i32,
+    // members a group may have
+    pub max_size: i32,
+    // longest metadata a committed offset may carry
+    pub offset_metadata_max_bytes: i32,
+    // how long the offsets of a group without members are kept
+    pub offsets_retention_ms: i64,
+}
+
+//
+// Join and sync results
+//
+
+// receive the outcome of a JoinGroup or SyncGroup once the rebalance gets there
+pub type JoinCallback = Box<dyn FnOnce(JoinGroupResult) + Send>;
+pub type SyncCallback = Box<dyn FnOnce(SyncGroupResult) + Send>;
+
+pub struct JoinGroupResultMember {
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    pub metadata: Vec<u8>,
+}
+
+pub struct JoinGroupResult {
+    // every member with its metadata for the selected protocol, only sent to the leader
+    pub members: Vec<JoinGroupResultMember>,
+    pub member_id: String,
+    pub generation_id: i32,
+    pub protocol_type: Option<String>,
+    pub protocol_name: Option<String>,
+    pub leader_id: String,
+    pub error: ErrorCode,
+}
+
+impl JoinGroupResult {
+    pub fn error(member_id: &str, error: ErrorCode) -> Self {
+        JoinGroupResult {
+            members: vec![],
+            member_id: member_id.to_string(),
+            generation_id: -1,
+            protocol_type: None,
+            protocol_name: None,
+            leader_id: String::new(),
+            error,
+        }
+    }
+}
+
+pub struct SyncGroupResult {
+    pub protocol_type: Option<String>,
+    pub protocol_name: Option<String>,
+    pub assignment: Vec<u8>,
+    pub error: ErrorCode,
+}
+
+impl SyncGroupResult {
+    pub fn error(error: ErrorCode) -> Self {
+        SyncGroupResult {
+            protocol_type: None,
+            protocol_name: None,
+            assignment: vec![],
+            error,
+        }
+    }
+}
+
+pub struct JoinGroupParams {
+    pub group_id: String,
+    // empty for a member joining for the first time
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    pub client_id: String,
+    pub session_timeout_ms: i32,
+    pub rebalance_timeout_ms: i32,
+    pub protocol_type: String,
+    // supported protocols with their metadata, most preferred first
+    pub protocols: Vec<(String, Vec<u8>)>,
+}
+
+pub struct SyncGroupParams {
+    pub group_id: String,
+    pub generation_id: i32,
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    // checked against the group's when set
+    pub protocol_type: Option<String>,
+    pub protocol_name: Option<String>,
+    // the assignment of every member, only sent by the leader
+    pub assignments: Vec<(String, Vec<u8>)>,
+}
+
+pub struct OffsetCommitParams {
+    pub group_id: String,
+    // -1 with an empty member id for a commit from outside the group
+    pub generation_id: i32,
+    pub member_id: String,
+    pub group_instance_id: Option<String>,
+    pub offsets: Vec<(TopicPartition, OffsetAndMetadata)>,
+}
+
+// write records of a group to its partition of the offsets topic, in one batch
+pub type AppendGroupRecords<'a> = &'a dyn Fn(&str, Vec<GroupRecord>) -> Result<(), ErrorCode>;
+
+// purgatory work decided under a group lock and done once it is released,
+// the delayed operations lock the group themselves
+#[derive(Default)]
+struct GroupActions {
+    // members whose session expiration to schedule, with their session timeout
+    heartbeats: Vec<(String, i32)>,
+    // member ids handed out that expire unless a member joins with them
+    pending_heartbeats: Vec<(String, i32)>,
+    // park the join of a rebalance for at most this long
+    delayed_join: Option<Duration>,
+    // a member joined or left, the parked join may complete
+    check_join: bool,
+}
+
+//
+// GroupCoordinator
+//
+
+// Runs the classic rebalance protocol of the consumer groups, as Kafka's GroupCoordinator. This broker is the
+// coordinator of every group. Joins wait in the rebalance purgatory until the members rejoined, member sessions
+// are expired from the heartbeat purgatory.
+pub struct GroupCoordinator {
+    config: GroupConfig,
+    groups: Mutex<HashMap<String, Arc<Mutex<GroupMetadata>>>>,
+    join_purgatory: Arc<Purgatory<String>>,
+    heartbeat_purgatory: Arc<Purgatory<String>>,
+}
+
+impl GroupCoordinator {
+    pub fn new(config: GroupConfig) -> Self {
+        GroupCoordinator {
+            config,
+            groups: Mutex::new(HashMap::new()),
+            join_purgatory: Purgatory::start("Rebalance"),
+            heartbeat_purgatory: Purgatory::start("Heartbeat"),
+        }
+    }
+
+    fn group(&self, group_id: &str) -> Option<Arc<Mutex<GroupMetadata>>> {
+        self.groups.lock().unwrap().get(group_id).cloned()
+    }
+
+    fn get_or_create_group(&self, group_id: &str) -> Arc<Mutex<GroupMetadata>> {
+        let mut groups = self.groups.lock().unwrap();
+        let group = groups.entry(group_id.to_string()).or_insert_with(|| {
+            println!("Creating group {}", group_id);
+            Arc::new(Mutex::new(GroupMetadata::new(group_id)))
+        });
+        Arc::clone(group)
+    }
+
+    //
+    // JoinGroup
+    //
+
+    // add the member to the group, or have it rejoin, and respond once the rebalance formed the next generation
+    pub fn join_group(self: &Arc<Self>, join: JoinGroupParams, respond: JoinCallback) {
+        if join.group_id.is_empty() {
+            return respond(JoinGroupResult::error(&join.member_id, ErrorCode::InvalidGroupId));
+        }
+        if join.session_timeout_ms < self.config.min_session_timeout_ms || join.session_timeout_ms > self.config.max_session_timeout_ms {
+            return respond(JoinGroupResult::error(&join.member_id, ErrorCode::InvalidSessionTimeout));
+        }
+
+        // only a member joining for the first time creates the group
+        let group = match self.group(&join.group_id) {
+            Some(group) => group,
+            None if !join.member_id.is_empty() => return respond(JoinGroupResult::error(&join.member_id, ErrorCode::UnknownMemberId)),
+            None => self.get_or_create_group(&join.group_id),
+        };
+
+        let group_id = join.group_id.clone();
+        let actions = self.do_join_group(&mut group.lock().unwrap(), join, respond);
+        self.run_actions(&group, &group_id, actions);
+    }
+
+    fn do_join_group(&self, group: &mut GroupMetadata, join: JoinGroupParams, respond: JoinCallback) -> GroupActions {
+        let mut actions = GroupActions::default();
+
+        // the group was removed while the request was in flight
+        if group.state == GroupState::Dead {
+            respond(JoinGroupResult::error(&join.member_id, ErrorCode::CoordinatorNotAvailable));
+        } else if !group.supports_protocols(&join.protocol_type, &join.protocols) {
+            respond(JoinGroupResult::error(&join.member_id, ErrorCode::InconsistentGroupProtocol));
+        } else if join.member_id.is_empty() {
+            self.join_unknown_member(group, join, respond, &mut actions);
+        } else {
+            self.join_known_member(group, join, respond, &mut actions);
+        }
+
+        actions
+    }
+
+    // a member joining without a member id is given one: a dynamic member has to join again with it
+    // (MEMBER_ID_REQUIRED), a static member joins right away and replaces the member of its group.instance.id
+    fn join_unknown_member(&self, group: &mut GroupMetadata, join: JoinGroupParams, respond: JoinCallback, actions: &mut GroupActions) {
+        let known_static_member = join.group_instance_id.as_ref().and_then(|group_instance_id| group.static_members.get(group_instance_id).cloned());
+
+        if known_static_member.is_none() && group.size() >= self.config.max_size as usize {
+            return respond(JoinGroupResult::error(&join.member_id, ErrorCode::GroupMaxSizeReached));
+        }
+
+        let prefix = join.group_instance_id.as_deref().unwrap_or(&join.client_id);
+        let member_id = format!("{}-{}", prefix, Uuid::new_v4());
+
+        match known_static_member {
+            Some(old_member_id) => {
+                println!("Static member {} of group {} rejoins as {}, fencing {}", prefix, group.group_id, member_id, old_member_id);
+                let was_leader = group.is_leader(&old_member_id);
+                group.remove_member(&old_member_id, ErrorCode::FencedInstanceId);
+                self.add_member_and_rebalance(group, member_id.clone(), join, respond, actions);
+                if was_leader {
+                    group.leader_id = Some(member_id);
+                }
+            }
+            None if join.group_instance_id.is_some() => {
+                self.add_member_and_rebalance(group, member_id, join, respond, actions);
+            }
+            None => {
+                println!("Member {} of group {} has to join again with its member id", member_id, group.group_id);
+                group.pending_members.insert(member_id.clone());
+                actions.pending_heartbeats.push((member_id.clone(), join.session_timeout_ms));
+                respond(JoinGroupResult::error(&member_id, ErrorCode::MemberIdRequired));
+            }
+        }
+    }
+
+    fn join_known_member(&self, group: &mut GroupMetadata, join: JoinGroupParams, respond: JoinCallback, actions: &mut GroupActions) {
+        let member_id = join.member_id.clone();
+
+        // the first join with a member id handed out with MEMBER_ID_REQUIRED
+        if group.pending_members.contains(&member_id) {
+            return self.add_member_and_rebalance(group, member_id, join, respond, actions);
+        }
+        if group.is_fenced(&member_id, join.group_instance_id.as_deref()) {
+            return respond(JoinGroupResult::error(&member_id, ErrorCode::FencedInstanceId));
+        }
+        let protocols_match = match group.members.get(&member_id) {
+            Some(member) => member.matches(&join.protocols),
+            None => return respond(JoinGroupResult::error(&member_id, ErrorCode::UnknownMemberId)),
+        };
+
+        match group.state {
+            GroupState::PreparingRebalance => {
+                self.update_member_and_rebalance(group, join, respond, actions);
+            }
+            // a member that missed the JoinGroup response gets it again, unless its protocols changed
+            GroupState::CompletingRebalance if protocols_match => {
+                respond(group.join_result(&member_id));
+            }
+            // a follower rejoining with the same protocols keeps the current generation,
+            // the leader rejoins to have the assignment computed again
+            GroupState::Stable if protocols_match && !group.is_leader(&member_id) => {
+                respond(group.join_result(&member_id));
+            }
+            GroupState::CompletingRebalance | GroupState::Stable => {
+                self.update_member_and_rebalance(group, join, respond, actions);
+            }
+            GroupState::Empty | GroupState::Dead => {
+                respond(JoinGroupResult::error(&member_id, ErrorCode::UnknownMemberId));
+            }
+        }
+    }
+
+    fn add_member_and_rebalance(&self, group: &mut GroupMetadata, member_id: String, join: JoinGroupParams, respond: JoinCallback, actions: &mut GroupActions) {
+        println!("Adding member {} to group {} in state {:?}", member_id, group.group_id, group.state);
+
+        group.pending_members.remove(&member_id);
+        group.add_member(MemberMetadata {
+            member_id,
+            group_instance_id: join.group_instance_id,
+            client_id: join.client_id,
+            rebalance_timeout_ms: join.rebalance_timeout_ms,
+            session_timeout_ms: join.session_timeout_ms,
+            protocol_type: join.protocol_type,
+            supported_protocols: join.protocols,
+            assignment: vec![],
+            awaiting_join: Some(respond),
+            awaiting_sync: None,
+            last_heartbeat: Instant::now(),
+        });
+
+        self.maybe_prepare_rebalance(group, actions);
+    }
+
+    fn update_member_and_rebalance(&self, group: &mut GroupMetadata, join: JoinGroupParams, respond: JoinCallback, actions: &mut GroupActions) {
+        if let Some(member) = group.members.get_mut(&join.member_id) {
+            member.client_id = join.client_id;
+            member.rebalance_timeout_ms = join.rebalance_timeout_ms;
+            member.session_timeout_ms = join.session_timeout_ms;
+            member.supported_protocols = join.protocols;
+            member.last_heartbeat = Instant::now();
+
+            // only the latest join of a member is answered with the new generation
+            if let Some(previous) = member.awaiting_join.replace(respond) {
+                previous(JoinGroupResult::error(&join.member_id, ErrorCode::RebalanceInProgress));
+            }
+        }
+
+        self.maybe_prepare_rebalance(group, actions);
+    }
+
+    fn maybe_prepare_rebalance(&self, group: &mut GroupMetadata, actions: &mut GroupActions) {
+        match group.state {
+            GroupState::Empty | GroupState::Stable | GroupState::CompletingRebalance => self.prepare_rebalance(group, actions),
+            GroupState::PreparingRebalance => actions.check_join = true,
+            GroupState::Dead => {}
+        }
+    }
+
+    // start a rebalance: members have to rejoin within the rebalance timeout
+    fn prepare_rebalance(&self, group: &mut GroupMetadata, actions: &mut GroupActions) {
+        // members waiting for an assignment will not get one for this generation
+        if group.state == GroupState::CompletingRebalance {
+            for member in group.members.values_mut() {
+                member.assignment.clear();
+                if let Some(respond) = member.awaiting_sync.take() {
+                    respond(SyncGroupResult::error(ErrorCode::RebalanceInProgress));
+                }
+            }
+        }
+
+        // the first rebalance of an empty group waits for more members to join before completing
+        let timeout_ms = match group.state {
+            GroupState::Empty => {
+                let delay_ms = self.config.initial_rebalance_delay_ms.min(group.max_rebalance_timeout_ms());
+                group.initial_rebalance_deadline = Some(Instant::now() + Duration::from_millis(delay_ms as u64));
+                delay_ms
+            }
+            _ => group.max_rebalance_timeout_ms(),
+        };
+
+        group.transition_to(GroupState::PreparingRebalance);
+        println!("Preparing to rebalance group {} with old generation {}", group.group_id, group.generation_id);
+
+        actions.delayed_join = Some(Duration::from_millis(timeout_ms.max(0) as u64));
+    }
+
+    // the parked join of a rebalance can complete once every member rejoined
+    pub fn can_complete_join(&self, group: &Arc<Mutex<GroupMetadata>>) -> bool {
+        let group = group.lock().unwrap();
+
+        group.state == GroupState::PreparingRebalance
+            && group.has_all_members_joined()
+            && group.initial_rebalance_deadline.map_or(true, |deadline| deadline <= Instant::now())
+    }
+
+    // form the next generation from the members that rejoined and answer their joins
+    pub fn on_complete_join(self: &Arc<Self>, group: &Arc<Mutex<GroupMetadata>>) {
+        let mut actions = GroupActions::default();
+
+        let group_id = {
+            let mut group = group.lock().unwrap();
+            if group.state != GroupState::PreparingRebalance {
+                return;
+            }
+            group.initial_rebalance_deadline = None;
+
+            let not_rejoined: Vec<String> = group.members.values()
+                .filter(|member| member.awaiting_join.is_none())
+                .map(|member| member.member_id.clone())
+                .collect();
+            for member_id in not_rejoined {
+                println!("Member {} of group {} did not rejoin, removing it", member_id, group.group_id);
+                group.remove_member(&member_id, ErrorCode::UnknownMemberId);
+            }
+
+            group.generation_id += 1;
+            if group.members.is_empty() {
+                group.protocol_name = None;
+                group.transition_to(GroupState::Empty);
+            } else {
+                group.protocol_name = group.select_protocol();
+                group.transition_to(GroupState::CompletingRebalance);
+                println!("Stabilized group {} generation {} with protocol {:?} and {} members", group.group_id, group.generation_id, group.protocol_name, group.members.len());
+
+                let member_ids: Vec<String> = group.members.keys().cloned().collect();
+                for member_id in member_ids {
+                    let result = group.join_result(&member_id);
+                    if let Some(member) = group.members.get_mut(&member_id) {
+                        if let Some(respond) = member.awaiting_join.take() {
+                            respond(result);
+                        }
+                        member.last_heartbeat = Instant::now();
+                        actions.heartbeats.push((member_id, member.session_timeout_ms));
+                    }
+                }
+            }
+
+            group.group_id.clone()
+        };
+
+        self.run_actions(group, &group_id, actions);
+    }
+
+    //
+    // SyncGroup
+    //
+
+    // the leader hands out the assignment of the generation, the other members wait for theirs
+    pub fn sync_group(self: &Arc<Self>, sync: SyncGroupParams, respond: SyncCallback) {
+        let group = match self.group(&sync.group_id) {
+            Some(group) => group,
+            None => return respond(SyncGroupResult::error(ErrorCode::UnknownMemberId)),
+        };
+
+        let group_id = sync.group_id.clone();
+        let actions = self.do_sync_group(&mut group.lock().unwrap(), sync, respond);
+        self.run_actions(&group, &group_id, actions);
+    }
+
+    fn do_sync_group(&self, group: &mut GroupMetadata, sync: SyncGroupParams, respond: SyncCallback) -> GroupActions {
+        let mut actions = GroupActions::default();
+
+        if let Err(error) = self.validate_member(group, &sync.member_id, sync.group_instance_id.as_deref(), sync.generation_id) {
+            respond(SyncGroupResult::error(error));
+            return actions;
+        }
+        if sync.protocol_type.as_ref().is_some_and(|protocol_type| group.protocol_type.as_ref() != Some(protocol_type))
+            || sync.protocol_name.as_ref().is_some_and(|protocol_name| group.protocol_name.as_ref() != Some(protocol_name)) {
+            respond(SyncGroupResult::error(ErrorCode::InconsistentGroupProtocol));
+            return actions;
+        }
+
+        match group.state {
+            GroupState::Empty | GroupState::Dead => respond(SyncGroupResult::error(ErrorCode::UnknownMemberId)),
+            GroupState::PreparingRebalance => respond(SyncGroupResult::error(ErrorCode::RebalanceInProgress)),
+            GroupState::CompletingRebalance => {
+                if let Some(member) = group.members.get_mut(&sync.member_id) {
+                    member.awaiting_sync = Some(respond);
+                }
+
+                if group.is_leader(&sync.member_id) {
+                    println!("Assignment received from leader {} for group {} generation {}", sync.member_id, group.group_id, group.generation_id);
+
+                    // members the leader left out get an empty assignment
+                    let mut assignments: HashMap<String, Vec<u8>> = sync.assignments.into_iter().collect();
+                    for member in group.members.values_mut() {
+                        member.assignment = assignments.remove(&member.member_id).unwrap_or_default();
+                    }
+
+                    group.transition_to(GroupState::Stable);
+
+                    let member_ids: Vec<String> = group.members.keys().cloned().collect();
+                    for member_id in member_ids {
+                        let result = group.sync_result(&member_id);
+                        if let Some(member) = group.members.get_mut(&member_id) {
+                            if let Some(respond) = member.awaiting_sync.take() {
+                                respond(result);
+                            }
+                            member.last_heartbeat = Instant::now();
+                            actions.heartbeats.push((member_id, member.session_timeout_ms));
+                        }
+                    }
+                }
+            }
+            // the assignment was already handed out, the member missed it
+            GroupState::Stable => {
+                respond(group.sync_result(&sync.member_id));
+                keep_alive(group, &sync.member_id, &mut actions);
+            }
+        }
+
+        actions
+    }
+
+    //
+    // Heartbeat
+    //
+
+    // keep the member's session alive, tells it to rejoin while the group rebalances
+    pub fn heartbeat(self: &Arc<Self>, group_id: &str, member_id: &str, group_instance_id: Option<&str>, generation_id: i32) -> ErrorCode {
+        let group = match self.group(group_id) {
+            Some(group) => group,
+            None => return ErrorCode::UnknownMemberId,
+        };
+
+        let mut actions = GroupActions::default();
+        let error = {
+            let mut group = group.lock().unwrap();
+
+            match self.validate_member(&group, member_id, group_instance_id, generation_id) {
+                Err(error) => error,
+                Ok(()) => match group.state {
+                    GroupState::Empty | GroupState::Dead => ErrorCode::UnknownMemberId,
+                    GroupState::PreparingRebalance => {
+                        keep_alive(&mut group, member_id, &mut actions);
+                        ErrorCode::RebalanceInProgress
+                    }
+                    GroupState::CompletingRebalance | GroupState::Stable => {
+                        keep_alive(&mut group, member_id, &mut actions);
+                        ErrorCode::None
+                    }
+                },
+            }
+        };
+
+        self.run_actions(&group, group_id, actions);
+        error
+    }
+
+    // the member has to be in the group, not fenced by a newer instance, and in its current generation
+    fn validate_member(&self, group: &GroupMetadata, member_id: &str, group_instance_id: Option<&str>, generation_id: i32) -> Result<(), ErrorCode> {
+        if group.state == GroupState::Dead {
+            Err(ErrorCode::CoordinatorNotAvailable)
+        } else if group.is_fenced(member_id, group_instance_id) {
+            Err(ErrorCode::FencedInstanceId)
+        } else if !group.members.contains_key(member_id) {
+            Err(ErrorCode::UnknownMemberId)
+        } else if generation_id != group.generation_id {
+            Err(ErrorCode::IllegalGeneration)
+        } else {
+            Ok(())
+        }
+    }
+
+    // the session expiration of a member is satisfied once it heartbeats again, or while it waits on a rebalance
+    pub fn heartbeat_satisfied(&self, group: &Arc<Mutex<GroupMetadata>>, member_id: &str, deadline: Instant, pending: bool) -> bool {
+        let group = group.lock().unwrap();
+
+        if pending {
+            return !group.pending_members.contains(member_id);
+        }
+        match group.members.get(member_id) {
+            Some(member) => is_alive(member, deadline),
+            None => true,
+        }
+    }
+
+    // the member did not heartbeat within its session timeout, remove it from the group
+    pub fn on_expire_heartbeat(self: &Arc<Self>, group: &Arc<Mutex<GroupMetadata>>, member_id: &str, deadline: Instant, pending: bool) {
+        let mut actions = GroupActions::default();
+
+        let group_id = {
+            let mut group = group.lock().unwrap();
+
+            if pending {
+                if group.pending_members.remove(member_id) {
+                    println!("Pending member {} of group {} did not join in time, removing it", member_id, group.group_id);
+                    if group.state == GroupState::PreparingRebalance {
+                        actions.check_join = true;
+                    }
+                }
+            } else if group.members.get(member_id).is_some_and(|member| !is_alive(member, deadline)) {
+                println!("Member {} of group {} failed its session timeout, removing it", member_id, group.group_id);
+                self.remove_member_and_update_group(&mut group, member_id, &mut actions);
+            }
+
+            group.group_id.clone()
+        };
+
+        self.run_actions(group, &group_id, actions);
+    }
+
+    //
+    // LeaveGroup
+    //
+
+    // remove the members from the group, each named by its member id or, for static members, its group.instance.id
+    // returns the error of each member
+    pub fn leave_group(self: &Arc<Self>, group_id: &str, members: &[(String, Option<String>)]) -> Result<Vec<ErrorCode>, ErrorCode> {
+        let group = match self.group(group_id) {
+            Some(group) => group,
+            None => return Ok(vec![ErrorCode::UnknownMemberId; members.len()]),
+        };
+
+        let mut actions = GroupActions::default();
+        let errors = {
+            let mut group = group.lock().unwrap();
+            if group.state == GroupState::Dead {
+                return Err(ErrorCode::CoordinatorNotAvailable);
+            }
+
+            members.iter().map(|(member_id, group_instance_id)| {
+                self.remove_leaving_member(&mut group, member_id, group_instance_id.as_deref(), &mut actions)
+            }).collect()
+        };
+
+        self.run_actions(&group, group_id, actions);
+        Ok(errors)
+    }
+
+    fn remove_leaving_member(&self, group: &mut GroupMetadata, member_id: &str, group_instance_id: Option<&str>, actions: &mut GroupActions) -> ErrorCode {
+        // a static member may leave by its group.instance.id alone
+        let member_id = match (member_id, group_instance_id) {
+            ("", Some(group_instance_id)) => match group.static_members.get(group_instance_id) {
+                Some(member_id) => member_id.clone(),
+                None => return ErrorCode::UnknownMemberId,
+            },
+            (member_id, _) => member_id.to_string(),
+        };
+
+        if group.pending_members.remove(&member_id) {
+            println!("Pending member {} is leaving group {}", member_id, group.group_id);
+            if group.state == GroupState::PreparingRebalance {
+                actions.check_join = true;
+            }
+            ErrorCode::None
+        } else if group.is_fenced(&member_id, group_instance_id) {
+            ErrorCode::FencedInstanceId
+        } else if !group.members.contains_key(&member_id) {
+            ErrorCode::UnknownMemberId
+        } else {
+            println!("Member {} is leaving group {}", member_id, group.group_id);
+            self.remove_member_and_update_group(group, &member_id, actions);
+            ErrorCode::None
+        }
+    }
+
+    // a member left or failed: the others rebalance without it
+    fn remove_member_and_update_group(&self, group: &mut GroupMetadata, member_id: &str, actions: &mut GroupActions) {
+        group.remove_member(member_id, ErrorCode::UnknownMemberId);
+
+        match group.state {
+            GroupState::Stable | GroupState::CompletingRebalance => self.prepare_rebalance(group, actions),
+            GroupState::PreparingRebalance => actions.check_join = true,
+            GroupState::Empty | GroupState::Dead => {}
+        }
+    }
+
+    //
+    // OffsetCommit
+    //
+
+    // store the offsets of a member of the group's current generation, or of a consumer outside of any group
+    // returns the error of each offset
+    pub fn commit_offsets(self: &Arc<Self>, commit: OffsetCommitParams, append: AppendGroupRecords) -> Result<Vec<ErrorCode>, ErrorCode> {
+        // only a commit from outside of any group creates the group
+        let group = match self.group(&commit.group_id) {
+            Some(group) => group,
+            None if commit.generation_id < 0 => self.get_or_create_group(&commit.group_id),
+            None => return Err(ErrorCode::IllegalGeneration),
+        };
+
+        let mut actions = GroupActions::default();
+        let errors = {
+            let mut group = group.lock().unwrap();
+
+            if group.state == GroupState::Dead {
+                return Err(ErrorCode::CoordinatorNotAvailable);
+            } else if group.is_fenced(&commit.member_id, commit.group_instance_id.as_deref()) {
+                return Err(ErrorCode::FencedInstanceId);
+            } else if commit.generation_id < 0 && group.state == GroupState::Empty {
+                // a simple commit, the group is only used to store the offsets
+            } else if group.state == GroupState::CompletingRebalance {
+                return Err(ErrorCode::RebalanceInProgress);
+            } else if !group.members.contains_key(&commit.member_id) {
+                return Err(ErrorCode::UnknownMemberId);
+            } else if commit.generation_id != group.generation_id {
+                return Err(ErrorCode::IllegalGeneration);
+            } else {
+                keep_alive(&mut group, &commit.member_id, &mut actions);
+            }
+
+            self.store_offsets(&mut group, commit.offsets, append)
+        };
+
+        self.run_actions(&group, &commit.group_id, actions);
+        Ok(errors)
+    }
+
+    // write the offsets to the offsets topic and cache them once written, offsets with too much metadata are left out
+    fn store_offsets(&self, group: &mut GroupMetadata, offsets: Vec<(TopicPartition, OffsetAndMetadata)>, append: AppendGroupRecords) -> Vec<ErrorCode> {
+        let valid = |offset_and_metadata: &OffsetAndMetadata| offset_and_metadata.metadata.len() <= self.config.offset_metadata_max_bytes.max(0) as usize;
+
+        let records: Vec<GroupRecord> = offsets.iter()
+            .filter(|(_, offset_and_metadata)| valid(offset_and_metadata))
+            .map(|(topic_partition, offset_and_metadata)| offset_commit_record(&group.group_id, &topic_partition.topic, topic_partition.partition, Some(offset_and_metadata)))
+            .collect();
+
+        let result = match records.is_empty() {
+            true => Ok(()),
+            false => append(&group.group_id, records),
+        };
+        if let Err(error) = result {
+            println!("Could not store the offsets of group {}: {:?}", group.group_id, error);
+        }
+
+        offsets.into_iter().map(|(topic_partition, offset_and_metadata)| {
+            if !valid(&offset_and_metadata) {
+                ErrorCode::OffsetMetadataTooLarge
+            } else if let Err(error) = result {
+                error
+            } else {
+                println!("Group {} committed offset {} for {}", group.group_id, offset_and_metadata.offset, topic_partition);
+                group.offsets.insert(topic_partition, offset_and_metadata);
+                ErrorCode::None
+            }
+        }).collect()
+    }
+
+    //
+    // OffsetFetch
+    //
+
+    // the committed offsets of the partitions, None when a partition has none
+    // without partitions, every offset the group committed
+    pub fn fetch_offsets(&self, group_id: &str, partitions: Option<Vec<TopicPartition>>) -> Result<Vec<(TopicPartition, Option<OffsetAndMetadata>)>, ErrorCode> {
+        let group = match self.group(group_id) {
+            Some(group) => group,
+            None => return Ok(partitions.unwrap_or_default().into_iter().map(|topic_partition| (topic_partition, None)).collect()),
+        };
+
+        let group = group.lock().unwrap();
+        if group.state == GroupState::Dead {
+            return Err(ErrorCode::CoordinatorNotAvailable);
+        }
+
+        let offsets = match partitions {
+            Some(partitions) => partitions.into_iter()
+                .map(|topic_partition| {
+                    let offset_and_metadata = group.offsets.get(&topic_partition).cloned();
+                    (topic_partition, offset_and_metadata)
+                })
+                .collect(),
+            None => group.offsets.iter()
+                .map(|(topic_partition, offset_and_metadata)| (topic_partition.clone(), Some(offset_and_metadata.clone())))
+                .collect(),
+        };
+        Ok(offsets)
+    }
+
+    //
+    // OffsetDelete
+    //
+
+    // delete the committed offsets of the partitions, unless the group's members are consuming them
+    // returns the error of each partition
+    pub fn delete_offsets(&self, group_id: &str, partitions: &[TopicPartition], append: AppendGroupRecords) -> Result<Vec<ErrorCode>, ErrorCode> {
+        if group_id.is_empty() {
+            return Err(ErrorCode::InvalidGroupId);
+        }
+        let group = self.group(group_id).ok_or(ErrorCode::GroupIdNotFound)?;
+        let mut group = group.lock().unwrap();
+
+        let subscribed_topics = match group.state {
+            GroupState::Dead => return Err(ErrorCode::GroupIdNotFound),
+            GroupState::Empty => HashSet::new(),
+            _ if group.protocol_type.as_deref() == Some(CONSUMER_PROTOCOL_TYPE) => match group.subscribed_topics() {
+                Some(subscribed_topics) => subscribed_topics,
+                // without a generation every topic may be consumed
+                None => partitions.iter().map(|topic_partition| topic_partition.topic.clone()).collect(),
+            },
+            _ => return Err(ErrorCode::NonEmptyGroup),
+        };
+
+        let deletable: Vec<&TopicPartition> = partitions.iter()
+            .filter(|topic_partition| !subscribed_topics.contains(&topic_partition.topic))
+            .collect();
+        let removed: Vec<&TopicPartition> = deletable.iter().copied()
+            .filter(|topic_partition| group.offsets.contains_key(topic_partition))
+            .collect();
+
+        if !removed.is_empty() {
+            let tombstones = removed.iter()
+                .map(|topic_partition| offset_commit_record(group_id, &topic_partition.topic, topic_partition.partition, None))
+                .collect();
+            append(group_id, tombstones)?;
+
+            for topic_partition in removed {
+                println!("Deleted offset of {} for group {}", topic_partition, group_id);
+                group.offsets.remove(topic_partition);
+            }
+        }
+
+        Ok(partitions.iter().map(|topic_partition| match deletable.contains(&topic_partition) {
+            true => ErrorCode::None,
+            false => ErrorCode::GroupSubscribedToTopic,
+        }).collect())
+    }
+
+    // drop the committed offsets of deleted topics, as Kafka's onPartitionsDeleted,
+    // and the groups left with neither members nor offsets
+    // returns the number of offsets removed
+    pub fn on_topics_deleted(&self, topics: &[String], append: AppendGroupRecords) -> usize {
+        let groups: Vec<Arc<Mutex<GroupMetadata>>> = self.groups.lock().unwrap().values().cloned().collect();
+        let mut removed_count = 0;
+
+        for group in groups {
+            let mut group = group.lock().unwrap();
+            let group_id = group.group_id.clone();
+
+            let removed: Vec<TopicPartition> = group.offsets.keys()
+                .filter(|topic_partition| topics.contains(&topic_partition.topic))
+                .cloned()
+                .collect();
+            if !removed.is_empty() {
+                let tombstones = removed.iter()
+                    .map(|topic_partition| offset_commit_record(&group_id, &topic_partition.topic, topic_partition.partition, None))
+                    .collect();
+                // the topic is gone either way, an offset left in the log only comes back on a reload
+                if let Err(error) = append(&group_id, tombstones) {
+                    println!("Could not write the deleted offsets of group {}: {:?}", group_id, error);
+                }
+
+                for topic_partition in &removed {
+                    group.offsets.remove(topic_partition);
+                }
+                println!("Removed {} offset(s) of deleted topics from group {}", removed.len(), group_id);
+                removed_count += removed.len();
+            }
+
+            if group.state == GroupState::Empty && group.offsets.is_empty() {
+                group.transition_to(GroupState::Dead);
+                self.groups.lock().unwrap().remove(&group_id);
+                println!("Removed group {}, it has neither members nor offsets", group_id);
+            }
+        }
+
+        removed_count
+    }
+
+    //
+    // Offsets topic
+    //
+
+    // replay a record of the offsets topic into the cache, a tombstone removes the offset
+    // groups are loaded without members, they rejoin after a restart
+    pub fn replay_offset_record(&self, key: &[u8], value: Option<&[u8]>) {
+        let key = match OffsetsRecordKey::decode(key, &RequestContext::None) {
+            Ok((OffsetsRecordKey::OffsetCommit(key), _)) => key,
+            Ok((OffsetsRecordKey::GroupMetadata(_), _)) => return,
+            Err(_) => {
+                println!("Could not decode a key of the offsets topic, skipping it");
+                return;
+            }
+        };
+        let topic_partition = TopicPartition::new(&key.topic, key.partition);
+
+        match value.map(|value| OffsetAndMetadata::decode(value, &RequestContext::None)) {
+            Some(Ok((offset_and_metadata, _))) => {
+                let group = self.group(&key.group).unwrap_or_else(|| {
+                    let group = self.get_or_create_group(&key.group);
+                    group.lock().unwrap().current_state_timestamp = None;
+                    group
+                });
+                group.lock().unwrap().offsets.insert(topic_partition, offset_and_metadata);
+            }
+            Some(Err(_)) => println!("Could not decode the offset of {} for group {}, skipping it", topic_partition, key.group),
+            None => {
+                let mut groups = self.groups.lock().unwrap();
+                let emptied = groups.get(&key.group).is_some_and(|group| {
+                    let mut group = group.lock().unwrap();
+                    group.offsets.remove(&topic_partition);
+                    group.offsets.is_empty() && group.state == GroupState::Empty
+                });
+                if emptied {
+                    groups.remove(&key.group);
+                }
+            }
+        }
+    }
+
+    // remove the offsets past offsets.retention.minutes and the groups left with neither members nor offsets
+    // returns the number of offsets removed
+    pub fn expire_offsets(&self, now: i64, append: AppendGroupRecords) -> usize {
+        let groups: Vec<Arc<Mutex<GroupMetadata>>> = self.groups.lock().unwrap().values().cloned().collect();
+        let mut expired_count = 0;
+
+        for group in groups {
+            let mut group = group.lock().unwrap();
+            let group_id = group.group_id.clone();
+
+            let expired = self.expired_offsets(&group, now);
+            if !expired.is_empty() {
+                let tombstones = expired.iter()
+                    .map(|topic_partition| offset_commit_record(&group_id, &topic_partition.topic, topic_partition.partition, None))
+                    .collect();
+                if let Err(error) = append(&group_id, tombstones) {
+                    println!("Could not write the expired offsets of group {}: {:?}", group_id, error);
+                    continue;
+                }
+
+                for topic_partition in &expired {
+                    group.offsets.remove(topic_partition);
+                }
+                println!("Removed {} expired offset(s) of group {}", expired.len(), group_id);
+                expired_count += expired.len();
+            }
+
+            if group.state == GroupState::Empty && group.offsets.is_empty() {
+                group.transition_to(GroupState::Dead);
+                self.groups.lock().unwrap().remove(&group_id);
+                println!("Removed group {}, it has neither members nor offsets", group_id);
+            }
+        }
+
+        expired_count
+    }
+
+    // offsets expire offsets.retention.minutes after the group became empty, or after their commit for groups only
+    // used to store offsets; a stable consumer group only loses the offsets of topics it stopped consuming
+    fn expired_offsets(&self, group: &GroupMetadata, now: i64) -> Vec<TopicPartition> {
+        let subscribed_topics = group.subscribed_topics();
+        let base_timestamp: Box<dyn Fn(&OffsetAndMetadata) -> i64> = match (group.state, group.protocol_type.as_deref()) {
+            (GroupState::Empty, Some(_)) => {
+                let current_state_timestamp = group.current_state_timestamp;
+                Box::new(move |offset_and_metadata| current_state_timestamp.unwrap_or(offset_and_metadata.commit_timestamp))
+            }
+            (GroupState::Stable, Some(CONSUMER_PROTOCOL_TYPE)) if subscribed_topics.is_some() => Box::new(|offset_and_metadata| offset_and_metadata.commit_timestamp),
+            (_, None) => Box::new(|offset_and_metadata| offset_and_metadata.commit_timestamp),
+            _ => return vec![],
+        };
+
+        group.offsets.iter()
+            .filter(|(topic_partition, _)| !subscribed_topics.as_ref().is_some_and(|topics| topics.contains(&topic_partition.topic)))
+            .filter(|(_, offset_and_metadata)| match offset_and_metadata.expire_timestamp {
+                Some(expire_timestamp) => now >= expire_timestamp,
+                None => now - base_timestamp(offset_and_metadata) >= self.config.offsets_retention_ms,
+            })
+            .map(|(topic_partition, _)| topic_partition.clone())
+            .collect()
+    }
+
+    //
+    // Purgatory actions
+    //
+
+    fn run_actions(self: &Arc<Self>, group: &Arc<Mutex<GroupMetadata>>, group_id: &str, actions: GroupActions) {
+        for (member_id, session_timeout_ms) in actions.heartbeats {
+            self.schedule_heartbeat(group, group_id, &member_id, session_timeout_ms, false);
+        }
+        for (member_id, session_timeout_ms) in actions.pending_heartbeats {
+            self.schedule_heartbeat(group, group_id, &member_id, session_timeout_ms, true);
+        }
+
+        if let Some(timeout) = actions.delayed_join {
+            let delayed_join = DelayedJoin::new(Arc::clone(self), Arc::clone(group));
+            self.join_purgatory.try_complete_else_watch(Box::new(delayed_join), timeout, vec![group_id.to_string()]);
+        }
+        if actions.check_join {
+            self.join_purgatory.check_and_complete(&group_id.to_string());
+        }
+    }
+
+    // expire the member unless it heartbeats within its session timeout
+    fn schedule_heartbeat(self: &Arc<Self>, group: &Arc<Mutex<GroupMetadata>>, group_id: &str, member_id: &str, session_timeout_ms: i32, pending: bool) {
+        let key = format!("{}-{}", group_id, member_id);
+
+        // the heartbeat that got here satisfies the previous expiration
+        self.heartbeat_purgatory.check_and_complete(&key);
+
+        let timeout = Duration::from_millis(session_timeout_ms.max(0) as u64);
+        let delayed_heartbeat = DelayedHeartbeat::new(Arc::clone(self), Arc::clone(group), member_id, Instant::now() + timeout, pending);
+        self.heartbeat_purgatory.try_complete_else_watch(Box::new(delayed_heartbeat), timeout, vec![key]);
+    }
+}
+
+// refresh the member's session and schedule its next expiration
+fn keep_alive(group: &mut GroupMetadata, member_id: &str, actions: &mut GroupActions) {
+    if let Some(member) = group.members.get_mut(member_id) {
+        member.last_heartbeat = Instant::now();
+        actions.heartbeats.push((member_id.to_string(), member.session_timeout_ms));
+    }
+}
+
+// a member waiting on a rebalance is kept alive by it, otherwise its session has to outlast `deadline`
+fn is_alive(member: &MemberMetadata, deadline: Instant) -> bool {
+    member.awaiting_join.is_some()
+        || member.awaiting_sync.is_some()
+        || member.last_heartbeat + Duration::from_millis(member.session_timeout_ms.max(0) as u64) > deadline
+}
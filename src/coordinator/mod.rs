@@ -0,0 +1,5 @@
+pub mod group;
+pub mod group_coordinator;
+pub mod delayed_join;
+pub mod delayed_heartbeat;
+pub mod offset_records;
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use crate::common::kafka_protocol::{RequestContext, TaggedFields};
+use crate::common::kafka_record::{RawBytesRecord, Record, RecordBatch, RecordValue};
+use crate::common::primitive_types::{CompactString, KafkaString, SVarInt};
+use crate::common::traits::{Decodable, Encodable};
+use crate::coordinator::group::OffsetAndMetadata;
+use crate::errors::KafkaError;
+use crate::storage::log::{batch_attributes, batch_next_offset, Log, RawBatchIter, CONTROL_FLAG};
+use crate::storage::log_segment::now_ms;
+
+// the internal topic committed offsets are written to, compacted so only the latest commit of each partition is kept
+pub const OFFSETS_TOPIC: &str = "__consumer_offsets";
+
+// offsets.topic.num.partitions and offsets.topic.replication.factor defaults, as in Kafka
+pub const DEFAULT_OFFSETS_TOPIC_NUM_PARTITIONS: i32 = 50;
+pub const DEFAULT_OFFSETS_TOPIC_REPLICATION_FACTOR: i16 = 3;
+// segment.bytes of the topic, Kafka's offsets.topic.segment.bytes default, smaller segments are compacted sooner
+pub const OFFSETS_TOPIC_SEGMENT_BYTES: u64 = 100 * 1024 * 1024;
+
+// key versions 0 and 1 are offset commits, 2 is the group metadata Kafka also writes to the topic
+const OFFSET_COMMIT_KEY_VERSION: i16 = 1;
+const GROUP_METADATA_KEY_VERSION: i16 = 2;
+// version 3 has the leader epoch and no expire timestamp, version 4 is its flexible form
+const OFFSET_COMMIT_VALUE_VERSION: i16 = 3;
+
+// how much of a partition is read at once when loading it
+const LOAD_BUFFER_SIZE: usize = 5 * 1024 * 1024;
+
+// a record of the offsets topic, a null value is the tombstone of its key
+pub type GroupRecord = (Vec<u8>, Option<Vec<u8>>);
+
+// the partition of the offsets topic a group's records go to, as Kafka's partitionFor:
+// the group id's Java hashCode, made positive, modulo the partition count
+pub fn partition_for(group_id: &str, partition_count: i32) -> i32 {
+    let hash_code = group_id.encode_utf16().fold(0i32, |hash, unit| hash.wrapping_mul(31).wrapping_add(unit as i32));
+    (hash_code & 0x7fffffff) % partition_count
+}
+
+//
+// OffsetCommitKey
+//
+
+// OffsetCommitKey (Version: 1) => group topic partition
+//   group => STRING
+//   topic => STRING
+//   partition => INT32
+pub struct OffsetCommitKey {
+    pub group: String,
+    pub topic: String,
+    pub partition: i32,
+}
+
+// the keys of the offsets topic, group metadata keys (GroupMetadataKey => group STRING) are only recognized
+pub enum OffsetsRecordKey {
+    OffsetCommit(OffsetCommitKey),
+    GroupMetadata(String),
+}
+
+impl Encodable for OffsetCommitKey {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(OFFSET_COMMIT_KEY_VERSION.to_be_bytes());
+        buf.extend(KafkaString::new(self.group.clone()).encode());
+        buf.extend(KafkaString::new(self.topic.clone()).encode());
+        buf.extend(self.partition.to_be_bytes());
+
+        buf
+    }
+}
+
+impl Decodable for OffsetsRecordKey {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode OffsetsRecordKey...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        let version = i16::from_be_bytes(read_bytes!(2).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let (group, group_len) = KafkaString::decode(&buf[offset..], request_context)?;
+        offset += group_len;
+
+        match version {
+            0 | 1 => {
+                let (topic, topic_len) = KafkaString::decode(&buf[offset..], request_context)?;
+                offset += topic_len;
+
+                let partition = i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+                Ok( (OffsetsRecordKey::OffsetCommit(OffsetCommitKey {
+                    group: group.data,
+                    topic: topic.data,
+                    partition
+                }), offset) )
+            }
+            GROUP_METADATA_KEY_VERSION => Ok( (OffsetsRecordKey::GroupMetadata(group.data), offset) ),
+            _ => {
+                println!("Unknown offsets topic key version {}", version);
+                Err(KafkaError::DecodeError)
+            }
+        }
+    }
+}
+
+//
+// OffsetCommitValue
+//
+
+// OffsetCommitValue (Version: 3) => offset leader_epoch metadata commit_timestamp
+//   offset => INT64
+//   leader_epoch => INT32 (version 3+)
+//   metadata => STRING
+//   commit_timestamp => INT64
+//   expire_timestamp => INT64 (version 1 only)
+// version 4 is flexible: a COMPACT_STRING metadata and a TAG_BUFFER at the end
+impl Encodable for OffsetAndMetadata {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        buf.extend(OFFSET_COMMIT_VALUE_VERSION.to_be_bytes());
+        buf.extend(self.offset.to_be_bytes());
+        buf.extend(self.leader_epoch.to_be_bytes());
+        buf.extend(KafkaString::new(self.metadata.clone()).encode());
+        buf.extend(self.commit_timestamp.to_be_bytes());
+
+        buf
+    }
+}
+
+impl Decodable for OffsetAndMetadata {
+    fn decode(buf: &[u8], request_context: &RequestContext) -> Result<(Self, usize), KafkaError> {
+        let mut offset = 0;
+
+        macro_rules! read_bytes {
+            ($num_bytes:expr) => {{
+                if(buf.len() < offset + $num_bytes) {
+                    println!("Insufficient bytes to decode OffsetCommitValue...");
+                    return Err(KafkaError::DecodeError);
+                }
+
+                let bytes = &buf[offset..offset + $num_bytes];
+                offset += $num_bytes;
+                bytes
+            }};
+        }
+
+        let version = i16::from_be_bytes(read_bytes!(2).try_into().map_err(|_| KafkaError::DecodeError)?);
+        if !(0..=4).contains(&version) {
+            println!("Unknown offsets topic value version {}", version);
+            return Err(KafkaError::DecodeError);
+        }
+
+        let committed_offset = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let leader_epoch = match version {
+            3 | 4 => i32::from_be_bytes(read_bytes!(4).try_into().map_err(|_| KafkaError::DecodeError)?),
+            _ => -1,
+        };
+
+        let metadata = match version {
+            4 => {
+                let (metadata, metadata_len) = CompactString::decode(&buf[offset..], request_context)?;
+                offset += metadata_len;
+                metadata.data
+            }
+            _ => {
+                let (metadata, metadata_len) = KafkaString::decode(&buf[offset..], request_context)?;
+                offset += metadata_len;
+                metadata.data
+            }
+        };
+
+        let commit_timestamp = i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?);
+
+        let expire_timestamp = match version {
+            1 => Some(i64::from_be_bytes(read_bytes!(8).try_into().map_err(|_| KafkaError::DecodeError)?)),
+            _ => None,
+        };
+
+        if version == 4 {
+            let (_, tf_len) = TaggedFields::decode(&buf[offset..], request_context)?;
+            offset += tf_len;
+        }
+
+        Ok( (OffsetAndMetadata {
+            offset: committed_offset,
+            leader_epoch,
+            metadata,
+            commit_timestamp,
+            expire_timestamp
+        }, offset) )
+    }
+}
+
+// the record of a committed offset, None to write its tombstone
+pub fn offset_commit_record(group_id: &str, topic: &str, partition: i32, offset_and_metadata: Option<&OffsetAndMetadata>) -> GroupRecord {
+    let key = OffsetCommitKey {
+        group: group_id.to_string(),
+        topic: topic.to_string(),
+        partition,
+    };
+
+    (key.encode(), offset_and_metadata.map(|offset_and_metadata| offset_and_metadata.encode()))
+}
+
+// the records of one group in a single batch, so a commit of several partitions is written at once
+pub fn group_records_batch(records: Vec<GroupRecord>) -> RecordBatch {
+    let now = now_ms();
+
+    RecordBatch {
+        // assigned by the log
+        base_offset: 0,
+        partition_leader_epoch: 0,
+        magic: 2,
+        crc: 0,
+        attributes: 0,
+        last_offset_delta: records.len() as i32 - 1,
+        base_timestamp: now,
+        max_timestamp: now,
+        producer_id: -1,
+        producer_epoch: -1,
+        base_sequence: -1,
+        records: records.into_iter().enumerate().map(|(offset_delta, (key, value))| Record {
+            attributes: 0,
+            timestamp_delta: SVarInt::new(0),
+            offset_delta: SVarInt::new(offset_delta as i32),
+            key: Some(key),
+            value: match value {
+                Some(data) => RecordValue::RawBytesRecord(RawBytesRecord { data }),
+                None => RecordValue::Null,
+            },
+            headers: vec![],
+        }).collect(),
+    }
+}
+
+// replay every record of a partition of the offsets topic from its log start, control batches left out
+pub fn read_group_records(log: &Log, mut replay: impl FnMut(Vec<u8>, Option<Vec<u8>>)) -> std::io::Result<usize> {
+    // segments only hold batches whose CRC was checked on append or recovery
+    let mut context_map: HashMap<String, String> = HashMap::new();
+    context_map.insert("skip_crc_validation".to_string(), "true".to_string());
+    context_map.insert("max_decompressed_bytes".to_string(), log.config().max_message_bytes.to_string());
+    let request_context = RequestContext::Some(context_map);
+
+    let log_end_offset = log.log_end_offset();
+    let mut fetch_offset = log.log_start_offset();
+    let mut replayed = 0;
+
+    while fetch_offset < log_end_offset {
+        let batches = log.read(fetch_offset, log_end_offset, LOAD_BUFFER_SIZE, true)?;
+
+        let mut next_offset = fetch_offset;
+        for batch in RawBatchIter::new(&batches) {
+            next_offset = batch_next_offset(batch);
+            if batch_attributes(batch) & CONTROL_FLAG != 0 {
+                continue;
+            }
+
+            let record_batch = match RecordBatch::decode(batch, &request_context) {
+                Ok((record_batch, _)) => record_batch,
+                Err(_) => {
+                    println!("Could not decode a record batch of the offsets topic, skipping it");
+                    continue;
+                }
+            };
+
+            for record in record_batch.records {
+                let key = match record.key {
+                    Some(key) => key,
+                    None => continue,
+                };
+                match record.value {
+                    RecordValue::Null => replay(key, None),
+                    RecordValue::RawBytesRecord(value) => replay(key, Some(value.data)),
+                    _ => continue,
+                }
+                replayed += 1;
+            }
+        }
+
+        // nothing complete left to read
+        if next_offset <= fetch_offset {
+            break;
+        }
+        fetch_offset = next_offset;
+    }
+
+    Ok(replayed)
+}
@@ -1,5 +1,6 @@
 // #![allow(unused_imports)]
 #![allow(dead_code)]
+#![allow(clippy::module_inception, clippy::enum_variant_names)]
 mod common;
 mod broker;
 mod client;
@@ -7,16 +8,47 @@ mod utils;
 mod errors;
 mod api_versions;
 mod metadata;
+mod storage;
+mod coordinator;
 
 use std::sync::Arc;
 use crate::broker::broker::Broker;
+use crate::broker::config::BrokerConfig;
+use crate::storage::meta_properties::{format_log_dirs, random_uuid};
 
 fn main() {
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // storage commands, as kafka-storage.sh
+    match args.first().map(String::as_str) {
+        Some("random-uuid") => {
+            println!("{}", random_uuid());
+            return;
+        }
+        Some("format") => {
+            if let Err(e) = format(&args[1..]) {
+                eprintln!("Error formatting log dirs: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        _ => {}
+    }
+
     // start broker service
 
+    // read the broker config: a server.properties path and --override name=value flags
+    let config = match BrokerConfig::from_args(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error reading broker config: {}", e);
+            std::process::exit(1);
+        }
+    };
+
     // create a new broker
-    let kbroker = match Broker::new("127.0.0.1:9092", 5) {
+    let kbroker = match Broker::new(config) {
         Ok(broker) => Arc::new(broker),
         Err(e) => {
             eprintln!("Error creating broker: {}", e);
@@ -30,4 +62,29 @@ fn main() {
         Err(e) => eprintln!("Error starting broker: {}", e),
     }
 
-}
\ No newline at end of file
+}
+
+// format -t <cluster id> [server.properties] [--override name=value]... [--ignore-formatted]
+// writes meta.properties to every log dir of the config
+fn format(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cluster_id: Option<String> = None;
+    let mut ignore_formatted = false;
+    let mut config_args: Vec<String> = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-t" | "--cluster-id" => cluster_id = args.next().cloned(),
+            "-g" | "--ignore-formatted" => ignore_formatted = true,
+            "-c" | "--config" => config_args.extend(args.next().cloned()),
+            _ => config_args.push(arg.clone()),
+        }
+    }
+
+    let cluster_id = cluster_id.ok_or("a cluster id is required, -t <cluster id>")?;
+    let config = BrokerConfig::from_args(&config_args)?;
+
+    let formatted = format_log_dirs(&config.all_log_dirs(), &cluster_id, config.node_id, ignore_formatted)?;
+    println!("Formatted {} log dir(s)", formatted);
+    Ok(())
+}
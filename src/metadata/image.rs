@@ -0,0 +1,440 @@
+use std::collections::{BTreeMap, HashMap};
+
+use uuid::Uuid;
+
+use crate::common::kafka_record::{BrokerRegistrationChangeRecord, PartitionChangeRecord, PartitionRecord, RecordValue, RegisterBrokerRecord, TopicRecord, NO_LEADER_CHANGE};
+
+// ConfigRecord resource types
+pub const TOPIC_RESOURCE_TYPE: i8 = 2;
+pub const BROKER_RESOURCE_TYPE: i8 = 4;
+
+//
+// MetadataImage
+//
+
+// In-memory view of the cluster metadata, built by replaying the records of the
+// __cluster_metadata log in order
+#[derive(Default)]
+pub struct MetadataImage {
+    topics_by_id: HashMap<Uuid, TopicImage>,
+    topic_ids_by_name: HashMap<String, Uuid>,
+    features: HashMap<String, i16>,
+    brokers: BTreeMap<i32, BrokerRegistration>,
+    // (resource type, resource name) -> config name -> value
+    configs: HashMap<(i8, String), HashMap<String, String>>,
+    next_producer_id: i64,
+}
+
+pub struct TopicImage {
+    pub name: String,
+    pub topic_id: Uuid,
+    pub partitions: BTreeMap<i32, PartitionImage>,
+}
+
+pub struct PartitionImage {
+    pub partition_id: i32,
+    pub replicas: Vec<i32>,
+    pub isr: Vec<i32>,
+    pub removing_replicas: Vec<i32>,
+    pub adding_replicas: Vec<i32>,
+    pub leader: i32,
+    pub leader_epoch: i32,
+    pub partition_epoch: i32,
+    pub directories: Vec<Uuid>,
+}
+
+pub struct BrokerRegistration {
+    pub broker_id: i32,
+    pub broker_epoch: i64,
+    pub incarnation_id: Uuid,
+    pub endpoints: Vec<BrokerEndpointImage>,
+    pub rack: Option<String>,
+    pub fenced: bool,
+    pub in_controlled_shutdown: bool,
+}
+
+pub struct BrokerEndpointImage {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub security_protocol: i16,
+}
+
+impl MetadataImage {
+    pub fn new() -> Self {
+        MetadataImage::default()
+    }
+
+    // apply one metadata record on top of the current image
+    pub fn replay(&mut self, record: &RecordValue) {
+        match record {
+            RecordValue::RegisterBrokerRecord(register_broker_record) => self.replay_register_broker(register_broker_record),
+            RecordValue::UnregisterBrokerRecord(unregister_broker_record) => {
+                self.brokers.remove(&unregister_broker_record.broker_id);
+            }
+            RecordValue::TopicRecord(topic_record) => self.replay_topic(topic_record),
+            RecordValue::PartitionRecord(partition_record) => self.replay_partition(partition_record),
+            RecordValue::ConfigRecord(config_record) => {
+                let resource = (config_record.resource_type, config_record.resource_name.data.clone());
+                match &config_record.value.data {
+                    Some(value) => {
+                        self.configs.entry(resource).or_default().insert(config_record.name.data.clone(), value.data.clone());
+                    }
+                    None => {
+                        if let Some(configs) = self.configs.get_mut(&resource) {
+                            configs.remove(&config_record.name.data);
+                        }
+                    }
+                }
+            }
+            RecordValue::PartitionChangeRecord(partition_change_record) => self.replay_partition_change(partition_change_record),
+            RecordValue::FenceBrokerRecord(fencing_record) => {
+                if let Some(broker) = self.brokers.get_mut(&fencing_record.id) {
+                    broker.fenced = true;
+                }
+            }
+            RecordValue::UnfenceBrokerRecord(fencing_record) => {
+                if let Some(broker) = self.brokers.get_mut(&fencing_record.id) {
+                    broker.fenced = false;
+                }
+            }
+            RecordValue::RemoveTopicRecord(remove_topic_record) => {
+                // the topic's config overrides go with it
+                if let Some(topic) = self.topics_by_id.remove(&remove_topic_record.topic_id) {
+                    self.topic_ids_by_name.remove(&topic.name);
+                    self.configs.remove(&(TOPIC_RESOURCE_TYPE, topic.name));
+                }
+            }
+            RecordValue::FeatureLevelRecord(feature_level_record) => {
+                self.features.insert(feature_level_record.name.data.clone(), feature_level_record.feature_level);
+            }
+            RecordValue::ProducerIdsRecord(producer_ids_record) => {
+                self.next_producer_id = producer_ids_record.next_producer_id;
+            }
+            RecordValue::BrokerRegistrationChangeRecord(registration_change_record) => self.replay_registration_change(registration_change_record),
+            // metadata transactions are applied record by record, the markers carry no state
+            RecordValue::NoOpRecord(_)
+            | RecordValue::BeginTransactionRecord(_)
+            | RecordValue::EndTransactionRecord(_)
+            | RecordValue::AbortTransactionRecord(_)
+            | RecordValue::RawBytesRecord(_)
+            | RecordValue::Null => {}
+        }
+    }
+
+    fn replay_topic(&mut self, topic_record: &TopicRecord) {
+        let name = topic_record.topic_name.data.clone();
+        self.topic_ids_by_name.insert(name.clone(), topic_record.topic_id);
+        self.topics_by_id.entry(topic_record.topic_id).or_insert(TopicImage {
+            name,
+            topic_id: topic_record.topic_id,
+            partitions: BTreeMap::new(),
+        });
+    }
+
+    fn replay_partition(&mut self, partition_record: &PartitionRecord) {
+        let topic = match self.topics_by_id.get_mut(&partition_record.topic_id) {
+            Some(topic) => topic,
+            None => {
+                println!("Partition record for unknown topic id: {}", partition_record.topic_id);
+                return;
+            }
+        };
+
+        topic.partitions.insert(partition_record.partition_id, PartitionImage {
+            partition_id: partition_record.partition_id,
+            replicas: partition_record.replica_array.data.clone(),
+            isr: partition_record.isr_array.data.clone(),
+            removing_replicas: partition_record.removing_replicas_array.data.clone(),
+            adding_replicas: partition_record.adding_replicas_array.data.clone(),
+            leader: partition_record.leader,
+            leader_epoch: partition_record.leader_epoch,
+            partition_epoch: partition_record.partition_epoch,
+            directories: partition_record.directories.data.clone(),
+        });
+    }
+
+    fn replay_partition_change(&mut self, partition_change_record: &PartitionChangeRecord) {
+        let partition = match self.topics_by_id.get_mut(&partition_change_record.topic_id).and_then(|topic| topic.partitions.get_mut(&partition_change_record.partition_id)) {
+            Some(partition) => partition,
+            None => {
+                println!("Partition change record for unknown partition: {}-{}", partition_change_record.topic_id, partition_change_record.partition_id);
+                return;
+            }
+        };
+
+        if let Some(isr) = &partition_change_record.isr {
+            partition.isr = isr.data.clone();
+        }
+        if let Some(replicas) = &partition_change_record.replicas {
+            partition.replicas = replicas.data.clone();
+        }
+        if let Some(removing_replicas) = &partition_change_record.removing_replicas {
+            partition.removing_replicas = removing_replicas.data.clone();
+        }
+        if let Some(adding_replicas) = &partition_change_record.adding_replicas {
+            partition.adding_replicas = adding_replicas.data.clone();
+        }
+        if let Some(directories) = &partition_change_record.directories {
+            partition.directories = directories.data.clone();
+        }
+        if partition_change_record.leader != NO_LEADER_CHANGE {
+            partition.leader = partition_change_record.leader;
+            partition.leader_epoch += 1;
+        }
+        partition.partition_epoch += 1;
+    }
+
+    fn replay_register_broker(&mut self, register_broker_record: &RegisterBrokerRecord) {
+        self.brokers.insert(register_broker_record.broker_id, BrokerRegistration {
+            broker_id: register_broker_record.broker_id,
+            broker_epoch: register_broker_record.broker_epoch,
+            incarnation_id: register_broker_record.incarnation_id,
+            endpoints: register_broker_record.end_points.data.iter().map(|end_point| BrokerEndpointImage {
+                name: end_point.name.data.clone(),
+                host: end_point.host.data.clone(),
+                port: end_point.port,
+                security_protocol: end_point.security_protocol,
+            }).collect(),
+            rack: register_broker_record.rack.data.as_ref().map(|rack| rack.data.clone()),
+            fenced: register_broker_record.fenced,
+            in_controlled_shutdown: register_broker_record.in_controlled_shutdown,
+        });
+    }
+
+    fn replay_registration_change(&mut self, registration_change_record: &BrokerRegistrationChangeRecord) {
+        if let Some(broker) = self.brokers.get_mut(&registration_change_record.broker_id) {
+            match registration_change_record.fenced {
+                -1 => broker.fenced = false,
+                1 => broker.fenced = true,
+                _ => {}
+            }
+            if registration_change_record.in_controlled_shutdown == 1 {
+                broker.in_controlled_shutdown = true;
+            }
+        }
+    }
+
+    pub fn topic_by_name(&self, name: &str) -> Option<&TopicImage> {
+        self.topic_ids_by_name.get(name).and_then(|topic_id| self.topics_by_id.get(topic_id))
+    }
+
+    pub fn topic_by_id(&self, topic_id: &Uuid) -> Option<&TopicImage> {
+        self.topics_by_id.get(topic_id)
+    }
+
+    pub fn topics(&self) -> impl Iterator<Item = &TopicImage> {
+        self.topics_by_id.values()
+    }
+
+    pub fn feature_level(&self, name: &str) -> Option<i16> {
+        self.features.get(name).copied()
+    }
+
+    pub fn brokers(&self) -> impl Iterator<Item = &BrokerRegistration> {
+        self.brokers.values()
+    }
+
+    // configs set for a resource, e.g. a topic (resource type 2)
+    pub fn configs(&self, resource_type: i8, resource_name: &str) -> Option<&HashMap<String, String>> {
+        self.configs.get(&(resource_type, resource_name.to_string()))
+    }
+
+    // config overrides set for a topic
+    pub fn topic_configs(&self, topic_name: &str) -> Option<&HashMap<String, String>> {
+        self.configs(TOPIC_RESOURCE_TYPE, topic_name)
+    }
+
+    pub fn next_producer_id(&self) -> i64 {
+        self.next_producer_id
+    }
+}
+
+impl TopicImage {
+    pub fn partition(&self, partition_id: i32) -> Option<&PartitionImage> {
+        self.partitions.get(&partition_id)
+    }
+
+    pub fn has_partition(&self, partition_id: i32) -> bool {
+        self.partitions.contains_key(&partition_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::kafka_protocol::TaggedFields;
+    use crate::common::kafka_record::RecordValueMetadata;
+    use crate::common::primitive_types::{CompactArray, CompactNullableString};
+    use crate::metadata::writer::{partition_record, remove_topic_record, topic_config_record, topic_record};
+
+    const TOPIC_ID: Uuid = Uuid::from_u128(1);
+
+    fn value_metadata(record_type: i8) -> RecordValueMetadata {
+        RecordValueMetadata {
+            frame_version: 1,
+            record_type,
+            version: 0,
+        }
+    }
+
+    // an image with topic "foo" and partition 0 replicated on brokers 1 and 2
+    fn image_with_topic() -> MetadataImage {
+        let mut image = MetadataImage::new();
+        image.replay(&topic_record("foo", TOPIC_ID));
+        image.replay(&partition_record(TOPIC_ID, 0, vec![1, 2], vec![Uuid::nil(), Uuid::nil()]));
+        image
+    }
+
+    fn partition_change(leader: i32, isr: Option<Vec<i32>>) -> RecordValue {
+        RecordValue::PartitionChangeRecord(PartitionChangeRecord {
+            value_metadata: value_metadata(5),
+            partition_id: 0,
+            topic_id: TOPIC_ID,
+            isr: isr.map(CompactArray::new),
+            leader,
+            replicas: None,
+            removing_replicas: None,
+            adding_replicas: None,
+            leader_recovery_state: 0,
+            eligible_leader_replicas: None,
+            last_known_elr: None,
+            directories: None,
+            tagged_fields: TaggedFields(None),
+        })
+    }
+
+    fn register_broker(broker_id: i32, fenced: bool) -> RecordValue {
+        RecordValue::RegisterBrokerRecord(RegisterBrokerRecord {
+            value_metadata: value_metadata(0),
+            broker_id,
+            is_migrating_zk_broker: false,
+            incarnation_id: Uuid::from_u128(broker_id as u128),
+            broker_epoch: 1,
+            end_points: CompactArray::new(vec![]),
+            features: CompactArray::new(vec![]),
+            rack: CompactNullableString::new(None),
+            fenced,
+            in_controlled_shutdown: false,
+            log_dirs: CompactArray::new(vec![]),
+            tagged_fields: TaggedFields(None),
+        })
+    }
+
+    fn registration_change(broker_id: i32, fenced: i8) -> RecordValue {
+        RecordValue::BrokerRegistrationChangeRecord(BrokerRegistrationChangeRecord {
+            value_metadata: value_metadata(17),
+            broker_id,
+            broker_epoch: 1,
+            fenced,
+            in_controlled_shutdown: 0,
+            log_dirs: None,
+            tagged_fields: TaggedFields(None),
+        })
+    }
+
+    fn partition(image: &MetadataImage) -> &PartitionImage {
+        image.topic_by_name("foo").unwrap().partition(0).unwrap()
+    }
+
+    fn fenced(image: &MetadataImage, broker_id: i32) -> bool {
+        image.brokers().find(|broker| broker.broker_id == broker_id).unwrap().fenced
+    }
+
+    #[test]
+    fn topics_and_partitions_are_replayed() {
+        let image = image_with_topic();
+
+        let topic = image.topic_by_name("foo").unwrap();
+        assert_eq!(topic.topic_id, TOPIC_ID);
+        assert!(image.topic_by_id(&TOPIC_ID).is_some_and(|topic| topic.name == "foo"));
+        assert!(topic.has_partition(0));
+        assert!(!topic.has_partition(1));
+
+        let partition = partition(&image);
+        assert_eq!(partition.replicas, vec![1, 2]);
+        assert_eq!(partition.isr, vec![1, 2]);
+        assert_eq!(partition.leader, 1);
+        assert_eq!((partition.leader_epoch, partition.partition_epoch), (0, 0));
+    }
+
+    #[test]
+    fn partitions_of_unknown_topics_are_ignored() {
+        let mut image = MetadataImage::new();
+        image.replay(&partition_record(TOPIC_ID, 0, vec![1], vec![Uuid::nil()]));
+        assert_eq!(image.topics().count(), 0);
+    }
+
+    #[test]
+    fn config_records_set_and_clear_topic_configs() {
+        let mut image = image_with_topic();
+
+        image.replay(&topic_config_record("foo", "retention.ms", Some("1000")));
+        assert_eq!(image.topic_configs("foo").and_then(|configs| configs.get("retention.ms")).map(String::as_str), Some("1000"));
+
+        // a null value removes the override
+        image.replay(&topic_config_record("foo", "retention.ms", None));
+        assert!(image.topic_configs("foo").map_or(true, |configs| !configs.contains_key("retention.ms")));
+    }
+
+    #[test]
+    fn removing_a_topic_drops_its_configs() {
+        let mut image = image_with_topic();
+        image.replay(&topic_config_record("foo", "cleanup.policy", Some("compact")));
+
+        image.replay(&remove_topic_record(TOPIC_ID));
+        assert!(image.topic_by_name("foo").is_none());
+        assert!(image.topic_by_id(&TOPIC_ID).is_none());
+        assert!(image.topic_configs("foo").is_none());
+
+        // a topic recreated with the name starts without overrides
+        image.replay(&topic_record("foo", Uuid::from_u128(2)));
+        assert!(image.topic_configs("foo").is_none());
+    }
+
+    #[test]
+    fn partition_changes_without_a_new_leader_bump_only_the_partition_epoch() {
+        let mut image = image_with_topic();
+
+        image.replay(&partition_change(NO_LEADER_CHANGE, Some(vec![1])));
+        let partition = partition(&image);
+        assert_eq!(partition.isr, vec![1]);
+        assert_eq!(partition.leader, 1);
+        assert_eq!((partition.leader_epoch, partition.partition_epoch), (0, 1));
+    }
+
+    #[test]
+    fn partition_changes_with_a_new_leader_bump_both_epochs() {
+        let mut image = image_with_topic();
+
+        image.replay(&partition_change(2, None));
+        let partition = partition(&image);
+        assert_eq!(partition.leader, 2);
+        // fields left out of the record keep their value
+        assert_eq!(partition.isr, vec![1, 2]);
+        assert_eq!((partition.leader_epoch, partition.partition_epoch), (1, 1));
+    }
+
+    #[test]
+    fn registration_changes_fence_and_unfence_brokers() {
+        let mut image = MetadataImage::new();
+        image.replay(&register_broker(1, true));
+
+        image.replay(&registration_change(1, -1));
+        assert!(!fenced(&image, 1));
+
+        // 0 leaves the fencing as it is
+        image.replay(&registration_change(1, 0));
+        assert!(!fenced(&image, 1));
+
+        image.replay(&registration_change(1, 1));
+        assert!(fenced(&image, 1));
+
+        image.replay(&registration_change(1, 0));
+        assert!(fenced(&image, 1));
+
+        // changes for unregistered brokers are ignored
+        image.replay(&registration_change(2, 1));
+        assert_eq!(image.brokers().count(), 1);
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::common::kafka_protocol::RequestContext;
+use crate::common::kafka_record::RecordBatch;
+use crate::common::traits::Decodable;
+use crate::metadata::image::MetadataImage;
+use crate::storage::log::{log_file_name, segment_base_offsets, RawBatchIter};
+use crate::storage::log_config::DEFAULT_MAX_MESSAGE_BYTES;
+use crate::storage::log_manager::TopicPartition;
+
+pub const METADATA_TOPIC: &str = "__cluster_metadata";
+
+//
+// MetadataLoader
+//
+
+// Replays the cluster metadata log into the metadata image at startup, following the log
+// from segment to segment; later changes are applied by the MetadataWriter as it appends them
+pub struct MetadataLoader {
+    dir: PathBuf,
+    // segment being tailed
+    segment_base_offset: i64,
+    // file position right after the last replayed batch in that segment
+    position: u64,
+}
+
+impl MetadataLoader {
+    pub fn new(metadata_log_dir: &Path) -> Self {
+        MetadataLoader {
+            dir: metadata_log_dir.join(TopicPartition::new(METADATA_TOPIC, 0).dir_name()),
+            segment_base_offset: 0,
+            position: 0,
+        }
+    }
+
+    // replay the complete batches appended to the metadata log since the last call
+    // returns the number of batches replayed
+    pub fn catch_up(&mut self, image: &RwLock<MetadataImage>) -> std::io::Result<usize> {
+        let mut replayed = 0;
+
+        loop {
+            // a segment is only rolled once it is complete, so look for the next one before reading
+            // the current one to not miss batches appended right before the roll
+            let next_segment = match segment_base_offsets(&self.dir) {
+                Ok(base_offsets) => base_offsets.into_iter().find(|&base_offset| base_offset > self.segment_base_offset),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0), // nothing written yet
+                Err(e) => return Err(e),
+            };
+
+            replayed += self.replay_segment(image)?;
+
+            match next_segment {
+                Some(base_offset) => {
+                    self.segment_base_offset = base_offset;
+                    self.position = 0;
+                }
+                None => break,
+            }
+        }
+
+        if replayed > 0 {
+            println!("Replayed {} metadata record batches, metadata log position: {} in segment {}", replayed, self.position, self.segment_base_offset);
+        }
+
+        Ok(replayed)
+    }
+
+    // replay the complete batches of the current segment after `position`
+    fn replay_segment(&mut self, image: &RwLock<MetadataImage>) -> std::io::Result<usize> {
+        let mut file = match File::open(self.dir.join(log_file_name(self.segment_base_offset))) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+
+        if file.metadata()?.len() <= self.position {
+            return Ok(0);
+        }
+
+        file.seek(SeekFrom::Start(self.position))?;
+        let mut buf: Vec<u8> = Vec::new();
+        file.read_to_end(&mut buf)?;
+
+        let mut context_map: HashMap<String, String> = HashMap::new();
+        context_map.insert("is_metadata_request".to_string(), "true".to_string());
+        // the metadata log has no topic configs, its batches get the default max.message.bytes
+        context_map.insert("max_decompressed_bytes".to_string(), DEFAULT_MAX_MESSAGE_BYTES.to_string());
+        let request_context = &RequestContext::Some(context_map);
+
+        let mut image = image.write().unwrap();
+        let mut batch_iter = RawBatchIter::new(&buf);
+        let mut batch_position = self.position;
+        let mut replayed = 0;
+
+        for batch in batch_iter.by_ref() {
+            match RecordBatch::decode(batch, request_context) {
+                Ok((record_batch, _)) => {
+                    for record in &record_batch.records {
+                        image.replay(&record.value);
+                    }
+                    replayed += 1;
+                }
+                Err(_) => {
+                    println!("Skipping undecodable metadata batch at position {}", batch_position);
+                }
+            }
+            batch_position += batch.len() as u64;
+        }
+
+        // a partial batch at the tail is picked up by a later call
+        self.position += batch_iter.position() as u64;
+
+        Ok(replayed)
+    }
+}
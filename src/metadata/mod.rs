@@ -1 +1,3 @@
-pub mod decode;
\ No newline at end of file
+pub mod image;
+pub mod loader;
+pub mod writer;
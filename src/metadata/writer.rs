@@ -0,0 +1,145 @@
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use uuid::Uuid;
+
+use crate::common::kafka_protocol::TaggedFields;
+use crate::common::kafka_record::{ConfigRecord, PartitionRecord, Record, RecordBatch, RecordValue, RecordValueMetadata, RemoveTopicRecord, TopicRecord};
+use crate::common::primitive_types::{CompactArray, CompactNullableString, CompactString, SVarInt};
+use crate::common::traits::Encodable;
+use crate::metadata::image::{MetadataImage, TOPIC_RESOURCE_TYPE};
+use crate::metadata::loader::METADATA_TOPIC;
+use crate::storage::log::Log;
+use crate::storage::log_config::LogConfig;
+use crate::storage::log_manager::TopicPartition;
+use crate::storage::log_segment::now_ms;
+
+// metadata records are framed with frame version 1, then their type and version
+const METADATA_FRAME_VERSION: i8 = 1;
+
+// DirectoryId.UNASSIGNED, for replicas whose log dir is not known
+pub const UNASSIGNED_DIRECTORY: Uuid = Uuid::nil();
+
+//
+// MetadataWriter
+//
+
+// Appends records to the cluster metadata log and replays them into the metadata image once they are
+// flushed, the MetadataLoader reads them back at startup like the records a controller writes.
+// The records of one change go in a single batch, so a crash never leaves half of it.
+pub struct MetadataWriter {
+    log: Log,
+    image: Arc<RwLock<MetadataImage>>,
+}
+
+impl MetadataWriter {
+    pub fn open(metadata_log_dir: &Path, image: Arc<RwLock<MetadataImage>>) -> std::io::Result<Self> {
+        let dir = metadata_log_dir.join(TopicPartition::new(METADATA_TOPIC, 0).dir_name());
+
+        // the metadata log has no recovery point checkpoint, a partial batch at its tail would stall the loader
+        let log = Log::open(&dir, LogConfig::default(), Some(0))?;
+
+        Ok(MetadataWriter { log, image })
+    }
+
+    // append `records` as one batch, flush it and apply it to the metadata image
+    // returns the offset of the first record
+    pub fn append(&mut self, records: Vec<RecordValue>) -> std::io::Result<i64> {
+        let now = now_ms();
+        let record_batch = RecordBatch {
+            // assigned by the log
+            base_offset: 0,
+            partition_leader_epoch: 0,
+            magic: 2,
+            crc: 0,
+            attributes: 0,
+            last_offset_delta: records.len() as i32 - 1,
+            base_timestamp: now,
+            max_timestamp: now,
+            producer_id: -1,
+            producer_epoch: -1,
+            base_sequence: -1,
+            records: records.into_iter().enumerate().map(|(offset_delta, value)| Record {
+                attributes: 0,
+                timestamp_delta: SVarInt::new(0),
+                offset_delta: SVarInt::new(offset_delta as i32),
+                key: None,
+                value,
+                headers: vec![],
+            }).collect(),
+        };
+
+        let base_offset = self.log.append(vec![record_batch.encode()])?;
+        self.log.flush()?;
+
+        // readers see the change once it is durable, the caller still holds the writer so changes apply in log order
+        let mut image = self.image.write().unwrap();
+        for record in &record_batch.records {
+            image.replay(&record.value);
+        }
+
+        Ok(base_offset)
+    }
+
+    pub fn close(&mut self) -> std::io::Result<()> {
+        self.log.close()
+    }
+}
+
+fn value_metadata(record_type: i8, version: i8) -> RecordValueMetadata {
+    RecordValueMetadata {
+        frame_version: METADATA_FRAME_VERSION,
+        record_type,
+        version,
+    }
+}
+
+pub fn topic_record(name: &str, topic_id: Uuid) -> RecordValue {
+    RecordValue::TopicRecord(TopicRecord {
+        value_metadata: value_metadata(2, 0),
+        topic_name: CompactString::new(name.to_string()),
+        topic_id,
+        tagged_fields: TaggedFields(None),
+    })
+}
+
+// a new partition led by its first replica, with every replica in sync
+// `directories` has the log dir of each replica, in the order of `replicas`
+pub fn partition_record(topic_id: Uuid, partition_id: i32, replicas: Vec<i32>, directories: Vec<Uuid>) -> RecordValue {
+    RecordValue::PartitionRecord(PartitionRecord {
+        value_metadata: value_metadata(3, 1),
+        partition_id,
+        topic_id,
+        leader: replicas.first().copied().unwrap_or(-1),
+        isr_array: CompactArray::new(replicas.clone()),
+        replica_array: CompactArray::new(replicas),
+        removing_replicas_array: CompactArray::new(vec![]),
+        adding_replicas_array: CompactArray::new(vec![]),
+        leader_epoch: 0,
+        partition_epoch: 0,
+        directories: CompactArray::new(directories),
+        leader_recovery_state: 0,
+        eligible_leader_replicas: None,
+        last_known_elr: None,
+        tagged_fields: TaggedFields(None),
+    })
+}
+
+pub fn topic_config_record(topic: &str, name: &str, value: Option<&str>) -> RecordValue {
+    RecordValue::ConfigRecord(ConfigRecord {
+        value_metadata: value_metadata(4, 0),
+        resource_type: TOPIC_RESOURCE_TYPE,
+        resource_name: CompactString::new(topic.to_string()),
+        name: CompactString::new(name.to_string()),
+        value: CompactNullableString::new(value.map(|value| CompactString::new(value.to_string()))),
+        tagged_fields: TaggedFields(None),
+    })
+}
+
+pub fn remove_topic_record(topic_id: Uuid) -> RecordValue {
+    RecordValue::RemoveTopicRecord(RemoveTopicRecord {
+        value_metadata: value_metadata(9, 0),
+        topic_id,
+        tagged_fields: TaggedFields(None),
+    })
+}
@@ -0,0 +1,176 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+
+//
+// Segment indexes
+//
+
+// Sparse indexes kept next to each log segment, in Kafka's on-disk layout:
+// entries are big endian and relative to the segment base offset, appended in increasing order
+
+// .index entry => relative_offset position
+//   relative_offset => INT32
+//   position => INT32
+pub const OFFSET_INDEX_ENTRY_SIZE: usize = 8;
+
+// .timeindex entry => timestamp relative_offset
+//   timestamp => INT64
+//   relative_offset => INT32
+pub const TIME_INDEX_ENTRY_SIZE: usize = 12;
+
+// open an index file, creating it if needed, and return its complete entries
+// a partial entry at the tail (e.g. from a crash mid-write) is cut off
+fn open_index_file(path: &Path, entry_size: usize) -> std::io::Result<(File, Vec<u8>)> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+
+    let mut buf: Vec<u8> = Vec::new();
+    file.read_to_end(&mut buf)?;
+
+    let complete_bytes = buf.len() - buf.len() % entry_size;
+    if complete_bytes < buf.len() {
+        file.set_len(complete_bytes as u64)?;
+        buf.truncate(complete_bytes);
+    }
+
+    Ok((file, buf))
+}
+
+// maps offsets to the file position of the batch that contains them
+pub struct OffsetIndex {
+    file: File,
+    base_offset: i64,
+    // absolute offset and position
+    entries: Vec<(i64, u64)>,
+}
+
+impl OffsetIndex {
+    pub fn open(path: &Path, base_offset: i64) -> std::io::Result<Self> {
+        let (file, buf) = open_index_file(path, OFFSET_INDEX_ENTRY_SIZE)?;
+
+        let entries = buf.chunks_exact(OFFSET_INDEX_ENTRY_SIZE)
+            .map(|entry| {
+                let relative_offset = i32::from_be_bytes(entry[0..4].try_into().unwrap());
+                let position = i32::from_be_bytes(entry[4..8].try_into().unwrap());
+                (base_offset + relative_offset as i64, position as u64)
+            })
+            .collect();
+
+        Ok(OffsetIndex { file, base_offset, entries })
+    }
+
+    // index `offset` at `position`, offsets must be appended in increasing order
+    pub fn append(&mut self, offset: i64, position: u64) -> std::io::Result<()> {
+        if self.entries.last().is_some_and(|&(last_offset, _)| offset <= last_offset) {
+            return Ok(());
+        }
+
+        let mut entry: Vec<u8> = Vec::with_capacity(OFFSET_INDEX_ENTRY_SIZE);
+        entry.extend(((offset - self.base_offset) as i32).to_be_bytes());
+        entry.extend((position as i32).to_be_bytes());
+        self.file.write_all(&entry)?;
+
+        self.entries.push((offset, position));
+        Ok(())
+    }
+
+    // the largest indexed offset at or below `offset` and its position,
+    // or the segment start when there is none
+    pub fn lookup(&self, offset: i64) -> (i64, u64) {
+        let index = self.entries.partition_point(|&(entry_offset, _)| entry_offset <= offset);
+        match index {
+            0 => (self.base_offset, 0),
+            _ => self.entries[index - 1],
+        }
+    }
+
+    pub fn last_entry(&self) -> Option<(i64, u64)> {
+        self.entries.last().copied()
+    }
+
+    // drop every entry, the index is rebuilt from the log
+    pub fn reset(&mut self) -> std::io::Result<()> {
+        self.file.set_len(0)?;
+        self.entries.clear();
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn flush(&self) -> std::io::Result<()> {
+        self.file.sync_all()
+    }
+}
+
+// maps timestamps to the first offset whose batch has a larger or equal max timestamp
+pub struct TimeIndex {
+    file: File,
+    base_offset: i64,
+    // timestamp and absolute offset
+    entries: Vec<(i64, i64)>,
+}
+
+impl TimeIndex {
+    pub fn open(path: &Path, base_offset: i64) -> std::io::Result<Self> {
+        let (file, buf) = open_index_file(path, TIME_INDEX_ENTRY_SIZE)?;
+
+        let entries = buf.chunks_exact(TIME_INDEX_ENTRY_SIZE)
+            .map(|entry| {
+                let timestamp = i64::from_be_bytes(entry[0..8].try_into().unwrap());
+                let relative_offset = i32::from_be_bytes(entry[8..12].try_into().unwrap());
+                (timestamp, base_offset + relative_offset as i64)
+            })
+            .collect();
+
+        Ok(TimeIndex { file, base_offset, entries })
+    }
+
+    // index `timestamp` at `offset` unless an entry with a larger or equal timestamp is already there
+    pub fn maybe_append(&mut self, timestamp: i64, offset: i64) -> std::io::Result<()> {
+        if self.entries.last().is_some_and(|&(last_timestamp, _)| timestamp <= last_timestamp) {
+            return Ok(());
+        }
+
+        let mut entry: Vec<u8> = Vec::with_capacity(TIME_INDEX_ENTRY_SIZE);
+        entry.extend(timestamp.to_be_bytes());
+        entry.extend(((offset - self.base_offset) as i32).to_be_bytes());
+        self.file.write_all(&entry)?;
+
+        self.entries.push((timestamp, offset));
+        Ok(())
+    }
+
+    // the largest indexed timestamp at or below `timestamp` and its offset,
+    // or (-1, segment base offset) when there is none
+    pub fn lookup(&self, timestamp: i64) -> (i64, i64) {
+        let index = self.entries.partition_point(|&(entry_timestamp, _)| entry_timestamp <= timestamp);
+        match index {
+            0 => (-1, self.base_offset),
+            _ => self.entries[index - 1],
+        }
+    }
+
+    pub fn last_entry(&self) -> Option<(i64, i64)> {
+        self.entries.last().copied()
+    }
+
+    pub fn reset(&mut self) -> std::io::Result<()> {
+        self.file.set_len(0)?;
+        self.entries.clear();
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn flush(&self) -> std::io::Result<()> {
+        self.file.sync_all()
+    }
+}
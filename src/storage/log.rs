@@ -0,0 +1,528 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::storage::log_config::LogConfig;
+use crate::storage::log_segment::{now_ms, BatchHeader, LogSegment};
+
+// byte positions of the RecordBatch header fields the log needs without decoding the whole batch
+// (see the RecordBatch layout in src/common/kafka_record.rs)
+pub const BASE_OFFSET_POS: usize = 0;
+pub const BATCH_LENGTH_POS: usize = 8;
+pub const MAGIC_POS: usize = 16;
+pub const CRC_POS: usize = 17;
+pub const ATTRIBUTES_POS: usize = 21;
+pub const LAST_OFFSET_DELTA_POS: usize = 23;
+pub const BASE_TIMESTAMP_POS: usize = 27;
+pub const MAX_TIMESTAMP_POS: usize = 35;
+pub const PRODUCER_ID_POS: usize = 43;
+
+// RecordBatch attributes flags
+// timestampType, set when the broker stamped the batch with the append time instead of the producer's
+pub const LOG_APPEND_TIME_FLAG: i16 = 0x08;
+pub const TRANSACTIONAL_FLAG: i16 = 0x10;
+pub const CONTROL_FLAG: i16 = 0x20;
+pub const DELETE_HORIZON_FLAG: i16 = 0x40;
+
+// baseOffset + batchLength, the part of a batch not counted in batchLength
+pub const LOG_OVERHEAD: usize = 12;
+// everything up to and including the records count
+pub const RECORD_BATCH_HEADER_SIZE: usize = 61;
+
+//
+// Log
+//
+
+// A partition log: segments ordered by base offset, appends go to the last (active) segment,
+// which is rolled once it reaches segment.bytes or spans segment.ms
+pub struct Log {
+    dir: PathBuf,
+    config: LogConfig,
+    segments: BTreeMap<i64, LogSegment>,
+    log_start_offset: i64,
+    log_end_offset: i64,
+    // this broker is the only replica, so the high watermark follows the log end offset once an append completes
+    high_watermark: i64,
+    // first offset of each producer's open transaction, keyed by producer id
+    ongoing_transactions: HashMap<i64, i64>,
+    // the log cleaner has compacted everything below this offset
+    first_dirty_offset: i64,
+    // everything below this offset is known to be on disk intact
+    recovery_point: i64,
+    // set on shutdown once the log is flushed, appends are refused from then on
+    closed: bool,
+}
+
+impl Log {
+    // open the partition log in `dir`, creating it if needed, and load its segments
+    // without a clean shutdown, the segments holding offsets at or past `recovery_point` may be partially written
+    // and are recovered, None when the log was closed cleanly
+    pub fn open(dir: &Path, config: LogConfig, recovery_point: Option<i64>) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+
+        let mut segments: BTreeMap<i64, LogSegment> = BTreeMap::new();
+        for base_offset in segment_base_offsets(dir)? {
+            segments.insert(base_offset, LogSegment::open(dir, base_offset, config.index_interval_bytes)?);
+        }
+        if segments.is_empty() {
+            segments.insert(0, LogSegment::open(dir, 0, config.index_interval_bytes)?);
+        }
+        if let Some(recovery_point) = recovery_point {
+            recover_segments(dir, &mut segments, recovery_point)?;
+        }
+
+        let mut log = Log {
+            dir: dir.to_path_buf(),
+            config,
+            log_start_offset: *segments.keys().next().unwrap(),
+            log_end_offset: segments.values().next_back().unwrap().next_offset(),
+            segments,
+            high_watermark: 0,
+            ongoing_transactions: HashMap::new(),
+            first_dirty_offset: 0,
+            recovery_point: 0,
+            closed: false,
+        };
+        log.high_watermark = log.log_end_offset;
+        log.first_dirty_offset = log.log_start_offset;
+        log.recovery_point = recovery_point.unwrap_or(log.log_end_offset).min(log.log_end_offset);
+
+        // replay the batch headers to find the transactions still open
+        let mut ongoing_transactions: HashMap<i64, i64> = HashMap::new();
+        for segment in log.segments.values() {
+            for header in segment.batch_headers(0) {
+                track_transaction(&mut ongoing_transactions, &header?);
+            }
+        }
+        log.ongoing_transactions = ongoing_transactions;
+
+        println!("Opened log {:?} with {} segment(s), log start offset: {}, log end offset: {}", dir, log.segments.len(), log.log_start_offset, log.log_end_offset);
+
+        Ok(log)
+    }
+
+    // append already validated record batches, assigning offsets starting at the log end offset
+    // returns the base offset of the first appended batch
+    pub fn append(&mut self, batches: Vec<Vec<u8>>) -> std::io::Result<i64> {
+        if self.closed {
+            return Err(std::io::Error::other(format!("log {:?} is closed", self.dir)));
+        }
+
+        let base_offset = self.log_end_offset;
+        let mut next_offset = self.log_end_offset;
+        let mut max_timestamp = -1;
+
+        let mut buf: Vec<u8> = Vec::new();
+        for mut batch in batches {
+            // baseOffset is not covered by the CRC, so it can be rewritten in place
+            batch[BASE_OFFSET_POS..BASE_OFFSET_POS + 8].copy_from_slice(&next_offset.to_be_bytes());
+            next_offset = batch_next_offset(&batch);
+            max_timestamp = max_timestamp.max(batch_max_timestamp(&batch));
+            buf.extend(batch);
+        }
+
+        self.maybe_roll(buf.len() as u64, max_timestamp, next_offset - 1)?;
+        self.active_segment_mut().append(&buf)?;
+
+        for batch in RawBatchIter::new(&buf) {
+            track_transaction(&mut self.ongoing_transactions, &BatchHeader::from_batch(batch));
+        }
+        self.log_end_offset = next_offset;
+        self.high_watermark = self.log_end_offset;
+
+        Ok(base_offset)
+    }
+
+    // start a new active segment at the log end offset when the current one is full or too old
+    fn maybe_roll(&mut self, messages_size: u64, max_timestamp: i64, last_offset: i64) -> std::io::Result<()> {
+        if !self.active_segment().should_roll(&self.config, messages_size, max_timestamp, last_offset) {
+            return Ok(());
+        }
+
+        let segment = LogSegment::open(&self.dir, self.log_end_offset, self.config.index_interval_bytes)?;
+        self.segments.insert(self.log_end_offset, segment);
+        println!("Rolled new log segment for {:?} at offset {}", self.dir, self.log_end_offset);
+
+        Ok(())
+    }
+
+    // delete the oldest segments past retention.ms or retention.bytes and advance the log start offset
+    // returns the number of segments deleted
+    pub fn delete_retention_breached_segments(&mut self) -> std::io::Result<usize> {
+        // compacted topics without the delete policy keep their segments
+        if !self.config.delete() {
+            return Ok(0);
+        }
+
+        let now = now_ms();
+        let retention_ms = self.config.retention_ms;
+        let mut deleted = 0;
+
+        if retention_ms >= 0 {
+            let segments = self.deletable_segments(|segment| now - segment.largest_timestamp() > retention_ms);
+            deleted += self.delete_segments(segments, "retention.ms")?;
+        }
+
+        let retention_bytes = self.config.retention_bytes;
+        let log_size: u64 = self.segments.values().map(|segment| segment.size()).sum();
+        if retention_bytes >= 0 && log_size > retention_bytes as u64 {
+            let mut bytes_over = log_size - retention_bytes as u64;
+            let segments = self.deletable_segments(|segment| {
+                if segment.size() > bytes_over {
+                    return false;
+                }
+                bytes_over -= segment.size();
+                true
+            });
+            deleted += self.delete_segments(segments, "retention.bytes")?;
+        }
+
+        Ok(deleted)
+    }
+
+    // base offsets of the oldest segments matching `predicate`, in order, stopping at the first one that does not
+    // a segment is only deletable once all of it is below the high watermark, and an empty active segment is kept
+    fn deletable_segments(&self, mut predicate: impl FnMut(&LogSegment) -> bool) -> Vec<i64> {
+        let mut deletable: Vec<i64> = Vec::new();
+        let mut segments = self.segments.values().peekable();
+
+        while let Some(segment) = segments.next() {
+            let next_segment = segments.peek();
+            let upper_bound_offset = next_segment.map_or(self.log_end_offset, |next_segment| next_segment.base_offset());
+            let is_last_and_empty = next_segment.is_none() && segment.size() == 0;
+
+            if upper_bound_offset > self.high_watermark || is_last_and_empty || !predicate(segment) {
+                break;
+            }
+            deletable.push(segment.base_offset());
+        }
+
+        deletable
+    }
+
+    fn delete_segments(&mut self, base_offsets: Vec<i64>, reason: &str) -> std::io::Result<usize> {
+        if base_offsets.is_empty() {
+            return Ok(0);
+        }
+
+        // the log always keeps a segment, so roll a new active one before deleting them all
+        if base_offsets.len() == self.segments.len() {
+            let segment = LogSegment::open(&self.dir, self.log_end_offset, self.config.index_interval_bytes)?;
+            self.segments.insert(self.log_end_offset, segment);
+            println!("Rolled new log segment for {:?} at offset {}", self.dir, self.log_end_offset);
+        }
+
+        for base_offset in &base_offsets {
+            if let Some(segment) = self.segments.remove(base_offset) {
+                println!("Deleting segment {} of {:?} due to {} breach", base_offset, self.dir, reason);
+                segment.delete()?;
+            }
+        }
+
+        let first_segment = *self.segments.keys().next().unwrap();
+        self.log_start_offset = self.log_start_offset.max(first_segment);
+        self.first_dirty_offset = self.first_dirty_offset.max(self.log_start_offset);
+        println!("Log start offset of {:?} advanced to {}", self.dir, self.log_start_offset);
+
+        Ok(base_offsets.len())
+    }
+
+    // swap a closed segment for the batches the log cleaner kept from it, its base offset stays the same
+    pub fn replace_segment(&mut self, base_offset: i64, batches: &[u8]) -> std::io::Result<()> {
+        let cleaned_path = self.dir.join(format!("{}{}", log_file_name(base_offset), CLEANED_FILE_SUFFIX));
+        let mut cleaned_file = File::create(&cleaned_path)?;
+        cleaned_file.write_all(batches)?;
+        cleaned_file.sync_all()?;
+
+        let segment = match self.segments.remove(&base_offset) {
+            Some(segment) => segment,
+            None => return fs::remove_file(&cleaned_path),
+        };
+        self.segments.insert(base_offset, segment.replace_with(&cleaned_path)?);
+
+        Ok(())
+    }
+
+    // flush the segments written since the recovery point and refuse further appends
+    pub fn close(&mut self) -> std::io::Result<()> {
+        self.flush()?;
+        self.closed = true;
+        Ok(())
+    }
+
+    // write the segments holding offsets past the recovery point through to disk
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        let recovery_point = self.recovery_point;
+        for segment in self.segments.values().filter(|segment| segment.next_offset() >= recovery_point) {
+            segment.flush()?;
+        }
+
+        self.recovery_point = self.log_end_offset;
+        Ok(())
+    }
+
+    pub fn update_config(&mut self, config: LogConfig) {
+        self.config = config;
+    }
+
+    // read whole record batches from the segment holding `fetch_offset`, starting with the batch that contains it,
+    // up to `max_bytes` and stopping before `max_offset` (the high watermark or last stable offset)
+    // with `min_one_batch`, the first batch is returned even when it is larger than `max_bytes`
+    pub fn read(&self, fetch_offset: i64, max_offset: i64, max_bytes: usize, min_one_batch: bool) -> std::io::Result<Vec<u8>> {
+        match self.position_of(fetch_offset)? {
+            Some((segment, position)) => segment.read(position, max_offset, max_bytes, min_one_batch),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // the first record from the log start whose timestamp is at or after `timestamp`, as (timestamp, offset)
+    // None when every record is older
+    pub fn fetch_offset_by_timestamp(&self, timestamp: i64) -> std::io::Result<Option<(i64, i64)>> {
+        for segment in self.segments.values().filter(|segment| segment.max_timestamp() >= timestamp) {
+            if let Some(found) = segment.find_offset_by_timestamp(timestamp, self.log_start_offset, self.config.max_message_bytes)? {
+                return Ok(Some(found));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // the record with the largest timestamp, the first one when several share it
+    // None for an empty log or one without timestamps
+    pub fn fetch_offset_of_max_timestamp(&self) -> std::io::Result<Option<(i64, i64)>> {
+        match self.segments.values().map(|segment| segment.max_timestamp()).max() {
+            Some(max_timestamp) if max_timestamp >= 0 => self.fetch_offset_by_timestamp(max_timestamp),
+            _ => Ok(None),
+        }
+    }
+
+    // number of bytes in the log from the batch containing `offset` to the end
+    pub fn bytes_after(&self, offset: i64) -> u64 {
+        let (segment, position) = match self.position_of(offset) {
+            Ok(Some(position)) => position,
+            _ => return 0,
+        };
+
+        let later_segments: u64 = self.segments.range(segment.base_offset() + 1..).map(|(_, segment)| segment.size()).sum();
+        segment.size() - position + later_segments
+    }
+
+    // segment and file position of the batch containing `offset`, None when it is at or past the log end
+    fn position_of(&self, offset: i64) -> std::io::Result<Option<(&LogSegment, u64)>> {
+        let floor = self.segments.range(..=offset).next_back().map_or(self.log_start_offset, |(&base_offset, _)| base_offset);
+
+        for segment in self.segments.range(floor..).map(|(_, segment)| segment) {
+            if let Some(position) = segment.translate_offset(offset)? {
+                return Ok(Some((segment, position)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn active_segment(&self) -> &LogSegment {
+        self.segments.values().next_back().unwrap()
+    }
+
+    fn active_segment_mut(&mut self) -> &mut LogSegment {
+        self.segments.values_mut().next_back().unwrap()
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = &LogSegment> {
+        self.segments.values()
+    }
+
+    pub fn config(&self) -> &LogConfig {
+        &self.config
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn log_start_offset(&self) -> i64 {
+        self.log_start_offset
+    }
+
+    pub fn log_end_offset(&self) -> i64 {
+        self.log_end_offset
+    }
+
+    pub fn high_watermark(&self) -> i64 {
+        self.high_watermark
+    }
+
+    pub fn recovery_point(&self) -> i64 {
+        self.recovery_point
+    }
+
+    pub fn first_dirty_offset(&self) -> i64 {
+        self.first_dirty_offset
+    }
+
+    pub fn set_first_dirty_offset(&mut self, offset: i64) {
+        self.first_dirty_offset = offset;
+    }
+
+    // offset up to which all transactions are decided, consumers with read_committed stop here
+    pub fn last_stable_offset(&self) -> i64 {
+        self.ongoing_transactions.values().copied().min().unwrap_or(self.high_watermark)
+    }
+}
+
+// a transaction is open from its first data batch until its commit or abort marker
+fn track_transaction(ongoing_transactions: &mut HashMap<i64, i64>, header: &BatchHeader) {
+    if header.attributes & TRANSACTIONAL_FLAG != 0 {
+        if header.attributes & CONTROL_FLAG != 0 {
+            ongoing_transactions.remove(&header.producer_id);
+        } else {
+            ongoing_transactions.entry(header.producer_id).or_insert(header.base_offset);
+        }
+    }
+}
+
+// recover the segments from the one holding `recovery_point` on
+// once a segment had to be truncated, the segments after it are deleted, their offsets would follow a gap
+fn recover_segments(dir: &Path, segments: &mut BTreeMap<i64, LogSegment>, recovery_point: i64) -> std::io::Result<()> {
+    let first_unflushed = match segments.range(..=recovery_point).next_back() {
+        Some((&base_offset, _)) => base_offset,
+        None => *segments.keys().next().unwrap(),
+    };
+
+    let mut truncated_at: Option<i64> = None;
+    for (&base_offset, segment) in segments.range_mut(first_unflushed..) {
+        if segment.recover()? > 0 {
+            truncated_at = Some(base_offset);
+            break;
+        }
+    }
+
+    if let Some(truncated_at) = truncated_at {
+        let later_segments: Vec<i64> = segments.range(truncated_at + 1..).map(|(&base_offset, _)| base_offset).collect();
+        for base_offset in later_segments {
+            println!("Deleting segment {} of {:?} after the truncated segment {}", base_offset, dir, truncated_at);
+            segments.remove(&base_offset).unwrap().delete()?;
+        }
+    }
+
+    Ok(())
+}
+
+// base offsets of the segments in a partition directory, in increasing order
+pub fn segment_base_offsets(dir: &Path) -> std::io::Result<Vec<i64>> {
+    let mut base_offsets: Vec<i64> = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let file_name = entry?.file_name();
+        let base_offset = file_name.to_str()
+            .and_then(|name| name.strip_suffix(LOG_FILE_SUFFIX))
+            .and_then(|base_offset| base_offset.parse::<i64>().ok());
+
+        if let Some(base_offset) = base_offset {
+            base_offsets.push(base_offset);
+        }
+    }
+
+    base_offsets.sort();
+    Ok(base_offsets)
+}
+
+pub const LOG_FILE_SUFFIX: &str = ".log";
+pub const INDEX_FILE_SUFFIX: &str = ".index";
+pub const TIME_INDEX_FILE_SUFFIX: &str = ".timeindex";
+// a segment rewritten by the log cleaner, before it replaces the .log file
+pub const CLEANED_FILE_SUFFIX: &str = ".cleaned";
+
+// segment files are named by the base offset of their first batch, zero padded to 20 digits
+pub fn log_file_name(base_offset: i64) -> String {
+    format!("{:020}{}", base_offset, LOG_FILE_SUFFIX)
+}
+
+pub fn index_file_name(base_offset: i64) -> String {
+    format!("{:020}{}", base_offset, INDEX_FILE_SUFFIX)
+}
+
+pub fn time_index_file_name(base_offset: i64) -> String {
+    format!("{:020}{}", base_offset, TIME_INDEX_FILE_SUFFIX)
+}
+
+pub fn batch_base_offset(batch: &[u8]) -> i64 {
+    i64::from_be_bytes(batch[BASE_OFFSET_POS..BASE_OFFSET_POS + 8].try_into().unwrap())
+}
+
+pub fn batch_last_offset_delta(batch: &[u8]) -> i32 {
+    i32::from_be_bytes(batch[LAST_OFFSET_DELTA_POS..LAST_OFFSET_DELTA_POS + 4].try_into().unwrap())
+}
+
+pub fn batch_attributes(batch: &[u8]) -> i16 {
+    i16::from_be_bytes(batch[ATTRIBUTES_POS..ATTRIBUTES_POS + 2].try_into().unwrap())
+}
+
+pub fn batch_base_timestamp(batch: &[u8]) -> i64 {
+    i64::from_be_bytes(batch[BASE_TIMESTAMP_POS..BASE_TIMESTAMP_POS + 8].try_into().unwrap())
+}
+
+pub fn batch_crc(batch: &[u8]) -> u32 {
+    u32::from_be_bytes(batch[CRC_POS..CRC_POS + 4].try_into().unwrap())
+}
+
+// CRC-32C of a whole batch, computed from the attributes to the end
+pub fn compute_batch_crc(batch: &[u8]) -> u32 {
+    crc32c::crc32c(&batch[ATTRIBUTES_POS..])
+}
+
+pub fn batch_max_timestamp(batch: &[u8]) -> i64 {
+    i64::from_be_bytes(batch[MAX_TIMESTAMP_POS..MAX_TIMESTAMP_POS + 8].try_into().unwrap())
+}
+
+pub fn batch_producer_id(batch: &[u8]) -> i64 {
+    i64::from_be_bytes(batch[PRODUCER_ID_POS..PRODUCER_ID_POS + 8].try_into().unwrap())
+}
+
+// offset right after the last record of the batch
+pub fn batch_next_offset(batch: &[u8]) -> i64 {
+    batch_base_offset(batch) + batch_last_offset_delta(batch) as i64 + 1
+}
+
+// iterates over complete record batches in a buffer without decoding them,
+// stops at the first partial batch
+pub struct RawBatchIter<'a> {
+    buf: &'a [u8],
+    position: usize,
+}
+
+impl<'a> RawBatchIter<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        RawBatchIter { buf, position: 0 }
+    }
+
+    // number of bytes covered by the complete batches returned so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl<'a> Iterator for RawBatchIter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = &self.buf[self.position..];
+        if remaining.len() < RECORD_BATCH_HEADER_SIZE {
+            return None;
+        }
+
+        let batch_length = i32::from_be_bytes(remaining[BATCH_LENGTH_POS..BATCH_LENGTH_POS + 4].try_into().unwrap());
+        if batch_length < (RECORD_BATCH_HEADER_SIZE - LOG_OVERHEAD) as i32 {
+            return None;
+        }
+
+        let batch_size = LOG_OVERHEAD + batch_length as usize;
+        if remaining.len() < batch_size {
+            return None;
+        }
+
+        self.position += batch_size;
+        Some(&remaining[..batch_size])
+    }
+}
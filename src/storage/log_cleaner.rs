@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+
+use crate::common::kafka_protocol::RequestContext;
+use crate::common::kafka_record::{RecordBatch, RecordValue};
+use crate::common::traits::{Decodable, Encodable};
+use crate::storage::log::{batch_attributes, Log, RawBatchIter, CONTROL_FLAG, DELETE_HORIZON_FLAG, TRANSACTIONAL_FLAG};
+use crate::storage::log_segment::now_ms;
+
+//
+// LogCleaner
+//
+
+// Compaction for cleanup.policy=compact topics, as in Kafka's log cleaner.
+// The cleanable part of a log runs from its start up to the first uncleanable offset: the active segment,
+// segments with records newer than min.compaction.lag.ms and open transactions are left alone.
+// A pass maps every key in the cleanable part to its latest offset, then rewrites each cleanable segment
+// keeping only those records, at their original offsets.
+// Tombstones (null values) are kept for delete.retention.ms: the first pass that keeps one stamps its batch
+// with a delete horizon (hasDeleteHorizonMs, baseTimestamp = now + delete.retention.ms), a pass after it drops them.
+
+// default for log.cleaner.backoff.ms, as in Kafka
+pub const DEFAULT_LOG_CLEANER_BACKOFF_MS: u64 = 15 * 1000;
+
+#[derive(Default)]
+pub struct CleanerStats {
+    pub segments_cleaned: usize,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+    pub records_removed: usize,
+}
+
+// compact the log if it has cleanup.policy=compact and enough of it is dirty
+// returns None when the log was left as is
+pub fn clean_log(log: &mut Log) -> std::io::Result<Option<CleanerStats>> {
+    if !log.config().compact() {
+        return Ok(None);
+    }
+
+    let now = now_ms();
+    let (cleanable, first_uncleanable_offset) = cleanable_segments(log, now);
+    if cleanable.is_empty() || !needs_cleaning(log, &cleanable, now)? {
+        return Ok(None);
+    }
+
+    println!("Cleaning {:?} up to offset {}", log.dir(), first_uncleanable_offset);
+
+    let mut segments: Vec<(i64, Vec<u8>)> = Vec::new();
+    for &(base_offset, _) in &cleanable {
+        let segment = log.segments().find(|segment| segment.base_offset() == base_offset).unwrap();
+        segments.push((base_offset, segment.read(0, i64::MAX, usize::MAX, true)?));
+    }
+
+    let offset_map = build_offset_map(&segments, log.config().max_message_bytes);
+    let delete_horizon = now + log.config().delete_retention_ms;
+
+    let mut stats = CleanerStats::default();
+    for (base_offset, batches) in &segments {
+        let cleaned = clean_batches(batches, &offset_map, log.config().max_message_bytes, now, delete_horizon, &mut stats);
+
+        stats.bytes_read += batches.len() as u64;
+        stats.bytes_written += cleaned.len() as u64;
+        if cleaned != *batches {
+            log.replace_segment(*base_offset, &cleaned)?;
+            stats.segments_cleaned += 1;
+        }
+    }
+
+    log.set_first_dirty_offset(first_uncleanable_offset);
+    println!("Cleaned {:?}: {} segment(s) rewritten, {} record(s) removed, {} bytes -> {} bytes",
+        log.dir(), stats.segments_cleaned, stats.records_removed, stats.bytes_read, stats.bytes_written);
+
+    Ok(Some(stats))
+}
+
+// base offsets and sizes of the segments the cleaner may rewrite, and the offset right after them
+fn cleanable_segments(log: &Log, now: i64) -> (Vec<(i64, u64)>, i64) {
+    let first_uncleanable_offset = log.last_stable_offset();
+    let min_compaction_lag_ms = log.config().min_compaction_lag_ms;
+
+    let mut cleanable: Vec<(i64, u64)> = Vec::new();
+    let mut upper_bound_offset = log.log_start_offset();
+    let mut segments = log.segments().peekable();
+
+    while let Some(segment) = segments.next() {
+        // the active segment is never cleaned
+        let next_segment = match segments.peek() {
+            Some(next_segment) => next_segment,
+            None => break,
+        };
+
+        if next_segment.base_offset() > first_uncleanable_offset || now - segment.largest_timestamp() < min_compaction_lag_ms {
+            break;
+        }
+
+        cleanable.push((segment.base_offset(), segment.size()));
+        upper_bound_offset = next_segment.base_offset();
+    }
+
+    (cleanable, upper_bound_offset)
+}
+
+// clean when the dirty share of the cleanable segments reaches min.cleanable.dirty.ratio,
+// or when cleaned segments hold tombstones past their delete horizon
+fn needs_cleaning(log: &Log, cleanable: &[(i64, u64)], now: i64) -> std::io::Result<bool> {
+    let first_dirty_offset = log.first_dirty_offset();
+    let total_bytes: u64 = cleanable.iter().map(|&(_, size)| size).sum();
+    let dirty_bytes: u64 = cleanable.iter().filter(|&&(base_offset, _)| base_offset >= first_dirty_offset).map(|&(_, size)| size).sum();
+
+    if dirty_bytes > 0 && dirty_bytes as f64 / total_bytes as f64 >= log.config().min_cleanable_dirty_ratio {
+        return Ok(true);
+    }
+
+    for segment in log.segments().filter(|segment| segment.base_offset() < first_dirty_offset) {
+        for header in segment.batch_headers(0) {
+            let header = header?;
+            if header.attributes & DELETE_HORIZON_FLAG != 0 && header.base_timestamp <= now {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+// batches outside transactions that are not control batches, rewritten ones keep their compression codec
+fn is_cleanable_batch(batch: &[u8]) -> bool {
+    batch_attributes(batch) & (TRANSACTIONAL_FLAG | CONTROL_FLAG) == 0
+}
+
+fn decode_batch(batch: &[u8], max_message_bytes: i32) -> Option<RecordBatch> {
+    // segments only hold batches whose CRC was checked on append or recovery
+    let mut context_map: HashMap<String, String> = HashMap::new();
+    context_map.insert("skip_crc_validation".to_string(), "true".to_string());
+    context_map.insert("max_decompressed_bytes".to_string(), max_message_bytes.to_string());
+
+    match RecordBatch::decode(batch, &RequestContext::Some(context_map)) {
+        Ok((record_batch, _)) => Some(record_batch),
+        Err(_) => {
+            println!("Log cleaner could not decode a record batch, keeping it as is");
+            None
+        }
+    }
+}
+
+// latest offset of every key in the cleanable segments
+fn build_offset_map(segments: &[(i64, Vec<u8>)], max_message_bytes: i32) -> HashMap<Vec<u8>, i64> {
+    let mut offset_map: HashMap<Vec<u8>, i64> = HashMap::new();
+
+    for (_, batches) in segments {
+        for batch in RawBatchIter::new(batches).filter(|batch| is_cleanable_batch(batch)) {
+            let record_batch = match decode_batch(batch, max_message_bytes) {
+                Some(record_batch) => record_batch,
+                None => continue,
+            };
+
+            for record in record_batch.records {
+                if let Some(key) = record.key {
+                    offset_map.insert(key, record_batch.base_offset + record.offset_delta.data as i64);
+                }
+            }
+        }
+    }
+
+    offset_map
+}
+
+// the batches of one segment with superseded records and expired tombstones removed
+// batches that lose nothing are copied byte for byte
+fn clean_batches(batches: &[u8], offset_map: &HashMap<Vec<u8>, i64>, max_message_bytes: i32, now: i64, delete_horizon: i64, stats: &mut CleanerStats) -> Vec<u8> {
+    let mut cleaned: Vec<u8> = Vec::new();
+
+    for batch in RawBatchIter::new(batches) {
+        let mut record_batch = match is_cleanable_batch(batch).then(|| decode_batch(batch, max_message_bytes)).flatten() {
+            Some(record_batch) => record_batch,
+            None => {
+                cleaned.extend(batch);
+                continue;
+            }
+        };
+
+        let has_delete_horizon = record_batch.attributes & DELETE_HORIZON_FLAG != 0;
+        let tombstones_expired = has_delete_horizon && record_batch.base_timestamp <= now;
+        let base_offset = record_batch.base_offset;
+        let records_count = record_batch.records.len();
+
+        record_batch.records.retain(|record| {
+            let offset = base_offset + record.offset_delta.data as i64;
+            let latest = record.key.as_ref()
+                .and_then(|key| offset_map.get(key))
+                .map_or(true, |&latest_offset| offset >= latest_offset);
+            let is_tombstone = matches!(record.value, RecordValue::Null);
+
+            latest && !(is_tombstone && tombstones_expired)
+        });
+        let removed = records_count - record_batch.records.len();
+        stats.records_removed += removed;
+
+        let has_tombstones = record_batch.records.iter().any(|record| matches!(record.value, RecordValue::Null));
+        let set_delete_horizon = has_tombstones && !has_delete_horizon && rebase_timestamps(&mut record_batch, delete_horizon);
+
+        if removed == 0 && !set_delete_horizon {
+            cleaned.extend(batch);
+            continue;
+        }
+
+        let last_record = match record_batch.records.last() {
+            Some(last_record) => last_record,
+            None => continue,
+        };
+        record_batch.last_offset_delta = last_record.offset_delta.data;
+        if set_delete_horizon {
+            record_batch.attributes |= DELETE_HORIZON_FLAG;
+        }
+
+        cleaned.extend(record_batch.encode());
+    }
+
+    cleaned
+}
+
+// make the delete horizon the batch base timestamp, record timestamps stay the same
+// false when a record timestamp no longer fits in its delta
+fn rebase_timestamps(record_batch: &mut RecordBatch, delete_horizon: i64) -> bool {
+    let mut timestamp_deltas: Vec<i32> = Vec::new();
+    for record in &record_batch.records {
+        let timestamp = record_batch.base_timestamp + record.timestamp_delta.data as i64;
+        match i32::try_from(timestamp - delete_horizon) {
+            Ok(timestamp_delta) => timestamp_deltas.push(timestamp_delta),
+            Err(_) => return false,
+        }
+    }
+
+    for (record, timestamp_delta) in record_batch.records.iter_mut().zip(timestamp_deltas) {
+        record.timestamp_delta.data = timestamp_delta;
+    }
+    record_batch.base_timestamp = delete_horizon;
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::kafka_record::{RawBytesRecord, Record};
+    use crate::common::primitive_types::SVarInt;
+    use crate::storage::log::ATTRIBUTES_POS;
+    use crate::storage::log_config::DEFAULT_MAX_MESSAGE_BYTES;
+
+    const NOW: i64 = 1_000_000;
+    const DELETE_HORIZON: i64 = NOW + 1000;
+
+    // an encoded batch at `base_offset` of (key, value) records, a None value is a tombstone
+    fn batch(base_offset: i64, records: &[(&str, Option<&str>)]) -> Vec<u8> {
+        RecordBatch {
+            base_offset,
+            partition_leader_epoch: 0,
+            magic: 2,
+            crc: 0,
+            attributes: 0,
+            last_offset_delta: records.len() as i32 - 1,
+            base_timestamp: NOW,
+            max_timestamp: NOW,
+            producer_id: -1,
+            producer_epoch: -1,
+            base_sequence: -1,
+            records: records.iter().enumerate().map(|(offset_delta, (key, value))| Record {
+                attributes: 0,
+                timestamp_delta: SVarInt::new(0),
+                offset_delta: SVarInt::new(offset_delta as i32),
+                key: Some(key.as_bytes().to_vec()),
+                value: match value {
+                    Some(value) => RecordValue::RawBytesRecord(RawBytesRecord { data: value.as_bytes().to_vec() }),
+                    None => RecordValue::Null,
+                },
+                headers: vec![],
+            }).collect(),
+        }.encode()
+    }
+
+    // (offset, key, value) of every record left in the cleaned batches
+    fn records(batches: &[u8]) -> Vec<(i64, String, Option<String>)> {
+        RawBatchIter::new(batches)
+            .flat_map(|batch| {
+                let record_batch = decode_batch(batch, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+                record_batch.records.into_iter().map(move |record| (
+                    record_batch.base_offset + record.offset_delta.data as i64,
+                    String::from_utf8(record.key.unwrap()).unwrap(),
+                    match record.value {
+                        RecordValue::RawBytesRecord(value) => Some(String::from_utf8(value.data).unwrap()),
+                        _ => None,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    fn clean(batches: &[u8], now: i64) -> (Vec<u8>, CleanerStats) {
+        let offset_map = build_offset_map(&[(0, batches.to_vec())], DEFAULT_MAX_MESSAGE_BYTES);
+        let mut stats = CleanerStats::default();
+        let cleaned = clean_batches(batches, &offset_map, DEFAULT_MAX_MESSAGE_BYTES, now, DELETE_HORIZON, &mut stats);
+        (cleaned, stats)
+    }
+
+    #[test]
+    fn keeps_the_latest_record_of_each_key() {
+        let mut batches = batch(0, &[("a", Some("1")), ("b", Some("2")), ("a", Some("3"))]);
+        batches.extend(batch(3, &[("b", Some("4")), ("c", Some("5"))]));
+
+        let offset_map = build_offset_map(&[(0, batches.clone())], DEFAULT_MAX_MESSAGE_BYTES);
+        assert_eq!(offset_map.get(b"a".as_slice()), Some(&2));
+        assert_eq!(offset_map.get(b"b".as_slice()), Some(&3));
+        assert_eq!(offset_map.get(b"c".as_slice()), Some(&4));
+
+        let (cleaned, stats) = clean(&batches, NOW);
+        assert_eq!(records(&cleaned), vec![
+            (2, "a".to_string(), Some("3".to_string())),
+            (3, "b".to_string(), Some("4".to_string())),
+            (4, "c".to_string(), Some("5".to_string())),
+        ]);
+        assert_eq!(stats.records_removed, 2);
+    }
+
+    #[test]
+    fn batches_that_lose_nothing_are_copied_as_is() {
+        let first = batch(0, &[("a", Some("1"))]);
+        let second = batch(1, &[("b", Some("2"))]);
+        let batches = [first, second].concat();
+
+        let (cleaned, stats) = clean(&batches, NOW);
+        assert_eq!(cleaned, batches);
+        assert_eq!(stats.records_removed, 0);
+    }
+
+    #[test]
+    fn batches_that_lose_every_record_are_dropped() {
+        let superseded = batch(0, &[("a", Some("1")), ("b", Some("2"))]);
+        let latest = batch(2, &[("a", Some("3")), ("b", Some("4"))]);
+        let batches = [superseded, latest.clone()].concat();
+
+        let (cleaned, stats) = clean(&batches, NOW);
+        assert_eq!(cleaned, latest);
+        assert_eq!(stats.records_removed, 2);
+    }
+
+    #[test]
+    fn tombstones_are_kept_until_their_delete_horizon() {
+        let batches = [batch(0, &[("a", Some("1"))]), batch(1, &[("a", None), ("b", Some("2"))])].concat();
+
+        // the first pass drops the superseded value and stamps the batch holding the tombstone
+        let (cleaned, _) = clean(&batches, NOW);
+        assert_eq!(records(&cleaned), vec![(1, "a".to_string(), None), (2, "b".to_string(), Some("2".to_string()))]);
+        let stamped = decode_batch(&cleaned, DEFAULT_MAX_MESSAGE_BYTES).unwrap();
+        assert_ne!(stamped.attributes & DELETE_HORIZON_FLAG, 0);
+        assert_eq!(stamped.base_timestamp, DELETE_HORIZON);
+
+        // record timestamps survive the rebase
+        assert!(stamped.records.iter().all(|record| stamped.base_timestamp + record.timestamp_delta.data as i64 == NOW));
+
+        // before delete.retention.ms has passed nothing changes
+        let (before_horizon, _) = clean(&cleaned, DELETE_HORIZON - 1);
+        assert_eq!(before_horizon, cleaned);
+
+        // after it the tombstone is removed
+        let (after_horizon, stats) = clean(&cleaned, DELETE_HORIZON);
+        assert_eq!(records(&after_horizon), vec![(2, "b".to_string(), Some("2".to_string()))]);
+        assert_eq!(stats.records_removed, 1);
+    }
+
+    #[test]
+    fn control_batches_are_not_cleaned() {
+        let mut control = batch(0, &[("a", Some("1"))]);
+        // the flag is in the low byte of the attributes, the CRC is not checked on segments
+        control[ATTRIBUTES_POS + 1] |= CONTROL_FLAG as u8;
+        let batches = [control.clone(), batch(1, &[("a", Some("2"))])].concat();
+
+        let (cleaned, _) = clean(&batches, NOW);
+        assert_eq!(&cleaned[..control.len()], control.as_slice());
+    }
+}
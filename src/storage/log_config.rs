@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use crate::common::compression::CompressionType;
+use crate::errors::LogConfigError;
+
+//
+// LogConfig
+//
+
+// defaults as in Kafka
+pub const DEFAULT_SEGMENT_BYTES: u64 = 1024 * 1024 * 1024;
+pub const DEFAULT_SEGMENT_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+pub const DEFAULT_INDEX_INTERVAL_BYTES: usize = 4096;
+pub const DEFAULT_SEGMENT_INDEX_BYTES: usize = 10 * 1024 * 1024;
+pub const DEFAULT_RETENTION_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+pub const DEFAULT_RETENTION_BYTES: i64 = -1;
+pub const DEFAULT_CLEANUP_POLICY: &str = "delete";
+pub const DEFAULT_DELETE_RETENTION_MS: i64 = 24 * 60 * 60 * 1000;
+pub const DEFAULT_MIN_COMPACTION_LAG_MS: i64 = 0;
+pub const DEFAULT_MIN_CLEANABLE_DIRTY_RATIO: f64 = 0.5;
+pub const DEFAULT_COMPRESSION_TYPE: &str = "producer";
+pub const DEFAULT_MAX_MESSAGE_BYTES: i32 = 1024 * 1024 + 12;
+
+// per-partition log settings: the broker defaults with the topic's config overrides applied
+#[derive(Clone, Debug)]
+pub struct LogConfig {
+    // segment.bytes, roll a new segment once the active one would grow past this
+    pub segment_bytes: u64,
+    // segment.ms, roll a new segment once the active one spans this much time
+    pub segment_ms: i64,
+    // index.interval.bytes, bytes appended between two index entries
+    pub index_interval_bytes: usize,
+    // segment.index.bytes, roll a new segment once either of its indexes holds this many bytes of entries
+    pub segment_index_bytes: usize,
+    // retention.ms, segments whose newest record is older than this are deleted, -1 keeps them forever
+    pub retention_ms: i64,
+    // retention.bytes, the oldest segments are deleted while the partition is larger than this, -1 for no limit
+    pub retention_bytes: i64,
+    // cleanup.policy, "delete", "compact" or both separated by a comma
+    pub cleanup_policy: String,
+    // delete.retention.ms, how long tombstones stay in a compacted log once their batch has been cleaned
+    pub delete_retention_ms: i64,
+    // min.compaction.lag.ms, records newer than this are not compacted
+    pub min_compaction_lag_ms: i64,
+    // min.cleanable.dirty.ratio, share of the cleanable log not yet compacted before the cleaner runs
+    pub min_cleanable_dirty_ratio: f64,
+    // compression.type, the codec batches are stored with, "producer" keeps the one they were produced with
+    pub compression_type: String,
+    // max.message.bytes, the largest record batch accepted, also the most its records may decompress to
+    pub max_message_bytes: i32,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            segment_bytes: DEFAULT_SEGMENT_BYTES,
+            segment_ms: DEFAULT_SEGMENT_MS,
+            index_interval_bytes: DEFAULT_INDEX_INTERVAL_BYTES,
+            segment_index_bytes: DEFAULT_SEGMENT_INDEX_BYTES,
+            retention_ms: DEFAULT_RETENTION_MS,
+            retention_bytes: DEFAULT_RETENTION_BYTES,
+            cleanup_policy: DEFAULT_CLEANUP_POLICY.to_string(),
+            delete_retention_ms: DEFAULT_DELETE_RETENTION_MS,
+            min_compaction_lag_ms: DEFAULT_MIN_COMPACTION_LAG_MS,
+            min_cleanable_dirty_ratio: DEFAULT_MIN_CLEANABLE_DIRTY_RATIO,
+            compression_type: DEFAULT_COMPRESSION_TYPE.to_string(),
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+        }
+    }
+}
+
+impl LogConfig {
+    // apply topic level overrides (ConfigRecords for the topic), unparseable values keep the default
+    pub fn with_overrides(&self, overrides: Option<&HashMap<String, String>>) -> LogConfig {
+        let mut config = self.clone();
+
+        let overrides = match overrides {
+            Some(overrides) => overrides,
+            None => return config,
+        };
+
+        for (name, value) in overrides {
+            match config.set(name, value) {
+                Ok(()) => {}
+                // names that are not log settings
+                Err(LogConfigError::UnknownConfig) => {}
+                Err(LogConfigError::InvalidValue) => println!("Ignoring invalid value {:?} for topic config {}", value, name),
+            }
+        }
+
+        config
+    }
+
+    // set a topic level config by name, values outside the ranges Kafka's validators accept are invalid
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), LogConfigError> {
+        let valid = match name {
+            // at least the overhead of one record
+            "segment.bytes" => value.parse().ok().filter(|&value: &u64| value >= 14).map(|value| self.segment_bytes = value).is_some(),
+            "segment.ms" => value.parse().ok().filter(|&value: &i64| value >= 1).map(|value| self.segment_ms = value).is_some(),
+            "index.interval.bytes" => value.parse().map(|value| self.index_interval_bytes = value).is_ok(),
+            "segment.index.bytes" => value.parse().ok().filter(|&value: &usize| value >= 4).map(|value| self.segment_index_bytes = value).is_some(),
+            // -1 for no limit
+            "retention.ms" => value.parse().ok().filter(|&value: &i64| value >= -1).map(|value| self.retention_ms = value).is_some(),
+            "retention.bytes" => value.parse().ok().filter(|&value: &i64| value >= -1).map(|value| self.retention_bytes = value).is_some(),
+            "cleanup.policy" => valid_cleanup_policy(value).then(|| self.cleanup_policy = value.to_string()).is_some(),
+            "delete.retention.ms" => value.parse().ok().filter(|&value: &i64| value >= 0).map(|value| self.delete_retention_ms = value).is_some(),
+            "min.compaction.lag.ms" => value.parse().ok().filter(|&value: &i64| value >= 0).map(|value| self.min_compaction_lag_ms = value).is_some(),
+            "min.cleanable.dirty.ratio" => value.parse().ok().filter(|value: &f64| (0.0..=1.0).contains(value)).map(|value| self.min_cleanable_dirty_ratio = value).is_some(),
+            "compression.type" => valid_compression_type(value).then(|| self.compression_type = value.to_string()).is_some(),
+            "max.message.bytes" => value.parse().ok().filter(|&value: &i32| value >= 0).map(|value| self.max_message_bytes = value).is_some(),
+            _ => return Err(LogConfigError::UnknownConfig),
+        };
+
+        match valid {
+            true => Ok(()),
+            false => Err(LogConfigError::InvalidValue),
+        }
+    }
+
+    // the log cleaner keeps only the latest record of each key
+    pub fn compact(&self) -> bool {
+        self.cleanup_policy.split(',').any(|policy| policy.trim() == "compact")
+    }
+
+    // the codec appended batches are recompressed with, None to keep the producer's
+    pub fn target_compression(&self) -> Option<CompressionType> {
+        CompressionType::from_name(&self.compression_type)
+    }
+
+    // segments past retention.ms or retention.bytes are deleted
+    pub fn delete(&self) -> bool {
+        self.cleanup_policy.split(',').any(|policy| policy.trim() == "delete")
+    }
+}
+
+fn valid_compression_type(value: &str) -> bool {
+    value == "producer" || (value != "none" && CompressionType::from_name(value).is_some())
+}
+
+fn valid_cleanup_policy(value: &str) -> bool {
+    value.split(',').all(|policy| matches!(policy.trim(), "compact" | "delete"))
+}
@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use uuid::Uuid;
+
+use crate::metadata::loader::METADATA_TOPIC;
+use crate::metadata::writer::UNASSIGNED_DIRECTORY;
+use crate::storage::log::Log;
+use crate::storage::log_cleaner::clean_log;
+use crate::storage::log_config::LogConfig;
+use crate::storage::meta_properties::MetaProperties;
+
+// default for log.retention.check.interval.ms, as in Kafka
+pub const DEFAULT_RETENTION_CHECK_INTERVAL_MS: u64 = 5 * 60 * 1000;
+
+// written on graceful shutdown once every log is flushed, its absence on startup means the logs need recovery
+pub const CLEAN_SHUTDOWN_FILE: &str = ".kafka_cleanshutdown";
+// offset up to which each partition was flushed at the last clean shutdown
+pub const RECOVERY_POINT_CHECKPOINT_FILE: &str = "recovery-point-offset-checkpoint";
+const CHECKPOINT_VERSION: i32 = 0;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TopicPartition {
+    pub topic: String,
+    pub partition: i32,
+}
+
+impl TopicPartition {
+    pub fn new(topic: &str, partition: i32) -> Self {
+        TopicPartition {
+            topic: topic.to_string(),
+            partition,
+        }
+    }
+
+    // partition directories are named <topic>-<partition>
+    pub fn dir_name(&self) -> String {
+        format!("{}-{}", self.topic, self.partition)
+    }
+
+    // topic names may contain '-', the partition is what follows the last one
+    pub fn from_dir_name(dir_name: &str) -> Option<Self> {
+        let (topic, partition) = dir_name.rsplit_once('-')?;
+        if topic.is_empty() {
+            return None;
+        }
+        Some(TopicPartition::new(topic, partition.parse().ok()?))
+    }
+}
+
+impl std::fmt::Display for TopicPartition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.dir_name())
+    }
+}
+
+//
+// LogDir
+//
+
+// one of the log.dirs, known to the controller by the directory.id in its meta.properties
+pub struct LogDir {
+    pub path: PathBuf,
+    // None when the dir has not been formatted
+    pub directory_id: Option<Uuid>,
+}
+
+impl LogDir {
+    fn open(path: &Path) -> std::io::Result<Self> {
+        fs::create_dir_all(path)?;
+        let directory_id = MetaProperties::read(path)?.and_then(|meta_properties| meta_properties.directory_id);
+
+        Ok(LogDir {
+            path: path.to_path_buf(),
+            directory_id,
+        })
+    }
+
+    // number of partition directories in the dir
+    fn partition_count(&self) -> std::io::Result<usize> {
+        let mut count = 0;
+        for entry in fs::read_dir(&self.path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() && entry.file_name().to_str().and_then(TopicPartition::from_dir_name).is_some() {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+//
+// LogManager
+//
+
+// owns the open partition logs, each guarded by its own lock so appends to
+// different partitions do not contend
+// partitions are spread over the log.dirs, each partition living in exactly one of them
+pub struct LogManager {
+    log_dirs: Vec<LogDir>,
+    // broker wide log settings, topics may override them
+    default_config: LogConfig,
+    logs: RwLock<HashMap<TopicPartition, Arc<Mutex<Log>>>>,
+}
+
+impl LogManager {
+    pub fn new(log_dirs: &[PathBuf], default_config: LogConfig) -> std::io::Result<Self> {
+        let log_dirs: Vec<LogDir> = log_dirs.iter().map(|path| LogDir::open(path)).collect::<std::io::Result<_>>()?;
+
+        for (index, log_dir) in log_dirs.iter().enumerate() {
+            let duplicate = log_dirs[..index].iter()
+                .any(|other| other.path == log_dir.path || (log_dir.directory_id.is_some() && other.directory_id == log_dir.directory_id));
+            if duplicate {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("log dir {:?} is listed twice or shares its directory.id", log_dir.path)));
+            }
+        }
+
+        Ok(LogManager {
+            log_dirs,
+            default_config,
+            logs: RwLock::new(HashMap::new()),
+        })
+    }
+
+    // open every partition log found in the log dirs
+    pub fn load_logs(&self, topic_configs: &HashMap<String, HashMap<String, String>>) -> std::io::Result<()> {
+        let mut logs = self.logs.write().unwrap();
+        for log_dir in &self.log_dirs {
+            self.load_log_dir(log_dir, topic_configs, &mut logs)?;
+        }
+
+        println!("Loaded {} log(s) from {} log dir(s)", logs.len(), self.log_dirs.len());
+        Ok(())
+    }
+
+    // without a clean shutdown marker in the dir, each log is recovered from its checkpointed recovery point
+    fn load_log_dir(&self, log_dir: &LogDir, topic_configs: &HashMap<String, HashMap<String, String>>, logs: &mut HashMap<TopicPartition, Arc<Mutex<Log>>>) -> std::io::Result<()> {
+        let clean_shutdown_path = log_dir.path.join(CLEAN_SHUTDOWN_FILE);
+        let had_clean_shutdown = clean_shutdown_path.exists();
+        let recovery_points = read_checkpoint(&log_dir.path.join(RECOVERY_POINT_CHECKPOINT_FILE))?;
+        if !had_clean_shutdown {
+            println!("No clean shutdown marker in {:?}, recovering logs", log_dir.path);
+        }
+
+        for entry in fs::read_dir(&log_dir.path)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let topic_partition = match entry.file_name().to_str().and_then(TopicPartition::from_dir_name) {
+                Some(topic_partition) if topic_partition.topic != METADATA_TOPIC => topic_partition,
+                _ => continue,
+            };
+            if logs.contains_key(&topic_partition) {
+                return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, format!("partition {} found in more than one log dir", topic_partition.dir_name())));
+            }
+
+            let recovery_point = match had_clean_shutdown {
+                true => None,
+                false => Some(recovery_points.get(&topic_partition).copied().unwrap_or(0)),
+            };
+            let config = self.log_config(topic_configs.get(&topic_partition.topic));
+            let log = Log::open(&entry.path(), config, recovery_point)?;
+            logs.insert(topic_partition, Arc::new(Mutex::new(log)));
+        }
+
+        // from now on a crash has to be recovered from
+        if had_clean_shutdown {
+            fs::remove_file(&clean_shutdown_path)?;
+        }
+
+        Ok(())
+    }
+
+    // flush and close every log, then checkpoint the recovery points and write the clean shutdown marker in each log dir
+    // the logs stay closed, appends arriving after this fail
+    pub fn shutdown(&self) -> std::io::Result<()> {
+        let mut recovery_points: HashMap<PathBuf, HashMap<TopicPartition, i64>> = HashMap::new();
+        for (topic_partition, log) in self.all_logs() {
+            let mut log = log.lock().unwrap();
+            log.close()?;
+
+            let log_dir = log.dir().parent().unwrap_or(Path::new(".")).to_path_buf();
+            recovery_points.entry(log_dir).or_default().insert(topic_partition, log.recovery_point());
+        }
+
+        for log_dir in &self.log_dirs {
+            let empty = HashMap::new();
+            let dir_recovery_points = recovery_points.get(&log_dir.path).unwrap_or(&empty);
+
+            write_checkpoint(&log_dir.path.join(RECOVERY_POINT_CHECKPOINT_FILE), dir_recovery_points)?;
+            fs::File::create(log_dir.path.join(CLEAN_SHUTDOWN_FILE))?.sync_all()?;
+            println!("Closed {} log(s) in {:?}", dir_recovery_points.len(), log_dir.path);
+        }
+
+        Ok(())
+    }
+
+    // the log dir a new partition log is created in: the one its PartitionRecord directories assign,
+    // otherwise the one holding the fewest partitions
+    fn select_log_dir(&self, directories: &[Uuid]) -> std::io::Result<&LogDir> {
+        let assigned = self.log_dirs.iter()
+            .find(|log_dir| log_dir.directory_id.is_some_and(|directory_id| directories.contains(&directory_id)));
+        if let Some(log_dir) = assigned {
+            return Ok(log_dir);
+        }
+
+        let mut least_used: Option<(usize, &LogDir)> = None;
+        for log_dir in &self.log_dirs {
+            let partition_count = log_dir.partition_count()?;
+            if least_used.map_or(true, |(least_count, _)| partition_count < least_count) {
+                least_used = Some((partition_count, log_dir));
+            }
+        }
+
+        least_used.map(|(_, log_dir)| log_dir)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no log dirs configured"))
+    }
+
+    // directory ids for `count` new partitions of this broker, each going to the dir holding the fewest
+    // partitions once the ones before it are counted; dirs without a directory.id are UNASSIGNED
+    pub fn assign_log_dirs(&self, count: usize) -> std::io::Result<Vec<Uuid>> {
+        let mut partition_counts: Vec<(usize, &LogDir)> = Vec::new();
+        for log_dir in &self.log_dirs {
+            partition_counts.push((log_dir.partition_count()?, log_dir));
+        }
+
+        let mut directories: Vec<Uuid> = Vec::new();
+        for _ in 0..count {
+            let least_used = match partition_counts.iter_mut().min_by_key(|(partition_count, _)| *partition_count) {
+                Some(least_used) => least_used,
+                None => return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no log dirs configured")),
+            };
+            least_used.0 += 1;
+            directories.push(least_used.1.directory_id.unwrap_or(UNASSIGNED_DIRECTORY));
+        }
+
+        Ok(directories)
+    }
+
+    // close the partition's log and remove its directory, whichever log dir it is in
+    // returns false when the partition had no log
+    pub fn delete_log(&self, topic_partition: &TopicPartition) -> std::io::Result<bool> {
+        let log = self.logs.write().unwrap().remove(topic_partition);
+
+        // appends and fetches still holding the log fail from now on
+        if let Some(log) = &log {
+            log.lock().unwrap().close()?;
+        }
+
+        let mut deleted = false;
+        for log_dir in &self.log_dirs {
+            let dir = log_dir.path.join(topic_partition.dir_name());
+            if dir.exists() {
+                fs::remove_dir_all(&dir)?;
+                println!("Deleted log for {} in {:?}", topic_partition.dir_name(), log_dir.path);
+                deleted = true;
+            }
+        }
+
+        Ok(deleted || log.is_some())
+    }
+
+    pub fn log_dirs(&self) -> &[LogDir] {
+        &self.log_dirs
+    }
+
+    // the broker defaults with a topic's config overrides applied
+    pub fn log_config(&self, topic_config: Option<&HashMap<String, String>>) -> LogConfig {
+        self.default_config.with_overrides(topic_config)
+    }
+
+    // the log for a partition if it is already open
+    pub fn get(&self, topic_partition: &TopicPartition) -> Option<Arc<Mutex<Log>>> {
+        self.logs.read().unwrap().get(topic_partition).cloned()
+    }
+
+    // get the log for a partition, opening (or creating) it on first use with the
+    // topic's config overrides applied to the broker defaults
+    // `directories` are the log dir ids the partition's metadata assigns its replicas to
+    pub fn get_or_open(&self, topic_partition: &TopicPartition, topic_config: Option<&HashMap<String, String>>, directories: &[Uuid]) -> std::io::Result<Arc<Mutex<Log>>> {
+        if let Some(log) = self.logs.read().unwrap().get(topic_partition) {
+            return Ok(Arc::clone(log));
+        }
+
+        let mut logs = self.logs.write().unwrap();
+        if let Some(log) = logs.get(topic_partition) {
+            return Ok(Arc::clone(log));
+        }
+
+        let log_dir = self.select_log_dir(directories)?;
+        println!("Creating log for {} in {:?}", topic_partition.dir_name(), log_dir.path);
+
+        let config = self.log_config(topic_config);
+        let log = Arc::new(Mutex::new(Log::open(&log_dir.path.join(topic_partition.dir_name()), config, Some(0))?));
+        logs.insert(topic_partition.clone(), Arc::clone(&log));
+
+        Ok(log)
+    }
+
+    // the open logs, so they can be worked on without holding the map lock
+    fn all_logs(&self) -> Vec<(TopicPartition, Arc<Mutex<Log>>)> {
+        self.logs.read().unwrap()
+            .iter()
+            .map(|(topic_partition, log)| (topic_partition.clone(), Arc::clone(log)))
+            .collect()
+    }
+
+    // delete the segments past retention in every open log, with the topic configs as they are now
+    // returns the number of segments deleted
+    pub fn cleanup_logs(&self, topic_configs: &HashMap<String, HashMap<String, String>>) -> usize {
+        let mut deleted = 0;
+        for (topic_partition, log) in self.all_logs() {
+            let mut log = log.lock().unwrap();
+            log.update_config(self.log_config(topic_configs.get(&topic_partition.topic)));
+
+            match log.delete_retention_breached_segments() {
+                Ok(count) => deleted += count,
+                Err(e) => println!("Error applying retention to {}: {}", topic_partition.dir_name(), e),
+            }
+        }
+
+        deleted
+    }
+
+    // compact every open log with cleanup.policy=compact, with the topic configs as they are now
+    // returns the number of logs cleaned
+    pub fn clean_logs(&self, topic_configs: &HashMap<String, HashMap<String, String>>) -> usize {
+        let mut cleaned = 0;
+        for (topic_partition, log) in self.all_logs() {
+            let mut log = log.lock().unwrap();
+            log.update_config(self.log_config(topic_configs.get(&topic_partition.topic)));
+
+            match clean_log(&mut log) {
+                Ok(Some(_)) => cleaned += 1,
+                Ok(None) => {}
+                Err(e) => println!("Error cleaning {}: {}", topic_partition.dir_name(), e),
+            }
+        }
+
+        cleaned
+    }
+}
+
+// Offset checkpoint files, as Kafka writes them:
+//   0                      version
+//   2                      number of entries
+//   foo 0 120              topic partition offset
+//   bar 3 42
+fn read_checkpoint(path: &Path) -> std::io::Result<HashMap<TopicPartition, i64>> {
+    let mut offsets: HashMap<TopicPartition, i64> = HashMap::new();
+
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(offsets),
+        Err(e) => return Err(e),
+    };
+
+    let mut lines = contents.lines();
+    if lines.next().and_then(|version| version.trim().parse::<i32>().ok()) != Some(CHECKPOINT_VERSION) {
+        println!("Ignoring checkpoint {:?} with an unknown version", path);
+        return Ok(offsets);
+    }
+    lines.next();
+
+    for line in lines {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields[..] {
+            [topic, partition, offset] => match (partition.parse(), offset.parse()) {
+                (Ok(partition), Ok(offset)) => {
+                    offsets.insert(TopicPartition::new(topic, partition), offset);
+                }
+                _ => println!("Ignoring malformed checkpoint entry {:?} in {:?}", line, path),
+            },
+            _ => println!("Ignoring malformed checkpoint entry {:?} in {:?}", line, path),
+        }
+    }
+
+    Ok(offsets)
+}
+
+// written to a temporary file first, so a crash never leaves a half written checkpoint
+fn write_checkpoint(path: &Path, offsets: &HashMap<TopicPartition, i64>) -> std::io::Result<()> {
+    let mut contents = format!("{}\n{}\n", CHECKPOINT_VERSION, offsets.len());
+    for (topic_partition, offset) in offsets {
+        contents.push_str(&format!("{} {} {}\n", topic_partition.topic, topic_partition.partition, offset));
+    }
+
+    let temp_path = path.with_extension("tmp");
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)
+}
@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::common::kafka_protocol::RequestContext;
+use crate::common::kafka_record::RecordBatch;
+use crate::common::traits::Decodable;
+use crate::storage::index::{OffsetIndex, TimeIndex, OFFSET_INDEX_ENTRY_SIZE, TIME_INDEX_ENTRY_SIZE};
+use crate::storage::log::{batch_attributes, batch_base_offset, batch_base_timestamp, batch_crc, compute_batch_crc, batch_last_offset_delta, batch_max_timestamp, batch_producer_id, index_file_name, log_file_name, time_index_file_name, RawBatchIter, BATCH_LENGTH_POS, LOG_APPEND_TIME_FLAG, LOG_OVERHEAD, MAGIC_POS, RECORD_BATCH_HEADER_SIZE};
+use crate::storage::log_config::LogConfig;
+
+//
+// BatchHeader
+//
+
+// the RecordBatch header fields the log needs, read without the records
+pub struct BatchHeader {
+    // file position of the batch in its segment
+    pub position: u64,
+    pub base_offset: i64,
+    // whole batch, including the log overhead
+    pub size: u64,
+    pub attributes: i16,
+    pub last_offset_delta: i32,
+    // the delete horizon when the batch has DELETE_HORIZON_FLAG set
+    pub base_timestamp: i64,
+    pub max_timestamp: i64,
+    pub producer_id: i64,
+}
+
+impl BatchHeader {
+    // header of a complete batch held in memory, its position is unknown
+    pub fn from_batch(batch: &[u8]) -> BatchHeader {
+        BatchHeader::parse(0, batch)
+    }
+
+    fn parse(position: u64, header: &[u8]) -> BatchHeader {
+        let batch_length = i32::from_be_bytes(header[BATCH_LENGTH_POS..BATCH_LENGTH_POS + 4].try_into().unwrap());
+
+        BatchHeader {
+            position,
+            base_offset: batch_base_offset(header),
+            size: (LOG_OVERHEAD + batch_length.max(0) as usize) as u64,
+            attributes: batch_attributes(header),
+            last_offset_delta: batch_last_offset_delta(header),
+            base_timestamp: batch_base_timestamp(header),
+            max_timestamp: batch_max_timestamp(header),
+            producer_id: batch_producer_id(header),
+        }
+    }
+
+    pub fn last_offset(&self) -> i64 {
+        self.base_offset + self.last_offset_delta as i64
+    }
+
+    pub fn next_offset(&self) -> i64 {
+        self.last_offset() + 1
+    }
+}
+
+// why a batch of a segment file failed verification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchCorruption {
+    // the file ends inside the batch
+    Partial,
+    // batchLength is too small for a batch header
+    InvalidLength(i32),
+    UnsupportedMagic(i8),
+    CrcMismatch { stored: u32, computed: u32 },
+}
+
+// the first batch of a segment file that failed verification
+#[derive(Debug, Clone, Copy)]
+pub struct CorruptBatch {
+    pub position: u64,
+    // None when not even the batch header is complete
+    pub base_offset: Option<i64>,
+    pub corruption: BatchCorruption,
+}
+
+enum BatchCheck {
+    // clean end of the file
+    End,
+    Valid(BatchHeader),
+    Corrupt(CorruptBatch),
+}
+
+// check the batch at `position`: complete, magic 2 and a matching CRC-32C
+fn check_batch_at(file: &File, position: u64, end: u64) -> std::io::Result<BatchCheck> {
+    if position == end {
+        return Ok(BatchCheck::End);
+    }
+
+    let corrupt = |base_offset: Option<i64>, corruption: BatchCorruption| Ok(BatchCheck::Corrupt(CorruptBatch { position, base_offset, corruption }));
+
+    if position + RECORD_BATCH_HEADER_SIZE as u64 > end {
+        return corrupt(None, BatchCorruption::Partial);
+    }
+
+    let mut header = [0; RECORD_BATCH_HEADER_SIZE];
+    file.read_exact_at(&mut header, position)?;
+    let base_offset = Some(batch_base_offset(&header));
+
+    let batch_length = i32::from_be_bytes(header[BATCH_LENGTH_POS..BATCH_LENGTH_POS + 4].try_into().unwrap());
+    if batch_length < (RECORD_BATCH_HEADER_SIZE - LOG_OVERHEAD) as i32 {
+        return corrupt(base_offset, BatchCorruption::InvalidLength(batch_length));
+    }
+    if header[MAGIC_POS] != 2 {
+        return corrupt(base_offset, BatchCorruption::UnsupportedMagic(header[MAGIC_POS] as i8));
+    }
+
+    let batch_header = BatchHeader::parse(position, &header);
+    if position + batch_header.size > end {
+        return corrupt(base_offset, BatchCorruption::Partial);
+    }
+
+    let mut batch = vec![0; batch_header.size as usize];
+    file.read_exact_at(&mut batch, position)?;
+    let (stored, computed) = (batch_crc(&batch), compute_batch_crc(&batch));
+    if stored != computed {
+        return corrupt(base_offset, BatchCorruption::CrcMismatch { stored, computed });
+    }
+
+    Ok(BatchCheck::Valid(batch_header))
+}
+
+// verify every batch of a segment file without opening it as a segment, for tools inspecting a log
+// returns the first corrupt batch, None when the whole file is valid
+pub fn verify_log_file(path: &Path) -> std::io::Result<Option<CorruptBatch>> {
+    let file = File::open(path)?;
+    verify_batches(&file, file.metadata()?.len())
+}
+
+fn verify_batches(file: &File, end: u64) -> std::io::Result<Option<CorruptBatch>> {
+    let mut position = 0;
+    loop {
+        match check_batch_at(file, position, end)? {
+            BatchCheck::End => return Ok(None),
+            BatchCheck::Valid(header) => position += header.size,
+            BatchCheck::Corrupt(corrupt_batch) => return Ok(Some(corrupt_batch)),
+        }
+    }
+}
+
+// walks the batch headers of a segment file starting at a batch boundary,
+// stops at the end of the file or at the first partial batch
+pub struct BatchHeaderIter<'a> {
+    file: &'a File,
+    position: u64,
+    end: u64,
+}
+
+impl Iterator for BatchHeaderIter<'_> {
+    type Item = std::io::Result<BatchHeader>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position + RECORD_BATCH_HEADER_SIZE as u64 > self.end {
+            return None;
+        }
+
+        let mut header = [0; RECORD_BATCH_HEADER_SIZE];
+        if let Err(e) = self.file.read_exact_at(&mut header, self.position) {
+            return Some(Err(e));
+        }
+
+        let batch_length = i32::from_be_bytes(header[BATCH_LENGTH_POS..BATCH_LENGTH_POS + 4].try_into().unwrap());
+        if batch_length < (RECORD_BATCH_HEADER_SIZE - LOG_OVERHEAD) as i32 {
+            return None;
+        }
+
+        let batch_header = BatchHeader::parse(self.position, &header);
+        if self.position + batch_header.size > self.end {
+            return None;
+        }
+
+        self.position += batch_header.size;
+        Some(Ok(batch_header))
+    }
+}
+
+//
+// LogSegment
+//
+
+// One file of a partition log, named by the offset of its first record, with its offset and time indexes:
+//   00000000000000000000.log        record batches
+//   00000000000000000000.index      offset -> position, one entry every index.interval.bytes
+//   00000000000000000000.timeindex  timestamp -> offset
+pub struct LogSegment {
+    base_offset: i64,
+    log_path: PathBuf,
+    log_file: File,
+    offset_index: OffsetIndex,
+    time_index: TimeIndex,
+    size: u64,
+    // offset right after the last batch
+    next_offset: i64,
+    // largest batch timestamp in the segment and the offset it is indexed at
+    max_timestamp: i64,
+    offset_of_max_timestamp: i64,
+    // max timestamp of the first batch, segment.ms counts from there
+    rolling_base_timestamp: Option<i64>,
+    created_ms: i64,
+    // index.interval.bytes
+    index_interval_bytes: usize,
+    bytes_since_last_index_entry: usize,
+}
+
+impl LogSegment {
+    // open the segment starting at `base_offset` in `dir`, creating its files if needed
+    // indexes that are missing, or that point past the end of the log, are rebuilt from the log
+    pub fn open(dir: &Path, base_offset: i64, index_interval_bytes: usize) -> std::io::Result<Self> {
+        let log_path = dir.join(log_file_name(base_offset));
+        let index_path = dir.join(index_file_name(base_offset));
+        let time_index_path = dir.join(time_index_file_name(base_offset));
+        let indexes_exist = index_path.exists() && time_index_path.exists();
+
+        let log_file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&log_path)?;
+        let size = log_file.metadata()?.len();
+
+        let mut segment = LogSegment {
+            base_offset,
+            log_path,
+            log_file,
+            offset_index: OffsetIndex::open(&index_path, base_offset)?,
+            time_index: TimeIndex::open(&time_index_path, base_offset)?,
+            size,
+            next_offset: base_offset,
+            max_timestamp: -1,
+            offset_of_max_timestamp: base_offset,
+            rolling_base_timestamp: None,
+            created_ms: now_ms(),
+            index_interval_bytes,
+            bytes_since_last_index_entry: 0,
+        };
+
+        let indexes_valid = segment.offset_index.last_entry().map_or(true, |(_, position)| position < size);
+        if size > 0 && !(indexes_exist && indexes_valid) {
+            segment.rebuild_indexes()?;
+        } else {
+            segment.load_tail()?;
+        }
+
+        Ok(segment)
+    }
+
+    // recover the next offset and max timestamp from the batches after the last index entries
+    fn load_tail(&mut self) -> std::io::Result<()> {
+        if let Some((timestamp, offset)) = self.time_index.last_entry() {
+            self.max_timestamp = timestamp;
+            self.offset_of_max_timestamp = offset;
+        }
+
+        if let Some(first_header) = self.batch_headers(0).next() {
+            self.rolling_base_timestamp = Some(first_header?.max_timestamp);
+        }
+
+        let (_, start_position) = self.offset_index.lookup(i64::MAX);
+        let headers: Vec<BatchHeader> = self.batch_headers(start_position).collect::<std::io::Result<_>>()?;
+        for header in headers {
+            if header.max_timestamp > self.max_timestamp {
+                self.max_timestamp = header.max_timestamp;
+                self.offset_of_max_timestamp = header.last_offset();
+            }
+            self.next_offset = header.next_offset();
+        }
+
+        Ok(())
+    }
+
+    // rebuild both indexes by scanning the whole log file
+    pub fn rebuild_indexes(&mut self) -> std::io::Result<()> {
+        println!("Rebuilding indexes for {:?}", self.log_path);
+        self.reset_indexes()?;
+
+        let headers: Vec<BatchHeader> = self.batch_headers(0).collect::<std::io::Result<_>>()?;
+        for header in &headers {
+            self.index_batch(header)?;
+        }
+
+        Ok(())
+    }
+
+    fn reset_indexes(&mut self) -> std::io::Result<()> {
+        self.offset_index.reset()?;
+        self.time_index.reset()?;
+        self.next_offset = self.base_offset;
+        self.max_timestamp = -1;
+        self.offset_of_max_timestamp = self.base_offset;
+        self.rolling_base_timestamp = None;
+        self.bytes_since_last_index_entry = 0;
+        Ok(())
+    }
+
+    // after an unclean shutdown: check every batch, truncate the log at the first partial or corrupt one
+    // and rebuild the indexes from what is left
+    // returns the number of bytes truncated
+    pub fn recover(&mut self) -> std::io::Result<u64> {
+        println!("Recovering segment {:?}", self.log_path);
+        self.reset_indexes()?;
+
+        let mut position = 0;
+        loop {
+            match check_batch_at(&self.log_file, position, self.size)? {
+                BatchCheck::End => break,
+                BatchCheck::Valid(header) => {
+                    self.index_batch(&header)?;
+                    position += header.size;
+                }
+                BatchCheck::Corrupt(corrupt_batch) => {
+                    println!("Corrupt batch in {:?} at position {}: {:?}", self.log_path, position, corrupt_batch.corruption);
+                    break;
+                }
+            }
+        }
+
+        let truncated = self.size - position;
+        if truncated > 0 {
+            println!("Truncating {:?} to {} bytes, dropping {} bytes of partial or corrupt batches", self.log_path, position, truncated);
+            self.log_file.set_len(position)?;
+            self.log_file.sync_all()?;
+            self.size = position;
+        }
+
+        Ok(truncated)
+    }
+
+    // the first batch of the segment that is partial or fails its CRC-32C, None when all of them are valid
+    pub fn verify(&self) -> std::io::Result<Option<CorruptBatch>> {
+        verify_batches(&self.log_file, self.size)
+    }
+
+    // update the indexes and offsets for a batch written at `header.position`
+    fn index_batch(&mut self, header: &BatchHeader) -> std::io::Result<()> {
+        if header.max_timestamp > self.max_timestamp {
+            self.max_timestamp = header.max_timestamp;
+            self.offset_of_max_timestamp = header.last_offset();
+        }
+
+        if self.bytes_since_last_index_entry > self.index_interval_bytes {
+            self.offset_index.append(header.last_offset(), header.position)?;
+            self.time_index.maybe_append(self.max_timestamp, self.offset_of_max_timestamp)?;
+            self.bytes_since_last_index_entry = 0;
+        }
+        self.bytes_since_last_index_entry += header.size as usize;
+
+        if self.rolling_base_timestamp.is_none() {
+            self.rolling_base_timestamp = Some(header.max_timestamp);
+        }
+        self.next_offset = header.next_offset();
+
+        Ok(())
+    }
+
+    // append record batches whose offsets are already assigned
+    pub fn append(&mut self, batches: &[u8]) -> std::io::Result<()> {
+        self.log_file.write_all(batches)?;
+        self.log_file.flush()?;
+
+        let mut position = self.size;
+        for batch in RawBatchIter::new(batches) {
+            let header = BatchHeader::parse(position, batch);
+            self.index_batch(&header)?;
+            position += header.size;
+        }
+        self.size += batches.len() as u64;
+
+        Ok(())
+    }
+
+    // whether appending `messages_size` bytes with the given max timestamp needs a new segment first
+    pub fn should_roll(&self, config: &LogConfig, messages_size: u64, max_timestamp_in_messages: i64, last_offset_in_messages: i64) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+
+        // segment.ms is measured from the first batch timestamp, or from when the segment was opened without one
+        let time_waited = match self.rolling_base_timestamp {
+            Some(timestamp) if timestamp >= 0 => max_timestamp_in_messages - timestamp,
+            _ => now_ms() - self.created_ms,
+        };
+
+        self.size + messages_size > config.segment_bytes
+            || time_waited > config.segment_ms
+            || self.offset_index.len() >= config.segment_index_bytes / OFFSET_INDEX_ENTRY_SIZE
+            || self.time_index.len() >= config.segment_index_bytes / TIME_INDEX_ENTRY_SIZE
+            // index entries store offsets relative to the base offset as INT32
+            || last_offset_in_messages - self.base_offset > i32::MAX as i64
+    }
+
+    // headers of the batches from `position` (a batch boundary) to the end of the segment
+    pub fn batch_headers(&self, position: u64) -> BatchHeaderIter<'_> {
+        BatchHeaderIter {
+            file: &self.log_file,
+            position,
+            end: self.size,
+        }
+    }
+
+    // position of the batch containing `offset`, or of the first batch after it
+    // None when `offset` is past the end of the segment
+    pub fn translate_offset(&self, offset: i64) -> std::io::Result<Option<u64>> {
+        let (_, start_position) = self.offset_index.lookup(offset);
+
+        for header in self.batch_headers(start_position) {
+            let header = header?;
+            if header.next_offset() > offset {
+                return Ok(Some(header.position));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // the first record at or after `start_offset` whose timestamp is at or after `timestamp`, as (timestamp, offset)
+    // the time index gives the batch to start scanning from, the first batch reaching `timestamp` is decoded
+    // to find the record in it
+    pub fn find_offset_by_timestamp(&self, timestamp: i64, start_offset: i64, max_message_bytes: i32) -> std::io::Result<Option<(i64, i64)>> {
+        let (_, indexed_offset) = self.time_index.lookup(timestamp);
+        let position = match self.translate_offset(indexed_offset.max(start_offset))? {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+
+        for header in self.batch_headers(position) {
+            let header = header?;
+            if header.max_timestamp < timestamp || header.last_offset() < start_offset {
+                continue;
+            }
+
+            let batch = self.read(header.position, i64::MAX, header.size as usize, true)?;
+            if let Some(found) = find_record_by_timestamp(&batch, &header, timestamp, start_offset, max_message_bytes) {
+                return Ok(Some(found));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // read whole batches starting at `position`, up to `max_bytes` and stopping before `max_offset`
+    // with `min_one_batch`, the first batch is returned even when it is larger than `max_bytes`
+    pub fn read(&self, position: u64, max_offset: i64, max_bytes: usize, min_one_batch: bool) -> std::io::Result<Vec<u8>> {
+        let mut end_position = position;
+
+        for header in self.batch_headers(position) {
+            let header = header?;
+            if header.base_offset >= max_offset {
+                break;
+            }
+
+            let read_bytes = (end_position - position) as usize;
+            if read_bytes + header.size as usize > max_bytes && !(min_one_batch && read_bytes == 0) {
+                break;
+            }
+
+            end_position += header.size;
+        }
+
+        let mut records = vec![0; (end_position - position) as usize];
+        self.log_file.read_exact_at(&mut records, position)?;
+
+        Ok(records)
+    }
+
+    pub fn base_offset(&self) -> i64 {
+        self.base_offset
+    }
+
+    pub fn next_offset(&self) -> i64 {
+        self.next_offset
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn max_timestamp(&self) -> i64 {
+        self.max_timestamp
+    }
+
+    // newest record timestamp, or the file modification time for a segment without timestamps
+    pub fn largest_timestamp(&self) -> i64 {
+        if self.max_timestamp >= 0 {
+            return self.max_timestamp;
+        }
+
+        self.log_file.metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_millis() as i64)
+    }
+
+    // write the log and its indexes through to disk
+    pub fn flush(&self) -> std::io::Result<()> {
+        self.log_file.sync_all()?;
+        self.offset_index.flush()?;
+        self.time_index.flush()
+    }
+
+    // remove the segment and its indexes from disk
+    pub fn delete(self) -> std::io::Result<()> {
+        for path in [self.log_path.with_extension("index"), self.log_path.with_extension("timeindex"), self.log_path.clone()] {
+            remove_file_if_exists(&path)?;
+        }
+        Ok(())
+    }
+
+    // replace the log file with `cleaned_path` and reopen the segment, rebuilding its indexes
+    // the indexes go first, so a crash in between leaves the old log to be reindexed on startup
+    pub fn replace_with(self, cleaned_path: &Path) -> std::io::Result<LogSegment> {
+        let LogSegment { base_offset, log_path, index_interval_bytes, .. } = self;
+        let dir = log_path.parent().unwrap_or(Path::new("."));
+
+        remove_file_if_exists(&log_path.with_extension("index"))?;
+        remove_file_if_exists(&log_path.with_extension("timeindex"))?;
+        fs::rename(cleaned_path, &log_path)?;
+
+        LogSegment::open(dir, base_offset, index_interval_bytes)
+    }
+
+    pub fn offset_index(&self) -> &OffsetIndex {
+        &self.offset_index
+    }
+
+    pub fn time_index(&self) -> &TimeIndex {
+        &self.time_index
+    }
+}
+
+// the first record of the batch at or after `start_offset` with a timestamp at or after `timestamp`
+// records of LOG_APPEND_TIME batches all carry the batch max timestamp
+fn find_record_by_timestamp(batch: &[u8], header: &BatchHeader, timestamp: i64, start_offset: i64, max_message_bytes: i32) -> Option<(i64, i64)> {
+    if header.attributes & LOG_APPEND_TIME_FLAG != 0 {
+        return Some((header.max_timestamp, header.base_offset.max(start_offset)));
+    }
+
+    // batches in a segment had their CRC checked on append or recovery
+    let mut context_map: HashMap<String, String> = HashMap::new();
+    context_map.insert("skip_crc_validation".to_string(), "true".to_string());
+    context_map.insert("max_decompressed_bytes".to_string(), max_message_bytes.to_string());
+
+    let (record_batch, _) = match RecordBatch::decode(batch, &RequestContext::Some(context_map)) {
+        Ok(decoded) => decoded,
+        Err(_) => {
+            println!("Could not decode the record batch at offset {} to look up timestamp {}", header.base_offset, timestamp);
+            return None;
+        }
+    };
+
+    record_batch.records.iter()
+        .map(|record| (record_batch.base_timestamp + record.timestamp_delta.data as i64, record_batch.base_offset + record.offset_delta.data as i64))
+        .find(|&(record_timestamp, offset)| record_timestamp >= timestamp && offset >= start_offset)
+}
+
+fn remove_file_if_exists(path: &Path) -> std::io::Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn now_ms() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_millis() as i64)
+}
@@ -0,0 +1,201 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use uuid::Uuid;
+
+use crate::utils::read_properties;
+
+//
+// MetaProperties
+//
+
+// the meta.properties file written in every log dir when it is formatted, e.g.
+//   version=1
+//   cluster.id=MkU3OEVBNTcwNTJENDM2Qk
+//   node.id=1
+//   directory.id=J8aAPcfLQt2bqs1JT_rMgQ
+pub const META_PROPERTIES_FILE: &str = "meta.properties";
+
+// KRaft meta.properties, version 0 is the ZooKeeper era format with broker.id
+pub const META_PROPERTIES_VERSION: i32 = 1;
+
+pub struct MetaProperties {
+    pub version: i32,
+    pub cluster_id: Option<String>,
+    pub node_id: Option<i32>,
+    // identifies the log dir in PartitionRecord directories
+    pub directory_id: Option<Uuid>,
+}
+
+impl MetaProperties {
+    // the meta.properties of a log dir, None when the dir has not been formatted
+    pub fn read(log_dir: &Path) -> std::io::Result<Option<MetaProperties>> {
+        let properties = match read_properties(&log_dir.join(META_PROPERTIES_FILE)) {
+            Ok(properties) => properties,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let invalid = |name: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("invalid {} in {:?}", name, log_dir.join(META_PROPERTIES_FILE)));
+
+        let version = match properties.get("version") {
+            Some(version) => version.parse().map_err(|_| invalid("version"))?,
+            None => 0,
+        };
+        let node_id = match properties.get("node.id").or(properties.get("broker.id")) {
+            Some(node_id) => Some(node_id.parse().map_err(|_| invalid("node.id"))?),
+            None => None,
+        };
+        let directory_id = match properties.get("directory.id") {
+            Some(directory_id) => Some(parse_uuid(directory_id).ok_or_else(|| invalid("directory.id"))?),
+            None => None,
+        };
+
+        Ok(Some(MetaProperties {
+            version,
+            cluster_id: properties.get("cluster.id").cloned(),
+            node_id,
+            directory_id,
+        }))
+    }
+
+    // written to a temporary file first so a crash never leaves a partial meta.properties
+    pub fn write(&self, log_dir: &Path) -> std::io::Result<()> {
+        let mut contents = format!("version={}\n", self.version);
+        if let Some(cluster_id) = &self.cluster_id {
+            contents.push_str(&format!("cluster.id={}\n", cluster_id));
+        }
+        if let Some(node_id) = self.node_id {
+            contents.push_str(&format!("node.id={}\n", node_id));
+        }
+        if let Some(directory_id) = &self.directory_id {
+            contents.push_str(&format!("directory.id={}\n", uuid_to_string(directory_id)));
+        }
+
+        let path = log_dir.join(META_PROPERTIES_FILE);
+        let temp_path = path.with_extension("tmp");
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    }
+}
+
+//
+// Formatting
+//
+
+// write meta.properties to every log dir, each with a new directory.id, like kafka-storage.sh format
+// already formatted dirs are an error unless `ignore_formatted`, then they are left as they are
+// returns the number of dirs formatted
+pub fn format_log_dirs(log_dirs: &[PathBuf], cluster_id: &str, node_id: i32, ignore_formatted: bool) -> std::io::Result<usize> {
+    if parse_uuid(cluster_id).is_none() {
+        return Err(invalid_data(format!("cluster id {:?} is not a base64 encoded UUID, generate one with random-uuid", cluster_id)));
+    }
+
+    let mut formatted = 0;
+    for log_dir in log_dirs {
+        if MetaProperties::read(log_dir)?.is_some() {
+            if ignore_formatted {
+                println!("{:?} is already formatted, skipping", log_dir);
+                continue;
+            }
+            return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, format!("{:?} is already formatted, use --ignore-formatted to skip it", log_dir)));
+        }
+
+        fs::create_dir_all(log_dir)?;
+        let meta_properties = MetaProperties {
+            version: META_PROPERTIES_VERSION,
+            cluster_id: Some(cluster_id.to_string()),
+            node_id: Some(node_id),
+            directory_id: Some(Uuid::new_v4()),
+        };
+        meta_properties.write(log_dir)?;
+
+        println!("Formatted {:?} with cluster.id {} and directory.id {}", log_dir, cluster_id, uuid_to_string(&meta_properties.directory_id.unwrap()));
+        formatted += 1;
+    }
+
+    Ok(formatted)
+}
+
+// check the meta.properties of the log dirs before the broker starts, returns the cluster.id they share
+// every formatted dir has to belong to the same cluster and to this node; unformatted dirs, e.g. ones
+// added to log.dirs later, are formatted with that cluster.id
+// a broker without any formatted dir runs without a cluster.id
+pub fn load_log_dirs(log_dirs: &[PathBuf], node_id: i32) -> std::io::Result<Option<String>> {
+    let mut cluster_id: Option<String> = None;
+    let mut unformatted: Vec<PathBuf> = Vec::new();
+
+    for log_dir in log_dirs {
+        let mut meta_properties = match MetaProperties::read(log_dir)? {
+            Some(meta_properties) => meta_properties,
+            None => {
+                unformatted.push(log_dir.clone());
+                continue;
+            }
+        };
+
+        match meta_properties.version {
+            0 => {}
+            META_PROPERTIES_VERSION if meta_properties.cluster_id.is_some() && meta_properties.node_id.is_some() => {}
+            META_PROPERTIES_VERSION => return Err(invalid_data(format!("meta.properties in {:?} lacks cluster.id or node.id", log_dir))),
+            version => return Err(invalid_data(format!("meta.properties in {:?} has unsupported version {}", log_dir, version))),
+        }
+
+        if let Some(dir_node_id) = meta_properties.node_id.filter(|&dir_node_id| dir_node_id != node_id) {
+            return Err(invalid_data(format!("meta.properties in {:?} has node.id {}, but the broker is configured with node.id {}", log_dir, dir_node_id, node_id)));
+        }
+
+        match (&cluster_id, &meta_properties.cluster_id) {
+            (Some(expected), Some(dir_cluster_id)) if expected != dir_cluster_id => {
+                return Err(invalid_data(format!("meta.properties in {:?} has cluster.id {}, but other log dirs belong to cluster {}", log_dir, dir_cluster_id, expected)));
+            }
+            (None, Some(dir_cluster_id)) => cluster_id = Some(dir_cluster_id.clone()),
+            _ => {}
+        }
+
+        // dirs formatted before directory ids existed get one
+        if meta_properties.directory_id.is_none() {
+            meta_properties.directory_id = Some(Uuid::new_v4());
+            meta_properties.write(log_dir)?;
+        }
+    }
+
+    match &cluster_id {
+        Some(cluster_id) => {
+            format_log_dirs(&unformatted, cluster_id, node_id, false)?;
+        }
+        None => println!("No formatted log dir, running without a cluster.id"),
+    }
+
+    Ok(cluster_id)
+}
+
+fn invalid_data(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+// Kafka prints UUIDs as 22 characters of URL safe base64 without padding
+pub fn parse_uuid(value: &str) -> Option<Uuid> {
+    let bytes = URL_SAFE_NO_PAD.decode(value).ok()?;
+    Uuid::from_slice(&bytes).ok()
+}
+
+// a new cluster id, as kafka-storage.sh random-uuid prints
+// ids starting with a dash are skipped so they can not be mistaken for command line flags
+pub fn random_uuid() -> String {
+    loop {
+        let uuid = uuid_to_string(&Uuid::new_v4());
+        if !uuid.starts_with('-') {
+            return uuid;
+        }
+    }
+}
+
+pub fn uuid_to_string(uuid: &Uuid) -> String {
+    URL_SAFE_NO_PAD.encode(uuid.as_bytes())
+}
@@ -0,0 +1,7 @@
+pub mod index;
+pub mod log;
+pub mod log_cleaner;
+pub mod log_config;
+pub mod log_manager;
+pub mod log_segment;
+pub mod meta_properties;
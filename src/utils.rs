@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+//
+// Properties files
+//
+
+// Java style .properties files, as server.properties and meta.properties are written:
+// one key=value (or key: value) per line, lines starting with # or ! are comments
+pub fn parse_properties(contents: &str) -> HashMap<String, String> {
+    let mut properties: HashMap<String, String> = HashMap::new();
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+
+        let (key, value) = match line.find(['=', ':']) {
+            Some(separator) => (&line[..separator], &line[separator + 1..]),
+            None => (line, ""),
+        };
+        properties.insert(key.trim().to_string(), value.trim().to_string());
+    }
+
+    properties
+}
+
+pub fn read_properties(path: &Path) -> std::io::Result<HashMap<String, String>> {
+    Ok(parse_properties(&std::fs::read_to_string(path)?))
+}
+
+//
+// Topic names
+//
+
+// as Kafka's Topic.validate: 1 to 249 characters of [a-zA-Z0-9._-], other than "." and ".."
+pub const MAX_TOPIC_NAME_LENGTH: usize = 249;
+
+pub fn valid_topic_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_TOPIC_NAME_LENGTH
+        && name != "."
+        && name != ".."
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+// topics the brokers keep their own state in
+pub const INTERNAL_TOPICS: [&str; 2] = ["__consumer_offsets", "__transaction_state"];
+
+pub fn is_internal_topic(name: &str) -> bool {
+    INTERNAL_TOPICS.contains(&name)
+}